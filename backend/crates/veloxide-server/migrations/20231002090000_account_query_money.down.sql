@@ -0,0 +1,15 @@
+UPDATE "account_query"
+SET "payload" = (
+  "payload"::JSONB || JSONB_BUILD_OBJECT(
+    'balance', (("payload" -> 'balance' ->> 'minor_units')::NUMERIC / 100)::FLOAT8,
+    'account_transactions', COALESCE((
+      SELECT JSONB_AGG(
+        "tx" || JSONB_BUILD_OBJECT(
+          'amount', (("tx" -> 'amount' ->> 'minor_units')::NUMERIC / 100)::FLOAT8
+        ) ORDER BY "position"
+      )
+      FROM JSONB_ARRAY_ELEMENTS("payload"::JSONB -> 'account_transactions') WITH ORDINALITY AS t("tx", "position")
+    ), '[]'::JSONB)
+  )
+)::JSON
+WHERE JSON_TYPEOF("payload" -> 'balance') = 'object';
@@ -0,0 +1,22 @@
+-- Converts the f64 balance and transaction amounts of existing account views into Money objects
+UPDATE "account_query"
+SET "payload" = (
+  "payload"::JSONB || JSONB_BUILD_OBJECT(
+    'balance', JSONB_BUILD_OBJECT(
+      'minor_units', ROUND(("payload" ->> 'balance')::NUMERIC * 100)::BIGINT,
+      'currency', 'USD'
+    ),
+    'account_transactions', COALESCE((
+      SELECT JSONB_AGG(
+        "tx" || JSONB_BUILD_OBJECT(
+          'amount', JSONB_BUILD_OBJECT(
+            'minor_units', ROUND(("tx" ->> 'amount')::NUMERIC * 100)::BIGINT,
+            'currency', 'USD'
+          )
+        ) ORDER BY "position"
+      )
+      FROM JSONB_ARRAY_ELEMENTS("payload"::JSONB -> 'account_transactions') WITH ORDINALITY AS t("tx", "position")
+    ), '[]'::JSONB)
+  )
+)::JSON
+WHERE JSON_TYPEOF("payload" -> 'balance') = 'number';
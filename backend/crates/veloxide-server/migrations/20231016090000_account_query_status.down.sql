@@ -0,0 +1,2 @@
+UPDATE "account_query"
+SET "payload" = ("payload"::JSONB - 'status')::JSON;
@@ -0,0 +1,4 @@
+-- Every account with a view has been opened, so existing views start out as open.
+UPDATE "account_query"
+SET "payload" = ("payload"::JSONB || '{"status": "Open"}'::JSONB)::JSON
+WHERE "payload"::JSONB -> 'status' IS NULL;
@@ -0,0 +1,2 @@
+UPDATE "account_query"
+SET "payload" = ("payload"::JSONB - 'overdraft_limit' - 'interest_free_buffer')::JSON;
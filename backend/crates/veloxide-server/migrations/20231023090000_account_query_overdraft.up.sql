@@ -0,0 +1,12 @@
+-- Existing accounts have no overdraft, expressed in the currency the account is held in.
+UPDATE "account_query"
+SET "payload" = (
+    "payload"::JSONB
+    || jsonb_build_object(
+        'overdraft_limit',
+        jsonb_build_object('minor_units', 0, 'currency', "payload"::JSONB -> 'balance' -> 'currency'),
+        'interest_free_buffer',
+        jsonb_build_object('minor_units', 0, 'currency', "payload"::JSONB -> 'balance' -> 'currency')
+    )
+)::JSON
+WHERE "payload"::JSONB -> 'overdraft_limit' IS NULL;
@@ -0,0 +1,11 @@
+-- Aggregate snapshots as read and written by postgres-es and mysql-es. Unquoted and portable
+-- so that the same migration serves both the postgres and mysql features.
+CREATE TABLE snapshots (
+  aggregate_type VARCHAR(255) NOT NULL,
+  aggregate_id VARCHAR(255) NOT NULL,
+  last_sequence BIGINT NOT NULL CHECK (last_sequence >= 0),
+  current_snapshot BIGINT NOT NULL CHECK (current_snapshot >= 0),
+  payload JSON NOT NULL,
+  timestamp TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (aggregate_type, aggregate_id)
+);
@@ -0,0 +1,17 @@
+-- One row per deposit, withdrawal, check and transfer, replacing the unbounded list of
+-- transactions in the account view. Unquoted and portable so that the same migration serves
+-- both the postgres and mysql features. Populate it from the event store by running
+-- `veloxide-server rebuild-projection account_transactions`.
+CREATE TABLE account_transactions (
+  account_id VARCHAR(255) NOT NULL,
+  sequence BIGINT NOT NULL,
+  transaction_type VARCHAR(32) NOT NULL,
+  description TEXT NOT NULL,
+  amount_minor_units BIGINT NOT NULL,
+  currency VARCHAR(3) NOT NULL,
+  original_amount JSON,
+  exchange_rate JSON,
+  occurred_at TIMESTAMP NOT NULL,
+  PRIMARY KEY (account_id, sequence)
+);
+CREATE INDEX account_transactions_occurred_at ON account_transactions (account_id, occurred_at);
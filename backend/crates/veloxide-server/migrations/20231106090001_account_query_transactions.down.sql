@@ -0,0 +1,4 @@
+-- The removed transactions are not restored, rebuild the account_query projection to get them back.
+UPDATE "account_query"
+SET "payload" = ("payload"::JSONB || '{"account_transactions": []}'::JSONB)::JSON
+WHERE "payload"::JSONB -> 'account_transactions' IS NULL;
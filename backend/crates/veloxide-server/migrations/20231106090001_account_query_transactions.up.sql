@@ -0,0 +1,3 @@
+-- The transactions now live in their own table, see the account_transactions migration.
+UPDATE "account_query"
+SET "payload" = ("payload"::JSONB - 'account_transactions')::JSON;
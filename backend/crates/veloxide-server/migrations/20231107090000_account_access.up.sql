@@ -0,0 +1,10 @@
+-- Who may access each bank account and in which role, including its owner. Unquoted and
+-- portable so that the same migration serves both the postgres and mysql features. Populate it
+-- from the event store by running `veloxide-server rebuild-projection account_access`.
+CREATE TABLE account_access (
+  account_id VARCHAR(255) NOT NULL,
+  user_id VARCHAR(255) NOT NULL,
+  role VARCHAR(32) NOT NULL,
+  PRIMARY KEY (account_id, user_id)
+);
+CREATE INDEX account_access_user_id ON account_access (user_id);
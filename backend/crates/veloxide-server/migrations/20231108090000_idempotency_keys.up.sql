@@ -0,0 +1,12 @@
+-- The outcome of commands issued with an idempotency key, so that retrying them replays the
+-- outcome instead of executing them again. Unquoted and portable so that the same migration
+-- serves both the postgres and mysql features. The outcome is empty while the command runs.
+CREATE TABLE idempotency_keys (
+  scope VARCHAR(255) NOT NULL,
+  idempotency_key VARCHAR(255) NOT NULL,
+  request TEXT NOT NULL,
+  outcome TEXT,
+  expires_at TIMESTAMP NOT NULL,
+  PRIMARY KEY (scope, idempotency_key)
+);
+CREATE INDEX idempotency_keys_expires_at ON idempotency_keys (expires_at);
@@ -0,0 +1,15 @@
+-- Events committed on aggregates that have yet to be published to external brokers, written in
+-- the same transaction as the events themselves. Rows are deleted once published. Unquoted and
+-- portable so that the same migration serves both the postgres and mysql features.
+CREATE TABLE outbox (
+  aggregate_type VARCHAR(255) NOT NULL,
+  aggregate_id VARCHAR(255) NOT NULL,
+  sequence BIGINT NOT NULL CHECK (sequence >= 0),
+  event_type VARCHAR(255) NOT NULL,
+  event_version VARCHAR(255) NOT NULL,
+  payload JSON NOT NULL,
+  metadata JSON NOT NULL,
+  recorded_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (aggregate_type, aggregate_id, sequence)
+);
+CREATE INDEX outbox_recorded_at ON outbox (recorded_at);
@@ -0,0 +1,47 @@
+-- Webhooks partners register to be called back on bank account events, the deliveries made to
+-- them and the deliveries that ran out of retries. Unquoted and portable so that the same
+-- migration serves both the postgres and mysql features. Subscriptions without an account_id
+-- receive the events of every account their owner owns, event_types is a comma separated list
+-- that is empty to receive every event.
+CREATE TABLE webhook_subscriptions (
+  subscription_id VARCHAR(36) NOT NULL,
+  owner_id VARCHAR(255) NOT NULL,
+  account_id VARCHAR(255),
+  url TEXT NOT NULL,
+  event_types TEXT NOT NULL,
+  secret VARCHAR(255) NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (subscription_id)
+);
+CREATE INDEX webhook_subscriptions_owner_id ON webhook_subscriptions (owner_id);
+CREATE INDEX webhook_subscriptions_account_id ON webhook_subscriptions (account_id);
+
+CREATE TABLE webhook_deliveries (
+  delivery_id VARCHAR(36) NOT NULL,
+  subscription_id VARCHAR(36) NOT NULL,
+  account_id VARCHAR(255) NOT NULL,
+  sequence BIGINT NOT NULL CHECK (sequence >= 0),
+  event_type VARCHAR(255) NOT NULL,
+  payload TEXT NOT NULL,
+  status VARCHAR(32) NOT NULL,
+  attempts INT NOT NULL DEFAULT 0,
+  next_attempt_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  last_response_status INT,
+  last_error TEXT,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (delivery_id),
+  UNIQUE (subscription_id, account_id, sequence)
+);
+CREATE INDEX webhook_deliveries_due ON webhook_deliveries (status, next_attempt_at);
+
+CREATE TABLE webhook_dead_letters (
+  delivery_id VARCHAR(36) NOT NULL,
+  subscription_id VARCHAR(36) NOT NULL,
+  url TEXT NOT NULL,
+  payload TEXT NOT NULL,
+  attempts INT NOT NULL,
+  last_error TEXT NOT NULL,
+  dead_lettered_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (delivery_id)
+);
+CREATE INDEX webhook_dead_letters_subscription_id ON webhook_dead_letters (subscription_id);
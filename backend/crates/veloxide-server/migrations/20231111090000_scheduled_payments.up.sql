@@ -0,0 +1,15 @@
+-- The views of scheduled payments, along with when each is next attempted so that the worker
+-- can find the due ones. Unquoted and portable so that the same migration serves both the
+-- postgres and mysql features. next_attempt_at is null once a payment is cancelled or completed.
+CREATE TABLE scheduled_payments (
+  payment_id VARCHAR(36) NOT NULL,
+  account_id VARCHAR(255) NOT NULL,
+  status VARCHAR(32) NOT NULL,
+  next_attempt_at TIMESTAMP,
+  version BIGINT NOT NULL CHECK (version >= 0),
+  payload TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (payment_id)
+);
+CREATE INDEX scheduled_payments_account_id ON scheduled_payments (account_id);
+CREATE INDEX scheduled_payments_due ON scheduled_payments (status, next_attempt_at);
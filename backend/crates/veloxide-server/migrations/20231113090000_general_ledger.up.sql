@@ -0,0 +1,30 @@
+-- The chart of accounts and the double-entry postings each bank account event is booked as.
+-- Every event that moves money is posted as a debit and a credit of the same amount, so the
+-- debits and credits of each currency always add up to the same total. Unquoted and portable so
+-- that the same migration serves both the postgres and mysql features. Populate the postings
+-- from the event store by running `veloxide-server rebuild-projection ledger_postings`.
+CREATE TABLE ledger_accounts (
+  code VARCHAR(16) NOT NULL,
+  name VARCHAR(255) NOT NULL,
+  account_type VARCHAR(32) NOT NULL,
+  PRIMARY KEY (code)
+);
+INSERT INTO ledger_accounts (code, name, account_type) VALUES
+  ('1000', 'Cash', 'Asset'),
+  ('2000', 'Customer deposits', 'Liability'),
+  ('2100', 'Cheques clearing', 'Liability'),
+  ('2200', 'Transfers in transit', 'Liability'),
+  ('2300', 'Card settlements', 'Liability'),
+  ('5000', 'Interest expense', 'Expense');
+CREATE TABLE ledger_postings (
+  account_id VARCHAR(255) NOT NULL,
+  sequence BIGINT NOT NULL,
+  ledger_account VARCHAR(16) NOT NULL REFERENCES ledger_accounts (code),
+  debit_minor_units BIGINT NOT NULL,
+  credit_minor_units BIGINT NOT NULL,
+  currency VARCHAR(3) NOT NULL,
+  description TEXT NOT NULL,
+  occurred_at TIMESTAMP NOT NULL,
+  PRIMARY KEY (account_id, sequence, ledger_account)
+);
+CREATE INDEX ledger_postings_ledger_account ON ledger_postings (ledger_account, currency);
@@ -0,0 +1 @@
+-- The backfilled versions are left in place, they are what rebuilding account_query sets them to.
@@ -0,0 +1,11 @@
+-- Views projected before the version was recorded are at version 0, set it to the sequence
+-- number of the last event of each account.
+UPDATE "account_query"
+SET "payload" = jsonb_set("payload"::JSONB, '{version}', to_jsonb("last_events"."sequence"))::JSON
+FROM (
+  SELECT "aggregate_id", MAX("sequence") AS "sequence"
+  FROM "events"
+  WHERE "aggregate_type" = 'account'
+  GROUP BY "aggregate_id"
+) AS "last_events"
+WHERE "account_query"."view_id" = "last_events"."aggregate_id";
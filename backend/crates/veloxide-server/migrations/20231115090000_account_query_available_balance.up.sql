@@ -0,0 +1,6 @@
+-- Views projected before holds were introduced have no available balance, and would show 0 as
+-- available. No holds were placed before then, so all of the balance is available.
+UPDATE "account_query"
+SET "payload" = jsonb_set("payload"::JSONB, '{available_balance}', "payload"::JSONB -> 'balance')::JSON
+WHERE NOT ("payload"::JSONB ? 'available_balance')
+  AND "payload"::JSONB ? 'balance';
@@ -0,0 +1,9 @@
+UPDATE "account_query"
+SET "payload" = (
+    "payload"::JSONB
+    || jsonb_build_object(
+        'interest_free_buffer',
+        jsonb_build_object('minor_units', 0, 'currency', "payload"::JSONB -> 'balance' -> 'currency')
+    )
+)::JSON
+WHERE "payload"::JSONB -> 'interest_free_buffer' IS NULL;
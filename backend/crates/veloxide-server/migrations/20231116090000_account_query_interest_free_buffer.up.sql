@@ -0,0 +1,4 @@
+-- Overdrafts no longer have an interest-free buffer.
+UPDATE "account_query"
+SET "payload" = ("payload"::JSONB - 'interest_free_buffer')::JSON
+WHERE "payload"::JSONB ? 'interest_free_buffer';
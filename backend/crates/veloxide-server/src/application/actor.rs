@@ -0,0 +1,81 @@
+use crate::domain::AccessRole;
+use crate::infrastructure::grpc::auth_grpc_service::UserView;
+
+/// Who a request is made on behalf of, as resolved from the session by `mw_authenticate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Actor {
+    Anonymous,
+    User { id: String },
+}
+
+impl Actor {
+    pub fn user_id(&self) -> Option<&str> {
+        match self {
+            Actor::Anonymous => None,
+            Actor::User { id } => Some(id),
+        }
+    }
+
+    /// The role of the actor on a bank account owned by `owner_id`, given the role they were
+    /// granted on it, if any. Accounts opened before accounts had owners have none, and no user
+    /// has a role on those until an administrator assigns their owner. Anonymous actors have no
+    /// role on any account.
+    pub fn role_on(
+        &self,
+        owner_id: Option<&str>,
+        granted: Option<AccessRole>,
+    ) -> Option<AccessRole> {
+        let user_id = self.user_id()?;
+        if user_id == owner_id? {
+            Some(AccessRole::Owner)
+        } else {
+            granted
+        }
+    }
+}
+
+impl From<Option<UserView>> for Actor {
+    fn from(user: Option<UserView>) -> Self {
+        match user {
+            Some(user) => Actor::User { id: user.id },
+            None => Actor::Anonymous,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn user(id: &str) -> Actor {
+        Actor::User { id: id.to_string() }
+    }
+
+    #[test]
+    fn the_owner_has_the_owner_role() {
+        assert_eq!(
+            user("1234").role_on(Some("1234"), None),
+            Some(AccessRole::Owner)
+        );
+    }
+
+    #[test]
+    fn other_users_have_the_role_they_were_granted() {
+        assert_eq!(
+            user("5678").role_on(Some("1234"), Some(AccessRole::ViewOnly)),
+            Some(AccessRole::ViewOnly)
+        );
+        assert_eq!(user("5678").role_on(Some("1234"), None), None);
+        assert_eq!(
+            Actor::Anonymous.role_on(Some("1234"), Some(AccessRole::ViewOnly)),
+            None
+        );
+    }
+
+    #[test]
+    fn no_one_has_a_role_on_accounts_without_owner() {
+        assert_eq!(user("5678").role_on(None, None), None);
+        assert_eq!(user("5678").role_on(None, Some(AccessRole::CoOwner)), None);
+        assert_eq!(Actor::Anonymous.role_on(None, None), None);
+    }
+}
@@ -1,49 +1,330 @@
 use cqrs_es::persist::ViewRepository;
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use cqrs_es::{AggregateError, EventStore};
 use postgres_es::PostgresViewRepository;
 
-use crate::domain::BankAccount;
+use crate::application::Actor;
+use crate::domain::{AccountPermission, BankAccount, BankAccountCommand, BankAccountError};
+use crate::infrastructure::idempotency::{
+    idempotency_scope, Claim, IdempotencyError, IdempotencyKeyStore,
+};
+use crate::infrastructure::outbox::{OutboxCqrs, EXPECTED_VERSION_METADATA};
+use crate::interfaces::bank_account::account_access::{
+    AccountAccess, AccountAccessError, AccountAccessQuery,
+};
+use crate::interfaces::bank_account::account_statements::{
+    AccountStatement, AccountStatementError, AccountStatementGenerator, StatementPeriod,
+};
+use crate::interfaces::bank_account::account_transactions::{
+    page_size, AccountTransactionFilter, AccountTransactionPage, AccountTransactionsError,
+    AccountTransactionsQuery,
+};
 use crate::interfaces::bank_account::bank_account_views::BankAccountView;
+use crate::interfaces::bank_account::BankAccountEventStore;
 
 #[derive(thiserror::Error, Debug)]
 pub enum BankAccountServiceError {
     #[error("bank account not found: {0}")]
     BankAccountNotFound(String),
 
+    #[error("access to bank account {0} denied")]
+    AccessDenied(String),
+
+    #[error("opening a bank account requires an authenticated user")]
+    Unauthenticated,
+
+    #[error(transparent)]
+    CommandRejected(BankAccountError),
+
+    #[error("{0}")]
+    CommandFailed(String),
+
+    #[error("{0}")]
+    Conflict(String),
+
     #[error(transparent)]
     Persistence(#[from] cqrs_es::persist::PersistenceError),
+
+    #[error(transparent)]
+    Transactions(#[from] AccountTransactionsError),
+
+    #[error(transparent)]
+    Statements(#[from] AccountStatementError),
+
+    #[error(transparent)]
+    Access(#[from] AccountAccessError),
+
+    #[error(transparent)]
+    Idempotency(#[from] IdempotencyError),
 }
 
+/// Reads and changes bank accounts on behalf of an `Actor`, whose role on the account must
+/// permit what they ask for.
 #[async_trait]
 pub trait BankAccountApplicationService: Send + Sync {
+    /// Returns the account if the role of the actor on it grants `permission`.
+    async fn authorize(
+        &self,
+        actor: &Actor,
+        account_id: String,
+        permission: AccountPermission,
+    ) -> Result<BankAccountView, BankAccountServiceError>;
+
     async fn get_bank_account(
         &self,
+        actor: &Actor,
         account_id: String,
     ) -> Result<BankAccountView, BankAccountServiceError>;
+
+    /// Returns a page of the account's transactions, newest first, starting after the
+    /// `after` cursor of a previous page.
+    async fn get_account_transactions(
+        &self,
+        actor: &Actor,
+        account_id: String,
+        filter: AccountTransactionFilter,
+        after: Option<String>,
+        limit: Option<usize>,
+    ) -> Result<AccountTransactionPage, BankAccountServiceError>;
+
+    /// Returns the users with access to the account and their roles.
+    async fn get_account_access(
+        &self,
+        actor: &Actor,
+        account_id: String,
+    ) -> Result<Vec<AccountAccess>, BankAccountServiceError>;
+
+    /// Returns the statement of the account for a month such as "2023-11".
+    async fn get_account_statement(
+        &self,
+        actor: &Actor,
+        account_id: String,
+        period: String,
+    ) -> Result<AccountStatement, BankAccountServiceError>;
+
+    /// Executes a command on the account. Opening an account makes the actor its owner, any
+    /// other command requires the permission `AccountPermission::required_for` it. With an
+    /// `expected_version`, the command is only executed while the `BankAccountView::version`
+    /// of the account still matches it. Executing the same command again with the same
+    /// `idempotency_key` returns the original outcome instead of executing it twice.
+    async fn execute(
+        &self,
+        actor: &Actor,
+        account_id: String,
+        command: BankAccountCommand,
+        metadata: HashMap<String, String>,
+        expected_version: Option<usize>,
+        idempotency_key: Option<String>,
+    ) -> Result<(), BankAccountServiceError>;
 }
 
 pub struct BankAccountServiceImpl {
+    cqrs: Arc<OutboxCqrs<BankAccount>>,
+    event_store: BankAccountEventStore,
     view_repository: Arc<PostgresViewRepository<BankAccountView, BankAccount>>,
+    transactions_query: AccountTransactionsQuery,
+    access_query: AccountAccessQuery,
+    statement_generator: AccountStatementGenerator,
+    idempotency_key_store: IdempotencyKeyStore,
 }
 
 impl BankAccountServiceImpl {
-    pub fn new(view_repository: Arc<PostgresViewRepository<BankAccountView, BankAccount>>) -> Self {
-        Self { view_repository }
+    pub fn new(
+        cqrs: Arc<OutboxCqrs<BankAccount>>,
+        event_store: BankAccountEventStore,
+        view_repository: Arc<PostgresViewRepository<BankAccountView, BankAccount>>,
+        transactions_query: AccountTransactionsQuery,
+        access_query: AccountAccessQuery,
+        statement_generator: AccountStatementGenerator,
+        idempotency_key_store: IdempotencyKeyStore,
+    ) -> Self {
+        Self {
+            cqrs,
+            event_store,
+            view_repository,
+            transactions_query,
+            access_query,
+            statement_generator,
+            idempotency_key_store,
+        }
     }
 }
 
 #[async_trait]
 impl BankAccountApplicationService for BankAccountServiceImpl {
+    async fn authorize(
+        &self,
+        actor: &Actor,
+        account_id: String,
+        permission: AccountPermission,
+    ) -> Result<BankAccountView, BankAccountServiceError> {
+        let view = match self.view_repository.load(&account_id).await? {
+            Some(view) => view,
+            None => return Err(BankAccountServiceError::BankAccountNotFound(account_id)),
+        };
+        // Roles are taken from the events of the account rather than its projections, which are
+        // only updated after a command commits, so that revoked access is never left behind.
+        let account = self
+            .event_store
+            .load_aggregate(&account_id)
+            .await
+            .map_err(|err| command_error(&account_id, err))?
+            .aggregate;
+        let granted = actor
+            .user_id()
+            .and_then(|user_id| account.granted_role(user_id));
+        match actor.role_on(account.owner_id(), granted) {
+            Some(role) if role.permits(permission) => Ok(view),
+            _ => Err(BankAccountServiceError::AccessDenied(account_id)),
+        }
+    }
+
     async fn get_bank_account(
         &self,
+        actor: &Actor,
         account_id: String,
     ) -> Result<BankAccountView, BankAccountServiceError> {
-        let view_option = self.view_repository.load(&account_id).await?;
-        match view_option {
-            Some(view) => Ok(view),
-            None => Err(BankAccountServiceError::BankAccountNotFound(account_id)),
+        self.authorize(actor, account_id, AccountPermission::View)
+            .await
+    }
+
+    async fn get_account_transactions(
+        &self,
+        actor: &Actor,
+        account_id: String,
+        filter: AccountTransactionFilter,
+        after: Option<String>,
+        limit: Option<usize>,
+    ) -> Result<AccountTransactionPage, BankAccountServiceError> {
+        // Also tells an account without transactions apart from one that does not exist.
+        self.get_bank_account(actor, account_id.clone()).await?;
+        Ok(self
+            .transactions_query
+            .find(&account_id, &filter, after.as_deref(), page_size(limit))
+            .await?)
+    }
+
+    async fn get_account_access(
+        &self,
+        actor: &Actor,
+        account_id: String,
+    ) -> Result<Vec<AccountAccess>, BankAccountServiceError> {
+        self.get_bank_account(actor, account_id.clone()).await?;
+        Ok(self.access_query.list(&account_id).await?)
+    }
+
+    async fn get_account_statement(
+        &self,
+        actor: &Actor,
+        account_id: String,
+        period: String,
+    ) -> Result<AccountStatement, BankAccountServiceError> {
+        let period = StatementPeriod::parse(&period)?;
+        self.get_bank_account(actor, account_id.clone()).await?;
+        Ok(self
+            .statement_generator
+            .generate(&account_id, &period)
+            .await?)
+    }
+
+    #[tracing::instrument(skip(self, metadata))]
+    async fn execute(
+        &self,
+        actor: &Actor,
+        account_id: String,
+        command: BankAccountCommand,
+        mut metadata: HashMap<String, String>,
+        expected_version: Option<usize>,
+        idempotency_key: Option<String>,
+    ) -> Result<(), BankAccountServiceError> {
+        let command = match command {
+            BankAccountCommand::OpenAccount(mut open_account) => {
+                let owner_id = actor
+                    .user_id()
+                    .ok_or(BankAccountServiceError::Unauthenticated)?;
+                open_account.owner_id = Some(owner_id.to_string());
+                BankAccountCommand::OpenAccount(open_account)
+            }
+            command => {
+                let permission = AccountPermission::required_for(&command);
+                // System commands are issued through the CQRS framework directly, e.g. by the
+                // `TransferProcessManager`, and never on behalf of an actor.
+                if permission == AccountPermission::System {
+                    return Err(BankAccountServiceError::AccessDenied(account_id));
+                }
+                self.authorize(actor, account_id.clone(), permission)
+                    .await?;
+                command
+            }
+        };
+        // The expected version is checked as the events are committed, against the account the
+        // command was handled on, so no other command can slip in between. An account that is
+        // yet to be opened has no events, and so is at version 0.
+        if let Some(expected_version) = expected_version {
+            metadata.insert(
+                EXPECTED_VERSION_METADATA.to_string(),
+                expected_version.to_string(),
+            );
+        }
+        let Some(idempotency_key) = idempotency_key else {
+            return self
+                .cqrs
+                .execute_with_metadata(&account_id, command, metadata)
+                .await
+                .map_err(|err| command_error(&account_id, err));
+        };
+
+        // Keys are scoped to the user, and only ever replay the command they were first used for.
+        let scope = idempotency_scope(actor.user_id())?;
+        let request = serde_json::to_string(&(&account_id, &command, expected_version))
+            .map_err(IdempotencyError::from)?;
+        let store = &self.idempotency_key_store;
+        if let Claim::Completed(outcome) = store
+            .claim::<Result<(), BankAccountError>>(scope, &idempotency_key, &request)
+            .await?
+        {
+            return outcome.map_err(BankAccountServiceError::CommandRejected);
+        }
+        match self
+            .cqrs
+            .execute_with_metadata(&account_id, command, metadata)
+            .await
+        {
+            Ok(()) => {
+                store
+                    .complete(scope, &idempotency_key, &Ok::<(), BankAccountError>(()))
+                    .await?;
+                Ok(())
+            }
+            // The command was rejected, which it will be again when it is retried.
+            Err(AggregateError::UserError(err)) => {
+                store
+                    .complete(scope, &idempotency_key, &Err::<(), _>(&err))
+                    .await?;
+                Err(BankAccountServiceError::CommandRejected(err))
+            }
+            Err(err) => {
+                store.release(scope, &idempotency_key).await?;
+                Err(command_error(&account_id, err))
+            }
         }
     }
 }
+
+// A conflict means another command on the account was committed while this one was handled.
+fn command_error(
+    account_id: &str,
+    err: AggregateError<BankAccountError>,
+) -> BankAccountServiceError {
+    match err {
+        AggregateError::UserError(err) => BankAccountServiceError::CommandRejected(err),
+        AggregateError::AggregateConflict => BankAccountServiceError::Conflict(format!(
+            "bank account {} was changed by another command or is not at the expected version, retry with its current version",
+            account_id
+        )),
+        err => BankAccountServiceError::CommandFailed(err.to_string()),
+    }
+}
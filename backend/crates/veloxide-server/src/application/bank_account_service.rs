@@ -1,20 +1,28 @@
 #[cfg(test)]
 use mockall::{automock, predicate::*};
 
+use std::sync::Arc;
+
 use async_trait::async_trait;
 use derivative::Derivative;
 
+use crate::application::{Clock, FxError, FxRateProvider, StaticFxRateProvider};
+use crate::domain::{CurrencyConversion, Money};
+
 #[derive(Derivative)]
 #[derivative(Debug)]
 pub struct BankAccountServices {
     #[derivative(Debug = "ignore")]
     pub services: Box<dyn BankAccountApi>,
+    /// Tells the command handlers the time, so that they never read the wall clock themselves.
+    #[derivative(Debug = "ignore")]
+    pub clock: Arc<dyn Clock>,
 }
 
 #[cfg_attr(test, automock)]
 impl BankAccountServices {
-    pub fn new(services: Box<dyn BankAccountApi>) -> Self {
-        Self { services }
+    pub fn new(services: Box<dyn BankAccountApi>, clock: Arc<dyn Clock>) -> Self {
+        Self { services, clock }
     }
 }
 
@@ -22,19 +30,49 @@ impl BankAccountServices {
 #[async_trait]
 #[cfg_attr(test, automock)]
 pub trait BankAccountApi: Sync + Send {
-    async fn atm_withdrawal(&self, atm_id: &str, amount: f64) -> Result<(), AtmError>;
+    async fn atm_withdrawal(
+        &self,
+        account_id: &str,
+        atm_id: &str,
+        amount: &Money,
+    ) -> Result<(), AtmError>;
     async fn validate_check(&self, account_id: &str, check: &str) -> Result<(), CheckingError>;
+    async fn convert_currency(
+        &self,
+        amount: &Money,
+        to_currency: &str,
+    ) -> Result<CurrencyConversion, FxError>;
 }
 pub struct AtmError;
 pub struct CheckingError;
 
-// A very simple "happy path" set of services that always succeed.
-pub struct HappyPathBankAccountServices;
+// A very simple "happy path" set of services that always succeed, converting currencies
+// with whichever rates the FX provider knows about.
+pub struct HappyPathBankAccountServices {
+    fx_rate_provider: Box<dyn FxRateProvider>,
+}
+
+impl HappyPathBankAccountServices {
+    pub fn new(fx_rate_provider: Box<dyn FxRateProvider>) -> Self {
+        Self { fx_rate_provider }
+    }
+}
+
+impl Default for HappyPathBankAccountServices {
+    fn default() -> Self {
+        Self::new(Box::<StaticFxRateProvider>::default())
+    }
+}
 
 #[async_trait]
 #[cfg_attr(test, automock)]
 impl BankAccountApi for HappyPathBankAccountServices {
-    async fn atm_withdrawal(&self, _atm_id: &str, _amount: f64) -> Result<(), AtmError> {
+    async fn atm_withdrawal(
+        &self,
+        _account_id: &str,
+        _atm_id: &str,
+        _amount: &Money,
+    ) -> Result<(), AtmError> {
         Ok(())
     }
 
@@ -45,6 +83,28 @@ impl BankAccountApi for HappyPathBankAccountServices {
     ) -> Result<(), CheckingError> {
         Ok(())
     }
+
+    async fn convert_currency(
+        &self,
+        amount: &Money,
+        to_currency: &str,
+    ) -> Result<CurrencyConversion, FxError> {
+        convert_currency(self.fx_rate_provider.as_ref(), amount, to_currency).await
+    }
+}
+
+/// Converts with the rate the provider knows about, for the `BankAccountApi` implementations
+/// that do not call out to a foreign exchange service.
+pub async fn convert_currency(
+    fx_rate_provider: &dyn FxRateProvider,
+    amount: &Money,
+    to_currency: &str,
+) -> Result<CurrencyConversion, FxError> {
+    let rate = fx_rate_provider
+        .get_rate(&amount.currency, to_currency)
+        .await?;
+    let converted = rate.convert(amount)?;
+    Ok(CurrencyConversion { converted, rate })
 }
 
 #[cfg(test)]
@@ -53,15 +113,38 @@ mod tests {
 
     #[tokio::test]
     async fn happy_path_bank_account_services_atm_withdrawal_returns_ok() {
-        let services = HappyPathBankAccountServices;
-        let result = services.atm_withdrawal("123", 100.0).await;
+        let services = HappyPathBankAccountServices::default();
+        let result = services
+            .atm_withdrawal("1234", "123", &Money::new(10000, "USD"))
+            .await;
         assert!(result.is_ok());
     }
 
     #[tokio::test]
     async fn happy_path_bank_account_services_validate_check_returns_ok() {
-        let services = HappyPathBankAccountServices;
+        let services = HappyPathBankAccountServices::default();
         let result = services.validate_check("123", "123").await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn happy_path_bank_account_services_convert_currency_applies_provider_rate() {
+        let provider = StaticFxRateProvider::parse("EUR:USD=1.0853").unwrap();
+        let services = HappyPathBankAccountServices::new(Box::new(provider));
+        let result = services
+            .convert_currency(&Money::new(10000, "EUR"), "USD")
+            .await
+            .unwrap();
+        assert_eq!(result.converted, Money::new(10853, "USD"));
+        assert_eq!(result.rate.to_string(), "EUR/USD 1.0853");
+    }
+
+    #[tokio::test]
+    async fn happy_path_bank_account_services_convert_currency_without_rate_fails() {
+        let services = HappyPathBankAccountServices::default();
+        let result = services
+            .convert_currency(&Money::new(10000, "EUR"), "USD")
+            .await;
+        assert!(result.is_err());
+    }
 }
@@ -0,0 +1,136 @@
+#[cfg(test)]
+use mockall::automock;
+
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+use crate::domain::{ExchangeRate, MoneyError};
+
+pub const FX_RATES_ENV_VAR: &str = "FX_RATES";
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum FxError {
+    #[error("no exchange rate available from {from} to {to}")]
+    RateNotFound { from: String, to: String },
+
+    #[error("invalid exchange rate configuration: {0}")]
+    InvalidConfiguration(String),
+
+    #[error(transparent)]
+    Conversion(#[from] MoneyError),
+}
+
+// Provides the exchange rates used to convert foreign currency deposits.
+#[async_trait]
+#[cfg_attr(test, automock)]
+pub trait FxRateProvider: Sync + Send {
+    async fn get_rate(
+        &self,
+        from_currency: &str,
+        to_currency: &str,
+    ) -> Result<ExchangeRate, FxError>;
+}
+
+// An in-memory set of fixed rates, useful for tests and for running without a rate feed.
+#[derive(Debug, Default)]
+pub struct StaticFxRateProvider {
+    rates: HashMap<(String, String), ExchangeRate>,
+}
+
+impl StaticFxRateProvider {
+    pub fn new(rates: Vec<ExchangeRate>) -> Self {
+        Self {
+            rates: rates
+                .into_iter()
+                .map(|rate| ((rate.from_currency.clone(), rate.to_currency.clone()), rate))
+                .collect(),
+        }
+    }
+
+    /// Reads rates from the `FX_RATES` env var, formatted as `EUR:USD=1.0853,GBP:USD=1.2644`.
+    pub fn from_env() -> Result<Self, FxError> {
+        match dotenvy::var(FX_RATES_ENV_VAR) {
+            Ok(rates) => Self::parse(&rates),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn parse(rates: &str) -> Result<Self, FxError> {
+        let rates = rates
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                entry
+                    .split_once('=')
+                    .and_then(|(pair, rate)| {
+                        let (from, to) = pair.trim().split_once(':')?;
+                        ExchangeRate::parse(from.trim(), to.trim(), rate)
+                    })
+                    .ok_or_else(|| FxError::InvalidConfiguration(entry.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self::new(rates))
+    }
+}
+
+#[async_trait]
+impl FxRateProvider for StaticFxRateProvider {
+    async fn get_rate(
+        &self,
+        from_currency: &str,
+        to_currency: &str,
+    ) -> Result<ExchangeRate, FxError> {
+        self.rates
+            .get(&(from_currency.to_string(), to_currency.to_string()))
+            .cloned()
+            .ok_or_else(|| FxError::RateNotFound {
+                from: from_currency.to_string(),
+                to: to_currency.to_string(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[tokio::test]
+    async fn static_fx_rate_provider_returns_configured_rate() {
+        let provider = StaticFxRateProvider::parse("EUR:USD=1.0853, GBP:USD=1.2644").unwrap();
+        let rate = provider.get_rate("GBP", "USD").await;
+        assert_eq!(rate, Ok(ExchangeRate::new("GBP", "USD", 126_440_000)));
+    }
+
+    #[tokio::test]
+    async fn static_fx_rate_provider_returns_error_for_unknown_pair() {
+        let provider = StaticFxRateProvider::parse("EUR:USD=1.0853").unwrap();
+        let rate = provider.get_rate("USD", "EUR").await;
+        assert_eq!(
+            rate,
+            Err(FxError::RateNotFound {
+                from: "USD".to_string(),
+                to: "EUR".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn static_fx_rate_provider_rejects_malformed_configuration() {
+        let result = StaticFxRateProvider::parse("EUR-USD=1.0853");
+        assert_eq!(
+            result.unwrap_err(),
+            FxError::InvalidConfiguration("EUR-USD=1.0853".to_string())
+        );
+    }
+
+    #[test]
+    fn static_fx_rate_provider_rejects_negative_rates() {
+        let result = StaticFxRateProvider::parse("EUR:USD=1.0853, USD:EUR=-0.9214");
+        assert_eq!(
+            result.unwrap_err(),
+            FxError::InvalidConfiguration("USD:EUR=-0.9214".to_string())
+        );
+    }
+}
@@ -1,8 +1,24 @@
+pub mod actor;
 pub mod auth_service;
 pub mod bank_account_application_service;
 pub mod bank_account_service;
+pub mod clock;
+pub mod fx_rate_service;
+pub mod scheduled_payment_application_service;
+pub mod scheduled_payment_scheduler;
+pub mod transfer_application_service;
+pub mod transfer_process_manager;
+pub mod webhook_application_service;
 
 // Re-exports
+pub use actor::*;
 pub use auth_service::*;
 pub use bank_account_application_service::*;
 pub use bank_account_service::*;
+pub use clock::*;
+pub use fx_rate_service::*;
+pub use scheduled_payment_application_service::*;
+pub use scheduled_payment_scheduler::*;
+pub use transfer_application_service::*;
+pub use transfer_process_manager::*;
+pub use webhook_application_service::*;
@@ -0,0 +1,225 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use cqrs_es::AggregateError;
+use postgres_es::PostgresCqrs;
+
+use crate::application::{Actor, BankAccountApplicationService, BankAccountServiceError};
+use crate::domain::{
+    AccountPermission, CreateScheduledPaymentCommandData, ScheduledPayment,
+    ScheduledPaymentCommand, ScheduledPaymentError, UpdateScheduledPaymentCommandData,
+};
+use crate::interfaces::scheduled_payment::{
+    ScheduledPaymentQuery, ScheduledPaymentQueryError, ScheduledPaymentView,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum ScheduledPaymentServiceError {
+    #[error("scheduled payment not found: {0}")]
+    ScheduledPaymentNotFound(String),
+
+    #[error("scheduling payments requires an authenticated user")]
+    Unauthenticated,
+
+    #[error("bank account not found: {0}")]
+    BankAccountNotFound(String),
+
+    #[error("access to bank account {0} denied")]
+    AccessDenied(String),
+
+    #[error(transparent)]
+    CommandRejected(ScheduledPaymentError),
+
+    #[error("{0}")]
+    CommandFailed(String),
+
+    #[error("{0}")]
+    BankAccount(String),
+
+    #[error(transparent)]
+    Query(#[from] ScheduledPaymentQueryError),
+}
+
+impl From<BankAccountServiceError> for ScheduledPaymentServiceError {
+    fn from(err: BankAccountServiceError) -> Self {
+        match err {
+            BankAccountServiceError::BankAccountNotFound(account_id) => {
+                ScheduledPaymentServiceError::BankAccountNotFound(account_id)
+            }
+            BankAccountServiceError::AccessDenied(account_id) => {
+                ScheduledPaymentServiceError::AccessDenied(account_id)
+            }
+            err => ScheduledPaymentServiceError::BankAccount(err.to_string()),
+        }
+    }
+}
+
+impl From<AggregateError<ScheduledPaymentError>> for ScheduledPaymentServiceError {
+    fn from(err: AggregateError<ScheduledPaymentError>) -> Self {
+        match err {
+            AggregateError::UserError(err) => ScheduledPaymentServiceError::CommandRejected(err),
+            err => ScheduledPaymentServiceError::CommandFailed(err.to_string()),
+        }
+    }
+}
+
+/// Schedules deposits and withdrawals on bank accounts, which the `ScheduledPaymentScheduler`
+/// then makes on behalf of the user who created them. Users whose role on the account lets
+/// them pay can schedule, change and cancel payments, users who can view it can see them.
+#[async_trait]
+pub trait ScheduledPaymentApplicationService: Send + Sync {
+    async fn create_scheduled_payment(
+        &self,
+        actor: &Actor,
+        command: CreateScheduledPaymentCommandData,
+    ) -> Result<ScheduledPaymentView, ScheduledPaymentServiceError>;
+
+    /// Replaces the amount and schedule of the payment, which next runs at the new start.
+    async fn update_scheduled_payment(
+        &self,
+        actor: &Actor,
+        payment_id: String,
+        command: UpdateScheduledPaymentCommandData,
+    ) -> Result<ScheduledPaymentView, ScheduledPaymentServiceError>;
+
+    /// Stops the payment from running again. A run already under way still completes.
+    async fn cancel_scheduled_payment(
+        &self,
+        actor: &Actor,
+        payment_id: String,
+    ) -> Result<ScheduledPaymentView, ScheduledPaymentServiceError>;
+
+    async fn get_scheduled_payment(
+        &self,
+        actor: &Actor,
+        payment_id: String,
+    ) -> Result<ScheduledPaymentView, ScheduledPaymentServiceError>;
+
+    /// Returns the payments scheduled on the account, oldest first.
+    async fn list_scheduled_payments(
+        &self,
+        actor: &Actor,
+        account_id: String,
+    ) -> Result<Vec<ScheduledPaymentView>, ScheduledPaymentServiceError>;
+}
+
+pub struct ScheduledPaymentServiceImpl {
+    bank_account_service: Arc<dyn BankAccountApplicationService>,
+    cqrs: Arc<PostgresCqrs<ScheduledPayment>>,
+    query: ScheduledPaymentQuery,
+}
+
+impl ScheduledPaymentServiceImpl {
+    pub fn new(
+        bank_account_service: Arc<dyn BankAccountApplicationService>,
+        cqrs: Arc<PostgresCqrs<ScheduledPayment>>,
+        query: ScheduledPaymentQuery,
+    ) -> Self {
+        Self {
+            bank_account_service,
+            cqrs,
+            query,
+        }
+    }
+
+    // Returns the payment if the role of the actor on its account grants `permission`.
+    async fn authorized_payment(
+        &self,
+        actor: &Actor,
+        payment_id: String,
+        permission: AccountPermission,
+    ) -> Result<ScheduledPaymentView, ScheduledPaymentServiceError> {
+        let Some(payment) = self.query.get(&payment_id).await? else {
+            return Err(ScheduledPaymentServiceError::ScheduledPaymentNotFound(
+                payment_id,
+            ));
+        };
+        self.bank_account_service
+            .authorize(actor, payment.account_id.clone(), permission)
+            .await?;
+        Ok(payment)
+    }
+
+    async fn execute(
+        &self,
+        payment_id: String,
+        command: ScheduledPaymentCommand,
+    ) -> Result<ScheduledPaymentView, ScheduledPaymentServiceError> {
+        self.cqrs.execute(&payment_id, command).await?;
+        self.query.get(&payment_id).await?.ok_or(
+            ScheduledPaymentServiceError::ScheduledPaymentNotFound(payment_id),
+        )
+    }
+}
+
+#[async_trait]
+impl ScheduledPaymentApplicationService for ScheduledPaymentServiceImpl {
+    #[tracing::instrument(skip(self))]
+    async fn create_scheduled_payment(
+        &self,
+        actor: &Actor,
+        mut command: CreateScheduledPaymentCommandData,
+    ) -> Result<ScheduledPaymentView, ScheduledPaymentServiceError> {
+        let owner_id = actor
+            .user_id()
+            .ok_or(ScheduledPaymentServiceError::Unauthenticated)?;
+        self.bank_account_service
+            .authorize(actor, command.account_id.clone(), AccountPermission::Pay)
+            .await?;
+        command.owner_id = Some(owner_id.to_string());
+        let payment_id = uuid::Uuid::new_v4().to_string();
+        self.execute(
+            payment_id,
+            ScheduledPaymentCommand::CreateScheduledPayment(command),
+        )
+        .await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_scheduled_payment(
+        &self,
+        actor: &Actor,
+        payment_id: String,
+        command: UpdateScheduledPaymentCommandData,
+    ) -> Result<ScheduledPaymentView, ScheduledPaymentServiceError> {
+        self.authorized_payment(actor, payment_id.clone(), AccountPermission::Pay)
+            .await?;
+        self.execute(
+            payment_id,
+            ScheduledPaymentCommand::UpdateScheduledPayment(command),
+        )
+        .await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn cancel_scheduled_payment(
+        &self,
+        actor: &Actor,
+        payment_id: String,
+    ) -> Result<ScheduledPaymentView, ScheduledPaymentServiceError> {
+        self.authorized_payment(actor, payment_id.clone(), AccountPermission::Pay)
+            .await?;
+        self.execute(payment_id, ScheduledPaymentCommand::CancelScheduledPayment)
+            .await
+    }
+
+    async fn get_scheduled_payment(
+        &self,
+        actor: &Actor,
+        payment_id: String,
+    ) -> Result<ScheduledPaymentView, ScheduledPaymentServiceError> {
+        self.authorized_payment(actor, payment_id, AccountPermission::View)
+            .await
+    }
+
+    async fn list_scheduled_payments(
+        &self,
+        actor: &Actor,
+        account_id: String,
+    ) -> Result<Vec<ScheduledPaymentView>, ScheduledPaymentServiceError> {
+        self.bank_account_service
+            .authorize(actor, account_id.clone(), AccountPermission::View)
+            .await?;
+        Ok(self.query.list(&account_id).await?)
+    }
+}
@@ -0,0 +1,467 @@
+use chrono::{DateTime, Utc};
+use cqrs_es::{CqrsFramework, EventStore};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::application::{Actor, BankAccountApplicationService, BankAccountServiceError, Clock};
+use crate::domain::{ScheduledPayment, ScheduledPaymentCommand};
+use crate::infrastructure::idempotency::IdempotencyError;
+use crate::infrastructure::scheduled_payments::ScheduledPaymentConfiguration;
+use crate::interfaces::bank_account::account_statements::AccountStatementError;
+use crate::interfaces::scheduled_payment::ScheduledPaymentView;
+
+/// Makes the runs of scheduled payments by executing their command on the bank account, on
+/// behalf of the user who created the payment, and records how each run went on the
+/// `ScheduledPayment` aggregate. Runs that fail for a reason that may pass are retried with
+/// backoff, runs the account rejects are given up on until the next one.
+pub struct ScheduledPaymentScheduler<PaymentStore>
+where
+    PaymentStore: EventStore<ScheduledPayment>,
+{
+    bank_account_service: Arc<dyn BankAccountApplicationService>,
+    payments: Arc<CqrsFramework<ScheduledPayment, PaymentStore>>,
+    clock: Arc<dyn Clock>,
+    configuration: ScheduledPaymentConfiguration,
+}
+
+impl<PaymentStore> ScheduledPaymentScheduler<PaymentStore>
+where
+    PaymentStore: EventStore<ScheduledPayment>,
+{
+    pub fn new(
+        bank_account_service: Arc<dyn BankAccountApplicationService>,
+        payments: Arc<CqrsFramework<ScheduledPayment, PaymentStore>>,
+        clock: Arc<dyn Clock>,
+        configuration: ScheduledPaymentConfiguration,
+    ) -> Self {
+        Self {
+            bank_account_service,
+            payments,
+            clock,
+            configuration,
+        }
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.now()
+    }
+
+    /// Makes the run of the payment that is due.
+    #[tracing::instrument(skip(self, payment), fields(payment_id = %payment.payment_id))]
+    pub async fn run(&self, payment: &ScheduledPaymentView) {
+        let Some(run_at) = payment.run_at() else {
+            return;
+        };
+        let actor = Actor::User {
+            id: payment.owner_id.clone(),
+        };
+        let metadata = HashMap::from([(
+            "scheduled_payment_id".to_string(),
+            payment.payment_id.clone(),
+        )]);
+        // Keyed by run, so that a run whose outcome failed to be recorded is not made twice.
+        let idempotency_key = format!(
+            "scheduled-payment-{}-{}",
+            payment.payment_id,
+            run_at.timestamp()
+        );
+        let outcome = self
+            .bank_account_service
+            .execute(
+                &actor,
+                payment.account_id.clone(),
+                payment.kind.command(&payment.payment_id, &payment.amount),
+                metadata,
+                None,
+                Some(idempotency_key),
+            )
+            .await;
+
+        let now = self.clock.now();
+        let record = match outcome {
+            Ok(()) => ScheduledPaymentCommand::RecordPaymentExecution {
+                run_at,
+                executed_at: now,
+            },
+            Err(err) => {
+                let retry_at = if is_transient(&err) {
+                    self.configuration
+                        .backoff(payment.attempts + 1)
+                        .map(|backoff| now + backoff)
+                } else {
+                    None
+                };
+                if retry_at.is_none() {
+                    tracing::warn!(
+                        "giving up on the run at {} of scheduled payment {}: {}",
+                        run_at,
+                        payment.payment_id,
+                        err
+                    );
+                }
+                ScheduledPaymentCommand::RecordPaymentFailure {
+                    run_at,
+                    failed_at: now,
+                    reason: err.to_string(),
+                    retry_at,
+                }
+            }
+        };
+        if let Err(err) = self.payments.execute(&payment.payment_id, record).await {
+            tracing::error!(
+                "failed to record the run of scheduled payment {}: {}",
+                payment.payment_id,
+                err
+            );
+        }
+    }
+}
+
+// Whether the run may succeed when attempted again, without anything about it changing.
+fn is_transient(err: &BankAccountServiceError) -> bool {
+    match err {
+        BankAccountServiceError::Conflict(_)
+        | BankAccountServiceError::CommandFailed(_)
+        | BankAccountServiceError::Persistence(_)
+        | BankAccountServiceError::Transactions(_)
+        | BankAccountServiceError::Access(_) => true,
+        BankAccountServiceError::Statements(err) => {
+            matches!(err, AccountStatementError::Replay { .. })
+        }
+        BankAccountServiceError::Idempotency(err) => {
+            matches!(
+                err,
+                IdempotencyError::InProgress(_) | IdempotencyError::Sqlx(_)
+            )
+        }
+        BankAccountServiceError::BankAccountNotFound(_)
+        | BankAccountServiceError::AccessDenied(_)
+        | BankAccountServiceError::Unauthenticated
+        | BankAccountServiceError::CommandRejected(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use cqrs_es::mem_store::MemStore;
+    use cqrs_es::{AggregateError, EventEnvelope, Query, View};
+    use pretty_assertions::assert_eq;
+    use std::sync::Mutex;
+
+    use crate::application::{BankAccountServices, HappyPathBankAccountServices, ManualClock};
+    use crate::domain::{
+        parse_schedule_timestamp, AccountPermission, BankAccount, BankAccountCommand,
+        BankAccountEvent, BankAccountOpenAccountCommandData, CreateScheduledPaymentCommandData,
+        Money, PaymentFrequency, ScheduledPaymentEvent, ScheduledPaymentKind,
+    };
+    use crate::infrastructure::scheduled_payments::DEFAULT_SCHEDULED_PAYMENT_MAX_ATTEMPTS;
+    use crate::interfaces::bank_account::account_access::AccountAccess;
+    use crate::interfaces::bank_account::account_statements::AccountStatement;
+    use crate::interfaces::bank_account::account_transactions::{
+        AccountTransactionFilter, AccountTransactionPage,
+    };
+    use crate::interfaces::bank_account::bank_account_views::BankAccountView;
+
+    type BankAccountCqrs = CqrsFramework<BankAccount, MemStore<BankAccount>>;
+
+    // Executes commands straight on an in-memory bank account framework, failing the ones it
+    // is told to fail first.
+    struct InMemoryBankAccounts {
+        cqrs: BankAccountCqrs,
+        failures: Mutex<Vec<BankAccountServiceError>>,
+    }
+
+    #[async_trait]
+    impl BankAccountApplicationService for InMemoryBankAccounts {
+        // The scheduler only executes commands, so the reads answer as if there were no
+        // account to read.
+        async fn authorize(
+            &self,
+            _actor: &Actor,
+            account_id: String,
+            _permission: AccountPermission,
+        ) -> Result<BankAccountView, BankAccountServiceError> {
+            Err(BankAccountServiceError::BankAccountNotFound(account_id))
+        }
+
+        async fn get_bank_account(
+            &self,
+            _actor: &Actor,
+            account_id: String,
+        ) -> Result<BankAccountView, BankAccountServiceError> {
+            Err(BankAccountServiceError::BankAccountNotFound(account_id))
+        }
+
+        async fn get_account_transactions(
+            &self,
+            _actor: &Actor,
+            account_id: String,
+            _filter: AccountTransactionFilter,
+            _after: Option<String>,
+            _limit: Option<usize>,
+        ) -> Result<AccountTransactionPage, BankAccountServiceError> {
+            Err(BankAccountServiceError::BankAccountNotFound(account_id))
+        }
+
+        async fn get_account_access(
+            &self,
+            _actor: &Actor,
+            account_id: String,
+        ) -> Result<Vec<AccountAccess>, BankAccountServiceError> {
+            Err(BankAccountServiceError::BankAccountNotFound(account_id))
+        }
+
+        async fn get_account_statement(
+            &self,
+            _actor: &Actor,
+            account_id: String,
+            _period: String,
+        ) -> Result<AccountStatement, BankAccountServiceError> {
+            Err(BankAccountServiceError::BankAccountNotFound(account_id))
+        }
+
+        async fn execute(
+            &self,
+            _actor: &Actor,
+            account_id: String,
+            command: BankAccountCommand,
+            metadata: HashMap<String, String>,
+            _expected_version: Option<usize>,
+            _idempotency_key: Option<String>,
+        ) -> Result<(), BankAccountServiceError> {
+            if let Some(err) = self.failures.lock().unwrap().pop() {
+                return Err(err);
+            }
+            self.cqrs
+                .execute_with_metadata(&account_id, command, metadata)
+                .await
+                .map_err(|err| match err {
+                    AggregateError::UserError(err) => BankAccountServiceError::CommandRejected(err),
+                    err => BankAccountServiceError::CommandFailed(err.to_string()),
+                })
+        }
+    }
+
+    // Keeps the view of the payment up to date the way `ScheduledPaymentQuery` does, and the
+    // events it was built from.
+    #[derive(Clone, Default)]
+    struct RecordingQuery {
+        view: Arc<Mutex<ScheduledPaymentView>>,
+        events: Arc<Mutex<Vec<ScheduledPaymentEvent>>>,
+    }
+
+    #[async_trait]
+    impl Query<ScheduledPayment> for RecordingQuery {
+        async fn dispatch(&self, _aggregate_id: &str, events: &[EventEnvelope<ScheduledPayment>]) {
+            let mut view = self.view.lock().unwrap();
+            for event in events {
+                view.update(event);
+            }
+            let mut recorded = self.events.lock().unwrap();
+            recorded.extend(events.iter().map(|event| event.payload.clone()));
+        }
+    }
+
+    #[derive(Clone, Default)]
+    struct RecordingBankAccountQuery {
+        events: Arc<Mutex<Vec<BankAccountEvent>>>,
+    }
+
+    #[async_trait]
+    impl Query<BankAccount> for RecordingBankAccountQuery {
+        async fn dispatch(&self, _aggregate_id: &str, events: &[EventEnvelope<BankAccount>]) {
+            let mut recorded = self.events.lock().unwrap();
+            recorded.extend(events.iter().map(|event| event.payload.clone()));
+        }
+    }
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        parse_schedule_timestamp(timestamp).unwrap()
+    }
+
+    struct Harness {
+        bank_accounts: Arc<InMemoryBankAccounts>,
+        bank_account_events: RecordingBankAccountQuery,
+        payment: RecordingQuery,
+        clock: Arc<ManualClock>,
+        scheduler: ScheduledPaymentScheduler<MemStore<ScheduledPayment>>,
+    }
+
+    impl Harness {
+        // A monthly payment of `kind` on account 1234, first due on the 1st of December.
+        async fn new(kind: ScheduledPaymentKind) -> Self {
+            let clock = Arc::new(ManualClock::new(at("2023-12-01T09:00:01Z")));
+            let bank_account_events = RecordingBankAccountQuery::default();
+            let bank_account_queries: Vec<Box<dyn Query<BankAccount>>> =
+                vec![Box::new(bank_account_events.clone())];
+            let bank_accounts = Arc::new(InMemoryBankAccounts {
+                cqrs: CqrsFramework::new(
+                    MemStore::<BankAccount>::default(),
+                    bank_account_queries,
+                    BankAccountServices::new(
+                        Box::<HappyPathBankAccountServices>::default(),
+                        clock.clone(),
+                    ),
+                ),
+                failures: Mutex::default(),
+            });
+            let open = BankAccountCommand::OpenAccount(BankAccountOpenAccountCommandData {
+                account_id: "1234".to_string(),
+                currency: "USD".to_string(),
+                owner_id: Some("1111".to_string()),
+            });
+            bank_accounts.cqrs.execute("1234", open).await.unwrap();
+
+            let payment = RecordingQuery::default();
+            let payment_queries: Vec<Box<dyn Query<ScheduledPayment>>> =
+                vec![Box::new(payment.clone())];
+            let payments = Arc::new(CqrsFramework::new(
+                MemStore::<ScheduledPayment>::default(),
+                payment_queries,
+                (),
+            ));
+            let create = ScheduledPaymentCommand::CreateScheduledPayment(
+                CreateScheduledPaymentCommandData {
+                    account_id: "1234".to_string(),
+                    kind,
+                    amount: Money::new(5000, "USD"),
+                    frequency: PaymentFrequency::Monthly,
+                    starts_at: "2023-12-01T09:00:00Z".to_string(),
+                    ends_at: None,
+                    owner_id: Some("1111".to_string()),
+                },
+            );
+            payments.execute("7f1c", create).await.unwrap();
+
+            let scheduler = ScheduledPaymentScheduler::new(
+                bank_accounts.clone(),
+                payments,
+                clock.clone(),
+                ScheduledPaymentConfiguration::default(),
+            );
+            Self {
+                bank_accounts,
+                bank_account_events,
+                payment,
+                clock,
+                scheduler,
+            }
+        }
+
+        fn fail_next_command(&self, err: BankAccountServiceError) {
+            self.bank_accounts.failures.lock().unwrap().push(err);
+        }
+
+        async fn run(&self) {
+            let view = self.payment.view.lock().unwrap().clone();
+            self.scheduler.run(&view).await;
+        }
+
+        // The events recorded on the payment since it was created.
+        fn payment_events(&self) -> Vec<ScheduledPaymentEvent> {
+            self.payment.events.lock().unwrap()[1..].to_vec()
+        }
+    }
+
+    #[tokio::test]
+    async fn makes_the_payment_and_schedules_the_next_run() {
+        let harness = Harness::new(ScheduledPaymentKind::Deposit).await;
+
+        harness.run().await;
+
+        assert_eq!(
+            harness.payment_events(),
+            vec![ScheduledPaymentEvent::PaymentExecuted {
+                run_at: at("2023-12-01T09:00:00Z"),
+                executed_at: at("2023-12-01T09:00:01Z"),
+                next_run_at: Some(at("2024-01-01T09:00:00Z")),
+            }]
+        );
+        assert!(harness
+            .bank_account_events
+            .events
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|event| matches!(
+                event,
+                BankAccountEvent::CustomerDepositedMoney { amount, .. }
+                    if *amount == Money::new(5000, "USD")
+            )));
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures_with_backoff() {
+        let harness = Harness::new(ScheduledPaymentKind::Deposit).await;
+        harness.fail_next_command(BankAccountServiceError::Conflict(
+            "bank account 1234 was changed by another command".to_string(),
+        ));
+
+        harness.run().await;
+        harness.clock.advance(chrono::Duration::seconds(60));
+        harness.run().await;
+
+        assert_eq!(
+            harness.payment_events(),
+            vec![
+                ScheduledPaymentEvent::PaymentAttemptFailed {
+                    run_at: at("2023-12-01T09:00:00Z"),
+                    failed_at: at("2023-12-01T09:00:01Z"),
+                    reason: "bank account 1234 was changed by another command".to_string(),
+                    retry_at: at("2023-12-01T09:01:01Z"),
+                },
+                ScheduledPaymentEvent::PaymentExecuted {
+                    run_at: at("2023-12-01T09:00:00Z"),
+                    executed_at: at("2023-12-01T09:01:01Z"),
+                    next_run_at: Some(at("2024-01-01T09:00:00Z")),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn gives_up_on_runs_the_account_rejects() {
+        let harness = Harness::new(ScheduledPaymentKind::Withdrawal).await;
+
+        harness.run().await;
+
+        assert_eq!(
+            harness.payment_events(),
+            vec![ScheduledPaymentEvent::PaymentFailed {
+                run_at: at("2023-12-01T09:00:00Z"),
+                failed_at: at("2023-12-01T09:00:01Z"),
+                reason: "insufficient funds".to_string(),
+                next_run_at: Some(at("2024-01-01T09:00:00Z")),
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn gives_up_once_retries_run_out() {
+        let harness = Harness::new(ScheduledPaymentKind::Deposit).await;
+        for _ in 0..DEFAULT_SCHEDULED_PAYMENT_MAX_ATTEMPTS {
+            harness.fail_next_command(BankAccountServiceError::CommandFailed(
+                "database unavailable".to_string(),
+            ));
+        }
+
+        for _ in 0..DEFAULT_SCHEDULED_PAYMENT_MAX_ATTEMPTS {
+            harness.run().await;
+            harness.clock.advance(chrono::Duration::hours(1));
+        }
+
+        let events = harness.payment_events();
+        assert_eq!(events.len() as u32, DEFAULT_SCHEDULED_PAYMENT_MAX_ATTEMPTS);
+        assert!(matches!(
+            events.last(),
+            Some(ScheduledPaymentEvent::PaymentFailed { next_run_at, .. })
+                if *next_run_at == Some(at("2024-01-01T09:00:00Z"))
+        ));
+        // The run was never made.
+        let view = harness.payment.view.lock().unwrap().clone();
+        assert_eq!(view.last_executed_at, None);
+        assert_eq!(view.attempts, 0);
+    }
+}
@@ -0,0 +1,240 @@
+use cqrs_es::persist::ViewRepository;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use cqrs_es::AggregateError;
+use postgres_es::{PostgresCqrs, PostgresViewRepository};
+
+use crate::application::{Actor, BankAccountApplicationService, BankAccountServiceError};
+use crate::domain::{
+    AccountPermission, BankAccount, BankAccountCommand, BankAccountDebitTransferCommandData,
+    StartTransferCommandData, Transfer, TransferCommand, TransferError,
+};
+use crate::infrastructure::idempotency::{
+    idempotency_scope, Claim, IdempotencyError, IdempotencyKeyStore,
+};
+use crate::infrastructure::outbox::OutboxCqrs;
+use crate::interfaces::transfer::transfer_views::TransferView;
+
+#[derive(thiserror::Error, Debug)]
+pub enum TransferServiceError {
+    #[error("transfer not found: {0}")]
+    TransferNotFound(String),
+
+    #[error("transfer rejected: {0}")]
+    TransferRejected(String),
+
+    #[error("access denied to {0}")]
+    AccessDenied(String),
+
+    #[error(transparent)]
+    Persistence(#[from] cqrs_es::persist::PersistenceError),
+
+    #[error(transparent)]
+    Idempotency(#[from] IdempotencyError),
+}
+
+#[async_trait]
+pub trait TransferApplicationService: Send + Sync {
+    /// Starts a transfer and debits the source account, the `TransferProcessManager` then
+    /// credits the destination or refunds the source. Returns the transfer as it stands once
+    /// the saga has run, which may have failed or been compensated. Only users whose role on
+    /// the source account lets them pay can transfer money out of it. Retrying with the same
+    /// `idempotency_key` returns the transfer the first request started instead of starting
+    /// another.
+    async fn transfer_money(
+        &self,
+        actor: &Actor,
+        command: StartTransferCommandData,
+        idempotency_key: Option<String>,
+    ) -> Result<TransferView, TransferServiceError>;
+
+    /// Returns the transfer to the users who can view its source or destination account.
+    async fn get_transfer(
+        &self,
+        actor: &Actor,
+        transfer_id: String,
+    ) -> Result<TransferView, TransferServiceError>;
+}
+
+// A transfer started by a request, or by an earlier request with the same idempotency key.
+enum StartedTransfer {
+    New(String),
+    Replayed(String),
+}
+
+pub struct TransferServiceImpl {
+    bank_account_service: Arc<dyn BankAccountApplicationService>,
+    bank_account_cqrs: Arc<OutboxCqrs<BankAccount>>,
+    transfer_cqrs: Arc<PostgresCqrs<Transfer>>,
+    view_repository: Arc<PostgresViewRepository<TransferView, Transfer>>,
+    idempotency_key_store: IdempotencyKeyStore,
+}
+
+impl TransferServiceImpl {
+    pub fn new(
+        bank_account_service: Arc<dyn BankAccountApplicationService>,
+        bank_account_cqrs: Arc<OutboxCqrs<BankAccount>>,
+        transfer_cqrs: Arc<PostgresCqrs<Transfer>>,
+        view_repository: Arc<PostgresViewRepository<TransferView, Transfer>>,
+        idempotency_key_store: IdempotencyKeyStore,
+    ) -> Self {
+        Self {
+            bank_account_service,
+            bank_account_cqrs,
+            transfer_cqrs,
+            view_repository,
+            idempotency_key_store,
+        }
+    }
+
+    async fn start_transfer(
+        &self,
+        command: &StartTransferCommandData,
+    ) -> Result<String, AggregateError<TransferError>> {
+        let transfer_id = uuid::Uuid::new_v4().to_string();
+        self.transfer_cqrs
+            .execute(
+                &transfer_id,
+                TransferCommand::StartTransfer(command.clone()),
+            )
+            .await?;
+        Ok(transfer_id)
+    }
+
+    // Once the transfer is started the key replays it, whatever happens to the debit after.
+    async fn start_transfer_idempotently(
+        &self,
+        actor: &Actor,
+        command: &StartTransferCommandData,
+        idempotency_key: &str,
+    ) -> Result<StartedTransfer, TransferServiceError> {
+        let scope = idempotency_scope(actor.user_id())?;
+        let request = serde_json::to_string(command).map_err(IdempotencyError::from)?;
+        let store = &self.idempotency_key_store;
+        match store
+            .claim::<Result<String, String>>(scope, idempotency_key, &request)
+            .await?
+        {
+            Claim::Completed(Ok(transfer_id)) => return Ok(StartedTransfer::Replayed(transfer_id)),
+            Claim::Completed(Err(reason)) => {
+                return Err(TransferServiceError::TransferRejected(reason))
+            }
+            Claim::New => {}
+        }
+        match self.start_transfer(command).await {
+            Ok(transfer_id) => {
+                store
+                    .complete(scope, idempotency_key, &Ok::<_, String>(&transfer_id))
+                    .await?;
+                Ok(StartedTransfer::New(transfer_id))
+            }
+            Err(err @ AggregateError::UserError(_)) => {
+                let reason = err.to_string();
+                store
+                    .complete(scope, idempotency_key, &Err::<String, _>(&reason))
+                    .await?;
+                Err(TransferServiceError::TransferRejected(reason))
+            }
+            Err(err) => {
+                store.release(scope, idempotency_key).await?;
+                Err(TransferServiceError::TransferRejected(err.to_string()))
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl TransferApplicationService for TransferServiceImpl {
+    #[tracing::instrument(skip(self))]
+    async fn transfer_money(
+        &self,
+        actor: &Actor,
+        command: StartTransferCommandData,
+        idempotency_key: Option<String>,
+    ) -> Result<TransferView, TransferServiceError> {
+        // The debit itself is issued by the system, so the actor is checked up front.
+        self.bank_account_service
+            .authorize(
+                actor,
+                command.source_account_id.clone(),
+                AccountPermission::Pay,
+            )
+            .await
+            .map_err(|err| match err {
+                BankAccountServiceError::AccessDenied(account_id) => {
+                    TransferServiceError::AccessDenied(format!("bank account {}", account_id))
+                }
+                BankAccountServiceError::Persistence(err) => TransferServiceError::Persistence(err),
+                err => TransferServiceError::TransferRejected(err.to_string()),
+            })?;
+
+        let transfer_id = match idempotency_key {
+            None => self
+                .start_transfer(&command)
+                .await
+                .map_err(|err| TransferServiceError::TransferRejected(err.to_string()))?,
+            Some(idempotency_key) => match self
+                .start_transfer_idempotently(actor, &command, &idempotency_key)
+                .await?
+            {
+                StartedTransfer::New(transfer_id) => transfer_id,
+                StartedTransfer::Replayed(transfer_id) => {
+                    return self.get_transfer(actor, transfer_id).await
+                }
+            },
+        };
+
+        let debit = BankAccountCommand::DebitTransfer(BankAccountDebitTransferCommandData {
+            transfer_id: transfer_id.clone(),
+            destination_account_id: command.destination_account_id,
+            amount: command.amount,
+        });
+        match self
+            .bank_account_cqrs
+            .execute(&command.source_account_id, debit)
+            .await
+        {
+            Ok(()) => {}
+            Err(AggregateError::UserError(err)) => {
+                let debit_failure = TransferCommand::RecordDebitFailure {
+                    reason: err.to_string(),
+                };
+                self.transfer_cqrs
+                    .execute(&transfer_id, debit_failure)
+                    .await
+                    .map_err(|err| TransferServiceError::TransferRejected(err.to_string()))?;
+            }
+            // The debit may have been committed, so the transfer is left started for the
+            // `PendingTransferJob` to resume.
+            Err(err) => tracing::error!("failed to debit transfer {}: {}", transfer_id, err),
+        }
+
+        self.get_transfer(actor, transfer_id).await
+    }
+
+    async fn get_transfer(
+        &self,
+        actor: &Actor,
+        transfer_id: String,
+    ) -> Result<TransferView, TransferServiceError> {
+        let view = match self.view_repository.load(&transfer_id).await? {
+            Some(view) => view,
+            None => return Err(TransferServiceError::TransferNotFound(transfer_id)),
+        };
+        for account_id in [&view.source_account_id, &view.destination_account_id] {
+            if self
+                .bank_account_service
+                .get_bank_account(actor, account_id.clone())
+                .await
+                .is_ok()
+            {
+                return Ok(view);
+            }
+        }
+        Err(TransferServiceError::AccessDenied(format!(
+            "transfer {}",
+            transfer_id
+        )))
+    }
+}
@@ -0,0 +1,455 @@
+use async_trait::async_trait;
+use cqrs_es::{AggregateError, CqrsFramework, EventEnvelope, EventStore, Query};
+use std::sync::{Arc, Weak};
+
+use crate::domain::{
+    BankAccount, BankAccountCommand, BankAccountCreditTransferCommandData,
+    BankAccountDebitTransferCommandData, BankAccountError, BankAccountEvent,
+    BankAccountRefundTransferCommandData, Money, Transfer, TransferCommand, TransferStatus,
+};
+use crate::interfaces::transfer::transfer_views::TransferView;
+
+/// Drives a transfer to completion by reacting to the events of the bank accounts taking part
+/// in it: once the source is debited the destination is credited, and if that credit is
+/// rejected the source is refunded. Each step is recorded on the `Transfer` aggregate.
+///
+/// A step that fails for any other reason than being rejected leaves the transfer where it is,
+/// for `resume` to take it on from there. Every step can be repeated, as the accounts refuse to
+/// move the money of a transfer twice.
+///
+/// The process manager is registered as a query on the bank account framework it issues
+/// commands to, so it only holds a weak reference to that framework.
+pub struct TransferProcessManager<BankAccountStore, TransferStore>
+where
+    BankAccountStore: EventStore<BankAccount>,
+    TransferStore: EventStore<Transfer>,
+{
+    bank_accounts: Weak<CqrsFramework<BankAccount, BankAccountStore>>,
+    transfers: Arc<CqrsFramework<Transfer, TransferStore>>,
+}
+
+impl<BankAccountStore, TransferStore> TransferProcessManager<BankAccountStore, TransferStore>
+where
+    BankAccountStore: EventStore<BankAccount>,
+    TransferStore: EventStore<Transfer>,
+{
+    pub fn new(
+        bank_accounts: Weak<CqrsFramework<BankAccount, BankAccountStore>>,
+        transfers: Arc<CqrsFramework<Transfer, TransferStore>>,
+    ) -> Self {
+        Self {
+            bank_accounts,
+            transfers,
+        }
+    }
+
+    /// Takes a transfer that stopped part way, such as when the server restarted or a step
+    /// failed transiently, on to its next step. A transfer that was started but not debited has
+    /// its debit issued again.
+    pub async fn resume(&self, transfer: &TransferView) {
+        let transfer_id = &transfer.transfer_id;
+        let source_account_id = &transfer.source_account_id;
+        let destination_account_id = &transfer.destination_account_id;
+        let amount = &transfer.amount;
+        match transfer.status {
+            TransferStatus::Started => {
+                self.debit(
+                    source_account_id,
+                    transfer_id,
+                    destination_account_id,
+                    amount,
+                )
+                .await
+            }
+            TransferStatus::SourceDebited => {
+                self.credit(
+                    source_account_id,
+                    transfer_id,
+                    destination_account_id,
+                    amount,
+                )
+                .await
+            }
+            TransferStatus::Compensating => {
+                self.refund(source_account_id, transfer_id, amount).await
+            }
+            TransferStatus::NotStarted
+            | TransferStatus::Completed
+            | TransferStatus::Compensated
+            | TransferStatus::Failed => {}
+        }
+    }
+
+    async fn debit(
+        &self,
+        source_account_id: &str,
+        transfer_id: &str,
+        destination_account_id: &str,
+        amount: &Money,
+    ) {
+        let Some(bank_accounts) = self.bank_accounts(transfer_id) else {
+            return;
+        };
+        let debit = BankAccountCommand::DebitTransfer(BankAccountDebitTransferCommandData {
+            transfer_id: transfer_id.to_string(),
+            destination_account_id: destination_account_id.to_string(),
+            amount: amount.clone(),
+        });
+        match bank_accounts.execute(source_account_id, debit).await {
+            // Credited as the debit is dispatched to the process manager.
+            Ok(()) => {}
+            // Debited before the saga stopped, so it carries on from there.
+            Err(AggregateError::UserError(BankAccountError::TransferAlreadyApplied)) => {
+                self.on_transfer_debited(
+                    source_account_id,
+                    transfer_id,
+                    destination_account_id,
+                    amount,
+                )
+                .await
+            }
+            Err(AggregateError::UserError(err)) => {
+                let debit_failure = TransferCommand::RecordDebitFailure {
+                    reason: err.to_string(),
+                };
+                self.record(transfer_id, debit_failure).await;
+            }
+            Err(err) => tracing::error!("failed to debit transfer {}: {}", transfer_id, err),
+        }
+    }
+
+    async fn on_transfer_debited(
+        &self,
+        source_account_id: &str,
+        transfer_id: &str,
+        destination_account_id: &str,
+        amount: &Money,
+    ) {
+        if !self.record(transfer_id, TransferCommand::RecordDebit).await {
+            return;
+        }
+        self.credit(
+            source_account_id,
+            transfer_id,
+            destination_account_id,
+            amount,
+        )
+        .await;
+    }
+
+    async fn credit(
+        &self,
+        source_account_id: &str,
+        transfer_id: &str,
+        destination_account_id: &str,
+        amount: &Money,
+    ) {
+        let Some(bank_accounts) = self.bank_accounts(transfer_id) else {
+            return;
+        };
+        let credit = BankAccountCommand::CreditTransfer(BankAccountCreditTransferCommandData {
+            transfer_id: transfer_id.to_string(),
+            source_account_id: source_account_id.to_string(),
+            amount: amount.clone(),
+        });
+        match bank_accounts.execute(destination_account_id, credit).await {
+            Ok(()) => {}
+            Err(AggregateError::UserError(BankAccountError::TransferAlreadyApplied)) => {
+                self.record(transfer_id, TransferCommand::RecordCredit)
+                    .await;
+            }
+            Err(AggregateError::UserError(err)) => {
+                tracing::warn!("transfer {} could not be credited: {}", transfer_id, err);
+                let credit_failure = TransferCommand::RecordCreditFailure {
+                    reason: err.to_string(),
+                };
+                if self.record(transfer_id, credit_failure).await {
+                    self.refund(source_account_id, transfer_id, amount).await;
+                }
+            }
+            Err(err) => tracing::error!("failed to credit transfer {}: {}", transfer_id, err),
+        }
+    }
+
+    async fn refund(&self, source_account_id: &str, transfer_id: &str, amount: &Money) {
+        let Some(bank_accounts) = self.bank_accounts(transfer_id) else {
+            return;
+        };
+        let refund = BankAccountCommand::RefundTransfer(BankAccountRefundTransferCommandData {
+            transfer_id: transfer_id.to_string(),
+            amount: amount.clone(),
+        });
+        match bank_accounts.execute(source_account_id, refund).await {
+            Ok(()) => {}
+            Err(AggregateError::UserError(BankAccountError::TransferAlreadyApplied)) => {
+                self.record(transfer_id, TransferCommand::RecordCompensation)
+                    .await;
+            }
+            Err(err) => tracing::error!("failed to refund transfer {}: {}", transfer_id, err),
+        }
+    }
+
+    fn bank_accounts(
+        &self,
+        transfer_id: &str,
+    ) -> Option<Arc<CqrsFramework<BankAccount, BankAccountStore>>> {
+        let bank_accounts = self.bank_accounts.upgrade();
+        if bank_accounts.is_none() {
+            tracing::error!(
+                "bank account framework dropped during transfer {}",
+                transfer_id
+            );
+        }
+        bank_accounts
+    }
+
+    // Returns whether the command was accepted so that the saga only proceeds from a known state.
+    async fn record(&self, transfer_id: &str, command: TransferCommand) -> bool {
+        match self.transfers.execute(transfer_id, command).await {
+            Ok(_) => true,
+            Err(err) => {
+                tracing::error!("failed to update transfer {}: {}", transfer_id, err);
+                false
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<BankAccountStore, TransferStore> Query<BankAccount>
+    for TransferProcessManager<BankAccountStore, TransferStore>
+where
+    BankAccountStore: EventStore<BankAccount>,
+    TransferStore: EventStore<Transfer>,
+{
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<BankAccount>]) {
+        for event in events {
+            match &event.payload {
+                BankAccountEvent::TransferDebited {
+                    transfer_id,
+                    destination_account_id,
+                    amount,
+                    ..
+                } => {
+                    self.on_transfer_debited(
+                        aggregate_id,
+                        transfer_id,
+                        destination_account_id,
+                        amount,
+                    )
+                    .await;
+                }
+                BankAccountEvent::TransferCredited { transfer_id, .. } => {
+                    self.record(transfer_id, TransferCommand::RecordCredit)
+                        .await;
+                }
+                BankAccountEvent::TransferRefunded { transfer_id, .. } => {
+                    self.record(transfer_id, TransferCommand::RecordCompensation)
+                        .await;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cqrs_es::mem_store::MemStore;
+    use pretty_assertions::assert_eq;
+    use std::sync::Mutex;
+
+    use crate::application::{BankAccountServices, HappyPathBankAccountServices, SystemClock};
+    use crate::domain::{
+        BankAccountDebitTransferCommandData, BankAccountDepositMoneyCommandData,
+        BankAccountOpenAccountCommandData, StartTransferCommandData, TransferEvent,
+    };
+
+    // Captures the transfer events so the progress of the saga can be asserted on.
+    #[derive(Clone, Default)]
+    struct RecordingQuery {
+        events: Arc<Mutex<Vec<TransferEvent>>>,
+    }
+
+    #[async_trait]
+    impl Query<Transfer> for RecordingQuery {
+        async fn dispatch(&self, _aggregate_id: &str, events: &[EventEnvelope<Transfer>]) {
+            let mut recorded = self.events.lock().unwrap();
+            recorded.extend(events.iter().map(|event| event.payload.clone()));
+        }
+    }
+
+    type BankAccountCqrs = CqrsFramework<BankAccount, MemStore<BankAccount>>;
+
+    struct Saga {
+        bank_accounts: Arc<BankAccountCqrs>,
+        transfers: Arc<CqrsFramework<Transfer, MemStore<Transfer>>>,
+        transfer_events: RecordingQuery,
+    }
+
+    impl Saga {
+        fn new() -> Self {
+            let transfer_events = RecordingQuery::default();
+            let transfer_queries: Vec<Box<dyn Query<Transfer>>> =
+                vec![Box::new(transfer_events.clone())];
+            let transfers = Arc::new(CqrsFramework::new(
+                MemStore::<Transfer>::default(),
+                transfer_queries,
+                (),
+            ));
+            let bank_accounts = Arc::new_cyclic(|bank_accounts: &Weak<BankAccountCqrs>| {
+                let queries: Vec<Box<dyn Query<BankAccount>>> = vec![Box::new(
+                    TransferProcessManager::new(bank_accounts.clone(), transfers.clone()),
+                )];
+                CqrsFramework::new(
+                    MemStore::<BankAccount>::default(),
+                    queries,
+                    BankAccountServices::new(
+                        Box::<HappyPathBankAccountServices>::default(),
+                        Arc::new(SystemClock),
+                    ),
+                )
+            });
+            Self {
+                bank_accounts,
+                transfers,
+                transfer_events,
+            }
+        }
+
+        async fn open_account(&self, account_id: &str, deposit: i64) {
+            let open = BankAccountCommand::OpenAccount(BankAccountOpenAccountCommandData {
+                account_id: account_id.to_string(),
+                currency: "USD".to_string(),
+                owner_id: None,
+            });
+            self.bank_accounts.execute(account_id, open).await.unwrap();
+            let deposit = BankAccountCommand::DepositMoney(BankAccountDepositMoneyCommandData {
+                amount: Money::new(deposit, "USD"),
+            });
+            self.bank_accounts
+                .execute(account_id, deposit)
+                .await
+                .unwrap();
+        }
+
+        async fn transfer(&self, source_account_id: &str, destination_account_id: &str) {
+            self.start(source_account_id, destination_account_id).await;
+            self.debit(source_account_id, destination_account_id).await;
+        }
+
+        async fn start(&self, source_account_id: &str, destination_account_id: &str) {
+            let start = TransferCommand::StartTransfer(StartTransferCommandData {
+                source_account_id: source_account_id.to_string(),
+                destination_account_id: destination_account_id.to_string(),
+                amount: Money::new(5000, "USD"),
+            });
+            self.transfers.execute("T-1", start).await.unwrap();
+        }
+
+        async fn debit(&self, source_account_id: &str, destination_account_id: &str) {
+            let debit = BankAccountCommand::DebitTransfer(BankAccountDebitTransferCommandData {
+                transfer_id: "T-1".to_string(),
+                destination_account_id: destination_account_id.to_string(),
+                amount: Money::new(5000, "USD"),
+            });
+            self.bank_accounts
+                .execute(source_account_id, debit)
+                .await
+                .unwrap();
+        }
+
+        async fn resume(&self, status: TransferStatus) {
+            let process_manager = TransferProcessManager::new(
+                Arc::downgrade(&self.bank_accounts),
+                self.transfers.clone(),
+            );
+            process_manager
+                .resume(&TransferView {
+                    transfer_id: "T-1".to_string(),
+                    source_account_id: "1234".to_string(),
+                    destination_account_id: "5678".to_string(),
+                    amount: Money::new(5000, "USD"),
+                    status,
+                    failure_reason: None,
+                })
+                .await;
+        }
+
+        fn transfer_events(&self) -> Vec<TransferEvent> {
+            self.transfer_events.events.lock().unwrap().clone()
+        }
+    }
+
+    fn transfer_started(destination_account_id: &str) -> TransferEvent {
+        TransferEvent::TransferStarted {
+            source_account_id: "1234".to_string(),
+            destination_account_id: destination_account_id.to_string(),
+            amount: Money::new(5000, "USD"),
+        }
+    }
+
+    #[tokio::test]
+    async fn credits_the_destination_once_the_source_is_debited() {
+        let saga = Saga::new();
+        saga.open_account("1234", 20000).await;
+        saga.open_account("5678", 0).await;
+
+        saga.transfer("1234", "5678").await;
+
+        assert_eq!(
+            saga.transfer_events(),
+            vec![
+                transfer_started("5678"),
+                TransferEvent::SourceAccountDebited,
+                TransferEvent::DestinationAccountCredited,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn resumes_a_transfer_that_stopped_once_the_source_was_debited() {
+        let saga = Saga::new();
+        saga.open_account("1234", 20000).await;
+        saga.open_account("5678", 0).await;
+
+        // Debited before the transfer was started, so the debit cannot be recorded on it.
+        saga.debit("1234", "5678").await;
+        saga.start("1234", "5678").await;
+        assert_eq!(saga.transfer_events(), vec![transfer_started("5678")]);
+
+        saga.resume(TransferStatus::Started).await;
+        // Resuming a step again neither moves the money twice nor changes the transfer.
+        saga.resume(TransferStatus::SourceDebited).await;
+
+        assert_eq!(
+            saga.transfer_events(),
+            vec![
+                transfer_started("5678"),
+                TransferEvent::SourceAccountDebited,
+                TransferEvent::DestinationAccountCredited,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn refunds_the_source_when_the_destination_cannot_be_credited() {
+        let saga = Saga::new();
+        saga.open_account("1234", 20000).await;
+
+        saga.transfer("1234", "does-not-exist").await;
+
+        assert_eq!(
+            saga.transfer_events(),
+            vec![
+                transfer_started("does-not-exist"),
+                TransferEvent::SourceAccountDebited,
+                TransferEvent::DestinationAccountCreditFailed {
+                    reason: "account not open".to_string(),
+                },
+                TransferEvent::TransferCompensated,
+            ]
+        );
+    }
+}
@@ -0,0 +1,330 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::application::{Actor, BankAccountApplicationService, BankAccountServiceError};
+use crate::domain::{AccountPermission, BANK_ACCOUNT_EVENT_TYPES};
+use crate::infrastructure::webhooks::{ensure_public_destination, is_public_address};
+use crate::interfaces::bank_account::account_transactions::page_size;
+use crate::interfaces::webhooks::{
+    WebhookDelivery, WebhookError, WebhookStore, WebhookSubscription, WebhookSubscriptionRequest,
+};
+
+#[derive(thiserror::Error, Debug)]
+pub enum WebhookServiceError {
+    #[error("webhook not found: {0}")]
+    WebhookNotFound(String),
+
+    #[error("registering webhooks requires an authenticated user")]
+    Unauthenticated,
+
+    #[error("bank account not found: {0}")]
+    BankAccountNotFound(String),
+
+    #[error("access to bank account {0} denied")]
+    AccessDenied(String),
+
+    #[error("invalid webhook: {0}")]
+    InvalidWebhook(String),
+
+    #[error("{0}")]
+    BankAccount(String),
+
+    #[error(transparent)]
+    Store(#[from] WebhookError),
+}
+
+impl From<BankAccountServiceError> for WebhookServiceError {
+    fn from(err: BankAccountServiceError) -> Self {
+        match err {
+            BankAccountServiceError::BankAccountNotFound(account_id) => {
+                WebhookServiceError::BankAccountNotFound(account_id)
+            }
+            BankAccountServiceError::AccessDenied(account_id) => {
+                WebhookServiceError::AccessDenied(account_id)
+            }
+            err => WebhookServiceError::BankAccount(err.to_string()),
+        }
+    }
+}
+
+/// Registers the webhooks of the signed in user. A webhook is called back with the events of a
+/// single account the user can view, or with the events of every account the user owns.
+#[async_trait]
+pub trait WebhookApplicationService: Send + Sync {
+    async fn register_webhook(
+        &self,
+        actor: &Actor,
+        request: WebhookSubscriptionRequest,
+    ) -> Result<WebhookSubscription, WebhookServiceError>;
+
+    /// Changes the account, URL and event types of the webhook, keeping its secret.
+    async fn update_webhook(
+        &self,
+        actor: &Actor,
+        subscription_id: String,
+        request: WebhookSubscriptionRequest,
+    ) -> Result<WebhookSubscription, WebhookServiceError>;
+
+    async fn get_webhook(
+        &self,
+        actor: &Actor,
+        subscription_id: String,
+    ) -> Result<WebhookSubscription, WebhookServiceError>;
+
+    async fn list_webhooks(
+        &self,
+        actor: &Actor,
+    ) -> Result<Vec<WebhookSubscription>, WebhookServiceError>;
+
+    /// Stops calling the webhook back, including the deliveries still pending.
+    async fn delete_webhook(
+        &self,
+        actor: &Actor,
+        subscription_id: String,
+    ) -> Result<(), WebhookServiceError>;
+
+    /// Returns the most recent deliveries to the webhook, newest first.
+    async fn get_webhook_deliveries(
+        &self,
+        actor: &Actor,
+        subscription_id: String,
+        limit: Option<usize>,
+    ) -> Result<Vec<WebhookDelivery>, WebhookServiceError>;
+}
+
+pub struct WebhookServiceImpl {
+    bank_account_service: Arc<dyn BankAccountApplicationService>,
+    store: WebhookStore,
+}
+
+impl WebhookServiceImpl {
+    pub fn new(
+        bank_account_service: Arc<dyn BankAccountApplicationService>,
+        store: WebhookStore,
+    ) -> Self {
+        Self {
+            bank_account_service,
+            store,
+        }
+    }
+
+    // Webhooks of other users are reported as not found, so that their ids are not revealed.
+    async fn owned_webhook(
+        &self,
+        actor: &Actor,
+        subscription_id: String,
+    ) -> Result<WebhookSubscription, WebhookServiceError> {
+        let owner_id = actor
+            .user_id()
+            .ok_or(WebhookServiceError::Unauthenticated)?;
+        match self.store.get(&subscription_id).await? {
+            Some(subscription) if subscription.owner_id == owner_id => Ok(subscription),
+            _ => Err(WebhookServiceError::WebhookNotFound(subscription_id)),
+        }
+    }
+
+    async fn authorize(
+        &self,
+        actor: &Actor,
+        request: &WebhookSubscriptionRequest,
+    ) -> Result<(), WebhookServiceError> {
+        validate_request(request)?;
+        ensure_public_destination(&request.url)
+            .await
+            .map_err(WebhookServiceError::InvalidWebhook)?;
+        if let Some(account_id) = &request.account_id {
+            self.bank_account_service
+                .authorize(actor, account_id.clone(), AccountPermission::View)
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+fn validate_request(request: &WebhookSubscriptionRequest) -> Result<(), WebhookServiceError> {
+    let url = url::Url::parse(&request.url)
+        .map_err(|_| WebhookServiceError::InvalidWebhook(format!("invalid URL {}", request.url)))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(WebhookServiceError::InvalidWebhook(format!(
+            "{} is not an http or https URL",
+            request.url
+        )));
+    }
+    // Hosts are resolved and checked again on every delivery, this only catches the obvious.
+    let private_host = match url.host() {
+        Some(url::Host::Ipv4(ip)) => !is_public_address(ip.into()),
+        Some(url::Host::Ipv6(ip)) => !is_public_address(ip.into()),
+        Some(url::Host::Domain(domain)) => domain.eq_ignore_ascii_case("localhost"),
+        None => true,
+    };
+    if private_host {
+        return Err(WebhookServiceError::InvalidWebhook(format!(
+            "{} is not a public URL",
+            request.url
+        )));
+    }
+    if let Some(event_type) = request
+        .event_types
+        .iter()
+        .find(|event_type| !BANK_ACCOUNT_EVENT_TYPES.contains(&event_type.as_str()))
+    {
+        return Err(WebhookServiceError::InvalidWebhook(format!(
+            "unknown event type {}",
+            event_type
+        )));
+    }
+    Ok(())
+}
+
+// 244 random bits, the 122 of each UUID, which receivers verify the signatures of deliveries with.
+fn new_secret() -> String {
+    format!(
+        "whsec_{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+#[async_trait]
+impl WebhookApplicationService for WebhookServiceImpl {
+    #[tracing::instrument(skip(self))]
+    async fn register_webhook(
+        &self,
+        actor: &Actor,
+        request: WebhookSubscriptionRequest,
+    ) -> Result<WebhookSubscription, WebhookServiceError> {
+        let owner_id = actor
+            .user_id()
+            .ok_or(WebhookServiceError::Unauthenticated)?;
+        self.authorize(actor, &request).await?;
+        Ok(self.store.create(owner_id, request, new_secret()).await?)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn update_webhook(
+        &self,
+        actor: &Actor,
+        subscription_id: String,
+        request: WebhookSubscriptionRequest,
+    ) -> Result<WebhookSubscription, WebhookServiceError> {
+        let subscription = self.owned_webhook(actor, subscription_id).await?;
+        self.authorize(actor, &request).await?;
+        let subscription = WebhookSubscription {
+            account_id: request.account_id,
+            url: request.url,
+            event_types: request.event_types,
+            ..subscription
+        };
+        self.store.update(&subscription).await?;
+        Ok(subscription)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_webhook(
+        &self,
+        actor: &Actor,
+        subscription_id: String,
+    ) -> Result<WebhookSubscription, WebhookServiceError> {
+        self.owned_webhook(actor, subscription_id).await
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn list_webhooks(
+        &self,
+        actor: &Actor,
+    ) -> Result<Vec<WebhookSubscription>, WebhookServiceError> {
+        let owner_id = actor
+            .user_id()
+            .ok_or(WebhookServiceError::Unauthenticated)?;
+        Ok(self.store.list(owner_id).await?)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn delete_webhook(
+        &self,
+        actor: &Actor,
+        subscription_id: String,
+    ) -> Result<(), WebhookServiceError> {
+        let subscription = self.owned_webhook(actor, subscription_id).await?;
+        Ok(self.store.delete(&subscription.subscription_id).await?)
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_webhook_deliveries(
+        &self,
+        actor: &Actor,
+        subscription_id: String,
+        limit: Option<usize>,
+    ) -> Result<Vec<WebhookDelivery>, WebhookServiceError> {
+        let subscription = self.owned_webhook(actor, subscription_id).await?;
+        Ok(self
+            .store
+            .deliveries(&subscription.subscription_id, page_size(limit))
+            .await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(url: &str, event_types: &[&str]) -> WebhookSubscriptionRequest {
+        WebhookSubscriptionRequest {
+            url: url.to_string(),
+            account_id: None,
+            event_types: event_types
+                .iter()
+                .map(|event_type| event_type.to_string())
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn accepts_http_urls_and_known_event_types() {
+        assert!(validate_request(&request(
+            "https://partner.example.com/hooks",
+            &["CustomerDepositedMoney"]
+        ))
+        .is_ok());
+        assert!(validate_request(&request("http://93.184.216.34/hooks", &[])).is_ok());
+    }
+
+    #[test]
+    fn rejects_other_urls() {
+        assert!(matches!(
+            validate_request(&request("partner.example.com", &[])),
+            Err(WebhookServiceError::InvalidWebhook(_))
+        ));
+        assert!(matches!(
+            validate_request(&request("ftp://partner.example.com/hooks", &[])),
+            Err(WebhookServiceError::InvalidWebhook(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_urls_of_private_hosts() {
+        for url in [
+            "http://localhost:9000/hooks",
+            "http://127.0.0.1:9000/hooks",
+            "http://169.254.169.254/latest/meta-data",
+            "http://[::1]/hooks",
+        ] {
+            assert!(matches!(
+                validate_request(&request(url, &[])),
+                Err(WebhookServiceError::InvalidWebhook(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_event_types() {
+        assert!(matches!(
+            validate_request(&request(
+                "https://partner.example.com/hooks",
+                &["MoneyPrinted"]
+            )),
+            Err(WebhookServiceError::InvalidWebhook(_))
+        ));
+    }
+}
@@ -0,0 +1,231 @@
+#[cfg(feature = "graphql")]
+use async_graphql::Enum;
+
+#[cfg(feature = "frontend")]
+use ts_rs::TS;
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::str::FromStr;
+
+use super::{BankAccountCommand, BankAccountError};
+
+/// The part a user plays on a bank account. Whoever opens the account is its owner, every
+/// other user is granted one of the remaining roles by the owner or a co-owner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "graphql", derive(Enum))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub enum AccessRole {
+    Owner,
+    CoOwner,
+    ViewOnly,
+    PaymentsOnly,
+}
+
+/// What a request needs to be allowed to do with a bank account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountPermission {
+    /// See the account and its transactions
+    View,
+    /// Move money in or out of the account
+    Pay,
+    /// Change the account itself: who may access it, its overdraft limit, freezing and closing it
+    Manage,
+    /// Issued only by the server itself, never on behalf of a user, so no role is granted it
+    System,
+}
+
+impl AccessRole {
+    /// Payments-only users can see the account they pay from, but not change it. No role permits
+    /// system commands.
+    pub fn permits(&self, permission: AccountPermission) -> bool {
+        match self {
+            AccessRole::Owner | AccessRole::CoOwner => permission != AccountPermission::System,
+            AccessRole::ViewOnly => permission == AccountPermission::View,
+            AccessRole::PaymentsOnly => {
+                matches!(permission, AccountPermission::View | AccountPermission::Pay)
+            }
+        }
+    }
+}
+
+impl AccountPermission {
+    /// The permission needed to issue the command on an existing account.
+    pub fn required_for(command: &BankAccountCommand) -> Self {
+        match command {
+            BankAccountCommand::DepositMoney(_)
+            | BankAccountCommand::WithdrawMoney(_)
+            | BankAccountCommand::WriteCheck(_)
+            | BankAccountCommand::CancelCheck(_)
+            | BankAccountCommand::PlaceHold(_) => AccountPermission::Pay,
+            // Transfers are debited by the `TransferApplicationService` once it has started the
+            // transfer, and credited and refunded by the `TransferProcessManager`. Interest is
+            // accrued by the `InterestJob` and holds are expired by the `HoldExpiryJob`. Holds are
+            // captured and released by whoever settles the payment they were placed for, never
+            // by the holders of the account. Owners are assigned by administrators.
+            BankAccountCommand::DebitTransfer(_)
+            | BankAccountCommand::CreditTransfer(_)
+            | BankAccountCommand::RefundTransfer(_)
+            | BankAccountCommand::AccrueInterest(_)
+            | BankAccountCommand::ReleaseHold(_)
+            | BankAccountCommand::CaptureHold(_)
+            | BankAccountCommand::ExpireHolds(_)
+            | BankAccountCommand::AssignOwner(_) => AccountPermission::System,
+            BankAccountCommand::OpenAccount(_)
+            | BankAccountCommand::FreezeAccount(_)
+            | BankAccountCommand::UnfreezeAccount(_)
+            | BankAccountCommand::CloseAccount(_)
+            | BankAccountCommand::SetOverdraftLimit(_)
+            | BankAccountCommand::GrantAccess(_)
+            | BankAccountCommand::RevokeAccess(_) => AccountPermission::Manage,
+        }
+    }
+}
+
+impl Display for AccessRole {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> core::result::Result<(), std::fmt::Error> {
+        let name = match self {
+            AccessRole::Owner => "Owner",
+            AccessRole::CoOwner => "CoOwner",
+            AccessRole::ViewOnly => "ViewOnly",
+            AccessRole::PaymentsOnly => "PaymentsOnly",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for AccessRole {
+    type Err = BankAccountError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "Owner" => Ok(AccessRole::Owner),
+            "CoOwner" => Ok(AccessRole::CoOwner),
+            "ViewOnly" => Ok(AccessRole::ViewOnly),
+            "PaymentsOnly" => Ok(AccessRole::PaymentsOnly),
+            _ => Err(BankAccountError::InvalidAccessRole),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        BankAccountAssignOwnerCommandData, BankAccountCreditTransferCommandData,
+        BankAccountDebitTransferCommandData, BankAccountRevokeAccessCommandData,
+        BankAccountWithdrawMoneyCommandData, Money,
+    };
+    use coverage_helper::test;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn owners_and_co_owners_may_do_anything_users_may() {
+        for role in [AccessRole::Owner, AccessRole::CoOwner] {
+            assert!(role.permits(AccountPermission::View));
+            assert!(role.permits(AccountPermission::Pay));
+            assert!(role.permits(AccountPermission::Manage));
+        }
+    }
+
+    #[test]
+    fn no_role_may_issue_system_commands() {
+        for role in [
+            AccessRole::Owner,
+            AccessRole::CoOwner,
+            AccessRole::ViewOnly,
+            AccessRole::PaymentsOnly,
+        ] {
+            assert!(!role.permits(AccountPermission::System));
+        }
+    }
+
+    #[test]
+    fn view_only_users_may_only_view() {
+        assert!(AccessRole::ViewOnly.permits(AccountPermission::View));
+        assert!(!AccessRole::ViewOnly.permits(AccountPermission::Pay));
+        assert!(!AccessRole::ViewOnly.permits(AccountPermission::Manage));
+    }
+
+    #[test]
+    fn payments_only_users_may_pay_but_not_manage() {
+        assert!(AccessRole::PaymentsOnly.permits(AccountPermission::View));
+        assert!(AccessRole::PaymentsOnly.permits(AccountPermission::Pay));
+        assert!(!AccessRole::PaymentsOnly.permits(AccountPermission::Manage));
+    }
+
+    #[test]
+    fn moving_money_requires_pay_and_changing_the_account_requires_manage() {
+        let withdraw = BankAccountCommand::WithdrawMoney(BankAccountWithdrawMoneyCommandData {
+            amount: Money::new(100, "USD"),
+            atm_id: "ATM34f1ba3c".to_string(),
+        });
+        assert_eq!(
+            AccountPermission::required_for(&withdraw),
+            AccountPermission::Pay
+        );
+
+        let revoke = BankAccountCommand::RevokeAccess(BankAccountRevokeAccessCommandData {
+            user_id: "2222".to_string(),
+        });
+        assert_eq!(
+            AccountPermission::required_for(&revoke),
+            AccountPermission::Manage
+        );
+    }
+
+    #[test]
+    fn moving_money_for_a_transfer_is_left_to_the_system() {
+        let debit = BankAccountCommand::DebitTransfer(BankAccountDebitTransferCommandData {
+            transfer_id: "9b0c7a4e".to_string(),
+            destination_account_id: "2222".to_string(),
+            amount: Money::new(100, "USD"),
+        });
+        assert_eq!(
+            AccountPermission::required_for(&debit),
+            AccountPermission::System
+        );
+
+        let credit = BankAccountCommand::CreditTransfer(BankAccountCreditTransferCommandData {
+            transfer_id: "9b0c7a4e".to_string(),
+            source_account_id: "1111".to_string(),
+            amount: Money::new(100, "USD"),
+        });
+        assert_eq!(
+            AccountPermission::required_for(&credit),
+            AccountPermission::System
+        );
+    }
+
+    #[test]
+    fn no_role_may_assign_the_owner() {
+        let assign = BankAccountCommand::AssignOwner(BankAccountAssignOwnerCommandData {
+            owner_id: "1111".to_string(),
+        });
+        assert_eq!(
+            AccountPermission::required_for(&assign),
+            AccountPermission::System
+        );
+    }
+
+    #[test]
+    fn round_trips_access_roles() {
+        for role in [
+            AccessRole::Owner,
+            AccessRole::CoOwner,
+            AccessRole::ViewOnly,
+            AccessRole::PaymentsOnly,
+        ] {
+            assert_eq!(role.to_string().parse::<AccessRole>(), Ok(role));
+        }
+        assert_eq!(
+            "Admin".parse::<AccessRole>(),
+            Err(BankAccountError::InvalidAccessRole)
+        );
+    }
+}
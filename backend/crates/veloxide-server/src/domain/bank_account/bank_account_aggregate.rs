@@ -1,15 +1,95 @@
+#[cfg(feature = "graphql")]
+use async_graphql::Enum;
+
+#[cfg(feature = "frontend")]
+use ts_rs::TS;
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
 use super::*;
 use async_trait::async_trait;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use cqrs_es::Aggregate;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use tracing::*;
 
 use crate::application::BankAccountServices;
+use crate::domain::{is_valid_currency_code, Money};
+
+/// The lifecycle of an account. A frozen account still accepts money coming in but refuses
+/// money going out, while a closed account refuses everything.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "graphql", derive(Enum))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub enum AccountStatus {
+    #[default]
+    NotOpen,
+    Open,
+    Frozen,
+    Closed,
+}
+
+/// How many days a check takes to clear, after which it can no longer be cancelled.
+pub const CHECK_CLEARING_DAYS: i64 = 2;
+
+/// A check written on the account, remembered so that its number cannot be used again.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct WrittenCheck {
+    amount: Money,
+    cancelled: bool,
+    #[serde(default)]
+    written_at: Option<DateTime<Utc>>,
+}
+
+/// Funds reserved on the account, which stay out of the available balance until the hold is
+/// captured, released or expires. Settled holds keep their id, so that it is not used again.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+struct Hold {
+    amount: Money,
+    expires_at: DateTime<Utc>,
+    settled: bool,
+}
+
+/// How far a transfer got on the account, remembered so that a step of the transfer saga that
+/// is retried does not move the money twice.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+enum TransferLeg {
+    Debited,
+    Credited,
+    Refunded,
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct BankAccount {
     account_id: String,
-    balance: f64,
+    balance: Money,
+    status: AccountStatus,
+    overdraft_limit: Money,
+    // Defaulted so that snapshots taken before accounts had users can still be loaded.
+    #[serde(default)]
+    owner_id: Option<String>,
+    #[serde(default)]
+    grants: HashMap<String, AccessRole>,
+    // Snapshots taken before checks were remembered load without them, rebuild the snapshots
+    // for checks written before then to be rejected as duplicates.
+    #[serde(default)]
+    checks: HashMap<String, WrittenCheck>,
+    // Interest earned but not posted yet, in millionths of the minor unit, and the last day it
+    // was earned on.
+    #[serde(default)]
+    accrued_interest_micro_units: i64,
+    #[serde(default)]
+    interest_accrued_on: Option<NaiveDate>,
+    #[serde(default)]
+    holds: HashMap<String, Hold>,
+    // Snapshots taken before transfers were remembered load without them, rebuild the
+    // snapshots for transfers made before then to be refunded or rejected as duplicates.
+    #[serde(default)]
+    transfers: HashMap<String, TransferLeg>,
 }
 
 #[async_trait]
@@ -54,25 +134,186 @@ impl Aggregate for BankAccount {
                 self.handle_write_check_command(services, check_number, amount)
                     .await
             }
+            BankAccountCommand::CancelCheck(BankAccountCancelCheckCommandData { check_number }) => {
+                self.handle_cancel_check_command(services, check_number)
+            }
+            BankAccountCommand::DebitTransfer(command) => {
+                self.handle_debit_transfer_command(command)
+            }
+            BankAccountCommand::CreditTransfer(command) => {
+                self.handle_credit_transfer_command(command)
+            }
+            BankAccountCommand::RefundTransfer(command) => {
+                self.handle_refund_transfer_command(command)
+            }
+            BankAccountCommand::FreezeAccount(BankAccountFreezeAccountCommandData { reason }) => {
+                self.handle_freeze_account_command(reason)
+            }
+            BankAccountCommand::UnfreezeAccount(BankAccountUnfreezeAccountCommandData {
+                reason,
+            }) => self.handle_unfreeze_account_command(reason),
+            BankAccountCommand::CloseAccount(BankAccountCloseAccountCommandData { reason }) => {
+                self.handle_close_account_command(reason)
+            }
+            BankAccountCommand::SetOverdraftLimit(command) => {
+                self.handle_set_overdraft_limit_command(command)
+            }
+            BankAccountCommand::GrantAccess(command) => self.handle_grant_access_command(command),
+            BankAccountCommand::RevokeAccess(command) => self.handle_revoke_access_command(command),
+            BankAccountCommand::AccrueInterest(command) => {
+                self.handle_accrue_interest_command(services, command)
+            }
+            BankAccountCommand::PlaceHold(command) => self.handle_place_hold_command(command),
+            BankAccountCommand::ReleaseHold(BankAccountReleaseHoldCommandData { hold_id }) => {
+                self.handle_release_hold_command(hold_id)
+            }
+            BankAccountCommand::CaptureHold(command) => self.handle_capture_hold_command(command),
+            BankAccountCommand::ExpireHolds(BankAccountExpireHoldsCommandData { now }) => {
+                self.handle_expire_holds_command(services, now)
+            }
+            BankAccountCommand::AssignOwner(BankAccountAssignOwnerCommandData { owner_id }) => {
+                self.handle_assign_owner_command(owner_id)
+            }
         }
     }
 
     fn apply(&mut self, event: Self::Event) {
         match event {
-            BankAccountEvent::AccountOpened { account_id } => {
+            BankAccountEvent::AccountOpened {
+                account_id,
+                currency,
+                owner_id,
+            } => {
                 self.account_id = account_id;
+                self.owner_id = owner_id;
+                self.balance = Money::zero(&currency);
+                self.overdraft_limit = Money::zero(&currency);
+                self.status = AccountStatus::Open;
             }
             BankAccountEvent::CustomerDepositedMoney { amount: _, balance } => {
                 self.balance = balance;
             }
+            BankAccountEvent::CustomerDepositedForeignCurrency { balance, .. } => {
+                self.balance = balance;
+            }
             BankAccountEvent::CustomerWithdrewCash { amount: _, balance } => {
                 self.balance = balance;
             }
             BankAccountEvent::CustomerWroteCheck {
-                check_number: _,
-                amount: _,
+                check_number,
+                amount,
+                balance,
+                written_at,
+            } => {
+                self.checks.insert(
+                    check_number,
+                    WrittenCheck {
+                        amount,
+                        cancelled: false,
+                        written_at,
+                    },
+                );
+                self.balance = balance;
+            }
+            BankAccountEvent::CheckCancelled {
+                check_number,
+                balance,
+                ..
+            } => {
+                if let Some(check) = self.checks.get_mut(&check_number) {
+                    check.cancelled = true;
+                }
+                self.balance = balance;
+            }
+            BankAccountEvent::TransferDebited {
+                transfer_id,
+                balance,
+                ..
+            } => {
+                self.transfers.insert(transfer_id, TransferLeg::Debited);
+                self.balance = balance;
+            }
+            BankAccountEvent::TransferCredited {
+                transfer_id,
+                balance,
+                ..
+            } => {
+                self.transfers.insert(transfer_id, TransferLeg::Credited);
+                self.balance = balance;
+            }
+            BankAccountEvent::TransferRefunded {
+                transfer_id,
                 balance,
+                ..
+            } => {
+                self.transfers.insert(transfer_id, TransferLeg::Refunded);
+                self.balance = balance;
+            }
+            BankAccountEvent::AccountFrozen { .. } => {
+                self.status = AccountStatus::Frozen;
+            }
+            BankAccountEvent::AccountUnfrozen { .. } => {
+                self.status = AccountStatus::Open;
+            }
+            BankAccountEvent::AccountClosed { .. } => {
+                self.status = AccountStatus::Closed;
+                self.accrued_interest_micro_units = 0;
+            }
+            BankAccountEvent::OverdraftLimitSet { limit } => {
+                self.overdraft_limit = limit;
+            }
+            BankAccountEvent::OverdraftEntered { .. } => {}
+            BankAccountEvent::AccessGranted { user_id, role } => {
+                self.grants.insert(user_id, role);
+            }
+            BankAccountEvent::AccessRevoked { user_id } => {
+                self.grants.remove(&user_id);
+            }
+            BankAccountEvent::OwnerAssigned { owner_id } => {
+                self.grants.remove(&owner_id);
+                self.owner_id = Some(owner_id);
+            }
+            BankAccountEvent::InterestAccrued {
+                date,
+                amount_micro_units,
+                ..
+            } => {
+                self.accrued_interest_micro_units += amount_micro_units;
+                self.interest_accrued_on = Some(date);
+            }
+            BankAccountEvent::InterestPosted {
+                amount, balance, ..
+            } => {
+                self.accrued_interest_micro_units -= amount.minor_units * INTEREST_MICRO_UNITS;
+                self.balance = balance;
+            }
+            BankAccountEvent::HoldPlaced {
+                hold_id,
+                amount,
+                expires_at,
+                ..
+            } => {
+                self.holds.insert(
+                    hold_id,
+                    Hold {
+                        amount,
+                        expires_at,
+                        settled: false,
+                    },
+                );
+            }
+            BankAccountEvent::HoldReleased { hold_id, .. }
+            | BankAccountEvent::HoldExpired { hold_id, .. } => {
+                if let Some(hold) = self.holds.get_mut(&hold_id) {
+                    hold.settled = true;
+                }
+            }
+            BankAccountEvent::HoldCaptured {
+                hold_id, balance, ..
             } => {
+                if let Some(hold) = self.holds.get_mut(&hold_id) {
+                    hold.settled = true;
+                }
                 self.balance = balance;
             }
         }
@@ -86,65 +327,98 @@ impl BankAccount {
         services: &BankAccountServices,
         command: BankAccountOpenAccountCommandData,
     ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
-        if !self.account_id.is_empty() {
+        if self.status != AccountStatus::NotOpen {
             return Err(BankAccountError::AccountAlreadyOpen);
         }
+        let currency = command.currency.to_uppercase();
+        if !is_valid_currency_code(&currency) {
+            return Err(BankAccountError::InvalidCurrency);
+        }
         Ok(vec![BankAccountEvent::AccountOpened {
             account_id: command.account_id,
+            currency,
+            owner_id: command.owner_id,
         }])
     }
 
     #[instrument]
     pub async fn handle_deposit_money_command(
         &self,
-        _services: &BankAccountServices,
-        amount: f64,
+        services: &BankAccountServices,
+        amount: Money,
     ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
-        if self.account_id.is_empty() {
-            return Err(BankAccountError::AccountNotOpen);
-        }
-        if amount < 0_f64 {
+        self.ensure_open()?;
+        if amount.is_negative() {
             return Err(BankAccountError::CannotDepositNegativeAmount);
         }
-        let balance = self.balance + amount;
+        if amount.currency != self.balance.currency {
+            return self.handle_foreign_currency_deposit(services, amount).await;
+        }
+        let balance = self.balance.checked_add(&amount)?;
         Ok(vec![BankAccountEvent::CustomerDepositedMoney {
             amount,
             balance,
         }])
     }
 
+    // Deposits in another currency are converted into the account's currency at the
+    // current rate, which is recorded on the event so the conversion can be audited.
+    #[instrument]
+    async fn handle_foreign_currency_deposit(
+        &self,
+        services: &BankAccountServices,
+        original_amount: Money,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        let conversion = match services
+            .services
+            .convert_currency(&original_amount, &self.balance.currency)
+            .await
+        {
+            Ok(conversion) => conversion,
+            Err(e) => {
+                tracing::error!("currency conversion failed: {}", e);
+                return Err(BankAccountError::ExchangeRateUnavailable);
+            }
+        };
+        let balance = self.balance.checked_add(&conversion.converted)?;
+        Ok(vec![BankAccountEvent::CustomerDepositedForeignCurrency {
+            original_amount,
+            exchange_rate: conversion.rate,
+            amount: conversion.converted,
+            balance,
+        }])
+    }
+
     #[instrument]
     pub async fn handle_withdraw_money_command(
         &self,
         services: &BankAccountServices,
-        amount: f64,
+        amount: Money,
         atm_id: String,
     ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
-        if self.account_id.is_empty() {
-            return Err(BankAccountError::AccountNotOpen);
-        }
-        if amount < 0_f64 {
+        self.ensure_open()?;
+        self.ensure_not_frozen()?;
+        if amount.is_negative() {
             tracing::error!("cannot withdraw negative amount");
             return Err(BankAccountError::CannotWithdrawNegativeAmount);
         }
-        let balance = self.balance - amount;
-        if balance < 0_f64 {
-            tracing::error!("insufficient funds");
-            return Err(BankAccountError::InsufficientFunds);
-        }
+        let balance = self.balance.checked_sub(&amount)?;
+        self.ensure_within_overdraft_limit(&balance)?;
         if services
             .services
-            .atm_withdrawal(&atm_id, amount)
+            .atm_withdrawal(&self.account_id, &atm_id, &amount)
             .await
             .is_err()
         {
             tracing::error!("atm rule violation");
             return Err(BankAccountError::AtmRuleViolation);
         };
-        Ok(vec![BankAccountEvent::CustomerWithdrewCash {
+        let mut events = vec![BankAccountEvent::CustomerWithdrewCash {
             amount,
-            balance,
-        }])
+            balance: balance.clone(),
+        }];
+        events.extend(self.overdraft_entered(balance));
+        Ok(events)
     }
 
     #[instrument]
@@ -152,20 +426,24 @@ impl BankAccount {
         &self,
         services: &BankAccountServices,
         check_number: String,
-        amount: f64,
+        amount: Money,
     ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
-        if self.account_id.is_empty() {
-            return Err(BankAccountError::AccountNotOpen);
-        }
-        if amount < 0_f64 {
+        self.ensure_open()?;
+        self.ensure_not_frozen()?;
+        if amount.is_negative() {
             tracing::error!("cannot write negative check amount");
             return Err(BankAccountError::CannotWriteNegativeCheckAmount);
         }
-        let balance = self.balance - amount;
-        if balance < 0_f64 {
-            tracing::error!("insufficient funds");
-            return Err(BankAccountError::InsufficientFunds);
+        if amount.is_zero() {
+            return Err(BankAccountError::InvalidAmount);
+        }
+        // Cancelled checks keep their number, so a check number is only ever used once.
+        if check_number.trim().is_empty() || self.checks.contains_key(&check_number) {
+            tracing::error!("invalid check number");
+            return Err(BankAccountError::InvalidCheckNumber);
         }
+        let balance = self.balance.checked_sub(&amount)?;
+        self.ensure_within_overdraft_limit(&balance)?;
         if services
             .services
             .validate_check(&self.account_id, &check_number)
@@ -175,156 +453,773 @@ impl BankAccount {
             tracing::error!("invalid check");
             return Err(BankAccountError::InvalidCheck);
         };
-        Ok(vec![BankAccountEvent::CustomerWroteCheck {
+        let mut events = vec![BankAccountEvent::CustomerWroteCheck {
             check_number,
             amount,
+            balance: balance.clone(),
+            written_at: Some(services.clock.now()),
+        }];
+        events.extend(self.overdraft_entered(balance));
+        Ok(events)
+    }
+
+    // Stops payment of a check, returning its amount to the account. Checks are paid out when
+    // they are written, but can be cancelled until they clear `CHECK_CLEARING_DAYS` later.
+    // Checks written before the time they were written was recorded count as cleared.
+    #[instrument]
+    pub fn handle_cancel_check_command(
+        &self,
+        services: &BankAccountServices,
+        check_number: String,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        let check = self
+            .checks
+            .get(&check_number)
+            .ok_or(BankAccountError::CheckNotFound)?;
+        if check.cancelled {
+            return Err(BankAccountError::CheckAlreadyCancelled);
+        }
+        let clears_at = check
+            .written_at
+            .map(|written_at| written_at + Duration::days(CHECK_CLEARING_DAYS));
+        if !clears_at.is_some_and(|clears_at| services.clock.now() < clears_at) {
+            return Err(BankAccountError::CheckAlreadyCleared);
+        }
+        let balance = self.balance.checked_add(&check.amount)?;
+        Ok(vec![BankAccountEvent::CheckCancelled {
+            check_number,
+            amount: check.amount.clone(),
             balance,
         }])
     }
-}
 
-impl Default for BankAccount {
-    fn default() -> Self {
-        BankAccount {
-            account_id: "".to_string(),
-            balance: 0_f64,
+    #[instrument]
+    pub fn handle_debit_transfer_command(
+        &self,
+        command: BankAccountDebitTransferCommandData,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        self.ensure_not_frozen()?;
+        if command.amount.is_negative() {
+            return Err(BankAccountError::CannotWithdrawNegativeAmount);
+        }
+        if self.transfers.contains_key(&command.transfer_id) {
+            return Err(BankAccountError::TransferAlreadyApplied);
         }
+        let balance = self.balance.checked_sub(&command.amount)?;
+        self.ensure_within_overdraft_limit(&balance)?;
+        let mut events = vec![BankAccountEvent::TransferDebited {
+            transfer_id: command.transfer_id,
+            destination_account_id: command.destination_account_id,
+            amount: command.amount,
+            balance: balance.clone(),
+        }];
+        events.extend(self.overdraft_entered(balance));
+        Ok(events)
     }
-}
-
-// The aggregate tests are the most important part of a CQRS system.
-// The simplicity and flexibility of these tests are a good part of what
-// makes an event sourced system so friendly to changing business requirements.
-#[cfg(test)]
-mod aggregate_tests {
-    use async_trait::async_trait;
-    use coverage_helper::test;
-    use cqrs_es::test::TestFramework;
-    use std::sync::Mutex;
 
-    use super::*;
-
-    use crate::application::{AtmError, BankAccountApi, BankAccountServices, CheckingError};
-    use crate::domain::bank_account_aggregate::BankAccount;
-    use crate::domain::bank_account_commands::BankAccountCommand;
-    use crate::domain::bank_account_events::BankAccountEvent;
+    #[instrument]
+    pub fn handle_credit_transfer_command(
+        &self,
+        command: BankAccountCreditTransferCommandData,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        if command.amount.is_negative() {
+            return Err(BankAccountError::CannotDepositNegativeAmount);
+        }
+        if self.transfers.contains_key(&command.transfer_id) {
+            return Err(BankAccountError::TransferAlreadyApplied);
+        }
+        let balance = self.balance.checked_add(&command.amount)?;
+        Ok(vec![BankAccountEvent::TransferCredited {
+            transfer_id: command.transfer_id,
+            source_account_id: command.source_account_id,
+            amount: command.amount,
+            balance,
+        }])
+    }
 
-    // A test framework that will apply our events and command
-    // and verify that the logic works as expected.
-    type AccountTestFramework = TestFramework<BankAccount>;
+    #[instrument]
+    pub fn handle_refund_transfer_command(
+        &self,
+        command: BankAccountRefundTransferCommandData,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        if command.amount.is_negative() {
+            return Err(BankAccountError::CannotDepositNegativeAmount);
+        }
+        match self.transfers.get(&command.transfer_id) {
+            Some(TransferLeg::Debited) => {}
+            Some(_) => return Err(BankAccountError::TransferAlreadyApplied),
+            None => return Err(BankAccountError::TransferNotDebited),
+        }
+        let balance = self.balance.checked_add(&command.amount)?;
+        Ok(vec![BankAccountEvent::TransferRefunded {
+            transfer_id: command.transfer_id,
+            amount: command.amount,
+            balance,
+        }])
+    }
 
-    pub struct MockBankAccountServices {
-        atm_withdrawal_response: Mutex<Option<Result<(), AtmError>>>,
-        validate_check_response: Mutex<Option<Result<(), CheckingError>>>,
+    #[instrument]
+    pub fn handle_freeze_account_command(
+        &self,
+        reason: String,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        if self.status == AccountStatus::Frozen {
+            return Err(BankAccountError::AccountAlreadyFrozen);
+        }
+        Ok(vec![BankAccountEvent::AccountFrozen { reason }])
     }
 
-    impl Default for MockBankAccountServices {
-        fn default() -> Self {
-            Self {
-                atm_withdrawal_response: Mutex::new(None),
-                validate_check_response: Mutex::new(None),
-            }
+    #[instrument]
+    pub fn handle_unfreeze_account_command(
+        &self,
+        reason: String,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        if self.status != AccountStatus::Frozen {
+            return Err(BankAccountError::AccountNotFrozen);
         }
+        Ok(vec![BankAccountEvent::AccountUnfrozen { reason }])
     }
 
-    impl MockBankAccountServices {
-        fn set_atm_withdrawal_response(&self, response: Result<(), AtmError>) {
-            *self.atm_withdrawal_response.lock().unwrap() = Some(response);
+    #[instrument]
+    pub fn handle_close_account_command(
+        &self,
+        reason: String,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        self.ensure_not_frozen()?;
+        if !self.balance.is_zero() {
+            return Err(BankAccountError::CannotCloseAccountWithNonZeroBalance);
         }
-        fn set_validate_check_response(&self, response: Result<(), CheckingError>) {
-            *self.validate_check_response.lock().unwrap() = Some(response);
+        if self.holds.values().any(|hold| !hold.settled) {
+            return Err(BankAccountError::CannotCloseAccountWithActiveHolds);
         }
+        // Interest of a minor unit or more is paid out once posted, what is left below that is
+        // forfeited as the account is closed.
+        if self.accrued_interest_micro_units >= INTEREST_MICRO_UNITS {
+            return Err(BankAccountError::CannotCloseAccountWithAccruedInterest);
+        }
+        Ok(vec![BankAccountEvent::AccountClosed { reason }])
     }
 
-    #[async_trait]
-    impl BankAccountApi for MockBankAccountServices {
-        async fn atm_withdrawal(&self, _atm_id: &str, _amount: f64) -> Result<(), AtmError> {
-            self.atm_withdrawal_response.lock().unwrap().take().unwrap()
+    #[instrument]
+    pub fn handle_set_overdraft_limit_command(
+        &self,
+        command: BankAccountSetOverdraftLimitCommandData,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        let limit = command.limit;
+        if limit.currency != self.balance.currency {
+            return Err(BankAccountError::CurrencyMismatch);
+        }
+        if limit.is_negative() {
+            return Err(BankAccountError::InvalidOverdraftLimit);
         }
+        // Lowering the limit must not leave an overdrawn balance outside of it.
+        if self.balance.checked_add(&limit)?.is_negative() {
+            return Err(BankAccountError::InvalidOverdraftLimit);
+        }
+        Ok(vec![BankAccountEvent::OverdraftLimitSet { limit }])
+    }
 
-        async fn validate_check(
-            &self,
-            _account_id: &str,
-            _check_number: &str,
-        ) -> Result<(), CheckingError> {
-            self.validate_check_response.lock().unwrap().take().unwrap()
+    #[instrument]
+    pub fn handle_grant_access_command(
+        &self,
+        command: BankAccountGrantAccessCommandData,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        if command.role == AccessRole::Owner {
+            return Err(BankAccountError::CannotGrantOwnerRole);
+        }
+        self.ensure_not_owner(&command.user_id)?;
+        if self.grants.get(&command.user_id) == Some(&command.role) {
+            return Err(BankAccountError::AccessAlreadyGranted);
         }
+        Ok(vec![BankAccountEvent::AccessGranted {
+            user_id: command.user_id,
+            role: command.role,
+        }])
     }
 
-    #[test]
-    fn deposit_money() {
-        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default());
-        AccountTestFramework::with(services)
-            .given(vec![BankAccountEvent::AccountOpened {
-                account_id: "1234".to_string(),
-            }])
-            .when(BankAccountCommand::DepositMoney(
-                BankAccountDepositMoneyCommandData { amount: 200.0 },
-            ))
-            .then_expect_events(vec![BankAccountEvent::CustomerDepositedMoney {
-                amount: 200.0,
-                balance: 200.0,
-            }]);
+    #[instrument]
+    pub fn handle_revoke_access_command(
+        &self,
+        command: BankAccountRevokeAccessCommandData,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        self.ensure_not_owner(&command.user_id)?;
+        if !self.grants.contains_key(&command.user_id) {
+            return Err(BankAccountError::AccessNotGranted);
+        }
+        Ok(vec![BankAccountEvent::AccessRevoked {
+            user_id: command.user_id,
+        }])
     }
 
-    #[test]
-    fn cannot_deposit_money_into_account_that_does_not_exist() {
-        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default());
-        AccountTestFramework::with(services)
-            .given_no_previous_events()
-            .when(BankAccountCommand::DepositMoney(
-                BankAccountDepositMoneyCommandData { amount: 200.0 },
-            ))
-            .then_expect_error(BankAccountError::AccountNotOpen);
+    // Accounts opened before accounts had owners are not accessible to any user until an
+    // administrator records who owns them. The owner of an account never changes once set.
+    #[instrument]
+    pub fn handle_assign_owner_command(
+        &self,
+        owner_id: String,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        if self.status == AccountStatus::NotOpen {
+            return Err(BankAccountError::AccountNotOpen);
+        }
+        if self.owner_id.is_some() {
+            return Err(BankAccountError::OwnerAlreadyAssigned);
+        }
+        Ok(vec![BankAccountEvent::OwnerAssigned { owner_id }])
     }
 
-    #[test]
-    fn can_deposit_money_with_balance() {
-        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default());
+    // Earns a day of interest on the balance, which is posted to the account on the last day of
+    // the month. Should that day have gone by without interest being accrued, the month is
+    // posted before the next day's interest is earned instead. What is left below one minor
+    // unit is carried into the next month. Days on which nothing was earned are not recorded.
+    #[instrument]
+    pub fn handle_accrue_interest_command(
+        &self,
+        services: &BankAccountServices,
+        command: BankAccountAccrueInterestCommandData,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        if command.annual_rate_basis_points > MAX_ANNUAL_INTEREST_RATE_BASIS_POINTS {
+            return Err(BankAccountError::InvalidInterestRate);
+        }
+        // Interest is only earned for days that have begun, or the last day of the month would
+        // be posted ahead of time.
+        let date = parse_interest_date(&command.date)
+            .filter(|date| *date <= services.clock.now().date_naive())
+            .ok_or(BankAccountError::InvalidInterestDate)?;
+        if self
+            .interest_accrued_on
+            .is_some_and(|accrued_on| accrued_on >= date)
+        {
+            return Err(BankAccountError::InterestAlreadyAccrued);
+        }
 
-        AccountTestFramework::with(services)
-            .given(vec![
-                BankAccountEvent::AccountOpened {
-                    account_id: "1234".to_string(),
-                },
-                BankAccountEvent::CustomerDepositedMoney {
-                    amount: 200.0,
-                    balance: 200.0,
-                },
-            ])
-            .when(BankAccountCommand::DepositMoney(
-                BankAccountDepositMoneyCommandData { amount: 200.0 },
-            ))
-            .then_expect_events(vec![BankAccountEvent::CustomerDepositedMoney {
-                amount: 200.0,
-                balance: 400.0,
-            }]);
+        let mut events = vec![];
+        let mut accrued = self.accrued_interest_micro_units;
+        let mut balance = self.balance.clone();
+        if let Some(accrued_on) = self.interest_accrued_on {
+            let period = interest_period(accrued_on);
+            if period != interest_period(date) {
+                events.extend(post_interest(period, &mut accrued, &mut balance)?);
+            }
+        }
+        let amount_micro_units =
+            daily_interest_micro_units(&balance, command.annual_rate_basis_points);
+        if amount_micro_units > 0 {
+            accrued = accrued
+                .checked_add(amount_micro_units)
+                .ok_or(BankAccountError::AmountOverflow)?;
+            events.push(BankAccountEvent::InterestAccrued {
+                date,
+                annual_rate_basis_points: command.annual_rate_basis_points,
+                principal: balance.clone(),
+                amount_micro_units,
+            });
+        }
+        if is_last_day_of_month(date) {
+            events.extend(post_interest(
+                interest_period(date),
+                &mut accrued,
+                &mut balance,
+            )?);
+        }
+        Ok(events)
     }
 
-    #[test]
-    fn can_withdraw_money_when_atm_withdrawal_response_is_ok() {
-        let services = MockBankAccountServices::default();
-        services.set_atm_withdrawal_response(Ok(()));
-
-        AccountTestFramework::with(BankAccountServices::new(Box::new(services)))
-            .given(vec![
-                BankAccountEvent::AccountOpened {
-                    account_id: "1234".to_string(),
+    // Reserves funds for a payment that is settled later, such as a card payment. The hold
+    // counts against the available balance, and so against the overdraft limit, right away.
+    #[instrument]
+    pub fn handle_place_hold_command(
+        &self,
+        command: BankAccountPlaceHoldCommandData,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        self.ensure_not_frozen()?;
+        if command.hold_id.trim().is_empty() || self.holds.contains_key(&command.hold_id) {
+            return Err(BankAccountError::InvalidHoldId);
+        }
+        if command.amount.is_negative() || command.amount.is_zero() {
+            return Err(BankAccountError::InvalidAmount);
+        }
+        let expires_at = DateTime::parse_from_rfc3339(&command.expires_at)
+            .map_err(|_| BankAccountError::InvalidHoldExpiry)?
+            .with_timezone(&Utc);
+        self.ensure_within_overdraft_limit(&self.balance.checked_sub(&command.amount)?)?;
+        let available_balance = self.available_balance()?.checked_sub(&command.amount)?;
+        Ok(vec![BankAccountEvent::HoldPlaced {
+            hold_id: command.hold_id,
+            amount: command.amount,
+            expires_at,
+            available_balance,
+        }])
+    }
+
+    #[instrument]
+    pub fn handle_release_hold_command(
+        &self,
+        hold_id: String,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        let hold = self.active_hold(&hold_id)?;
+        let available_balance = self.available_balance()?.checked_add(&hold.amount)?;
+        Ok(vec![BankAccountEvent::HoldReleased {
+            hold_id,
+            amount: hold.amount.clone(),
+            available_balance,
+        }])
+    }
+
+    // Pays out the hold, or part of it, with the rest of it released. The funds were reserved
+    // when the hold was placed, so a capture is never short of them.
+    #[instrument]
+    pub fn handle_capture_hold_command(
+        &self,
+        command: BankAccountCaptureHoldCommandData,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        self.ensure_not_frozen()?;
+        let hold = self.active_hold(&command.hold_id)?;
+        let amount = command.amount.unwrap_or_else(|| hold.amount.clone());
+        let released = hold.amount.checked_sub(&amount)?;
+        if amount.is_negative() || released.is_negative() {
+            return Err(BankAccountError::InvalidCaptureAmount);
+        }
+        let balance = self.balance.checked_sub(&amount)?;
+        let available_balance = self.available_balance()?.checked_add(&released)?;
+        let mut events = vec![BankAccountEvent::HoldCaptured {
+            hold_id: command.hold_id,
+            amount,
+            released,
+            balance: balance.clone(),
+            available_balance,
+        }];
+        events.extend(self.overdraft_entered(balance));
+        Ok(events)
+    }
+
+    // Releases every hold that expired by `now`. Expiry is left to the `HoldExpiryJob`, holds
+    // that expired can still be captured until it releases them.
+    #[instrument]
+    pub fn handle_expire_holds_command(
+        &self,
+        services: &BankAccountServices,
+        now: String,
+    ) -> Result<Vec<BankAccountEvent>, BankAccountError> {
+        self.ensure_open()?;
+        // Holds are never expired ahead of the server's clock, whatever time the command says.
+        let now = DateTime::parse_from_rfc3339(&now)
+            .map_err(|_| BankAccountError::InvalidHoldExpiry)?
+            .with_timezone(&Utc)
+            .min(services.clock.now());
+        let mut expired: Vec<(&String, &Hold)> = self
+            .holds
+            .iter()
+            .filter(|(_, hold)| !hold.settled && hold.expires_at <= now)
+            .collect();
+        expired.sort_by_key(|(hold_id, hold)| (hold.expires_at, hold_id.as_str()));
+        let mut available_balance = self.available_balance()?;
+        let mut events = vec![];
+        for (hold_id, hold) in expired {
+            available_balance = available_balance.checked_add(&hold.amount)?;
+            events.push(BankAccountEvent::HoldExpired {
+                hold_id: hold_id.clone(),
+                amount: hold.amount.clone(),
+                available_balance: available_balance.clone(),
+            });
+        }
+        Ok(events)
+    }
+
+    // The owner always keeps full access, so their role is never granted or revoked.
+    fn ensure_not_owner(&self, user_id: &str) -> Result<(), BankAccountError> {
+        if self.owner_id.as_deref() == Some(user_id) {
+            return Err(BankAccountError::CannotChangeOwnerAccess);
+        }
+        Ok(())
+    }
+
+    // Frozen accounts count as open, they are only restricted in what they may pay out.
+    fn ensure_open(&self) -> Result<(), BankAccountError> {
+        match self.status {
+            AccountStatus::NotOpen => Err(BankAccountError::AccountNotOpen),
+            AccountStatus::Closed => Err(BankAccountError::AccountClosed),
+            AccountStatus::Open | AccountStatus::Frozen => Ok(()),
+        }
+    }
+
+    /// The ledger balance the events of the account leave it at.
+    pub fn balance(&self) -> &Money {
+        &self.balance
+    }
+
+    /// The user who opened the account, if it was opened after accounts had owners.
+    pub fn owner_id(&self) -> Option<&str> {
+        self.owner_id.as_deref()
+    }
+
+    /// The role granted to a user other than the owner, if they were granted one.
+    pub fn granted_role(&self, user_id: &str) -> Option<AccessRole> {
+        self.grants.get(user_id).copied()
+    }
+
+    fn active_hold(&self, hold_id: &str) -> Result<&Hold, BankAccountError> {
+        self.holds
+            .get(hold_id)
+            .filter(|hold| !hold.settled)
+            .ok_or(BankAccountError::HoldNotFound)
+    }
+
+    // The funds reserved by the holds that are yet to be settled.
+    fn held(&self) -> Result<Money, BankAccountError> {
+        let mut held = Money::zero(&self.balance.currency);
+        for hold in self.holds.values().filter(|hold| !hold.settled) {
+            held = held.checked_add(&hold.amount)?;
+        }
+        Ok(held)
+    }
+
+    // The balance less what is held, which is what may still be paid out.
+    fn available_balance(&self) -> Result<Money, BankAccountError> {
+        Ok(self.balance.checked_sub(&self.held()?)?)
+    }
+
+    // Money may only be paid out as long as the available balance it leaves stays within the
+    // overdraft limit.
+    fn ensure_within_overdraft_limit(&self, balance: &Money) -> Result<(), BankAccountError> {
+        if balance
+            .checked_sub(&self.held()?)?
+            .checked_add(&self.overdraft_limit)?
+            .is_negative()
+        {
+            tracing::error!("insufficient funds");
+            return Err(BankAccountError::InsufficientFunds);
+        }
+        Ok(())
+    }
+
+    fn overdraft_entered(&self, balance: Money) -> Option<BankAccountEvent> {
+        (!self.balance.is_negative() && balance.is_negative())
+            .then_some(BankAccountEvent::OverdraftEntered { balance })
+    }
+
+    fn ensure_not_frozen(&self) -> Result<(), BankAccountError> {
+        if self.status == AccountStatus::Frozen {
+            tracing::error!("account frozen");
+            return Err(BankAccountError::AccountFrozen);
+        }
+        Ok(())
+    }
+}
+
+// Posts the whole minor units of the accrued interest, if there are any, leaving the rest.
+fn post_interest(
+    period: String,
+    accrued_micro_units: &mut i64,
+    balance: &mut Money,
+) -> Result<Option<BankAccountEvent>, BankAccountError> {
+    let amount = Money::new(
+        *accrued_micro_units / INTEREST_MICRO_UNITS,
+        &balance.currency,
+    );
+    if amount.minor_units <= 0 {
+        return Ok(None);
+    }
+    *accrued_micro_units -= amount.minor_units * INTEREST_MICRO_UNITS;
+    *balance = balance.checked_add(&amount)?;
+    Ok(Some(BankAccountEvent::InterestPosted {
+        period,
+        amount,
+        balance: balance.clone(),
+    }))
+}
+
+impl Default for BankAccount {
+    fn default() -> Self {
+        BankAccount {
+            account_id: "".to_string(),
+            balance: Money::default(),
+            status: AccountStatus::default(),
+            overdraft_limit: Money::default(),
+            owner_id: None,
+            grants: HashMap::new(),
+            checks: HashMap::new(),
+            accrued_interest_micro_units: 0,
+            interest_accrued_on: None,
+            holds: HashMap::new(),
+            transfers: HashMap::new(),
+        }
+    }
+}
+
+// The aggregate tests are the most important part of a CQRS system.
+// The simplicity and flexibility of these tests are a good part of what
+// makes an event sourced system so friendly to changing business requirements.
+#[cfg(test)]
+mod aggregate_tests {
+    use async_trait::async_trait;
+    use coverage_helper::test;
+    use cqrs_es::test::TestFramework;
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    use crate::application::{
+        AtmError, BankAccountApi, BankAccountServices, CheckingError, FxError, ManualClock,
+    };
+    use crate::domain::bank_account_aggregate::BankAccount;
+    use crate::domain::bank_account_commands::BankAccountCommand;
+    use crate::domain::bank_account_events::BankAccountEvent;
+    use crate::domain::{CurrencyConversion, ExchangeRate};
+
+    // A test framework that will apply our events and command
+    // and verify that the logic works as expected.
+    type AccountTestFramework = TestFramework<BankAccount>;
+
+    // The time the clock of the tests stands at.
+    fn now() -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339("2023-12-04T12:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn clock() -> Arc<ManualClock> {
+        Arc::new(ManualClock::new(now()))
+    }
+
+    fn usd(minor_units: i64) -> Money {
+        Money::new(minor_units, "USD")
+    }
+
+    pub struct MockBankAccountServices {
+        atm_withdrawal_response: Mutex<Option<Result<(), AtmError>>>,
+        validate_check_response: Mutex<Option<Result<(), CheckingError>>>,
+        convert_currency_response: Mutex<Option<Result<CurrencyConversion, FxError>>>,
+    }
+
+    impl Default for MockBankAccountServices {
+        fn default() -> Self {
+            Self {
+                atm_withdrawal_response: Mutex::new(None),
+                validate_check_response: Mutex::new(None),
+                convert_currency_response: Mutex::new(None),
+            }
+        }
+    }
+
+    impl MockBankAccountServices {
+        fn set_atm_withdrawal_response(&self, response: Result<(), AtmError>) {
+            *self.atm_withdrawal_response.lock().unwrap() = Some(response);
+        }
+        fn set_validate_check_response(&self, response: Result<(), CheckingError>) {
+            *self.validate_check_response.lock().unwrap() = Some(response);
+        }
+        fn set_convert_currency_response(&self, response: Result<CurrencyConversion, FxError>) {
+            *self.convert_currency_response.lock().unwrap() = Some(response);
+        }
+    }
+
+    #[async_trait]
+    impl BankAccountApi for MockBankAccountServices {
+        async fn atm_withdrawal(
+            &self,
+            _account_id: &str,
+            _atm_id: &str,
+            _amount: &Money,
+        ) -> Result<(), AtmError> {
+            self.atm_withdrawal_response.lock().unwrap().take().unwrap()
+        }
+
+        async fn validate_check(
+            &self,
+            _account_id: &str,
+            _check_number: &str,
+        ) -> Result<(), CheckingError> {
+            self.validate_check_response.lock().unwrap().take().unwrap()
+        }
+
+        async fn convert_currency(
+            &self,
+            _amount: &Money,
+            _to_currency: &str,
+        ) -> Result<CurrencyConversion, FxError> {
+            self.convert_currency_response
+                .lock()
+                .unwrap()
+                .take()
+                .unwrap()
+        }
+    }
+
+    #[test]
+    fn deposit_money() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![BankAccountEvent::AccountOpened {
+                account_id: "1234".to_string(),
+                currency: "USD".to_string(),
+                owner_id: None,
+            }])
+            .when(BankAccountCommand::DepositMoney(
+                BankAccountDepositMoneyCommandData { amount: usd(20000) },
+            ))
+            .then_expect_events(vec![BankAccountEvent::CustomerDepositedMoney {
+                amount: usd(20000),
+                balance: usd(20000),
+            }]);
+    }
+
+    #[test]
+    fn cannot_deposit_money_into_account_that_does_not_exist() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given_no_previous_events()
+            .when(BankAccountCommand::DepositMoney(
+                BankAccountDepositMoneyCommandData { amount: usd(20000) },
+            ))
+            .then_expect_error(BankAccountError::AccountNotOpen);
+    }
+
+    #[test]
+    fn can_deposit_money_with_balance() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+
+        AccountTestFramework::with(services)
+            .given(vec![
+                BankAccountEvent::AccountOpened {
+                    account_id: "1234".to_string(),
+                    currency: "USD".to_string(),
+                    owner_id: None,
+                },
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(20000),
+                    balance: usd(20000),
+                },
+            ])
+            .when(BankAccountCommand::DepositMoney(
+                BankAccountDepositMoneyCommandData { amount: usd(20000) },
+            ))
+            .then_expect_events(vec![BankAccountEvent::CustomerDepositedMoney {
+                amount: usd(20000),
+                balance: usd(40000),
+            }]);
+    }
+
+    #[test]
+    fn deposits_of_fractional_amounts_do_not_drift() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+
+        AccountTestFramework::with(services)
+            .given(vec![
+                BankAccountEvent::AccountOpened {
+                    account_id: "1234".to_string(),
+                    currency: "USD".to_string(),
+                    owner_id: None,
+                },
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(10),
+                    balance: usd(10),
+                },
+            ])
+            .when(BankAccountCommand::DepositMoney(
+                BankAccountDepositMoneyCommandData { amount: usd(20) },
+            ))
+            .then_expect_events(vec![BankAccountEvent::CustomerDepositedMoney {
+                amount: usd(20),
+                balance: usd(30),
+            }]);
+    }
+
+    #[test]
+    fn deposit_in_a_foreign_currency_is_converted_at_the_current_rate() {
+        let exchange_rate = ExchangeRate::new("EUR", "USD", 108_530_000);
+        let services = MockBankAccountServices::default();
+        services.set_convert_currency_response(Ok(CurrencyConversion {
+            converted: usd(10853),
+            rate: exchange_rate.clone(),
+        }));
+
+        AccountTestFramework::with(BankAccountServices::new(Box::new(services), clock()))
+            .given(vec![
+                BankAccountEvent::AccountOpened {
+                    account_id: "1234".to_string(),
+                    currency: "USD".to_string(),
+                    owner_id: None,
+                },
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(20000),
+                    balance: usd(20000),
+                },
+            ])
+            .when(BankAccountCommand::DepositMoney(
+                BankAccountDepositMoneyCommandData {
+                    amount: Money::new(10000, "EUR"),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::CustomerDepositedForeignCurrency {
+                original_amount: Money::new(10000, "EUR"),
+                exchange_rate,
+                amount: usd(10853),
+                balance: usd(30853),
+            }]);
+    }
+
+    #[test]
+    fn cannot_deposit_a_foreign_currency_without_an_exchange_rate() {
+        let services = MockBankAccountServices::default();
+        services.set_convert_currency_response(Err(FxError::RateNotFound {
+            from: "EUR".to_string(),
+            to: "USD".to_string(),
+        }));
+
+        AccountTestFramework::with(BankAccountServices::new(Box::new(services), clock()))
+            .given(vec![BankAccountEvent::AccountOpened {
+                account_id: "1234".to_string(),
+                currency: "USD".to_string(),
+                owner_id: None,
+            }])
+            .when(BankAccountCommand::DepositMoney(
+                BankAccountDepositMoneyCommandData {
+                    amount: Money::new(20000, "EUR"),
+                },
+            ))
+            .then_expect_error(BankAccountError::ExchangeRateUnavailable);
+    }
+
+    #[test]
+    fn can_withdraw_money_when_atm_withdrawal_response_is_ok() {
+        let services = MockBankAccountServices::default();
+        services.set_atm_withdrawal_response(Ok(()));
+
+        AccountTestFramework::with(BankAccountServices::new(Box::new(services), clock()))
+            .given(vec![
+                BankAccountEvent::AccountOpened {
+                    account_id: "1234".to_string(),
+                    currency: "USD".to_string(),
+                    owner_id: None,
                 },
                 BankAccountEvent::CustomerDepositedMoney {
-                    amount: 200.0,
-                    balance: 200.0,
+                    amount: usd(20000),
+                    balance: usd(20000),
                 },
             ])
             .when(BankAccountCommand::WithdrawMoney(
                 BankAccountWithdrawMoneyCommandData {
-                    amount: 100.0,
+                    amount: usd(10000),
                     atm_id: "ATM34f1ba3c".to_string(),
                 },
             ))
             .then_expect_events(vec![BankAccountEvent::CustomerWithdrewCash {
-                amount: 100.0,
-                balance: 100.0,
+                amount: usd(10000),
+                balance: usd(10000),
             }]);
     }
 
@@ -332,21 +1227,23 @@ mod aggregate_tests {
     fn cannot_withdraw_money_when_there_is_an_atm_error() {
         let services = MockBankAccountServices::default();
         services.set_atm_withdrawal_response(Err(AtmError));
-        let services = BankAccountServices::new(Box::new(services));
+        let services = BankAccountServices::new(Box::new(services), clock());
 
         AccountTestFramework::with(services)
             .given(vec![
                 BankAccountEvent::AccountOpened {
                     account_id: "1234".to_string(),
+                    currency: "USD".to_string(),
+                    owner_id: None,
                 },
                 BankAccountEvent::CustomerDepositedMoney {
-                    amount: 200.0,
-                    balance: 200.0,
+                    amount: usd(20000),
+                    balance: usd(20000),
                 },
             ])
             .when(BankAccountCommand::WithdrawMoney(
                 BankAccountWithdrawMoneyCommandData {
-                    amount: 100.0,
+                    amount: usd(10000),
                     atm_id: "ATM34f1ba3c".to_string(),
                 },
             ))
@@ -355,14 +1252,16 @@ mod aggregate_tests {
 
     #[test]
     fn withdraw_money_funds_not_available_returns_insufficient_funds_error() {
-        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default());
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
         AccountTestFramework::with(services)
             .given(vec![BankAccountEvent::AccountOpened {
                 account_id: "1234".to_string(),
+                currency: "USD".to_string(),
+                owner_id: None,
             }])
             .when(BankAccountCommand::WithdrawMoney(
                 BankAccountWithdrawMoneyCommandData {
-                    amount: 200.0,
+                    amount: usd(20000),
                     atm_id: "ATM34f1ba3c".to_string(),
                 },
             ))
@@ -370,55 +1269,73 @@ mod aggregate_tests {
             .then_expect_error_message(BankAccountError::InsufficientFunds.to_string().as_str());
     }
 
-    #[test]
-    fn wrote_check() {
+    // Services that accept every check.
+    fn check_services() -> BankAccountServices {
         let services = MockBankAccountServices::default();
         services.set_validate_check_response(Ok(()));
-        let services = BankAccountServices::new(Box::new(services));
+        BankAccountServices::new(Box::new(services), clock())
+    }
 
-        AccountTestFramework::with(services)
+    fn write_check(amount: i64) -> BankAccountCommand {
+        BankAccountCommand::WriteCheck(BankAccountWriteCheckCommandData {
+            check_number: "1170".to_string(),
+            amount: usd(amount),
+        })
+    }
+
+    #[test]
+    fn wrote_check() {
+        AccountTestFramework::with(check_services())
             .given(vec![
                 BankAccountEvent::AccountOpened {
                     account_id: "1234".to_string(),
+                    currency: "USD".to_string(),
+                    owner_id: None,
                 },
                 BankAccountEvent::CustomerDepositedMoney {
-                    amount: 200.0,
-                    balance: 200.0,
+                    amount: usd(20000),
+                    balance: usd(20000),
                 },
             ])
-            .when(BankAccountCommand::WriteCheck(
-                BankAccountWriteCheckCommandData {
-                    check_number: "1170".to_string(),
-                    amount: 100.0,
-                },
-            ))
+            .when(write_check(10000))
             .then_expect_events(vec![BankAccountEvent::CustomerWroteCheck {
                 check_number: "1170".to_string(),
-                amount: 100.0,
-                balance: 100.0,
+                amount: usd(10000),
+                balance: usd(10000),
+                written_at: Some(now()),
             }]);
     }
 
+    #[test]
+    fn cannot_write_a_check_for_nothing() {
+        AccountTestFramework::with(check_services())
+            .given(vec![account_opened(), deposited(20000)])
+            .when(write_check(0))
+            .then_expect_error(BankAccountError::InvalidAmount);
+    }
+
     #[test]
     fn wrote_check_bad_check() {
         let services = MockBankAccountServices::default();
         services.set_validate_check_response(Err(CheckingError));
-        let services = BankAccountServices::new(Box::new(services));
+        let services = BankAccountServices::new(Box::new(services), clock());
 
         AccountTestFramework::with(services)
             .given(vec![
                 BankAccountEvent::AccountOpened {
                     account_id: "1234".to_string(),
+                    currency: "USD".to_string(),
+                    owner_id: None,
                 },
                 BankAccountEvent::CustomerDepositedMoney {
-                    amount: 200.0,
-                    balance: 200.0,
+                    amount: usd(20000),
+                    balance: usd(20000),
                 },
             ])
             .when(BankAccountCommand::WriteCheck(
                 BankAccountWriteCheckCommandData {
                     check_number: "1170".to_string(),
-                    amount: 100.0,
+                    amount: usd(10000),
                 },
             ))
             .then_expect_error_message(BankAccountError::InvalidCheck.to_string().as_str());
@@ -426,32 +1343,1382 @@ mod aggregate_tests {
 
     #[test]
     fn wrote_check_funds_not_available() {
-        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default());
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
         AccountTestFramework::with(services)
             .given(vec![BankAccountEvent::AccountOpened {
                 account_id: "1234".to_string(),
+                currency: "USD".to_string(),
+                owner_id: None,
             }])
             .when(BankAccountCommand::WriteCheck(
                 BankAccountWriteCheckCommandData {
                     check_number: "1170".to_string(),
-                    amount: 100.0,
+                    amount: usd(10000),
                 },
             ))
             .then_expect_error_message(BankAccountError::InsufficientFunds.to_string().as_str())
     }
 
+    fn check_written(check_number: &str, amount: i64, balance: i64) -> BankAccountEvent {
+        BankAccountEvent::CustomerWroteCheck {
+            check_number: check_number.to_string(),
+            amount: usd(amount),
+            balance: usd(balance),
+            written_at: Some(now()),
+        }
+    }
+
     #[test]
-    fn open_account() {
-        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default());
+    fn wrote_check_rejects_reused_check_number() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
         AccountTestFramework::with(services)
-            .given_no_previous_events()
-            .when(BankAccountCommand::OpenAccount(
-                BankAccountOpenAccountCommandData {
-                    account_id: "1234".to_string(),
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(20000),
+                    balance: usd(20000),
                 },
-            ))
-            .then_expect_events(vec![BankAccountEvent::AccountOpened {
-                account_id: "1234".to_string(),
+                check_written("1170", 5000, 15000),
+            ])
+            .when(BankAccountCommand::WriteCheck(
+                BankAccountWriteCheckCommandData {
+                    check_number: "1170".to_string(),
+                    amount: usd(5000),
+                },
+            ))
+            .then_expect_error_message(BankAccountError::InvalidCheckNumber.to_string().as_str());
+    }
+
+    #[test]
+    fn wrote_check_rejects_number_of_cancelled_check() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(20000),
+                    balance: usd(20000),
+                },
+                check_written("1170", 5000, 15000),
+                BankAccountEvent::CheckCancelled {
+                    check_number: "1170".to_string(),
+                    amount: usd(5000),
+                    balance: usd(20000),
+                },
+            ])
+            .when(BankAccountCommand::WriteCheck(
+                BankAccountWriteCheckCommandData {
+                    check_number: "1170".to_string(),
+                    amount: usd(5000),
+                },
+            ))
+            .then_expect_error_message(BankAccountError::InvalidCheckNumber.to_string().as_str());
+    }
+
+    #[test]
+    fn wrote_check_rejects_blank_check_number() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(20000),
+                    balance: usd(20000),
+                },
+            ])
+            .when(BankAccountCommand::WriteCheck(
+                BankAccountWriteCheckCommandData {
+                    check_number: " ".to_string(),
+                    amount: usd(5000),
+                },
+            ))
+            .then_expect_error_message(BankAccountError::InvalidCheckNumber.to_string().as_str());
+    }
+
+    #[test]
+    fn cancel_check_returns_its_amount() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(20000),
+                    balance: usd(20000),
+                },
+                check_written("1170", 5000, 15000),
+            ])
+            .when(BankAccountCommand::CancelCheck(
+                BankAccountCancelCheckCommandData {
+                    check_number: "1170".to_string(),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::CheckCancelled {
+                check_number: "1170".to_string(),
+                amount: usd(5000),
+                balance: usd(20000),
+            }]);
+    }
+
+    #[test]
+    fn cancel_check_on_frozen_account() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                check_written("1170", 5000, -5000),
+                account_frozen(),
+            ])
+            .when(BankAccountCommand::CancelCheck(
+                BankAccountCancelCheckCommandData {
+                    check_number: "1170".to_string(),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::CheckCancelled {
+                check_number: "1170".to_string(),
+                amount: usd(5000),
+                balance: usd(0),
+            }]);
+    }
+
+    #[test]
+    fn cancel_unknown_check() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened(), check_written("1170", 5000, -5000)])
+            .when(BankAccountCommand::CancelCheck(
+                BankAccountCancelCheckCommandData {
+                    check_number: "1171".to_string(),
+                },
+            ))
+            .then_expect_error_message(BankAccountError::CheckNotFound.to_string().as_str());
+    }
+
+    #[test]
+    fn cancel_check_twice() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                check_written("1170", 5000, -5000),
+                BankAccountEvent::CheckCancelled {
+                    check_number: "1170".to_string(),
+                    amount: usd(5000),
+                    balance: usd(0),
+                },
+            ])
+            .when(BankAccountCommand::CancelCheck(
+                BankAccountCancelCheckCommandData {
+                    check_number: "1170".to_string(),
+                },
+            ))
+            .then_expect_error_message(
+                BankAccountError::CheckAlreadyCancelled.to_string().as_str(),
+            );
+    }
+
+    #[test]
+    fn cannot_cancel_a_check_once_it_has_cleared() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        let written_at = now() - Duration::days(CHECK_CLEARING_DAYS);
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::CustomerWroteCheck {
+                    check_number: "1170".to_string(),
+                    amount: usd(5000),
+                    balance: usd(-5000),
+                    written_at: Some(written_at),
+                },
+            ])
+            .when(BankAccountCommand::CancelCheck(
+                BankAccountCancelCheckCommandData {
+                    check_number: "1170".to_string(),
+                },
+            ))
+            .then_expect_error(BankAccountError::CheckAlreadyCleared);
+    }
+
+    #[test]
+    fn can_cancel_a_check_until_it_clears() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        let written_at = now() - Duration::days(CHECK_CLEARING_DAYS) + Duration::seconds(1);
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::CustomerWroteCheck {
+                    check_number: "1170".to_string(),
+                    amount: usd(5000),
+                    balance: usd(-5000),
+                    written_at: Some(written_at),
+                },
+            ])
+            .when(BankAccountCommand::CancelCheck(
+                BankAccountCancelCheckCommandData {
+                    check_number: "1170".to_string(),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::CheckCancelled {
+                check_number: "1170".to_string(),
+                amount: usd(5000),
+                balance: usd(0),
+            }]);
+    }
+
+    #[test]
+    fn checks_written_before_their_time_was_recorded_count_as_cleared() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::CustomerWroteCheck {
+                    check_number: "1170".to_string(),
+                    amount: usd(5000),
+                    balance: usd(-5000),
+                    written_at: None,
+                },
+            ])
+            .when(BankAccountCommand::CancelCheck(
+                BankAccountCancelCheckCommandData {
+                    check_number: "1170".to_string(),
+                },
+            ))
+            .then_expect_error(BankAccountError::CheckAlreadyCleared);
+    }
+
+    #[test]
+    fn open_account() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given_no_previous_events()
+            .when(BankAccountCommand::OpenAccount(
+                BankAccountOpenAccountCommandData {
+                    account_id: "1234".to_string(),
+                    currency: "USD".to_string(),
+                    owner_id: None,
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::AccountOpened {
+                account_id: "1234".to_string(),
+                currency: "USD".to_string(),
+                owner_id: None,
+            }]);
+    }
+
+    #[test]
+    fn open_account_records_owner() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given_no_previous_events()
+            .when(BankAccountCommand::OpenAccount(
+                BankAccountOpenAccountCommandData {
+                    account_id: "1234".to_string(),
+                    currency: "USD".to_string(),
+                    owner_id: Some("5f2b4e1c".to_string()),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::AccountOpened {
+                account_id: "1234".to_string(),
+                currency: "USD".to_string(),
+                owner_id: Some("5f2b4e1c".to_string()),
+            }]);
+    }
+
+    #[test]
+    fn debit_transfer() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                BankAccountEvent::AccountOpened {
+                    account_id: "1234".to_string(),
+                    currency: "USD".to_string(),
+                    owner_id: None,
+                },
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(20000),
+                    balance: usd(20000),
+                },
+            ])
+            .when(BankAccountCommand::DebitTransfer(
+                BankAccountDebitTransferCommandData {
+                    transfer_id: "T-1".to_string(),
+                    destination_account_id: "5678".to_string(),
+                    amount: usd(5000),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::TransferDebited {
+                transfer_id: "T-1".to_string(),
+                destination_account_id: "5678".to_string(),
+                amount: usd(5000),
+                balance: usd(15000),
+            }]);
+    }
+
+    #[test]
+    fn cannot_debit_transfer_without_sufficient_funds() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![BankAccountEvent::AccountOpened {
+                account_id: "1234".to_string(),
+                currency: "USD".to_string(),
+                owner_id: None,
+            }])
+            .when(BankAccountCommand::DebitTransfer(
+                BankAccountDebitTransferCommandData {
+                    transfer_id: "T-1".to_string(),
+                    destination_account_id: "5678".to_string(),
+                    amount: usd(5000),
+                },
+            ))
+            .then_expect_error(BankAccountError::InsufficientFunds);
+    }
+
+    #[test]
+    fn credit_transfer() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![BankAccountEvent::AccountOpened {
+                account_id: "5678".to_string(),
+                currency: "USD".to_string(),
+                owner_id: None,
+            }])
+            .when(BankAccountCommand::CreditTransfer(
+                BankAccountCreditTransferCommandData {
+                    transfer_id: "T-1".to_string(),
+                    source_account_id: "1234".to_string(),
+                    amount: usd(5000),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::TransferCredited {
+                transfer_id: "T-1".to_string(),
+                source_account_id: "1234".to_string(),
+                amount: usd(5000),
+                balance: usd(5000),
+            }]);
+    }
+
+    #[test]
+    fn cannot_credit_transfer_into_account_that_does_not_exist() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given_no_previous_events()
+            .when(BankAccountCommand::CreditTransfer(
+                BankAccountCreditTransferCommandData {
+                    transfer_id: "T-1".to_string(),
+                    source_account_id: "1234".to_string(),
+                    amount: usd(5000),
+                },
+            ))
+            .then_expect_error(BankAccountError::AccountNotOpen);
+    }
+
+    #[test]
+    fn refund_transfer() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                BankAccountEvent::AccountOpened {
+                    account_id: "1234".to_string(),
+                    currency: "USD".to_string(),
+                    owner_id: None,
+                },
+                BankAccountEvent::TransferDebited {
+                    transfer_id: "T-1".to_string(),
+                    destination_account_id: "5678".to_string(),
+                    amount: usd(5000),
+                    balance: usd(0),
+                },
+            ])
+            .when(BankAccountCommand::RefundTransfer(
+                BankAccountRefundTransferCommandData {
+                    transfer_id: "T-1".to_string(),
+                    amount: usd(5000),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::TransferRefunded {
+                transfer_id: "T-1".to_string(),
+                amount: usd(5000),
+                balance: usd(5000),
+            }]);
+    }
+
+    #[test]
+    fn cannot_debit_a_transfer_twice() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(20000),
+                    balance: usd(20000),
+                },
+                BankAccountEvent::TransferDebited {
+                    transfer_id: "T-1".to_string(),
+                    destination_account_id: "5678".to_string(),
+                    amount: usd(5000),
+                    balance: usd(15000),
+                },
+            ])
+            .when(BankAccountCommand::DebitTransfer(
+                BankAccountDebitTransferCommandData {
+                    transfer_id: "T-1".to_string(),
+                    destination_account_id: "5678".to_string(),
+                    amount: usd(5000),
+                },
+            ))
+            .then_expect_error(BankAccountError::TransferAlreadyApplied);
+    }
+
+    #[test]
+    fn cannot_credit_a_transfer_twice() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::TransferCredited {
+                    transfer_id: "T-1".to_string(),
+                    source_account_id: "5678".to_string(),
+                    amount: usd(5000),
+                    balance: usd(5000),
+                },
+            ])
+            .when(BankAccountCommand::CreditTransfer(
+                BankAccountCreditTransferCommandData {
+                    transfer_id: "T-1".to_string(),
+                    source_account_id: "5678".to_string(),
+                    amount: usd(5000),
+                },
+            ))
+            .then_expect_error(BankAccountError::TransferAlreadyApplied);
+    }
+
+    #[test]
+    fn cannot_refund_a_transfer_twice() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::TransferDebited {
+                    transfer_id: "T-1".to_string(),
+                    destination_account_id: "5678".to_string(),
+                    amount: usd(5000),
+                    balance: usd(-5000),
+                },
+                BankAccountEvent::TransferRefunded {
+                    transfer_id: "T-1".to_string(),
+                    amount: usd(5000),
+                    balance: usd(0),
+                },
+            ])
+            .when(BankAccountCommand::RefundTransfer(
+                BankAccountRefundTransferCommandData {
+                    transfer_id: "T-1".to_string(),
+                    amount: usd(5000),
+                },
+            ))
+            .then_expect_error(BankAccountError::TransferAlreadyApplied);
+    }
+
+    #[test]
+    fn cannot_refund_a_transfer_that_was_not_debited() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened()])
+            .when(BankAccountCommand::RefundTransfer(
+                BankAccountRefundTransferCommandData {
+                    transfer_id: "T-1".to_string(),
+                    amount: usd(5000),
+                },
+            ))
+            .then_expect_error(BankAccountError::TransferNotDebited);
+    }
+
+    fn account_opened() -> BankAccountEvent {
+        BankAccountEvent::AccountOpened {
+            account_id: "1234".to_string(),
+            currency: "USD".to_string(),
+            owner_id: None,
+        }
+    }
+
+    fn account_frozen() -> BankAccountEvent {
+        BankAccountEvent::AccountFrozen {
+            reason: "suspected fraud".to_string(),
+        }
+    }
+
+    #[test]
+    fn freeze_account() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened()])
+            .when(BankAccountCommand::FreezeAccount(
+                BankAccountFreezeAccountCommandData {
+                    reason: "suspected fraud".to_string(),
+                },
+            ))
+            .then_expect_events(vec![account_frozen()]);
+    }
+
+    #[test]
+    fn cannot_freeze_a_frozen_account() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened(), account_frozen()])
+            .when(BankAccountCommand::FreezeAccount(
+                BankAccountFreezeAccountCommandData {
+                    reason: "suspected fraud".to_string(),
+                },
+            ))
+            .then_expect_error(BankAccountError::AccountAlreadyFrozen);
+    }
+
+    #[test]
+    fn frozen_account_refuses_withdrawals() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(20000),
+                    balance: usd(20000),
+                },
+                account_frozen(),
+            ])
+            .when(BankAccountCommand::WithdrawMoney(
+                BankAccountWithdrawMoneyCommandData {
+                    amount: usd(10000),
+                    atm_id: "ATM34f1ba3c".to_string(),
+                },
+            ))
+            .then_expect_error(BankAccountError::AccountFrozen);
+    }
+
+    #[test]
+    fn frozen_account_refuses_checks() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(20000),
+                    balance: usd(20000),
+                },
+                account_frozen(),
+            ])
+            .when(BankAccountCommand::WriteCheck(
+                BankAccountWriteCheckCommandData {
+                    check_number: "1170".to_string(),
+                    amount: usd(10000),
+                },
+            ))
+            .then_expect_error(BankAccountError::AccountFrozen);
+    }
+
+    #[test]
+    fn frozen_account_accepts_deposits() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened(), account_frozen()])
+            .when(BankAccountCommand::DepositMoney(
+                BankAccountDepositMoneyCommandData { amount: usd(20000) },
+            ))
+            .then_expect_events(vec![BankAccountEvent::CustomerDepositedMoney {
+                amount: usd(20000),
+                balance: usd(20000),
+            }]);
+    }
+
+    #[test]
+    fn unfreeze_account() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened(), account_frozen()])
+            .when(BankAccountCommand::UnfreezeAccount(
+                BankAccountUnfreezeAccountCommandData {
+                    reason: "cleared".to_string(),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::AccountUnfrozen {
+                reason: "cleared".to_string(),
+            }]);
+    }
+
+    #[test]
+    fn cannot_unfreeze_an_account_that_is_not_frozen() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened()])
+            .when(BankAccountCommand::UnfreezeAccount(
+                BankAccountUnfreezeAccountCommandData {
+                    reason: "cleared".to_string(),
+                },
+            ))
+            .then_expect_error(BankAccountError::AccountNotFrozen);
+    }
+
+    #[test]
+    fn close_account_with_zero_balance() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened()])
+            .when(BankAccountCommand::CloseAccount(
+                BankAccountCloseAccountCommandData {
+                    reason: "customer request".to_string(),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::AccountClosed {
+                reason: "customer request".to_string(),
+            }]);
+    }
+
+    #[test]
+    fn cannot_close_account_with_non_zero_balance() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(1),
+                    balance: usd(1),
+                },
+            ])
+            .when(BankAccountCommand::CloseAccount(
+                BankAccountCloseAccountCommandData {
+                    reason: "customer request".to_string(),
+                },
+            ))
+            .then_expect_error(BankAccountError::CannotCloseAccountWithNonZeroBalance);
+    }
+
+    #[test]
+    fn closed_account_refuses_deposits() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::AccountClosed {
+                    reason: "customer request".to_string(),
+                },
+            ])
+            .when(BankAccountCommand::DepositMoney(
+                BankAccountDepositMoneyCommandData { amount: usd(20000) },
+            ))
+            .then_expect_error(BankAccountError::AccountClosed);
+    }
+
+    fn overdraft_limit_set(limit: i64) -> BankAccountEvent {
+        BankAccountEvent::OverdraftLimitSet { limit: usd(limit) }
+    }
+
+    #[test]
+    fn set_overdraft_limit() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened()])
+            .when(BankAccountCommand::SetOverdraftLimit(
+                BankAccountSetOverdraftLimitCommandData { limit: usd(50000) },
+            ))
+            .then_expect_events(vec![BankAccountEvent::OverdraftLimitSet {
+                limit: usd(50000),
+            }]);
+    }
+
+    #[test]
+    fn cannot_set_a_negative_overdraft_limit() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened()])
+            .when(BankAccountCommand::SetOverdraftLimit(
+                BankAccountSetOverdraftLimitCommandData { limit: usd(-10000) },
+            ))
+            .then_expect_error(BankAccountError::InvalidOverdraftLimit);
+    }
+
+    #[test]
+    fn cannot_set_an_overdraft_limit_in_another_currency() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened()])
+            .when(BankAccountCommand::SetOverdraftLimit(
+                BankAccountSetOverdraftLimitCommandData {
+                    limit: Money::new(10000, "EUR"),
+                },
+            ))
+            .then_expect_error(BankAccountError::CurrencyMismatch);
+    }
+
+    #[test]
+    fn cannot_lower_the_overdraft_limit_below_the_overdrawn_balance() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                overdraft_limit_set(50000),
+                BankAccountEvent::CustomerWithdrewCash {
+                    amount: usd(30000),
+                    balance: usd(-30000),
+                },
+            ])
+            .when(BankAccountCommand::SetOverdraftLimit(
+                BankAccountSetOverdraftLimitCommandData { limit: usd(20000) },
+            ))
+            .then_expect_error(BankAccountError::InvalidOverdraftLimit);
+    }
+
+    #[test]
+    fn withdrawal_into_the_overdraft_enters_overdraft() {
+        let services = MockBankAccountServices::default();
+        services.set_atm_withdrawal_response(Ok(()));
+
+        AccountTestFramework::with(BankAccountServices::new(Box::new(services), clock()))
+            .given(vec![
+                account_opened(),
+                overdraft_limit_set(50000),
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(10000),
+                    balance: usd(10000),
+                },
+            ])
+            .when(BankAccountCommand::WithdrawMoney(
+                BankAccountWithdrawMoneyCommandData {
+                    amount: usd(60000),
+                    atm_id: "ATM34f1ba3c".to_string(),
+                },
+            ))
+            .then_expect_events(vec![
+                BankAccountEvent::CustomerWithdrewCash {
+                    amount: usd(60000),
+                    balance: usd(-50000),
+                },
+                BankAccountEvent::OverdraftEntered {
+                    balance: usd(-50000),
+                },
+            ]);
+    }
+
+    #[test]
+    fn check_written_while_overdrawn_does_not_enter_overdraft_again() {
+        AccountTestFramework::with(check_services())
+            .given(vec![
+                account_opened(),
+                overdraft_limit_set(50000),
+                BankAccountEvent::CustomerWithdrewCash {
+                    amount: usd(10000),
+                    balance: usd(-10000),
+                },
+                BankAccountEvent::OverdraftEntered {
+                    balance: usd(-10000),
+                },
+            ])
+            .when(write_check(10000))
+            .then_expect_events(vec![BankAccountEvent::CustomerWroteCheck {
+                check_number: "1170".to_string(),
+                amount: usd(10000),
+                balance: usd(-20000),
+                written_at: Some(now()),
+            }]);
+    }
+
+    #[test]
+    fn cannot_withdraw_beyond_the_overdraft_limit() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened(), overdraft_limit_set(50000)])
+            .when(BankAccountCommand::WithdrawMoney(
+                BankAccountWithdrawMoneyCommandData {
+                    amount: usd(50001),
+                    atm_id: "ATM34f1ba3c".to_string(),
+                },
+            ))
+            .then_expect_error(BankAccountError::InsufficientFunds);
+    }
+
+    fn account_opened_by(owner_id: &str) -> BankAccountEvent {
+        BankAccountEvent::AccountOpened {
+            account_id: "1234".to_string(),
+            currency: "USD".to_string(),
+            owner_id: Some(owner_id.to_string()),
+        }
+    }
+
+    fn grant_access(user_id: &str, role: AccessRole) -> BankAccountCommand {
+        BankAccountCommand::GrantAccess(BankAccountGrantAccessCommandData {
+            user_id: user_id.to_string(),
+            role,
+        })
+    }
+
+    #[test]
+    fn grant_access() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened_by("1111")])
+            .when(grant_access("2222", AccessRole::CoOwner))
+            .then_expect_events(vec![BankAccountEvent::AccessGranted {
+                user_id: "2222".to_string(),
+                role: AccessRole::CoOwner,
+            }]);
+    }
+
+    #[test]
+    fn granting_another_role_changes_the_role() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened_by("1111"),
+                BankAccountEvent::AccessGranted {
+                    user_id: "2222".to_string(),
+                    role: AccessRole::ViewOnly,
+                },
+            ])
+            .when(grant_access("2222", AccessRole::PaymentsOnly))
+            .then_expect_events(vec![BankAccountEvent::AccessGranted {
+                user_id: "2222".to_string(),
+                role: AccessRole::PaymentsOnly,
+            }]);
+    }
+
+    #[test]
+    fn cannot_grant_the_same_role_twice() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened_by("1111"),
+                BankAccountEvent::AccessGranted {
+                    user_id: "2222".to_string(),
+                    role: AccessRole::ViewOnly,
+                },
+            ])
+            .when(grant_access("2222", AccessRole::ViewOnly))
+            .then_expect_error(BankAccountError::AccessAlreadyGranted);
+    }
+
+    #[test]
+    fn cannot_grant_the_owner_role() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened_by("1111")])
+            .when(grant_access("2222", AccessRole::Owner))
+            .then_expect_error(BankAccountError::CannotGrantOwnerRole);
+    }
+
+    #[test]
+    fn cannot_change_the_access_of_the_owner() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened_by("1111")])
+            .when(grant_access("1111", AccessRole::ViewOnly))
+            .then_expect_error(BankAccountError::CannotChangeOwnerAccess);
+
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened_by("1111")])
+            .when(BankAccountCommand::RevokeAccess(
+                BankAccountRevokeAccessCommandData {
+                    user_id: "1111".to_string(),
+                },
+            ))
+            .then_expect_error(BankAccountError::CannotChangeOwnerAccess);
+    }
+
+    #[test]
+    fn revoke_access() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened_by("1111"),
+                BankAccountEvent::AccessGranted {
+                    user_id: "2222".to_string(),
+                    role: AccessRole::CoOwner,
+                },
+            ])
+            .when(BankAccountCommand::RevokeAccess(
+                BankAccountRevokeAccessCommandData {
+                    user_id: "2222".to_string(),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::AccessRevoked {
+                user_id: "2222".to_string(),
+            }]);
+    }
+
+    #[test]
+    fn cannot_revoke_access_that_was_not_granted() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened_by("1111"),
+                BankAccountEvent::AccessGranted {
+                    user_id: "2222".to_string(),
+                    role: AccessRole::CoOwner,
+                },
+                BankAccountEvent::AccessRevoked {
+                    user_id: "2222".to_string(),
+                },
+            ])
+            .when(BankAccountCommand::RevokeAccess(
+                BankAccountRevokeAccessCommandData {
+                    user_id: "2222".to_string(),
+                },
+            ))
+            .then_expect_error(BankAccountError::AccessNotGranted);
+    }
+
+    fn assign_owner(owner_id: &str) -> BankAccountCommand {
+        BankAccountCommand::AssignOwner(BankAccountAssignOwnerCommandData {
+            owner_id: owner_id.to_string(),
+        })
+    }
+
+    #[test]
+    fn assign_owner_to_an_account_opened_without_one() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened()])
+            .when(assign_owner("1111"))
+            .then_expect_events(vec![BankAccountEvent::OwnerAssigned {
+                owner_id: "1111".to_string(),
+            }]);
+    }
+
+    #[test]
+    fn cannot_change_the_owner_of_an_account() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened_by("1111")])
+            .when(assign_owner("2222"))
+            .then_expect_error(BankAccountError::OwnerAlreadyAssigned);
+
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::OwnerAssigned {
+                    owner_id: "1111".to_string(),
+                },
+            ])
+            .when(assign_owner("2222"))
+            .then_expect_error(BankAccountError::OwnerAlreadyAssigned);
+    }
+
+    #[test]
+    fn the_assigned_owner_keeps_full_access() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                BankAccountEvent::AccessGranted {
+                    user_id: "1111".to_string(),
+                    role: AccessRole::ViewOnly,
+                },
+                BankAccountEvent::OwnerAssigned {
+                    owner_id: "1111".to_string(),
+                },
+            ])
+            .when(BankAccountCommand::RevokeAccess(
+                BankAccountRevokeAccessCommandData {
+                    user_id: "1111".to_string(),
+                },
+            ))
+            .then_expect_error(BankAccountError::CannotChangeOwnerAccess);
+    }
+
+    fn accrue_interest(date: &str) -> BankAccountCommand {
+        BankAccountCommand::AccrueInterest(BankAccountAccrueInterestCommandData {
+            date: date.to_string(),
+            annual_rate_basis_points: 200,
+        })
+    }
+
+    fn interest_accrued(date: &str, principal: i64, amount_micro_units: i64) -> BankAccountEvent {
+        BankAccountEvent::InterestAccrued {
+            date: parse_interest_date(date).unwrap(),
+            annual_rate_basis_points: 200,
+            principal: usd(principal),
+            amount_micro_units,
+        }
+    }
+
+    fn deposited(amount: i64) -> BankAccountEvent {
+        BankAccountEvent::CustomerDepositedMoney {
+            amount: usd(amount),
+            balance: usd(amount),
+        }
+    }
+
+    #[test]
+    fn accrue_interest() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened(), deposited(10000)])
+            .when(accrue_interest("2023-11-14"))
+            .then_expect_events(vec![interest_accrued("2023-11-14", 10000, 547_945)]);
+    }
+
+    #[test]
+    fn an_empty_account_earns_no_interest() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened()])
+            .when(accrue_interest("2023-11-14"))
+            .then_expect_events(vec![]);
+    }
+
+    #[test]
+    fn interest_is_posted_on_the_last_day_of_the_month() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                interest_accrued("2023-11-29", 10000, 600_000),
+            ])
+            .when(accrue_interest("2023-11-30"))
+            .then_expect_events(vec![
+                interest_accrued("2023-11-30", 10000, 547_945),
+                BankAccountEvent::InterestPosted {
+                    period: "2023-11".to_string(),
+                    amount: usd(1),
+                    balance: usd(10001),
+                },
+            ]);
+    }
+
+    #[test]
+    fn interest_of_a_missed_month_end_is_posted_before_accruing() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                interest_accrued("2023-11-29", 10000, 1_500_000),
+            ])
+            .when(accrue_interest("2023-12-02"))
+            .then_expect_events(vec![
+                BankAccountEvent::InterestPosted {
+                    period: "2023-11".to_string(),
+                    amount: usd(1),
+                    balance: usd(10001),
+                },
+                interest_accrued("2023-12-02", 10001, 548_000),
+            ]);
+    }
+
+    #[test]
+    fn cannot_accrue_interest_for_a_day_twice() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                interest_accrued("2023-11-14", 10000, 547_945),
+            ])
+            .when(accrue_interest("2023-11-14"))
+            .then_expect_error(BankAccountError::InterestAlreadyAccrued);
+    }
+
+    #[test]
+    fn cannot_accrue_interest_for_an_earlier_day() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                interest_accrued("2023-11-14", 10000, 547_945),
+            ])
+            .when(accrue_interest("2023-11-13"))
+            .then_expect_error(BankAccountError::InterestAlreadyAccrued);
+    }
+
+    #[test]
+    fn cannot_accrue_interest_for_a_day_yet_to_come() {
+        let tomorrow = (now() + chrono::Duration::days(1)).date_naive();
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened(), deposited(10000)])
+            .when(accrue_interest(&tomorrow.format("%Y-%m-%d").to_string()))
+            .then_expect_error(BankAccountError::InvalidInterestDate);
+    }
+
+    #[test]
+    fn cannot_accrue_interest_above_the_highest_rate() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened(), deposited(10000)])
+            .when(BankAccountCommand::AccrueInterest(
+                BankAccountAccrueInterestCommandData {
+                    date: "2023-11-14".to_string(),
+                    annual_rate_basis_points: MAX_ANNUAL_INTEREST_RATE_BASIS_POINTS + 1,
+                },
+            ))
+            .then_expect_error(BankAccountError::InvalidInterestRate);
+    }
+
+    #[test]
+    fn accrued_interest_that_would_overflow_is_rejected() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                interest_accrued("2023-11-13", 10000, i64::MAX),
+            ])
+            .when(accrue_interest("2023-11-14"))
+            .then_expect_error(BankAccountError::AmountOverflow);
+    }
+
+    fn place_hold(hold_id: &str, amount: i64) -> BankAccountCommand {
+        BankAccountCommand::PlaceHold(BankAccountPlaceHoldCommandData {
+            hold_id: hold_id.to_string(),
+            amount: usd(amount),
+            expires_at: "2023-11-21T12:00:00Z".to_string(),
+        })
+    }
+
+    fn hold_placed(hold_id: &str, amount: i64, available_balance: i64) -> BankAccountEvent {
+        BankAccountEvent::HoldPlaced {
+            hold_id: hold_id.to_string(),
+            amount: usd(amount),
+            expires_at: DateTime::parse_from_rfc3339("2023-11-21T12:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc),
+            available_balance: usd(available_balance),
+        }
+    }
+
+    #[test]
+    fn place_hold() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened(), deposited(10000)])
+            .when(place_hold("H-1", 2500))
+            .then_expect_events(vec![hold_placed("H-1", 2500, 7500)]);
+    }
+
+    #[test]
+    fn cannot_hold_more_than_the_available_balance() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                hold_placed("H-1", 8000, 2000),
+            ])
+            .when(place_hold("H-2", 2500))
+            .then_expect_error(BankAccountError::InsufficientFunds);
+    }
+
+    #[test]
+    fn cannot_use_a_hold_id_twice() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                hold_placed("H-1", 2500, 7500),
+                BankAccountEvent::HoldReleased {
+                    hold_id: "H-1".to_string(),
+                    amount: usd(2500),
+                    available_balance: usd(10000),
+                },
+            ])
+            .when(place_hold("H-1", 2500))
+            .then_expect_error(BankAccountError::InvalidHoldId);
+    }
+
+    #[test]
+    fn withdrawals_are_limited_to_the_available_balance() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                hold_placed("H-1", 8000, 2000),
+            ])
+            .when(BankAccountCommand::WithdrawMoney(
+                BankAccountWithdrawMoneyCommandData {
+                    amount: usd(3000),
+                    atm_id: "ATM34f1ba3c".to_string(),
+                },
+            ))
+            .then_expect_error(BankAccountError::InsufficientFunds);
+    }
+
+    #[test]
+    fn capture_part_of_a_hold() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                hold_placed("H-1", 2500, 7500),
+            ])
+            .when(BankAccountCommand::CaptureHold(
+                BankAccountCaptureHoldCommandData {
+                    hold_id: "H-1".to_string(),
+                    amount: Some(usd(2000)),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::HoldCaptured {
+                hold_id: "H-1".to_string(),
+                amount: usd(2000),
+                released: usd(500),
+                balance: usd(8000),
+                available_balance: usd(8000),
+            }]);
+    }
+
+    #[test]
+    fn cannot_capture_more_than_the_hold() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                hold_placed("H-1", 2500, 7500),
+            ])
+            .when(BankAccountCommand::CaptureHold(
+                BankAccountCaptureHoldCommandData {
+                    hold_id: "H-1".to_string(),
+                    amount: Some(usd(3000)),
+                },
+            ))
+            .then_expect_error(BankAccountError::InvalidCaptureAmount);
+    }
+
+    #[test]
+    fn release_hold() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                hold_placed("H-1", 2500, 7500),
+            ])
+            .when(BankAccountCommand::ReleaseHold(
+                BankAccountReleaseHoldCommandData {
+                    hold_id: "H-1".to_string(),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::HoldReleased {
+                hold_id: "H-1".to_string(),
+                amount: usd(2500),
+                available_balance: usd(10000),
+            }]);
+    }
+
+    #[test]
+    fn cannot_capture_an_expired_hold() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                hold_placed("H-1", 2500, 7500),
+                BankAccountEvent::HoldExpired {
+                    hold_id: "H-1".to_string(),
+                    amount: usd(2500),
+                    available_balance: usd(10000),
+                },
+            ])
+            .when(BankAccountCommand::CaptureHold(
+                BankAccountCaptureHoldCommandData {
+                    hold_id: "H-1".to_string(),
+                    amount: None,
+                },
+            ))
+            .then_expect_error(BankAccountError::HoldNotFound);
+    }
+
+    #[test]
+    fn expire_holds_releases_only_the_expired_holds() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                hold_placed("H-1", 2500, 7500),
+                BankAccountEvent::HoldPlaced {
+                    hold_id: "H-2".to_string(),
+                    amount: usd(1000),
+                    expires_at: DateTime::parse_from_rfc3339("2023-11-28T12:00:00Z")
+                        .unwrap()
+                        .with_timezone(&Utc),
+                    available_balance: usd(6500),
+                },
+            ])
+            .when(BankAccountCommand::ExpireHolds(
+                BankAccountExpireHoldsCommandData {
+                    now: "2023-11-21T12:00:00Z".to_string(),
+                },
+            ))
+            .then_expect_events(vec![BankAccountEvent::HoldExpired {
+                hold_id: "H-1".to_string(),
+                amount: usd(2500),
+                available_balance: usd(9000),
+            }]);
+    }
+
+    #[test]
+    fn holds_do_not_expire_ahead_of_the_server_clock() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                deposited(10000),
+                BankAccountEvent::HoldPlaced {
+                    hold_id: "H-1".to_string(),
+                    amount: usd(2500),
+                    expires_at: now() + Duration::seconds(1),
+                    available_balance: usd(7500),
+                },
+            ])
+            .when(BankAccountCommand::ExpireHolds(
+                BankAccountExpireHoldsCommandData {
+                    now: (now() + Duration::days(1)).to_rfc3339(),
+                },
+            ))
+            .then_expect_events(vec![]);
+    }
+
+    fn close_account() -> BankAccountCommand {
+        BankAccountCommand::CloseAccount(BankAccountCloseAccountCommandData {
+            reason: "customer request".to_string(),
+        })
+    }
+
+    #[test]
+    fn cannot_close_account_with_active_holds() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![account_opened(), hold_placed("H-1", 2500, -2500)])
+            .when(close_account())
+            .then_expect_error(BankAccountError::CannotCloseAccountWithActiveHolds);
+    }
+
+    #[test]
+    fn cannot_close_account_with_unposted_interest() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                interest_accrued("2023-11-14", 10000, 1_500_000),
+            ])
+            .when(close_account())
+            .then_expect_error(BankAccountError::CannotCloseAccountWithAccruedInterest);
+    }
+
+    #[test]
+    fn closing_forfeits_interest_below_a_minor_unit() {
+        let services = BankAccountServices::new(Box::<MockBankAccountServices>::default(), clock());
+        AccountTestFramework::with(services)
+            .given(vec![
+                account_opened(),
+                interest_accrued("2023-11-14", 10000, 547_945),
+            ])
+            .when(close_account())
+            .then_expect_events(vec![BankAccountEvent::AccountClosed {
+                reason: "customer request".to_string(),
             }]);
     }
 }
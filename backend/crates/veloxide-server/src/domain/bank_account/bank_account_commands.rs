@@ -3,6 +3,8 @@ use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 use utoipa::ToSchema;
 
+use crate::domain::{default_currency, AccessRole, Money};
+
 #[derive(Clone, Debug, Serialize, Deserialize, ToSchema, OneofObject, TS)]
 #[ts(export, export_to = "frontend/src/bindings/")]
 pub enum BankAccountCommand {
@@ -17,24 +19,85 @@ pub enum BankAccountCommand {
 
     /// WriteCheck
     WriteCheck(BankAccountWriteCheckCommandData),
+
+    /// CancelCheck, stops payment of a check and returns its amount to the account
+    CancelCheck(BankAccountCancelCheckCommandData),
+
+    /// DebitTransfer, issued on the source account of a transfer
+    DebitTransfer(BankAccountDebitTransferCommandData),
+
+    /// CreditTransfer, issued on the destination account of a transfer
+    CreditTransfer(BankAccountCreditTransferCommandData),
+
+    /// RefundTransfer, compensates a debit when the transfer could not be credited
+    RefundTransfer(BankAccountRefundTransferCommandData),
+
+    /// FreezeAccount, stops money leaving the account until it is unfrozen
+    FreezeAccount(BankAccountFreezeAccountCommandData),
+
+    /// UnfreezeAccount
+    UnfreezeAccount(BankAccountUnfreezeAccountCommandData),
+
+    /// CloseAccount, only possible once the balance is zero
+    CloseAccount(BankAccountCloseAccountCommandData),
+
+    /// SetOverdraftLimit, allows withdrawals and checks to take the balance down to `-limit`
+    SetOverdraftLimit(BankAccountSetOverdraftLimitCommandData),
+
+    /// GrantAccess, gives another user a role on the account or changes the role they have
+    GrantAccess(BankAccountGrantAccessCommandData),
+
+    /// RevokeAccess, takes the role of another user on the account away
+    RevokeAccess(BankAccountRevokeAccessCommandData),
+
+    /// AccrueInterest, earns a day of interest on the balance and posts the month's interest
+    /// to the account on its last day
+    AccrueInterest(BankAccountAccrueInterestCommandData),
+
+    /// PlaceHold, reserves part of the available balance until the hold is captured, released
+    /// or expires
+    PlaceHold(BankAccountPlaceHoldCommandData),
+
+    /// ReleaseHold, returns the amount of a hold to the available balance
+    ReleaseHold(BankAccountReleaseHoldCommandData),
+
+    /// CaptureHold, pays out all or part of a hold and releases the rest
+    CaptureHold(BankAccountCaptureHoldCommandData),
+
+    /// ExpireHolds, releases the holds that expired by `now`
+    ExpireHolds(BankAccountExpireHoldsCommandData),
+
+    /// AssignOwner, records the owner of an account opened before accounts had owners
+    AssignOwner(BankAccountAssignOwnerCommandData),
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
 #[ts(export, export_to = "frontend/src/bindings/")]
 pub struct BankAccountOpenAccountCommandData {
     pub account_id: String,
+
+    /// ISO-4217 code of the currency the account is held in, defaults to USD
+    #[serde(default = "default_currency")]
+    #[graphql(default_with = "default_currency()")]
+    pub currency: String,
+
+    /// Id of the `User` opening the account. Never taken from the request, the application
+    /// service sets it to the authenticated user.
+    #[serde(skip)]
+    #[graphql(skip)]
+    pub owner_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
 #[ts(export, export_to = "frontend/src/bindings/")]
 pub struct BankAccountDepositMoneyCommandData {
-    pub amount: f64,
+    pub amount: Money,
 }
 
 #[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
 #[ts(export, export_to = "frontend/src/bindings/")]
 pub struct BankAccountWithdrawMoneyCommandData {
-    pub amount: f64,
+    pub amount: Money,
     pub atm_id: String,
 }
 
@@ -42,5 +105,134 @@ pub struct BankAccountWithdrawMoneyCommandData {
 #[ts(export, export_to = "frontend/src/bindings/")]
 pub struct BankAccountWriteCheckCommandData {
     pub check_number: String,
-    pub amount: f64,
+    pub amount: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountCancelCheckCommandData {
+    pub check_number: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountDebitTransferCommandData {
+    pub transfer_id: String,
+    pub destination_account_id: String,
+    pub amount: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountCreditTransferCommandData {
+    pub transfer_id: String,
+    pub source_account_id: String,
+    pub amount: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountRefundTransferCommandData {
+    pub transfer_id: String,
+    pub amount: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountFreezeAccountCommandData {
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountUnfreezeAccountCommandData {
+    #[serde(default)]
+    #[graphql(default)]
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountCloseAccountCommandData {
+    #[serde(default)]
+    #[graphql(default)]
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountSetOverdraftLimitCommandData {
+    /// How far the balance may go below zero, in the account currency
+    pub limit: Money,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountGrantAccessCommandData {
+    /// Id of the `User` to grant access to
+    pub user_id: String,
+
+    /// Any role but `Owner`, the owner is whoever opened the account
+    pub role: AccessRole,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountRevokeAccessCommandData {
+    /// Id of the `User` to revoke access from
+    pub user_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountAccrueInterestCommandData {
+    /// The day to earn interest for, e.g. "2023-11-14"
+    pub date: String,
+
+    /// The annual interest rate in basis points, e.g. 425 is 4.25%
+    pub annual_rate_basis_points: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountPlaceHoldCommandData {
+    /// Id of the hold, chosen by the caller and never used again on the account
+    pub hold_id: String,
+
+    /// The amount to reserve, in the account currency
+    pub amount: Money,
+
+    /// RFC 3339 timestamp after which the hold is released if it was not captured
+    pub expires_at: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountReleaseHoldCommandData {
+    pub hold_id: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountCaptureHoldCommandData {
+    pub hold_id: String,
+
+    /// The amount to pay out, at most the amount held and the whole hold by default
+    #[serde(default)]
+    #[graphql(default)]
+    pub amount: Option<Money>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountExpireHoldsCommandData {
+    /// RFC 3339 timestamp, holds expiring at or before it are released
+    pub now: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct BankAccountAssignOwnerCommandData {
+    /// Id of the `User` that owns the account
+    pub owner_id: String,
 }
@@ -1,6 +1,10 @@
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
-#[derive(thiserror::Error, Debug, PartialEq)]
+use crate::domain::MoneyError;
+
+// Serializable so that the outcome of a command can be stored against its idempotency key.
+#[derive(thiserror::Error, Debug, PartialEq, Serialize, Deserialize)]
 pub enum BankAccountError {
     #[error("account already open")]
     AccountAlreadyOpen,
@@ -35,6 +39,96 @@ pub enum BankAccountError {
     #[error("account not open")]
     AccountNotOpen,
 
+    #[error("currency mismatch")]
+    CurrencyMismatch,
+
+    #[error("amount overflow")]
+    AmountOverflow,
+
+    #[error("invalid currency")]
+    InvalidCurrency,
+
+    #[error("exchange rate unavailable")]
+    ExchangeRateUnavailable,
+
+    #[error("account frozen")]
+    AccountFrozen,
+
+    #[error("account already frozen")]
+    AccountAlreadyFrozen,
+
+    #[error("account not frozen")]
+    AccountNotFrozen,
+
+    #[error("account closed")]
+    AccountClosed,
+
+    #[error("cannot close account with non-zero balance")]
+    CannotCloseAccountWithNonZeroBalance,
+
+    #[error("cannot close account with active holds")]
+    CannotCloseAccountWithActiveHolds,
+
+    #[error("cannot close account with unposted interest")]
+    CannotCloseAccountWithAccruedInterest,
+
+    #[error("invalid overdraft limit")]
+    InvalidOverdraftLimit,
+
+    #[error("invalid access role")]
+    InvalidAccessRole,
+
+    #[error("the owner role cannot be granted")]
+    CannotGrantOwnerRole,
+
+    #[error("the access of the account owner cannot be changed")]
+    CannotChangeOwnerAccess,
+
+    #[error("the account already has an owner")]
+    OwnerAlreadyAssigned,
+
+    #[error("access already granted")]
+    AccessAlreadyGranted,
+
+    #[error("access not granted")]
+    AccessNotGranted,
+
+    #[error("check not found")]
+    CheckNotFound,
+
+    #[error("check already cancelled")]
+    CheckAlreadyCancelled,
+
+    #[error("check already cleared")]
+    CheckAlreadyCleared,
+
+    #[error("invalid interest date")]
+    InvalidInterestDate,
+
+    #[error("interest already accrued for the day")]
+    InterestAlreadyAccrued,
+
+    #[error("invalid interest rate")]
+    InvalidInterestRate,
+
+    #[error("invalid hold id")]
+    InvalidHoldId,
+
+    #[error("invalid hold expiry")]
+    InvalidHoldExpiry,
+
+    #[error("hold not found")]
+    HoldNotFound,
+
+    #[error("cannot capture more than the amount held")]
+    InvalidCaptureAmount,
+
+    #[error("transfer already applied to the account")]
+    TransferAlreadyApplied,
+
+    #[error("transfer was not debited from the account")]
+    TransferNotDebited,
+
     #[error("Unexpected Error: {0}")]
     UnexpectedError(String),
 }
@@ -51,11 +145,56 @@ impl From<&str> for BankAccountError {
             "InvalidCheck" => BankAccountError::InvalidCheck,
             "AtmRuleViolation" => BankAccountError::AtmRuleViolation,
             "CannotWriteNegativeCheckAmount" => BankAccountError::CannotWriteNegativeCheckAmount,
+            "CurrencyMismatch" => BankAccountError::CurrencyMismatch,
+            "AmountOverflow" => BankAccountError::AmountOverflow,
+            "InvalidCurrency" => BankAccountError::InvalidCurrency,
+            "ExchangeRateUnavailable" => BankAccountError::ExchangeRateUnavailable,
+            "AccountFrozen" => BankAccountError::AccountFrozen,
+            "AccountAlreadyFrozen" => BankAccountError::AccountAlreadyFrozen,
+            "AccountNotFrozen" => BankAccountError::AccountNotFrozen,
+            "AccountClosed" => BankAccountError::AccountClosed,
+            "CannotCloseAccountWithNonZeroBalance" => {
+                BankAccountError::CannotCloseAccountWithNonZeroBalance
+            }
+            "CannotCloseAccountWithActiveHolds" => {
+                BankAccountError::CannotCloseAccountWithActiveHolds
+            }
+            "CannotCloseAccountWithAccruedInterest" => {
+                BankAccountError::CannotCloseAccountWithAccruedInterest
+            }
+            "InvalidOverdraftLimit" => BankAccountError::InvalidOverdraftLimit,
+            "InvalidAccessRole" => BankAccountError::InvalidAccessRole,
+            "CannotGrantOwnerRole" => BankAccountError::CannotGrantOwnerRole,
+            "CannotChangeOwnerAccess" => BankAccountError::CannotChangeOwnerAccess,
+            "OwnerAlreadyAssigned" => BankAccountError::OwnerAlreadyAssigned,
+            "AccessAlreadyGranted" => BankAccountError::AccessAlreadyGranted,
+            "AccessNotGranted" => BankAccountError::AccessNotGranted,
+            "CheckNotFound" => BankAccountError::CheckNotFound,
+            "CheckAlreadyCancelled" => BankAccountError::CheckAlreadyCancelled,
+            "CheckAlreadyCleared" => BankAccountError::CheckAlreadyCleared,
+            "InvalidInterestDate" => BankAccountError::InvalidInterestDate,
+            "InterestAlreadyAccrued" => BankAccountError::InterestAlreadyAccrued,
+            "InvalidInterestRate" => BankAccountError::InvalidInterestRate,
+            "InvalidHoldId" => BankAccountError::InvalidHoldId,
+            "InvalidHoldExpiry" => BankAccountError::InvalidHoldExpiry,
+            "HoldNotFound" => BankAccountError::HoldNotFound,
+            "InvalidCaptureAmount" => BankAccountError::InvalidCaptureAmount,
+            "TransferAlreadyApplied" => BankAccountError::TransferAlreadyApplied,
+            "TransferNotDebited" => BankAccountError::TransferNotDebited,
             _ => BankAccountError::UnexpectedError(msg.to_string()),
         }
     }
 }
 
+impl From<MoneyError> for BankAccountError {
+    fn from(error: MoneyError) -> Self {
+        match error {
+            MoneyError::CurrencyMismatch { .. } => BankAccountError::CurrencyMismatch,
+            MoneyError::Overflow => BankAccountError::AmountOverflow,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -91,10 +230,115 @@ mod tests {
         let error = BankAccountError::from("CannotWriteNegativeCheckAmount");
         assert_eq!(error, BankAccountError::CannotWriteNegativeCheckAmount);
 
+        let error = BankAccountError::from("CurrencyMismatch");
+        assert_eq!(error, BankAccountError::CurrencyMismatch);
+
+        let error = BankAccountError::from("AmountOverflow");
+        assert_eq!(error, BankAccountError::AmountOverflow);
+
+        let error = BankAccountError::from("InvalidCurrency");
+        assert_eq!(error, BankAccountError::InvalidCurrency);
+
+        let error = BankAccountError::from("ExchangeRateUnavailable");
+        assert_eq!(error, BankAccountError::ExchangeRateUnavailable);
+
+        let error = BankAccountError::from("AccountFrozen");
+        assert_eq!(error, BankAccountError::AccountFrozen);
+
+        let error = BankAccountError::from("AccountAlreadyFrozen");
+        assert_eq!(error, BankAccountError::AccountAlreadyFrozen);
+
+        let error = BankAccountError::from("AccountNotFrozen");
+        assert_eq!(error, BankAccountError::AccountNotFrozen);
+
+        let error = BankAccountError::from("AccountClosed");
+        assert_eq!(error, BankAccountError::AccountClosed);
+
+        let error = BankAccountError::from("CannotCloseAccountWithNonZeroBalance");
+        assert_eq!(
+            error,
+            BankAccountError::CannotCloseAccountWithNonZeroBalance
+        );
+
+        let error = BankAccountError::from("CannotCloseAccountWithActiveHolds");
+        assert_eq!(error, BankAccountError::CannotCloseAccountWithActiveHolds);
+
+        let error = BankAccountError::from("CannotCloseAccountWithAccruedInterest");
+        assert_eq!(
+            error,
+            BankAccountError::CannotCloseAccountWithAccruedInterest
+        );
+
+        let error = BankAccountError::from("InvalidOverdraftLimit");
+        assert_eq!(error, BankAccountError::InvalidOverdraftLimit);
+
+        let error = BankAccountError::from("InvalidAccessRole");
+        assert_eq!(error, BankAccountError::InvalidAccessRole);
+
+        let error = BankAccountError::from("CannotGrantOwnerRole");
+        assert_eq!(error, BankAccountError::CannotGrantOwnerRole);
+
+        let error = BankAccountError::from("CannotChangeOwnerAccess");
+        assert_eq!(error, BankAccountError::CannotChangeOwnerAccess);
+
+        let error = BankAccountError::from("AccessAlreadyGranted");
+        assert_eq!(error, BankAccountError::AccessAlreadyGranted);
+
+        let error = BankAccountError::from("AccessNotGranted");
+        assert_eq!(error, BankAccountError::AccessNotGranted);
+
+        let error = BankAccountError::from("CheckNotFound");
+        assert_eq!(error, BankAccountError::CheckNotFound);
+
+        let error = BankAccountError::from("CheckAlreadyCancelled");
+        assert_eq!(error, BankAccountError::CheckAlreadyCancelled);
+
+        let error = BankAccountError::from("CheckAlreadyCleared");
+        assert_eq!(error, BankAccountError::CheckAlreadyCleared);
+
+        let error = BankAccountError::from("InvalidInterestDate");
+        assert_eq!(error, BankAccountError::InvalidInterestDate);
+
+        let error = BankAccountError::from("InterestAlreadyAccrued");
+        assert_eq!(error, BankAccountError::InterestAlreadyAccrued);
+
+        let error = BankAccountError::from("InvalidInterestRate");
+        assert_eq!(error, BankAccountError::InvalidInterestRate);
+
+        let error = BankAccountError::from("InvalidHoldId");
+        assert_eq!(error, BankAccountError::InvalidHoldId);
+
+        let error = BankAccountError::from("InvalidHoldExpiry");
+        assert_eq!(error, BankAccountError::InvalidHoldExpiry);
+
+        let error = BankAccountError::from("HoldNotFound");
+        assert_eq!(error, BankAccountError::HoldNotFound);
+
+        let error = BankAccountError::from("InvalidCaptureAmount");
+        assert_eq!(error, BankAccountError::InvalidCaptureAmount);
+
+        let error = BankAccountError::from("TransferAlreadyApplied");
+        assert_eq!(error, BankAccountError::TransferAlreadyApplied);
+
+        let error = BankAccountError::from("TransferNotDebited");
+        assert_eq!(error, BankAccountError::TransferNotDebited);
+
         let error = BankAccountError::from("AnyNonMatchingErrorString");
         assert_eq!(
             error,
             BankAccountError::UnexpectedError("AnyNonMatchingErrorString".to_string())
         );
     }
+
+    #[test]
+    fn can_create_bank_account_error_from_money_error() {
+        let error = BankAccountError::from(MoneyError::CurrencyMismatch {
+            expected: "USD".to_string(),
+            actual: "EUR".to_string(),
+        });
+        assert_eq!(error, BankAccountError::CurrencyMismatch);
+
+        let error = BankAccountError::from(MoneyError::Overflow);
+        assert_eq!(error, BankAccountError::AmountOverflow);
+    }
 }
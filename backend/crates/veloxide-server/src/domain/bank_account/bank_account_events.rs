@@ -2,38 +2,250 @@ use cqrs_es::DomainEvent;
 use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
 
+use chrono::{DateTime, NaiveDate, Utc};
+
+use crate::domain::{default_currency, AccessRole, ExchangeRate, Money};
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum BankAccountEvent {
     AccountOpened {
         account_id: String,
+        #[serde(default = "default_currency")]
+        currency: String,
+        /// Id of the `User` that opened the account, absent for accounts opened before
+        /// accounts had owners
+        #[serde(default)]
+        owner_id: Option<String>,
     },
     CustomerDepositedMoney {
-        amount: f64,
-        balance: f64,
+        amount: Money,
+        balance: Money,
+    },
+    CustomerDepositedForeignCurrency {
+        original_amount: Money,
+        exchange_rate: ExchangeRate,
+        amount: Money,
+        balance: Money,
     },
     CustomerWithdrewCash {
-        amount: f64,
-        balance: f64,
+        amount: Money,
+        balance: Money,
     },
     CustomerWroteCheck {
         check_number: String,
-        amount: f64,
-        balance: f64,
+        amount: Money,
+        balance: Money,
+        /// When the check was written, absent for checks written before that was recorded
+        #[serde(default)]
+        written_at: Option<DateTime<Utc>>,
+    },
+    CheckCancelled {
+        check_number: String,
+        amount: Money,
+        balance: Money,
+    },
+    TransferDebited {
+        transfer_id: String,
+        destination_account_id: String,
+        amount: Money,
+        balance: Money,
+    },
+    TransferCredited {
+        transfer_id: String,
+        source_account_id: String,
+        amount: Money,
+        balance: Money,
+    },
+    TransferRefunded {
+        transfer_id: String,
+        amount: Money,
+        balance: Money,
+    },
+    AccountFrozen {
+        reason: String,
+    },
+    AccountUnfrozen {
+        reason: String,
+    },
+    AccountClosed {
+        reason: String,
+    },
+    OverdraftLimitSet {
+        limit: Money,
+    },
+    OverdraftEntered {
+        balance: Money,
+    },
+    AccessGranted {
+        user_id: String,
+        role: AccessRole,
+    },
+    AccessRevoked {
+        user_id: String,
+    },
+    /// The owner of an account opened before accounts had owners was recorded
+    OwnerAssigned {
+        owner_id: String,
+    },
+    InterestAccrued {
+        /// The day the interest was earned on, e.g. "2023-11-14"
+        date: NaiveDate,
+        annual_rate_basis_points: u32,
+        /// The balance the interest was earned on
+        principal: Money,
+        /// Interest earned on the day, in millionths of the minor unit, see `INTEREST_MICRO_UNITS`
+        amount_micro_units: i64,
+    },
+    InterestPosted {
+        /// The month the interest was earned in, e.g. "2023-11"
+        period: String,
+        amount: Money,
+        balance: Money,
+    },
+    HoldPlaced {
+        hold_id: String,
+        amount: Money,
+        expires_at: DateTime<Utc>,
+        /// The balance left available once the hold is placed
+        available_balance: Money,
+    },
+    HoldReleased {
+        hold_id: String,
+        amount: Money,
+        available_balance: Money,
+    },
+    HoldExpired {
+        hold_id: String,
+        amount: Money,
+        available_balance: Money,
+    },
+    HoldCaptured {
+        hold_id: String,
+        /// The amount paid out
+        amount: Money,
+        /// The rest of the hold, returned to the available balance
+        released: Money,
+        balance: Money,
+        available_balance: Money,
     },
 }
 
+impl BankAccountEvent {
+    /// The balance of the account after the event, for the events that change or report it.
+    pub fn balance(&self) -> Option<&Money> {
+        match self {
+            BankAccountEvent::CustomerDepositedMoney { balance, .. }
+            | BankAccountEvent::CustomerDepositedForeignCurrency { balance, .. }
+            | BankAccountEvent::CustomerWithdrewCash { balance, .. }
+            | BankAccountEvent::CustomerWroteCheck { balance, .. }
+            | BankAccountEvent::CheckCancelled { balance, .. }
+            | BankAccountEvent::TransferDebited { balance, .. }
+            | BankAccountEvent::TransferCredited { balance, .. }
+            | BankAccountEvent::TransferRefunded { balance, .. }
+            | BankAccountEvent::InterestPosted { balance, .. }
+            | BankAccountEvent::HoldCaptured { balance, .. }
+            | BankAccountEvent::OverdraftEntered { balance } => Some(balance),
+            BankAccountEvent::AccountOpened { .. }
+            | BankAccountEvent::AccountFrozen { .. }
+            | BankAccountEvent::AccountUnfrozen { .. }
+            | BankAccountEvent::AccountClosed { .. }
+            | BankAccountEvent::OverdraftLimitSet { .. }
+            | BankAccountEvent::AccessGranted { .. }
+            | BankAccountEvent::AccessRevoked { .. }
+            | BankAccountEvent::OwnerAssigned { .. }
+            | BankAccountEvent::InterestAccrued { .. }
+            | BankAccountEvent::HoldPlaced { .. }
+            | BankAccountEvent::HoldReleased { .. }
+            | BankAccountEvent::HoldExpired { .. } => None,
+        }
+    }
+}
+
+/// The `event_type` of every kind of `BankAccountEvent`, e.g. for subscribers to filter on.
+pub const BANK_ACCOUNT_EVENT_TYPES: &[&str] = &[
+    "AccountOpened",
+    "CustomerDepositedMoney",
+    "CustomerDepositedForeignCurrency",
+    "CustomerWithdrewCash",
+    "CustomerWroteCheck",
+    "CheckCancelled",
+    "TransferDebited",
+    "TransferCredited",
+    "TransferRefunded",
+    "AccountFrozen",
+    "AccountUnfrozen",
+    "AccountClosed",
+    "OverdraftLimitSet",
+    "OverdraftEntered",
+    "AccessGranted",
+    "AccessRevoked",
+    "OwnerAssigned",
+    "InterestAccrued",
+    "InterestPosted",
+    "HoldPlaced",
+    "HoldReleased",
+    "HoldExpired",
+    "HoldCaptured",
+];
+
 impl DomainEvent for BankAccountEvent {
     fn event_type(&self) -> String {
         match self {
             BankAccountEvent::AccountOpened { .. } => "AccountOpened".to_string(),
             BankAccountEvent::CustomerDepositedMoney { .. } => "CustomerDepositedMoney".to_string(),
+            BankAccountEvent::CustomerDepositedForeignCurrency { .. } => {
+                "CustomerDepositedForeignCurrency".to_string()
+            }
             BankAccountEvent::CustomerWithdrewCash { .. } => "CustomerWithdrewCash".to_string(),
             BankAccountEvent::CustomerWroteCheck { .. } => "CustomerWroteCheck".to_string(),
+            BankAccountEvent::CheckCancelled { .. } => "CheckCancelled".to_string(),
+            BankAccountEvent::TransferDebited { .. } => "TransferDebited".to_string(),
+            BankAccountEvent::TransferCredited { .. } => "TransferCredited".to_string(),
+            BankAccountEvent::TransferRefunded { .. } => "TransferRefunded".to_string(),
+            BankAccountEvent::AccountFrozen { .. } => "AccountFrozen".to_string(),
+            BankAccountEvent::AccountUnfrozen { .. } => "AccountUnfrozen".to_string(),
+            BankAccountEvent::AccountClosed { .. } => "AccountClosed".to_string(),
+            BankAccountEvent::OverdraftLimitSet { .. } => "OverdraftLimitSet".to_string(),
+            BankAccountEvent::OverdraftEntered { .. } => "OverdraftEntered".to_string(),
+            BankAccountEvent::AccessGranted { .. } => "AccessGranted".to_string(),
+            BankAccountEvent::AccessRevoked { .. } => "AccessRevoked".to_string(),
+            BankAccountEvent::OwnerAssigned { .. } => "OwnerAssigned".to_string(),
+            BankAccountEvent::InterestAccrued { .. } => "InterestAccrued".to_string(),
+            BankAccountEvent::InterestPosted { .. } => "InterestPosted".to_string(),
+            BankAccountEvent::HoldPlaced { .. } => "HoldPlaced".to_string(),
+            BankAccountEvent::HoldReleased { .. } => "HoldReleased".to_string(),
+            BankAccountEvent::HoldExpired { .. } => "HoldExpired".to_string(),
+            BankAccountEvent::HoldCaptured { .. } => "HoldCaptured".to_string(),
         }
     }
 
+    // Events carrying amounts moved from f64 to `Money` in 2.0, see `bank_account_upcasters`.
     fn event_version(&self) -> String {
-        "1.0".to_string()
+        match self {
+            BankAccountEvent::AccountOpened { .. }
+            | BankAccountEvent::CustomerDepositedForeignCurrency { .. }
+            | BankAccountEvent::CheckCancelled { .. }
+            | BankAccountEvent::TransferDebited { .. }
+            | BankAccountEvent::TransferCredited { .. }
+            | BankAccountEvent::TransferRefunded { .. }
+            | BankAccountEvent::AccountFrozen { .. }
+            | BankAccountEvent::AccountUnfrozen { .. }
+            | BankAccountEvent::AccountClosed { .. }
+            | BankAccountEvent::OverdraftLimitSet { .. }
+            | BankAccountEvent::OverdraftEntered { .. }
+            | BankAccountEvent::AccessGranted { .. }
+            | BankAccountEvent::AccessRevoked { .. }
+            | BankAccountEvent::OwnerAssigned { .. }
+            | BankAccountEvent::InterestAccrued { .. }
+            | BankAccountEvent::InterestPosted { .. }
+            | BankAccountEvent::HoldPlaced { .. }
+            | BankAccountEvent::HoldReleased { .. }
+            | BankAccountEvent::HoldExpired { .. }
+            | BankAccountEvent::HoldCaptured { .. } => "1.0".to_string(),
+            BankAccountEvent::CustomerDepositedMoney { .. }
+            | BankAccountEvent::CustomerWithdrewCash { .. }
+            | BankAccountEvent::CustomerWroteCheck { .. } => "2.0".to_string(),
+        }
     }
 }
 
@@ -46,14 +258,27 @@ mod tests {
     fn bank_account_event_version_is_1_0() {
         let event = BankAccountEvent::AccountOpened {
             account_id: "123".to_string(),
+            currency: "USD".to_string(),
+            owner_id: None,
         };
         assert_eq!(event.event_version(), "1.0".to_string());
     }
 
+    #[test]
+    fn bank_account_money_event_version_is_2_0() {
+        let event = BankAccountEvent::CustomerDepositedMoney {
+            amount: Money::new(10000, "USD"),
+            balance: Money::new(10000, "USD"),
+        };
+        assert_eq!(event.event_version(), "2.0".to_string());
+    }
+
     #[test]
     fn bank_account_event_type_is_account_opened() {
         let event = BankAccountEvent::AccountOpened {
             account_id: "123".to_string(),
+            currency: "USD".to_string(),
+            owner_id: None,
         };
         assert_eq!(event.event_type(), "AccountOpened".to_string());
     }
@@ -61,17 +286,46 @@ mod tests {
     #[test]
     fn bank_account_event_type_is_customer_deposited_money() {
         let event = BankAccountEvent::CustomerDepositedMoney {
-            amount: 100.0,
-            balance: 100.0,
+            amount: Money::new(10000, "USD"),
+            balance: Money::new(10000, "USD"),
         };
         assert_eq!(event.event_type(), "CustomerDepositedMoney".to_string());
     }
 
+    #[test]
+    fn bank_account_event_type_is_customer_deposited_foreign_currency() {
+        let event = BankAccountEvent::CustomerDepositedForeignCurrency {
+            original_amount: Money::new(10000, "EUR"),
+            exchange_rate: ExchangeRate::new("EUR", "USD", 108_530_000),
+            amount: Money::new(10853, "USD"),
+            balance: Money::new(10853, "USD"),
+        };
+        assert_eq!(
+            event.event_type(),
+            "CustomerDepositedForeignCurrency".to_string()
+        );
+    }
+
+    #[test]
+    fn account_opened_without_currency_defaults_to_usd() {
+        let event: BankAccountEvent =
+            serde_json::from_value(serde_json::json!({"AccountOpened": {"account_id": "123"}}))
+                .unwrap();
+        assert_eq!(
+            event,
+            BankAccountEvent::AccountOpened {
+                account_id: "123".to_string(),
+                currency: "USD".to_string(),
+                owner_id: None,
+            }
+        );
+    }
+
     #[test]
     fn bank_account_event_type_is_customer_withdrew_cash() {
         let event = BankAccountEvent::CustomerWithdrewCash {
-            amount: 100.0,
-            balance: 100.0,
+            amount: Money::new(10000, "USD"),
+            balance: Money::new(10000, "USD"),
         };
         assert_eq!(event.event_type(), "CustomerWithdrewCash".to_string());
     }
@@ -80,9 +334,84 @@ mod tests {
     fn bank_account_event_type_is_customer_wrote_check() {
         let event = BankAccountEvent::CustomerWroteCheck {
             check_number: "123".to_string(),
-            amount: 100.0,
-            balance: 100.0,
+            amount: Money::new(10000, "USD"),
+            balance: Money::new(10000, "USD"),
+            written_at: None,
         };
         assert_eq!(event.event_type(), "CustomerWroteCheck".to_string());
     }
+
+    #[test]
+    fn bank_account_event_type_is_transfer_refunded() {
+        let event = BankAccountEvent::TransferRefunded {
+            transfer_id: "T-1".to_string(),
+            amount: Money::new(10000, "USD"),
+            balance: Money::new(10000, "USD"),
+        };
+        assert_eq!(event.event_type(), "TransferRefunded".to_string());
+    }
+
+    #[test]
+    fn bank_account_event_type_is_account_closed() {
+        let event = BankAccountEvent::AccountClosed {
+            reason: "customer request".to_string(),
+        };
+        assert_eq!(event.event_type(), "AccountClosed".to_string());
+    }
+
+    #[test]
+    fn bank_account_event_type_is_overdraft_entered() {
+        let event = BankAccountEvent::OverdraftEntered {
+            balance: Money::new(-100, "USD"),
+        };
+        assert_eq!(event.event_type(), "OverdraftEntered".to_string());
+    }
+
+    #[test]
+    fn bank_account_event_type_is_access_granted() {
+        let event = BankAccountEvent::AccessGranted {
+            user_id: "5678".to_string(),
+            role: AccessRole::ViewOnly,
+        };
+        assert_eq!(event.event_type(), "AccessGranted".to_string());
+        assert_eq!(event.event_version(), "1.0".to_string());
+    }
+
+    #[test]
+    fn bank_account_event_type_is_interest_posted() {
+        let event = BankAccountEvent::InterestPosted {
+            period: "2023-11".to_string(),
+            amount: Money::new(16, "USD"),
+            balance: Money::new(10016, "USD"),
+        };
+        assert_eq!(event.event_type(), "InterestPosted".to_string());
+        assert_eq!(event.balance(), Some(&Money::new(10016, "USD")));
+    }
+
+    #[test]
+    fn bank_account_event_type_is_hold_captured() {
+        let event = BankAccountEvent::HoldCaptured {
+            hold_id: "H-1".to_string(),
+            amount: Money::new(2000, "USD"),
+            released: Money::new(500, "USD"),
+            balance: Money::new(8000, "USD"),
+            available_balance: Money::new(8000, "USD"),
+        };
+        assert_eq!(event.event_type(), "HoldCaptured".to_string());
+        assert_eq!(event.balance(), Some(&Money::new(8000, "USD")));
+    }
+
+    #[test]
+    fn only_events_that_report_a_balance_have_one() {
+        let event = BankAccountEvent::CustomerWithdrewCash {
+            amount: Money::new(2000, "USD"),
+            balance: Money::new(8000, "USD"),
+        };
+        assert_eq!(event.balance(), Some(&Money::new(8000, "USD")));
+
+        let event = BankAccountEvent::AccountFrozen {
+            reason: "suspected fraud".to_string(),
+        };
+        assert_eq!(event.balance(), None);
+    }
 }
@@ -0,0 +1,80 @@
+use chrono::{Datelike, Months, NaiveDate};
+
+use crate::domain::Money;
+
+/// Accrued interest is kept in millionths of the currency's minor unit, so that the interest on
+/// small balances adds up over the month instead of being rounded away every day.
+pub const INTEREST_MICRO_UNITS: i64 = 1_000_000;
+
+/// Interest accrues daily at 1/365th of the annual rate, whatever the length of the year.
+pub const INTEREST_DAYS_PER_YEAR: i64 = 365;
+
+const BASIS_POINTS: i64 = 10_000;
+
+/// The highest annual rate interest accrues at, 100%. Anything higher is taken to be a mistake.
+pub const MAX_ANNUAL_INTEREST_RATE_BASIS_POINTS: u32 = 10_000;
+
+/// The interest earned by `balance` over one day, in millionths of its minor unit and rounded
+/// down. Balances at or below zero earn nothing.
+pub fn daily_interest_micro_units(balance: &Money, annual_rate_basis_points: u32) -> i64 {
+    if balance.minor_units <= 0 {
+        return 0;
+    }
+    let interest = i128::from(balance.minor_units)
+        * i128::from(annual_rate_basis_points)
+        * i128::from(INTEREST_MICRO_UNITS)
+        / i128::from(BASIS_POINTS * INTEREST_DAYS_PER_YEAR);
+    i64::try_from(interest).unwrap_or(i64::MAX)
+}
+
+/// Parses a calendar date such as "2023-11-14".
+pub fn parse_interest_date(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d").ok()
+}
+
+/// The month the date falls in, as interest is posted for, e.g. "2023-11".
+pub fn interest_period(date: NaiveDate) -> String {
+    date.format("%Y-%m").to_string()
+}
+
+pub fn is_last_day_of_month(date: NaiveDate) -> bool {
+    date.succ_opt()
+        .map_or(true, |next_day| next_day.month() != date.month())
+}
+
+/// The first day of the month after the one `date` falls in.
+pub fn first_day_of_next_month(date: NaiveDate) -> Option<NaiveDate> {
+    date.with_day(1)?.checked_add_months(Months::new(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn daily_interest_keeps_fractions_of_a_minor_unit() {
+        // 100.00 USD at 2% earns 0.5479 cents a day.
+        assert_eq!(
+            daily_interest_micro_units(&Money::new(10_000, "USD"), 200),
+            547_945
+        );
+        assert_eq!(
+            daily_interest_micro_units(&Money::new(-10_000, "USD"), 200),
+            0
+        );
+    }
+
+    #[test]
+    fn months_end_on_their_last_day() {
+        let date = |date: &str| parse_interest_date(date).unwrap();
+        assert!(is_last_day_of_month(date("2024-02-29")));
+        assert!(!is_last_day_of_month(date("2023-11-14")));
+        assert_eq!(interest_period(date("2023-11-14")), "2023-11");
+        assert_eq!(
+            first_day_of_next_month(date("2023-12-31")),
+            Some(date("2024-01-01"))
+        );
+        assert_eq!(parse_interest_date("14/11/2023"), None);
+    }
+}
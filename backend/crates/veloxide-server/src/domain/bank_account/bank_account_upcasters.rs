@@ -0,0 +1,140 @@
+use cqrs_es::persist::EventUpcaster;
+use serde_json::Value;
+
+use crate::domain::{Money, UpcasterRegistry, DEFAULT_CURRENCY};
+
+const MONEY_EVENT_TYPES: [&str; 3] = [
+    "CustomerDepositedMoney",
+    "CustomerWithdrewCash",
+    "CustomerWroteCheck",
+];
+const MONEY_FIELDS: [&str; 2] = ["amount", "balance"];
+
+/// The migrations of stored bank account events. Bumping the `event_version` of a
+/// `BankAccountEvent` means registering how to get there from the previous version here.
+pub fn bank_account_upcaster_registry() -> UpcasterRegistry {
+    MONEY_EVENT_TYPES
+        .iter()
+        .fold(UpcasterRegistry::new(), |registry, event_type| {
+            registry.register(event_type, "1.0", "2.0", upcast_f64_amounts_to_money)
+        })
+}
+
+/// The upcasters that must be registered with the event store so that previously stored
+/// bank account events can still be deserialized into the current `BankAccountEvent` shape.
+pub fn bank_account_upcasters() -> Vec<Box<dyn EventUpcaster>> {
+    vec![Box::new(bank_account_upcaster_registry())]
+}
+
+// 1.0 events stored `amount` and `balance` as f64 major units with no currency, e.g.
+// `{"CustomerDepositedMoney": {"amount": 200.0, "balance": 200.0}}`.
+fn upcast_f64_amounts_to_money(mut payload: Value) -> Value {
+    if let Some(Value::Object(fields)) = payload.as_object_mut().and_then(|p| p.values_mut().next())
+    {
+        for field in MONEY_FIELDS {
+            if let Some(amount) = fields.get(field).and_then(Value::as_f64) {
+                let money = Money::from_major_units_f64(amount, DEFAULT_CURRENCY);
+                fields.insert(
+                    field.to_string(),
+                    serde_json::to_value(money).expect("money is always serializable"),
+                );
+            }
+        }
+    }
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::BankAccountEvent;
+    use cqrs_es::persist::SerializedEvent;
+    use cqrs_es::DomainEvent;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn serialized_event(event_type: &str, event_version: &str, payload: Value) -> SerializedEvent {
+        SerializedEvent {
+            aggregate_id: "1234".to_string(),
+            sequence: 2,
+            aggregate_type: "account".to_string(),
+            event_type: event_type.to_string(),
+            event_version: event_version.to_string(),
+            payload,
+            metadata: json!({}),
+        }
+    }
+
+    fn upcast(event: SerializedEvent) -> SerializedEvent {
+        bank_account_upcasters()
+            .iter()
+            .filter(|upcaster| upcaster.can_upcast(&event.event_type, &event.event_version))
+            .fold(event, |event, upcaster| upcaster.upcast(event))
+    }
+
+    #[test]
+    fn upcasts_v1_deposit_to_money() {
+        let event = upcast(serialized_event(
+            "CustomerDepositedMoney",
+            "1.0",
+            json!({"CustomerDepositedMoney": {"amount": 0.1, "balance": 200.3}}),
+        ));
+
+        assert_eq!(event.event_version, "2.0");
+        let event: BankAccountEvent = serde_json::from_value(event.payload).unwrap();
+        assert_eq!(
+            event,
+            BankAccountEvent::CustomerDepositedMoney {
+                amount: Money::new(10, "USD"),
+                balance: Money::new(20030, "USD"),
+            }
+        );
+    }
+
+    #[test]
+    fn upcasts_v1_check_to_money() {
+        let event = upcast(serialized_event(
+            "CustomerWroteCheck",
+            "1.0",
+            json!({"CustomerWroteCheck": {"check_number": "1170", "amount": 256.28, "balance": 743.72}}),
+        ));
+
+        let event: BankAccountEvent = serde_json::from_value(event.payload).unwrap();
+        assert_eq!(
+            event,
+            BankAccountEvent::CustomerWroteCheck {
+                check_number: "1170".to_string(),
+                amount: Money::new(25628, "USD"),
+                balance: Money::new(74372, "USD"),
+                written_at: None,
+            }
+        );
+    }
+
+    #[test]
+    fn does_not_upcast_current_version() {
+        let upcasters = bank_account_upcasters();
+        assert!(!upcasters
+            .iter()
+            .any(|upcaster| upcaster.can_upcast("CustomerWithdrewCash", "2.0")));
+        assert!(!upcasters
+            .iter()
+            .any(|upcaster| upcaster.can_upcast("AccountOpened", "1.0")));
+    }
+
+    #[test]
+    fn upcasts_v1_money_events_to_their_current_version() {
+        let current = BankAccountEvent::CustomerDepositedMoney {
+            amount: Money::zero("USD"),
+            balance: Money::zero("USD"),
+        };
+        for event_type in MONEY_EVENT_TYPES {
+            let event = upcast(serialized_event(
+                event_type,
+                "1.0",
+                json!({ event_type: {"amount": 1.0, "balance": 1.0} }),
+            ));
+            assert_eq!(event.event_version, current.event_version());
+        }
+    }
+}
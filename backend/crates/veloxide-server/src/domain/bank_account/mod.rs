@@ -1,10 +1,16 @@
+pub mod bank_account_access;
 pub mod bank_account_aggregate;
 pub mod bank_account_commands;
 pub mod bank_account_errors;
 pub mod bank_account_events;
+pub mod bank_account_interest;
+pub mod bank_account_upcasters;
 
 // Re-exports
+pub use bank_account_access::*;
 pub use bank_account_aggregate::*;
 pub use bank_account_commands::*;
 pub use bank_account_errors::*;
 pub use bank_account_events::*;
+pub use bank_account_interest::*;
+pub use bank_account_upcasters::*;
@@ -1,8 +1,16 @@
 pub mod bank_account;
+pub mod money;
 pub mod oauth2_state;
+pub mod scheduled_payment;
+pub mod transfer;
+pub mod upcasting;
 pub mod user;
 
 // Re-exports
 pub use bank_account::*;
+pub use money::*;
 pub use oauth2_state::*;
+pub use scheduled_payment::*;
+pub use transfer::*;
+pub use upcasting::*;
 pub use user::*;
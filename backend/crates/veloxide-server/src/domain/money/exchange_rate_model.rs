@@ -0,0 +1,180 @@
+#[cfg(feature = "graphql")]
+use async_graphql::SimpleObject;
+
+#[cfg(feature = "frontend")]
+use ts_rs::TS;
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use super::{minor_unit_exponent, Money, MoneyError};
+
+/// Exchange rates are stored as integers scaled by this factor, i.e. with 8 decimal places.
+pub const EXCHANGE_RATE_SCALE: i64 = 100_000_000;
+
+/// The rate at which one unit of `from_currency` is converted into `to_currency`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "graphql", derive(SimpleObject))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct ExchangeRate {
+    pub from_currency: String,
+    pub to_currency: String,
+
+    /// The rate multiplied by `EXCHANGE_RATE_SCALE`, e.g. 1.0853 is 108530000
+    #[ts(type = "number")]
+    pub scaled_rate: i64,
+}
+
+impl ExchangeRate {
+    pub fn new(from_currency: &str, to_currency: &str, scaled_rate: i64) -> Self {
+        Self {
+            from_currency: from_currency.to_uppercase(),
+            to_currency: to_currency.to_uppercase(),
+            scaled_rate,
+        }
+    }
+
+    /// Parses a decimal rate such as "1.0853" without going through floating point. Only
+    /// unsigned rates above zero are accepted.
+    pub fn parse(from_currency: &str, to_currency: &str, rate: &str) -> Option<Self> {
+        let (whole, fraction) = rate.trim().split_once('.').unwrap_or((rate.trim(), ""));
+        if fraction.len() > 8 || (whole.is_empty() && fraction.is_empty()) {
+            return None;
+        }
+        if !whole
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+        {
+            return None;
+        }
+        let whole: i64 = if whole.is_empty() {
+            0
+        } else {
+            whole.parse().ok()?
+        };
+        let fraction: i64 = if fraction.is_empty() {
+            0
+        } else {
+            format!("{:0<8}", fraction).parse().ok()?
+        };
+        let scaled_rate = whole
+            .checked_mul(EXCHANGE_RATE_SCALE)?
+            .checked_add(fraction)?;
+        if scaled_rate <= 0 {
+            return None;
+        }
+        Some(Self::new(from_currency, to_currency, scaled_rate))
+    }
+
+    /// Converts `amount` into `to_currency`, rounding half away from zero to the nearest minor unit.
+    pub fn convert(&self, amount: &Money) -> Result<Money, MoneyError> {
+        if amount.currency != self.from_currency {
+            return Err(MoneyError::CurrencyMismatch {
+                expected: self.from_currency.clone(),
+                actual: amount.currency.clone(),
+            });
+        }
+        let numerator = i128::from(amount.minor_units)
+            * i128::from(self.scaled_rate)
+            * 10_i128.pow(minor_unit_exponent(&self.to_currency));
+        let denominator =
+            i128::from(EXCHANGE_RATE_SCALE) * 10_i128.pow(minor_unit_exponent(&self.from_currency));
+        let mut converted = numerator / denominator;
+        let remainder = numerator % denominator;
+        if remainder.abs() * 2 >= denominator {
+            converted += numerator.signum();
+        }
+        let minor_units = i64::try_from(converted).map_err(|_| MoneyError::Overflow)?;
+        Ok(Money::new(minor_units, &self.to_currency))
+    }
+}
+
+/// Formats the rate as a decimal, e.g. `EUR/USD 1.0853`.
+impl Display for ExchangeRate {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> core::result::Result<(), std::fmt::Error> {
+        let fraction = format!("{:08}", self.scaled_rate % EXCHANGE_RATE_SCALE);
+        let fraction = fraction.trim_end_matches('0');
+        write!(
+            f,
+            "{}/{} {}.{}",
+            self.from_currency,
+            self.to_currency,
+            self.scaled_rate / EXCHANGE_RATE_SCALE,
+            if fraction.is_empty() { "0" } else { fraction }
+        )
+    }
+}
+
+/// The outcome of converting an amount from one currency into another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurrencyConversion {
+    pub converted: Money,
+    pub rate: ExchangeRate,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_decimal_rates() {
+        assert_eq!(
+            ExchangeRate::parse("eur", "usd", "1.0853"),
+            Some(ExchangeRate::new("EUR", "USD", 108_530_000))
+        );
+        assert_eq!(
+            ExchangeRate::parse("USD", "JPY", "149"),
+            Some(ExchangeRate::new("USD", "JPY", 14_900_000_000))
+        );
+        assert_eq!(ExchangeRate::parse("USD", "EUR", "abc"), None);
+        assert_eq!(ExchangeRate::parse("USD", "EUR", "0"), None);
+        assert_eq!(ExchangeRate::parse("USD", "EUR", "1.123456789"), None);
+    }
+
+    #[test]
+    fn rejects_rates_that_are_not_above_zero() {
+        assert_eq!(ExchangeRate::parse("USD", "EUR", "0.0"), None);
+        assert_eq!(ExchangeRate::parse("USD", "EUR", ".00000000"), None);
+        assert_eq!(ExchangeRate::parse("USD", "EUR", "-1.0853"), None);
+        assert_eq!(ExchangeRate::parse("USD", "EUR", "-0.5"), None);
+        assert_eq!(ExchangeRate::parse("USD", "EUR", "1.-5"), None);
+        assert_eq!(ExchangeRate::parse("USD", "EUR", "+1.0853"), None);
+    }
+
+    #[test]
+    fn converts_between_currencies() {
+        let rate = ExchangeRate::parse("EUR", "USD", "1.0853").unwrap();
+        assert_eq!(
+            rate.convert(&Money::new(10000, "EUR")),
+            Ok(Money::new(10853, "USD"))
+        );
+    }
+
+    #[test]
+    fn converts_between_currencies_with_different_minor_units() {
+        let rate = ExchangeRate::parse("USD", "JPY", "149.5").unwrap();
+        assert_eq!(
+            rate.convert(&Money::new(1001, "USD")),
+            Ok(Money::new(1496, "JPY"))
+        );
+    }
+
+    #[test]
+    fn cannot_convert_from_the_wrong_currency() {
+        let rate = ExchangeRate::parse("EUR", "USD", "1.0853").unwrap();
+        assert!(rate.convert(&Money::new(10000, "GBP")).is_err());
+    }
+
+    #[test]
+    fn displays_as_decimal() {
+        let rate = ExchangeRate::parse("EUR", "USD", "1.0853").unwrap();
+        assert_eq!(rate.to_string(), "EUR/USD 1.0853");
+    }
+}
@@ -0,0 +1,8 @@
+pub mod exchange_rate_model;
+pub mod money_errors;
+pub mod money_model;
+
+// Re-exports
+pub use exchange_rate_model::*;
+pub use money_errors::*;
+pub use money_model::*;
@@ -0,0 +1,10 @@
+use std::fmt::Debug;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum MoneyError {
+    #[error("currency mismatch: expected {expected}, got {actual}")]
+    CurrencyMismatch { expected: String, actual: String },
+
+    #[error("amount overflow")]
+    Overflow,
+}
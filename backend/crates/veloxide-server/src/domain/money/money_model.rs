@@ -0,0 +1,210 @@
+#[cfg(feature = "graphql")]
+use async_graphql::{InputObject, SimpleObject};
+
+#[cfg(feature = "frontend")]
+use ts_rs::TS;
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use super::MoneyError;
+
+/// The currency used when none has been chosen, and the currency legacy `f64` amounts are assumed to be in.
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+/// An exact monetary amount, stored as an integer number of the currency's minor unit
+/// (e.g. cents for USD) so that arithmetic never drifts the way floating point does.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "graphql", derive(SimpleObject, InputObject))]
+#[cfg_attr(feature = "graphql", graphql(input_name = "MoneyInput"))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct Money {
+    /// Amount in the currency's minor unit, e.g. 1050 is 10.50 USD
+    #[ts(type = "number")]
+    pub minor_units: i64,
+
+    /// ISO-4217 currency code, e.g. "USD"
+    pub currency: String,
+}
+
+impl Money {
+    pub fn new(minor_units: i64, currency: &str) -> Self {
+        Self {
+            minor_units,
+            currency: currency.to_uppercase(),
+        }
+    }
+
+    pub fn zero(currency: &str) -> Self {
+        Self::new(0, currency)
+    }
+
+    /// Converts a legacy floating point amount expressed in major units (e.g. dollars) into `Money`,
+    /// rounding to the nearest minor unit.
+    pub fn from_major_units_f64(amount: f64, currency: &str) -> Self {
+        let factor = 10_f64.powi(minor_unit_exponent(currency) as i32);
+        Self::new((amount * factor).round() as i64, currency)
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.minor_units < 0
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.minor_units == 0
+    }
+
+    pub fn checked_add(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.ensure_same_currency(other)?;
+        let minor_units = self
+            .minor_units
+            .checked_add(other.minor_units)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Money::new(minor_units, &self.currency))
+    }
+
+    pub fn checked_sub(&self, other: &Money) -> Result<Money, MoneyError> {
+        self.ensure_same_currency(other)?;
+        let minor_units = self
+            .minor_units
+            .checked_sub(other.minor_units)
+            .ok_or(MoneyError::Overflow)?;
+        Ok(Money::new(minor_units, &self.currency))
+    }
+
+    fn ensure_same_currency(&self, other: &Money) -> Result<(), MoneyError> {
+        if self.currency != other.currency {
+            return Err(MoneyError::CurrencyMismatch {
+                expected: self.currency.clone(),
+                actual: other.currency.clone(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl Default for Money {
+    fn default() -> Self {
+        Money::zero(DEFAULT_CURRENCY)
+    }
+}
+
+/// Formats the amount in major units followed by the currency code, e.g. `-10.50 USD`.
+impl Display for Money {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> core::result::Result<(), std::fmt::Error> {
+        let exponent = minor_unit_exponent(&self.currency);
+        let sign = if self.is_negative() { "-" } else { "" };
+        let abs = self.minor_units.unsigned_abs();
+        if exponent == 0 {
+            return write!(f, "{}{} {}", sign, abs, self.currency);
+        }
+        let factor = 10_u64.pow(exponent);
+        write!(
+            f,
+            "{}{}.{:0width$} {}",
+            sign,
+            abs / factor,
+            abs % factor,
+            self.currency,
+            width = exponent as usize
+        )
+    }
+}
+
+/// Serde default for currency fields that predate multi-currency support.
+pub fn default_currency() -> String {
+    DEFAULT_CURRENCY.to_string()
+}
+
+/// Whether the code is shaped like an ISO-4217 alphabetic currency code, e.g. "EUR".
+pub fn is_valid_currency_code(currency: &str) -> bool {
+    currency.len() == 3 && currency.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// The number of decimal places of the currency's minor unit as defined by ISO-4217.
+pub fn minor_unit_exponent(currency: &str) -> u32 {
+    match currency.to_uppercase().as_str() {
+        "BIF" | "CLP" | "DJF" | "GNF" | "ISK" | "JPY" | "KMF" | "KRW" | "PYG" | "RWF" | "UGX"
+        | "VND" | "VUV" | "XAF" | "XOF" | "XPF" => 0,
+        "BHD" | "IQD" | "JOD" | "KWD" | "LYD" | "OMR" | "TND" => 3,
+        _ => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn adding_tenths_does_not_drift() {
+        let total = Money::new(10, "USD")
+            .checked_add(&Money::new(20, "USD"))
+            .unwrap();
+        assert_eq!(total, Money::new(30, "USD"));
+    }
+
+    #[test]
+    fn cannot_add_different_currencies() {
+        let result = Money::new(10, "USD").checked_add(&Money::new(20, "EUR"));
+        assert_eq!(
+            result,
+            Err(MoneyError::CurrencyMismatch {
+                expected: "USD".to_string(),
+                actual: "EUR".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn subtraction_overflow_is_an_error() {
+        let result = Money::new(i64::MIN, "USD").checked_sub(&Money::new(1, "USD"));
+        assert_eq!(result, Err(MoneyError::Overflow));
+    }
+
+    #[test]
+    fn converts_legacy_f64_amounts_to_minor_units() {
+        assert_eq!(
+            Money::from_major_units_f64(256.28, "USD"),
+            Money::new(25628, "USD")
+        );
+        assert_eq!(
+            Money::from_major_units_f64(0.1 + 0.2, "usd"),
+            Money::new(30, "USD")
+        );
+        assert_eq!(
+            Money::from_major_units_f64(1500.0, "JPY"),
+            Money::new(1500, "JPY")
+        );
+    }
+
+    #[test]
+    fn displays_in_major_units() {
+        assert_eq!(Money::new(1050, "USD").to_string(), "10.50 USD");
+        assert_eq!(Money::new(-5, "EUR").to_string(), "-0.05 EUR");
+        assert_eq!(Money::new(1500, "JPY").to_string(), "1500 JPY");
+        assert_eq!(Money::new(1234, "KWD").to_string(), "1.234 KWD");
+    }
+
+    #[test]
+    fn validates_currency_codes() {
+        assert!(is_valid_currency_code("EUR"));
+        assert!(!is_valid_currency_code("eur"));
+        assert!(!is_valid_currency_code("EURO"));
+        assert!(!is_valid_currency_code(""));
+    }
+
+    #[test]
+    fn serializes_as_minor_units_and_currency() {
+        let json = serde_json::to_value(Money::new(1050, "USD")).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"minor_units": 1050, "currency": "USD"})
+        );
+    }
+}
@@ -0,0 +1,12 @@
+pub mod scheduled_payment_aggregate;
+pub mod scheduled_payment_commands;
+pub mod scheduled_payment_errors;
+pub mod scheduled_payment_events;
+pub mod scheduled_payment_schedule;
+
+// Re-exports
+pub use scheduled_payment_aggregate::*;
+pub use scheduled_payment_commands::*;
+pub use scheduled_payment_errors::*;
+pub use scheduled_payment_events::*;
+pub use scheduled_payment_schedule::*;
@@ -0,0 +1,439 @@
+#[cfg(feature = "graphql")]
+use async_graphql::Enum;
+
+#[cfg(feature = "frontend")]
+use ts_rs::TS;
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+use super::*;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use cqrs_es::Aggregate;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use tracing::*;
+
+use crate::domain::Money;
+
+/// Where a scheduled payment is in its life: it runs while active, until it is cancelled or
+/// has run for the last time.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "graphql", derive(Enum))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub enum ScheduledPaymentStatus {
+    #[default]
+    NotCreated,
+    Active,
+    Completed,
+    Cancelled,
+}
+
+impl Display for ScheduledPaymentStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> core::result::Result<(), std::fmt::Error> {
+        let status = match self {
+            ScheduledPaymentStatus::NotCreated => "not created",
+            ScheduledPaymentStatus::Active => "active",
+            ScheduledPaymentStatus::Completed => "completed",
+            ScheduledPaymentStatus::Cancelled => "cancelled",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ScheduledPayment {
+    status: ScheduledPaymentStatus,
+    frequency: Option<PaymentFrequency>,
+    starts_at: DateTime<Utc>,
+    ends_at: Option<DateTime<Utc>>,
+    next_run_at: Option<DateTime<Utc>>,
+}
+
+#[async_trait]
+impl Aggregate for ScheduledPayment {
+    type Command = ScheduledPaymentCommand;
+    type Event = ScheduledPaymentEvent;
+    type Error = ScheduledPaymentError;
+    type Services = ();
+
+    fn aggregate_type() -> String {
+        "scheduled_payment".to_string()
+    }
+
+    #[instrument(skip(_services))]
+    async fn handle(
+        &self,
+        command: Self::Command,
+        _services: &Self::Services,
+    ) -> Result<Vec<Self::Event>, Self::Error> {
+        match command {
+            ScheduledPaymentCommand::CreateScheduledPayment(command) => {
+                self.handle_create_scheduled_payment_command(command)
+            }
+            ScheduledPaymentCommand::UpdateScheduledPayment(command) => {
+                self.ensure_active("update")?;
+                let (starts_at, ends_at) = parse_schedule(&command.starts_at, &command.ends_at)?;
+                ensure_positive(&command.amount)?;
+                Ok(vec![ScheduledPaymentEvent::ScheduledPaymentUpdated {
+                    amount: command.amount,
+                    frequency: command.frequency,
+                    starts_at,
+                    ends_at,
+                }])
+            }
+            ScheduledPaymentCommand::CancelScheduledPayment => {
+                self.ensure_active("cancel")?;
+                Ok(vec![ScheduledPaymentEvent::ScheduledPaymentCancelled])
+            }
+            ScheduledPaymentCommand::RecordPaymentExecution {
+                run_at,
+                executed_at,
+            } => {
+                self.ensure_scheduled(run_at)?;
+                Ok(vec![ScheduledPaymentEvent::PaymentExecuted {
+                    run_at,
+                    executed_at,
+                    next_run_at: self.next_run_after(run_at.max(executed_at)),
+                }])
+            }
+            ScheduledPaymentCommand::RecordPaymentFailure {
+                run_at,
+                failed_at,
+                reason,
+                retry_at,
+            } => {
+                self.ensure_scheduled(run_at)?;
+                match retry_at {
+                    Some(retry_at) => Ok(vec![ScheduledPaymentEvent::PaymentAttemptFailed {
+                        run_at,
+                        failed_at,
+                        reason,
+                        retry_at,
+                    }]),
+                    None => Ok(vec![ScheduledPaymentEvent::PaymentFailed {
+                        run_at,
+                        failed_at,
+                        reason,
+                        next_run_at: self.next_run_after(run_at.max(failed_at)),
+                    }]),
+                }
+            }
+        }
+    }
+
+    fn apply(&mut self, event: Self::Event) {
+        match event {
+            ScheduledPaymentEvent::ScheduledPaymentCreated {
+                frequency,
+                starts_at,
+                ends_at,
+                ..
+            }
+            | ScheduledPaymentEvent::ScheduledPaymentUpdated {
+                frequency,
+                starts_at,
+                ends_at,
+                ..
+            } => {
+                self.status = ScheduledPaymentStatus::Active;
+                self.frequency = Some(frequency);
+                self.starts_at = starts_at;
+                self.ends_at = ends_at;
+                self.next_run_at = Some(starts_at);
+            }
+            ScheduledPaymentEvent::ScheduledPaymentCancelled => {
+                self.status = ScheduledPaymentStatus::Cancelled;
+                self.next_run_at = None;
+            }
+            // The run stays scheduled until it succeeds or is given up on.
+            ScheduledPaymentEvent::PaymentAttemptFailed { .. } => {}
+            ScheduledPaymentEvent::PaymentExecuted { next_run_at, .. }
+            | ScheduledPaymentEvent::PaymentFailed { next_run_at, .. } => {
+                self.next_run_at = next_run_at;
+                if next_run_at.is_none() {
+                    self.status = ScheduledPaymentStatus::Completed;
+                }
+            }
+        }
+    }
+}
+
+impl ScheduledPayment {
+    #[instrument]
+    fn handle_create_scheduled_payment_command(
+        &self,
+        command: CreateScheduledPaymentCommandData,
+    ) -> Result<Vec<ScheduledPaymentEvent>, ScheduledPaymentError> {
+        if self.status != ScheduledPaymentStatus::NotCreated {
+            return Err(ScheduledPaymentError::ScheduledPaymentAlreadyCreated);
+        }
+        let (starts_at, ends_at) = parse_schedule(&command.starts_at, &command.ends_at)?;
+        ensure_positive(&command.amount)?;
+        Ok(vec![ScheduledPaymentEvent::ScheduledPaymentCreated {
+            account_id: command.account_id,
+            owner_id: command.owner_id.unwrap_or_default(),
+            kind: command.kind,
+            amount: command.amount,
+            frequency: command.frequency,
+            starts_at,
+            ends_at,
+        }])
+    }
+
+    fn ensure_active(&self, command: &str) -> Result<(), ScheduledPaymentError> {
+        match self.status {
+            ScheduledPaymentStatus::NotCreated => {
+                Err(ScheduledPaymentError::ScheduledPaymentNotCreated)
+            }
+            ScheduledPaymentStatus::Active => Ok(()),
+            status => Err(ScheduledPaymentError::InvalidTransition {
+                command: command.to_string(),
+                status: status.to_string(),
+            }),
+        }
+    }
+
+    // Outcomes are only recorded for the run that is due, so that an outcome that arrives after
+    // the payment was rescheduled does not move the new schedule along.
+    fn ensure_scheduled(&self, run_at: DateTime<Utc>) -> Result<(), ScheduledPaymentError> {
+        self.ensure_active("record a run of")?;
+        if self.next_run_at != Some(run_at) {
+            return Err(ScheduledPaymentError::RunNotScheduled(
+                format_schedule_timestamp(run_at),
+            ));
+        }
+        Ok(())
+    }
+
+    fn next_run_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.frequency?
+            .next_run_after(self.starts_at, after)
+            .filter(|next_run_at| self.ends_at.map_or(true, |ends_at| *next_run_at <= ends_at))
+    }
+}
+
+fn parse_schedule(
+    starts_at: &str,
+    ends_at: &Option<String>,
+) -> Result<(DateTime<Utc>, Option<DateTime<Utc>>), ScheduledPaymentError> {
+    let parse = |timestamp: &str| {
+        parse_schedule_timestamp(timestamp)
+            .ok_or_else(|| ScheduledPaymentError::InvalidTimestamp(timestamp.to_string()))
+    };
+    let starts_at = parse(starts_at)?;
+    let ends_at = ends_at.as_deref().map(parse).transpose()?;
+    if ends_at.map_or(false, |ends_at| ends_at < starts_at) {
+        return Err(ScheduledPaymentError::EndsBeforeStart);
+    }
+    Ok((starts_at, ends_at))
+}
+
+fn ensure_positive(amount: &Money) -> Result<(), ScheduledPaymentError> {
+    if amount.is_negative() || amount.is_zero() {
+        return Err(ScheduledPaymentError::AmountMustBePositive);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod aggregate_tests {
+    use coverage_helper::test;
+    use cqrs_es::test::TestFramework;
+
+    use super::*;
+
+    type ScheduledPaymentTestFramework = TestFramework<ScheduledPayment>;
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        parse_schedule_timestamp(timestamp).unwrap()
+    }
+
+    fn create_command(frequency: PaymentFrequency) -> CreateScheduledPaymentCommandData {
+        CreateScheduledPaymentCommandData {
+            account_id: "1234".to_string(),
+            kind: ScheduledPaymentKind::Withdrawal,
+            amount: Money::new(5000, "USD"),
+            frequency,
+            starts_at: "2023-12-01T09:00:00Z".to_string(),
+            ends_at: None,
+            owner_id: Some("1111".to_string()),
+        }
+    }
+
+    fn scheduled_payment_created(
+        frequency: PaymentFrequency,
+        ends_at: Option<&str>,
+    ) -> ScheduledPaymentEvent {
+        ScheduledPaymentEvent::ScheduledPaymentCreated {
+            account_id: "1234".to_string(),
+            owner_id: "1111".to_string(),
+            kind: ScheduledPaymentKind::Withdrawal,
+            amount: Money::new(5000, "USD"),
+            frequency,
+            starts_at: at("2023-12-01T09:00:00Z"),
+            ends_at: ends_at.map(at),
+        }
+    }
+
+    #[test]
+    fn create_scheduled_payment() {
+        ScheduledPaymentTestFramework::with(())
+            .given_no_previous_events()
+            .when(ScheduledPaymentCommand::CreateScheduledPayment(
+                create_command(PaymentFrequency::Monthly),
+            ))
+            .then_expect_events(vec![scheduled_payment_created(
+                PaymentFrequency::Monthly,
+                None,
+            )]);
+    }
+
+    #[test]
+    fn cannot_schedule_a_zero_amount() {
+        let command = CreateScheduledPaymentCommandData {
+            amount: Money::zero("USD"),
+            ..create_command(PaymentFrequency::Monthly)
+        };
+        ScheduledPaymentTestFramework::with(())
+            .given_no_previous_events()
+            .when(ScheduledPaymentCommand::CreateScheduledPayment(command))
+            .then_expect_error(ScheduledPaymentError::AmountMustBePositive);
+    }
+
+    #[test]
+    fn cannot_end_before_the_start() {
+        let command = CreateScheduledPaymentCommandData {
+            ends_at: Some("2023-11-30T09:00:00Z".to_string()),
+            ..create_command(PaymentFrequency::Monthly)
+        };
+        ScheduledPaymentTestFramework::with(())
+            .given_no_previous_events()
+            .when(ScheduledPaymentCommand::CreateScheduledPayment(command))
+            .then_expect_error(ScheduledPaymentError::EndsBeforeStart);
+    }
+
+    #[test]
+    fn rejects_timestamps_that_are_not_rfc_3339() {
+        let command = CreateScheduledPaymentCommandData {
+            starts_at: "1st of December".to_string(),
+            ..create_command(PaymentFrequency::Monthly)
+        };
+        ScheduledPaymentTestFramework::with(())
+            .given_no_previous_events()
+            .when(ScheduledPaymentCommand::CreateScheduledPayment(command))
+            .then_expect_error(ScheduledPaymentError::InvalidTimestamp(
+                "1st of December".to_string(),
+            ));
+    }
+
+    #[test]
+    fn executing_a_run_schedules_the_next_one() {
+        ScheduledPaymentTestFramework::with(())
+            .given(vec![scheduled_payment_created(
+                PaymentFrequency::Monthly,
+                None,
+            )])
+            .when(ScheduledPaymentCommand::RecordPaymentExecution {
+                run_at: at("2023-12-01T09:00:00Z"),
+                executed_at: at("2023-12-01T09:00:02Z"),
+            })
+            .then_expect_events(vec![ScheduledPaymentEvent::PaymentExecuted {
+                run_at: at("2023-12-01T09:00:00Z"),
+                executed_at: at("2023-12-01T09:00:02Z"),
+                next_run_at: Some(at("2024-01-01T09:00:00Z")),
+            }]);
+    }
+
+    #[test]
+    fn the_last_run_before_the_end_completes_the_payment() {
+        ScheduledPaymentTestFramework::with(())
+            .given(vec![scheduled_payment_created(
+                PaymentFrequency::Monthly,
+                Some("2023-12-31T00:00:00Z"),
+            )])
+            .when(ScheduledPaymentCommand::RecordPaymentFailure {
+                run_at: at("2023-12-01T09:00:00Z"),
+                failed_at: at("2023-12-01T09:00:02Z"),
+                reason: "insufficient funds".to_string(),
+                retry_at: None,
+            })
+            .then_expect_events(vec![ScheduledPaymentEvent::PaymentFailed {
+                run_at: at("2023-12-01T09:00:00Z"),
+                failed_at: at("2023-12-01T09:00:02Z"),
+                reason: "insufficient funds".to_string(),
+                next_run_at: None,
+            }]);
+    }
+
+    #[test]
+    fn retried_runs_stay_scheduled() {
+        ScheduledPaymentTestFramework::with(())
+            .given(vec![
+                scheduled_payment_created(PaymentFrequency::Daily, None),
+                ScheduledPaymentEvent::PaymentAttemptFailed {
+                    run_at: at("2023-12-01T09:00:00Z"),
+                    failed_at: at("2023-12-01T09:00:02Z"),
+                    reason: "database unavailable".to_string(),
+                    retry_at: at("2023-12-01T09:01:02Z"),
+                },
+            ])
+            .when(ScheduledPaymentCommand::RecordPaymentExecution {
+                run_at: at("2023-12-01T09:00:00Z"),
+                executed_at: at("2023-12-01T09:01:03Z"),
+            })
+            .then_expect_events(vec![ScheduledPaymentEvent::PaymentExecuted {
+                run_at: at("2023-12-01T09:00:00Z"),
+                executed_at: at("2023-12-01T09:01:03Z"),
+                next_run_at: Some(at("2023-12-02T09:00:00Z")),
+            }]);
+    }
+
+    #[test]
+    fn cannot_record_a_run_that_is_not_due() {
+        ScheduledPaymentTestFramework::with(())
+            .given(vec![scheduled_payment_created(
+                PaymentFrequency::Monthly,
+                None,
+            )])
+            .when(ScheduledPaymentCommand::RecordPaymentExecution {
+                run_at: at("2024-01-01T09:00:00Z"),
+                executed_at: at("2024-01-01T09:00:02Z"),
+            })
+            .then_expect_error(ScheduledPaymentError::RunNotScheduled(
+                "2024-01-01T09:00:00Z".to_string(),
+            ));
+    }
+
+    #[test]
+    fn cannot_update_a_cancelled_payment() {
+        ScheduledPaymentTestFramework::with(())
+            .given(vec![
+                scheduled_payment_created(PaymentFrequency::Monthly, None),
+                ScheduledPaymentEvent::ScheduledPaymentCancelled,
+            ])
+            .when(ScheduledPaymentCommand::UpdateScheduledPayment(
+                UpdateScheduledPaymentCommandData {
+                    amount: Money::new(7500, "USD"),
+                    frequency: PaymentFrequency::Monthly,
+                    starts_at: "2024-01-01T09:00:00Z".to_string(),
+                    ends_at: None,
+                },
+            ))
+            .then_expect_error(ScheduledPaymentError::InvalidTransition {
+                command: "update".to_string(),
+                status: "cancelled".to_string(),
+            });
+    }
+
+    #[test]
+    fn cannot_cancel_an_unknown_payment() {
+        ScheduledPaymentTestFramework::with(())
+            .given_no_previous_events()
+            .when(ScheduledPaymentCommand::CancelScheduledPayment)
+            .then_expect_error(ScheduledPaymentError::ScheduledPaymentNotCreated);
+    }
+}
@@ -0,0 +1,64 @@
+use async_graphql::*;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::ToSchema;
+
+use crate::domain::{Money, PaymentFrequency, ScheduledPaymentKind};
+
+// Creating, updating and cancelling are issued by users, recording the outcome of a run is
+// issued by the `ScheduledPaymentScheduler` with the time of its clock.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum ScheduledPaymentCommand {
+    CreateScheduledPayment(CreateScheduledPaymentCommandData),
+    UpdateScheduledPayment(UpdateScheduledPaymentCommandData),
+    CancelScheduledPayment,
+    RecordPaymentExecution {
+        run_at: DateTime<Utc>,
+        executed_at: DateTime<Utc>,
+    },
+    /// Retried at `retry_at` when given, otherwise the run is given up on.
+    RecordPaymentFailure {
+        run_at: DateTime<Utc>,
+        failed_at: DateTime<Utc>,
+        reason: String,
+        retry_at: Option<DateTime<Utc>>,
+    },
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct CreateScheduledPaymentCommandData {
+    pub account_id: String,
+    pub kind: ScheduledPaymentKind,
+    pub amount: Money,
+    pub frequency: PaymentFrequency,
+
+    /// RFC 3339 timestamp of the first run
+    pub starts_at: String,
+
+    /// RFC 3339 timestamp after which the payment no longer runs
+    #[serde(default)]
+    pub ends_at: Option<String>,
+
+    /// Id of the `User` creating the payment, who it is made on behalf of. Never taken from
+    /// the request, the application service sets it to the authenticated user.
+    #[serde(skip)]
+    #[graphql(skip)]
+    pub owner_id: Option<String>,
+}
+
+/// Replaces the amount and schedule of a payment, which next runs at the new `starts_at`.
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct UpdateScheduledPaymentCommandData {
+    pub amount: Money,
+    pub frequency: PaymentFrequency,
+
+    /// RFC 3339 timestamp of the next run
+    pub starts_at: String,
+
+    /// RFC 3339 timestamp after which the payment no longer runs
+    #[serde(default)]
+    pub ends_at: Option<String>,
+}
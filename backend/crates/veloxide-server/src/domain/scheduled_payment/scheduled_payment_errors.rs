@@ -0,0 +1,25 @@
+use std::fmt::Debug;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum ScheduledPaymentError {
+    #[error("scheduled payment already created")]
+    ScheduledPaymentAlreadyCreated,
+
+    #[error("scheduled payment not created")]
+    ScheduledPaymentNotCreated,
+
+    #[error("scheduled payment amount must be positive")]
+    AmountMustBePositive,
+
+    #[error("invalid timestamp {0}, expected RFC 3339")]
+    InvalidTimestamp(String),
+
+    #[error("scheduled payment ends before it starts")]
+    EndsBeforeStart,
+
+    #[error("cannot {command} a scheduled payment that is {status}")]
+    InvalidTransition { command: String, status: String },
+
+    #[error("scheduled payment is not due to run at {0}")]
+    RunNotScheduled(String),
+}
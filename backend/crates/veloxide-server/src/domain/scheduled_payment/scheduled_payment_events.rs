@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use cqrs_es::DomainEvent;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+use crate::domain::{Money, PaymentFrequency, ScheduledPaymentKind};
+
+// Every event that moves the schedule along carries the next run, or `None` once the payment
+// has run for the last time, so that views do not have to work the schedule out themselves.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum ScheduledPaymentEvent {
+    ScheduledPaymentCreated {
+        account_id: String,
+        owner_id: String,
+        kind: ScheduledPaymentKind,
+        amount: Money,
+        frequency: PaymentFrequency,
+        starts_at: DateTime<Utc>,
+        ends_at: Option<DateTime<Utc>>,
+    },
+    ScheduledPaymentUpdated {
+        amount: Money,
+        frequency: PaymentFrequency,
+        starts_at: DateTime<Utc>,
+        ends_at: Option<DateTime<Utc>>,
+    },
+    ScheduledPaymentCancelled,
+    PaymentExecuted {
+        run_at: DateTime<Utc>,
+        executed_at: DateTime<Utc>,
+        next_run_at: Option<DateTime<Utc>>,
+    },
+    PaymentAttemptFailed {
+        run_at: DateTime<Utc>,
+        failed_at: DateTime<Utc>,
+        reason: String,
+        retry_at: DateTime<Utc>,
+    },
+    PaymentFailed {
+        run_at: DateTime<Utc>,
+        failed_at: DateTime<Utc>,
+        reason: String,
+        next_run_at: Option<DateTime<Utc>>,
+    },
+}
+
+impl DomainEvent for ScheduledPaymentEvent {
+    fn event_type(&self) -> String {
+        match self {
+            ScheduledPaymentEvent::ScheduledPaymentCreated { .. } => {
+                "ScheduledPaymentCreated".to_string()
+            }
+            ScheduledPaymentEvent::ScheduledPaymentUpdated { .. } => {
+                "ScheduledPaymentUpdated".to_string()
+            }
+            ScheduledPaymentEvent::ScheduledPaymentCancelled => {
+                "ScheduledPaymentCancelled".to_string()
+            }
+            ScheduledPaymentEvent::PaymentExecuted { .. } => "PaymentExecuted".to_string(),
+            ScheduledPaymentEvent::PaymentAttemptFailed { .. } => {
+                "PaymentAttemptFailed".to_string()
+            }
+            ScheduledPaymentEvent::PaymentFailed { .. } => "PaymentFailed".to_string(),
+        }
+    }
+
+    fn event_version(&self) -> String {
+        "1.0".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn scheduled_payment_event_version_is_1_0() {
+        assert_eq!(
+            ScheduledPaymentEvent::ScheduledPaymentCancelled.event_version(),
+            "1.0".to_string()
+        );
+    }
+
+    #[test]
+    fn scheduled_payment_event_type_is_payment_attempt_failed() {
+        let event = ScheduledPaymentEvent::PaymentAttemptFailed {
+            run_at: Utc::now(),
+            failed_at: Utc::now(),
+            reason: "bank account was changed by another command".to_string(),
+            retry_at: Utc::now(),
+        };
+        assert_eq!(event.event_type(), "PaymentAttemptFailed".to_string());
+    }
+}
@@ -0,0 +1,201 @@
+#[cfg(feature = "graphql")]
+use async_graphql::Enum;
+
+#[cfg(feature = "frontend")]
+use ts_rs::TS;
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+use chrono::{DateTime, Datelike, Duration, Months, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+use crate::domain::{
+    BankAccountCommand, BankAccountDepositMoneyCommandData, BankAccountWithdrawMoneyCommandData,
+    Money,
+};
+
+/// The bank account command a scheduled payment issues every time it runs.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "graphql", derive(Enum))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub enum ScheduledPaymentKind {
+    /// Withdraws the amount, approved by the ATM network like any other withdrawal
+    #[default]
+    Withdrawal,
+    Deposit,
+}
+
+impl ScheduledPaymentKind {
+    /// The command to issue on the account. Withdrawals name the scheduled payment as their ATM,
+    /// so that the ATM network can tell them apart.
+    pub fn command(&self, payment_id: &str, amount: &Money) -> BankAccountCommand {
+        match self {
+            ScheduledPaymentKind::Withdrawal => {
+                BankAccountCommand::WithdrawMoney(BankAccountWithdrawMoneyCommandData {
+                    amount: amount.clone(),
+                    atm_id: format!("scheduled-payment-{}", payment_id),
+                })
+            }
+            ScheduledPaymentKind::Deposit => {
+                BankAccountCommand::DepositMoney(BankAccountDepositMoneyCommandData {
+                    amount: amount.clone(),
+                })
+            }
+        }
+    }
+}
+
+/// How often a scheduled payment runs. Every run falls on the time of day of the first one,
+/// and monthly runs on its day of the month, or on the last day of shorter months.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "graphql", derive(Enum))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub enum PaymentFrequency {
+    #[default]
+    Once,
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+impl Display for PaymentFrequency {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> core::result::Result<(), std::fmt::Error> {
+        let frequency = match self {
+            PaymentFrequency::Once => "once",
+            PaymentFrequency::Daily => "daily",
+            PaymentFrequency::Weekly => "weekly",
+            PaymentFrequency::Monthly => "monthly",
+        };
+        write!(f, "{}", frequency)
+    }
+}
+
+impl PaymentFrequency {
+    /// The `occurrence`th run of a schedule starting at `starts_at`, counting from 0. Counting
+    /// from the start rather than from the previous run keeps monthly runs on the 31st after a
+    /// run on the 28th of February.
+    pub fn occurrence(&self, starts_at: DateTime<Utc>, occurrence: u32) -> Option<DateTime<Utc>> {
+        match self {
+            PaymentFrequency::Once if occurrence == 0 => Some(starts_at),
+            PaymentFrequency::Once => None,
+            PaymentFrequency::Daily => {
+                starts_at.checked_add_signed(Duration::days(occurrence.into()))
+            }
+            PaymentFrequency::Weekly => {
+                starts_at.checked_add_signed(Duration::weeks(occurrence.into()))
+            }
+            PaymentFrequency::Monthly => starts_at.checked_add_months(Months::new(occurrence)),
+        }
+    }
+
+    /// The first run of a schedule starting at `starts_at` that falls after `after`, if any.
+    pub fn next_run_after(
+        &self,
+        starts_at: DateTime<Utc>,
+        after: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        if starts_at > after {
+            return Some(starts_at);
+        }
+        // Estimates the last run at or before `after`, so only a few runs are stepped through.
+        let elapsed = after - starts_at;
+        let estimate = match self {
+            PaymentFrequency::Once => return None,
+            PaymentFrequency::Daily => elapsed.num_days(),
+            PaymentFrequency::Weekly => elapsed.num_weeks(),
+            PaymentFrequency::Monthly => {
+                i64::from(after.year() - starts_at.year()) * 12 + i64::from(after.month())
+                    - i64::from(starts_at.month())
+            }
+        };
+        let mut occurrence = u32::try_from(estimate.max(0)).ok()?;
+        loop {
+            let run_at = self.occurrence(starts_at, occurrence)?;
+            if run_at > after {
+                return Some(run_at);
+            }
+            occurrence = occurrence.checked_add(1)?;
+        }
+    }
+}
+
+/// Parses the RFC 3339 timestamps scheduled payments are requested with.
+pub fn parse_schedule_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(timestamp.trim())
+        .ok()
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+}
+
+/// Formats a timestamp of a scheduled payment for its view.
+pub fn format_schedule_timestamp(timestamp: DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        parse_schedule_timestamp(timestamp).unwrap()
+    }
+
+    #[test]
+    fn monthly_runs_fall_on_the_same_day_or_the_end_of_shorter_months() {
+        let starts_at = at("2024-01-31T09:00:00Z");
+        assert_eq!(
+            PaymentFrequency::Monthly.next_run_after(starts_at, starts_at),
+            Some(at("2024-02-29T09:00:00Z"))
+        );
+        assert_eq!(
+            PaymentFrequency::Monthly.next_run_after(starts_at, at("2024-02-29T09:00:00Z")),
+            Some(at("2024-03-31T09:00:00Z"))
+        );
+        assert_eq!(
+            PaymentFrequency::Monthly.next_run_after(starts_at, at("2024-04-15T00:00:00Z")),
+            Some(at("2024-04-30T09:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn skips_the_runs_missed_while_late() {
+        let starts_at = at("2023-11-01T09:00:00Z");
+        assert_eq!(
+            PaymentFrequency::Daily.next_run_after(starts_at, at("2023-11-05T10:00:00Z")),
+            Some(at("2023-11-06T09:00:00Z"))
+        );
+        assert_eq!(
+            PaymentFrequency::Weekly.next_run_after(starts_at, at("2023-11-08T09:00:00Z")),
+            Some(at("2023-11-15T09:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn runs_once_only_at_its_start() {
+        let starts_at = at("2023-11-01T09:00:00Z");
+        assert_eq!(
+            PaymentFrequency::Once.next_run_after(starts_at, at("2023-10-31T09:00:00Z")),
+            Some(starts_at)
+        );
+        assert_eq!(
+            PaymentFrequency::Once.next_run_after(starts_at, starts_at),
+            None
+        );
+    }
+
+    #[test]
+    fn withdrawals_name_the_scheduled_payment_as_their_atm() {
+        let command = ScheduledPaymentKind::Withdrawal.command("7f1c", &Money::new(2000, "USD"));
+        assert!(matches!(
+            command,
+            BankAccountCommand::WithdrawMoney(BankAccountWithdrawMoneyCommandData { atm_id, .. })
+                if atm_id == "scheduled-payment-7f1c"
+        ));
+    }
+}
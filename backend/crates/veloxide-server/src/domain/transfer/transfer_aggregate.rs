@@ -0,0 +1,271 @@
+#[cfg(feature = "graphql")]
+use async_graphql::Enum;
+
+#[cfg(feature = "frontend")]
+use ts_rs::TS;
+
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+use super::*;
+use async_trait::async_trait;
+use cqrs_es::Aggregate;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use tracing::*;
+
+/// Where a transfer is in its saga: the source account is debited first, then the
+/// destination is credited, and if the credit fails the debit is refunded.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "graphql", derive(Enum))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub enum TransferStatus {
+    #[default]
+    NotStarted,
+    Started,
+    SourceDebited,
+    Completed,
+    Compensating,
+    Compensated,
+    Failed,
+}
+
+impl Display for TransferStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> core::result::Result<(), std::fmt::Error> {
+        let status = match self {
+            TransferStatus::NotStarted => "not started",
+            TransferStatus::Started => "started",
+            TransferStatus::SourceDebited => "source debited",
+            TransferStatus::Completed => "completed",
+            TransferStatus::Compensating => "compensating",
+            TransferStatus::Compensated => "compensated",
+            TransferStatus::Failed => "failed",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Transfer {
+    status: TransferStatus,
+}
+
+#[async_trait]
+impl Aggregate for Transfer {
+    type Command = TransferCommand;
+    type Event = TransferEvent;
+    type Error = TransferError;
+    type Services = ();
+
+    fn aggregate_type() -> String {
+        "transfer".to_string()
+    }
+
+    #[instrument(skip(_services))]
+    async fn handle(
+        &self,
+        command: Self::Command,
+        _services: &Self::Services,
+    ) -> Result<Vec<Self::Event>, Self::Error> {
+        match command {
+            TransferCommand::StartTransfer(command) => self.handle_start_transfer_command(command),
+            TransferCommand::RecordDebit => {
+                self.ensure_status("record the debit of", TransferStatus::Started)?;
+                Ok(vec![TransferEvent::SourceAccountDebited])
+            }
+            TransferCommand::RecordDebitFailure { reason } => {
+                self.ensure_status("fail", TransferStatus::Started)?;
+                Ok(vec![TransferEvent::SourceAccountDebitFailed { reason }])
+            }
+            TransferCommand::RecordCredit => {
+                self.ensure_status("complete", TransferStatus::SourceDebited)?;
+                Ok(vec![TransferEvent::DestinationAccountCredited])
+            }
+            TransferCommand::RecordCreditFailure { reason } => {
+                self.ensure_status("compensate", TransferStatus::SourceDebited)?;
+                Ok(vec![TransferEvent::DestinationAccountCreditFailed {
+                    reason,
+                }])
+            }
+            TransferCommand::RecordCompensation => {
+                self.ensure_status("record the refund of", TransferStatus::Compensating)?;
+                Ok(vec![TransferEvent::TransferCompensated])
+            }
+        }
+    }
+
+    fn apply(&mut self, event: Self::Event) {
+        match event {
+            TransferEvent::TransferStarted { .. } => self.status = TransferStatus::Started,
+            TransferEvent::SourceAccountDebited => self.status = TransferStatus::SourceDebited,
+            TransferEvent::SourceAccountDebitFailed { .. } => self.status = TransferStatus::Failed,
+            TransferEvent::DestinationAccountCredited => self.status = TransferStatus::Completed,
+            TransferEvent::DestinationAccountCreditFailed { .. } => {
+                self.status = TransferStatus::Compensating
+            }
+            TransferEvent::TransferCompensated => self.status = TransferStatus::Compensated,
+        }
+    }
+}
+
+impl Transfer {
+    #[instrument]
+    fn handle_start_transfer_command(
+        &self,
+        command: StartTransferCommandData,
+    ) -> Result<Vec<TransferEvent>, TransferError> {
+        if self.status != TransferStatus::NotStarted {
+            return Err(TransferError::TransferAlreadyStarted);
+        }
+        if command.source_account_id == command.destination_account_id {
+            return Err(TransferError::CannotTransferToSameAccount);
+        }
+        if command.amount.is_negative() || command.amount.is_zero() {
+            return Err(TransferError::AmountMustBePositive);
+        }
+        Ok(vec![TransferEvent::TransferStarted {
+            source_account_id: command.source_account_id,
+            destination_account_id: command.destination_account_id,
+            amount: command.amount,
+        }])
+    }
+
+    fn ensure_status(&self, command: &str, expected: TransferStatus) -> Result<(), TransferError> {
+        match self.status {
+            TransferStatus::NotStarted => Err(TransferError::TransferNotStarted),
+            status if status == expected => Ok(()),
+            status => Err(TransferError::InvalidTransition {
+                command: command.to_string(),
+                status: status.to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod aggregate_tests {
+    use coverage_helper::test;
+    use cqrs_es::test::TestFramework;
+
+    use super::*;
+    use crate::domain::Money;
+
+    type TransferTestFramework = TestFramework<Transfer>;
+
+    fn transfer_started() -> TransferEvent {
+        TransferEvent::TransferStarted {
+            source_account_id: "1234".to_string(),
+            destination_account_id: "5678".to_string(),
+            amount: Money::new(10000, "USD"),
+        }
+    }
+
+    #[test]
+    fn start_transfer() {
+        TransferTestFramework::with(())
+            .given_no_previous_events()
+            .when(TransferCommand::StartTransfer(StartTransferCommandData {
+                source_account_id: "1234".to_string(),
+                destination_account_id: "5678".to_string(),
+                amount: Money::new(10000, "USD"),
+            }))
+            .then_expect_events(vec![transfer_started()]);
+    }
+
+    #[test]
+    fn cannot_transfer_to_the_same_account() {
+        TransferTestFramework::with(())
+            .given_no_previous_events()
+            .when(TransferCommand::StartTransfer(StartTransferCommandData {
+                source_account_id: "1234".to_string(),
+                destination_account_id: "1234".to_string(),
+                amount: Money::new(10000, "USD"),
+            }))
+            .then_expect_error(TransferError::CannotTransferToSameAccount);
+    }
+
+    #[test]
+    fn cannot_transfer_a_zero_amount() {
+        TransferTestFramework::with(())
+            .given_no_previous_events()
+            .when(TransferCommand::StartTransfer(StartTransferCommandData {
+                source_account_id: "1234".to_string(),
+                destination_account_id: "5678".to_string(),
+                amount: Money::zero("USD"),
+            }))
+            .then_expect_error(TransferError::AmountMustBePositive);
+    }
+
+    #[test]
+    fn cannot_start_a_transfer_twice() {
+        TransferTestFramework::with(())
+            .given(vec![transfer_started()])
+            .when(TransferCommand::StartTransfer(StartTransferCommandData {
+                source_account_id: "1234".to_string(),
+                destination_account_id: "5678".to_string(),
+                amount: Money::new(10000, "USD"),
+            }))
+            .then_expect_error(TransferError::TransferAlreadyStarted);
+    }
+
+    #[test]
+    fn completes_after_debit_and_credit() {
+        TransferTestFramework::with(())
+            .given(vec![
+                transfer_started(),
+                TransferEvent::SourceAccountDebited,
+            ])
+            .when(TransferCommand::RecordCredit)
+            .then_expect_events(vec![TransferEvent::DestinationAccountCredited]);
+    }
+
+    #[test]
+    fn failed_credit_starts_compensation() {
+        TransferTestFramework::with(())
+            .given(vec![
+                transfer_started(),
+                TransferEvent::SourceAccountDebited,
+            ])
+            .when(TransferCommand::RecordCreditFailure {
+                reason: "account not open".to_string(),
+            })
+            .then_expect_events(vec![TransferEvent::DestinationAccountCreditFailed {
+                reason: "account not open".to_string(),
+            }]);
+    }
+
+    #[test]
+    fn records_compensation_once_the_refund_is_made() {
+        TransferTestFramework::with(())
+            .given(vec![
+                transfer_started(),
+                TransferEvent::SourceAccountDebited,
+                TransferEvent::DestinationAccountCreditFailed {
+                    reason: "account not open".to_string(),
+                },
+            ])
+            .when(TransferCommand::RecordCompensation)
+            .then_expect_events(vec![TransferEvent::TransferCompensated]);
+    }
+
+    #[test]
+    fn cannot_credit_before_the_source_is_debited() {
+        TransferTestFramework::with(())
+            .given(vec![transfer_started()])
+            .when(TransferCommand::RecordCredit)
+            .then_expect_error(TransferError::InvalidTransition {
+                command: "complete".to_string(),
+                status: "started".to_string(),
+            });
+    }
+
+    #[test]
+    fn cannot_record_a_debit_for_an_unknown_transfer() {
+        TransferTestFramework::with(())
+            .given_no_previous_events()
+            .when(TransferCommand::RecordDebit)
+            .then_expect_error(TransferError::TransferNotStarted);
+    }
+}
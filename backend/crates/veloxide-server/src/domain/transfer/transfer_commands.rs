@@ -0,0 +1,26 @@
+use async_graphql::*;
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+use utoipa::ToSchema;
+
+use crate::domain::Money;
+
+// Only `StartTransfer` is issued by users, the remaining commands are issued by the
+// `TransferProcessManager` as it observes the bank accounts taking part in the transfer.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum TransferCommand {
+    StartTransfer(StartTransferCommandData),
+    RecordDebit,
+    RecordDebitFailure { reason: String },
+    RecordCredit,
+    RecordCreditFailure { reason: String },
+    RecordCompensation,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema, InputObject, Clone, TS)]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct StartTransferCommandData {
+    pub source_account_id: String,
+    pub destination_account_id: String,
+    pub amount: Money,
+}
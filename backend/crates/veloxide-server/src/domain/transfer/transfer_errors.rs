@@ -0,0 +1,19 @@
+use std::fmt::Debug;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum TransferError {
+    #[error("transfer already started")]
+    TransferAlreadyStarted,
+
+    #[error("transfer not started")]
+    TransferNotStarted,
+
+    #[error("cannot transfer to the same account")]
+    CannotTransferToSameAccount,
+
+    #[error("transfer amount must be positive")]
+    AmountMustBePositive,
+
+    #[error("cannot {command} a transfer that is {status}")]
+    InvalidTransition { command: String, status: String },
+}
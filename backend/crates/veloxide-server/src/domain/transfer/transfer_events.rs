@@ -0,0 +1,79 @@
+use cqrs_es::DomainEvent;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+
+use crate::domain::Money;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum TransferEvent {
+    TransferStarted {
+        source_account_id: String,
+        destination_account_id: String,
+        amount: Money,
+    },
+    SourceAccountDebited,
+    SourceAccountDebitFailed {
+        reason: String,
+    },
+    DestinationAccountCredited,
+    DestinationAccountCreditFailed {
+        reason: String,
+    },
+    TransferCompensated,
+}
+
+impl DomainEvent for TransferEvent {
+    fn event_type(&self) -> String {
+        match self {
+            TransferEvent::TransferStarted { .. } => "TransferStarted".to_string(),
+            TransferEvent::SourceAccountDebited => "SourceAccountDebited".to_string(),
+            TransferEvent::SourceAccountDebitFailed { .. } => {
+                "SourceAccountDebitFailed".to_string()
+            }
+            TransferEvent::DestinationAccountCredited => "DestinationAccountCredited".to_string(),
+            TransferEvent::DestinationAccountCreditFailed { .. } => {
+                "DestinationAccountCreditFailed".to_string()
+            }
+            TransferEvent::TransferCompensated => "TransferCompensated".to_string(),
+        }
+    }
+
+    fn event_version(&self) -> String {
+        "1.0".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn transfer_event_version_is_1_0() {
+        assert_eq!(
+            TransferEvent::TransferCompensated.event_version(),
+            "1.0".to_string()
+        );
+    }
+
+    #[test]
+    fn transfer_event_type_is_transfer_started() {
+        let event = TransferEvent::TransferStarted {
+            source_account_id: "A".to_string(),
+            destination_account_id: "B".to_string(),
+            amount: Money::new(10000, "USD"),
+        };
+        assert_eq!(event.event_type(), "TransferStarted".to_string());
+    }
+
+    #[test]
+    fn transfer_event_type_is_destination_account_credit_failed() {
+        let event = TransferEvent::DestinationAccountCreditFailed {
+            reason: "account not open".to_string(),
+        };
+        assert_eq!(
+            event.event_type(),
+            "DestinationAccountCreditFailed".to_string()
+        );
+    }
+}
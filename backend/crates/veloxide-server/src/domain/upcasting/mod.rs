@@ -0,0 +1,4 @@
+pub mod upcaster_registry;
+
+// Re-exports
+pub use upcaster_registry::*;
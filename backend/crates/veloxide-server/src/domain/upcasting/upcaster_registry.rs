@@ -0,0 +1,151 @@
+use cqrs_es::persist::{EventUpcaster, SerializedEvent};
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Transforms the stored JSON payload of an event from one version into the next.
+pub type UpcastFn = Box<dyn Fn(Value) -> Value + Send + Sync>;
+
+struct Upcast {
+    to_version: String,
+    upcast: UpcastFn,
+}
+
+/// The payload migrations of an aggregate's events, keyed by the `(event_type, event_version)`
+/// they apply to. Registered with the event store, each stored event is upcast step by step,
+/// e.g. 1.0 to 2.0 to 3.0, until no migration is registered for its version, so a migration
+/// only ever has to know about the version directly before it.
+#[derive(Default)]
+pub struct UpcasterRegistry {
+    upcasts: HashMap<(String, String), Upcast>,
+}
+
+impl UpcasterRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the migration of `event_type` payloads from `from_version` to `to_version`.
+    ///
+    /// Panics if a migration from that version is already registered, as only one of them
+    /// could ever be applied.
+    pub fn register(
+        mut self,
+        event_type: &str,
+        from_version: &str,
+        to_version: &str,
+        upcast: impl Fn(Value) -> Value + Send + Sync + 'static,
+    ) -> Self {
+        let key = (event_type.to_string(), from_version.to_string());
+        assert!(
+            !self.upcasts.contains_key(&key),
+            "an upcaster for {} {} is already registered",
+            event_type,
+            from_version
+        );
+        self.upcasts.insert(
+            key,
+            Upcast {
+                to_version: to_version.to_string(),
+                upcast: Box::new(upcast),
+            },
+        );
+        self
+    }
+
+    fn lookup(&self, event_type: &str, event_version: &str) -> Option<&Upcast> {
+        self.upcasts
+            .get(&(event_type.to_string(), event_version.to_string()))
+    }
+}
+
+impl EventUpcaster for UpcasterRegistry {
+    fn can_upcast(&self, event_type: &str, event_version: &str) -> bool {
+        self.lookup(event_type, event_version).is_some()
+    }
+
+    fn upcast(&self, mut event: SerializedEvent) -> SerializedEvent {
+        // Bounded by the number of migrations so that a cycle in the versions cannot hang a replay.
+        for _ in 0..self.upcasts.len() {
+            let Some(step) = self.lookup(&event.event_type, &event.event_version) else {
+                break;
+            };
+            event.payload = (step.upcast)(event.payload);
+            event.event_version = step.to_version.clone();
+        }
+        event
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn serialized_event(event_type: &str, event_version: &str, payload: Value) -> SerializedEvent {
+        SerializedEvent {
+            aggregate_id: "1234".to_string(),
+            sequence: 1,
+            aggregate_type: "account".to_string(),
+            event_type: event_type.to_string(),
+            event_version: event_version.to_string(),
+            payload,
+            metadata: json!({}),
+        }
+    }
+
+    fn rename_field(from: &'static str, to: &'static str) -> impl Fn(Value) -> Value {
+        move |mut payload| {
+            if let Some(fields) = payload.as_object_mut() {
+                if let Some(value) = fields.remove(from) {
+                    fields.insert(to.to_string(), value);
+                }
+            }
+            payload
+        }
+    }
+
+    fn registry() -> UpcasterRegistry {
+        UpcasterRegistry::new()
+            .register("Renamed", "1.0", "2.0", rename_field("a", "b"))
+            .register("Renamed", "2.0", "3.0", rename_field("b", "c"))
+    }
+
+    #[test]
+    fn upcasts_through_every_registered_version() {
+        let event = registry().upcast(serialized_event("Renamed", "1.0", json!({"a": 1})));
+
+        assert_eq!(event.event_version, "3.0");
+        assert_eq!(event.payload, json!({"c": 1}));
+    }
+
+    #[test]
+    fn upcasts_from_an_intermediate_version() {
+        let event = registry().upcast(serialized_event("Renamed", "2.0", json!({"b": 1})));
+
+        assert_eq!(event.event_version, "3.0");
+        assert_eq!(event.payload, json!({"c": 1}));
+    }
+
+    #[test]
+    fn only_upcasts_registered_event_types_and_versions() {
+        let registry = registry();
+        assert!(registry.can_upcast("Renamed", "1.0"));
+        assert!(!registry.can_upcast("Renamed", "3.0"));
+        assert!(!registry.can_upcast("Other", "1.0"));
+    }
+
+    #[test]
+    fn leaves_unregistered_events_untouched() {
+        let event = registry().upcast(serialized_event("Other", "1.0", json!({"a": 1})));
+
+        assert_eq!(event.event_version, "1.0");
+        assert_eq!(event.payload, json!({"a": 1}));
+    }
+
+    #[test]
+    #[should_panic(expected = "an upcaster for Renamed 1.0 is already registered")]
+    fn cannot_register_two_upcasters_for_the_same_version() {
+        registry().register("Renamed", "1.0", "1.1", rename_field("a", "d"));
+    }
+}
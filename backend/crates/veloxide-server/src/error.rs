@@ -36,6 +36,24 @@ pub enum Error {
 
     #[error(transparent)]
     Tonic(#[from] tonic::transport::Error),
+
+    #[error(transparent)]
+    Snapshot(#[from] crate::infrastructure::snapshots::SnapshotError),
+
+    #[error(transparent)]
+    Projection(#[from] crate::infrastructure::projections::ProjectionError),
+
+    #[error(transparent)]
+    Idempotency(#[from] crate::infrastructure::idempotency::IdempotencyError),
+
+    #[error(transparent)]
+    Outbox(#[from] crate::infrastructure::outbox::OutboxError),
+
+    #[error(transparent)]
+    Reconciliation(#[from] crate::infrastructure::reconciliation::ReconciliationError),
+
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
 }
 
 impl IntoResponse for Error {
@@ -0,0 +1,198 @@
+use std::time::Duration;
+
+use crate::application::{BankAccountApi, FxRateProvider, HappyPathBankAccountServices};
+
+use super::{AtmWithdrawals, HttpBankAccountServices, RulesBankAccountServices};
+
+pub const BANK_ACCOUNT_SERVICES_ENV_VAR: &str = "BANK_ACCOUNT_SERVICES";
+pub const BANK_ACCOUNT_SERVICES_URL_ENV_VAR: &str = "BANK_ACCOUNT_SERVICES_URL";
+pub const BANK_ACCOUNT_SERVICES_TIMEOUT_ENV_VAR: &str = "BANK_ACCOUNT_SERVICES_TIMEOUT_MS";
+pub const BANK_ACCOUNT_SERVICES_RETRIES_ENV_VAR: &str = "BANK_ACCOUNT_SERVICES_RETRIES";
+pub const ATM_DAILY_LIMIT_ENV_VAR: &str = "ATM_DAILY_LIMIT";
+
+pub const DEFAULT_BANK_ACCOUNT_SERVICES_TIMEOUT_MS: u64 = 5000;
+pub const DEFAULT_BANK_ACCOUNT_SERVICES_RETRIES: u32 = 2;
+/// 1000.00 in currencies with two decimals.
+pub const DEFAULT_ATM_DAILY_LIMIT: i64 = 100_000;
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum BankAccountServicesError {
+    #[error("invalid bank account services configuration: {0}")]
+    InvalidConfiguration(String),
+}
+
+/// Which services approve the ATM withdrawals and checks of bank accounts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BankAccountServicesConfiguration {
+    /// Approves every withdrawal and check.
+    HappyPath,
+    Http {
+        base_url: String,
+        timeout: Duration,
+        retries: u32,
+    },
+    Rules {
+        atm_daily_limit: i64,
+    },
+}
+
+impl BankAccountServicesConfiguration {
+    /// Reads the services from the `BANK_ACCOUNT_SERVICES` env var, one of `happy_path`, `http`
+    /// and `rules`. Unset or empty means `happy_path`.
+    pub fn from_env() -> Result<Self, BankAccountServicesError> {
+        match dotenvy::var(BANK_ACCOUNT_SERVICES_ENV_VAR) {
+            Ok(services) if !services.trim().is_empty() => {
+                Self::parse(&services, |name| dotenvy::var(name).ok())
+            }
+            _ => Ok(Self::HappyPath),
+        }
+    }
+
+    pub fn parse(
+        services: &str,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, BankAccountServicesError> {
+        let invalid = |name: &str, value: &str| {
+            BankAccountServicesError::InvalidConfiguration(format!("{}={}", name, value))
+        };
+        let number = |name: &str, default: u64| match var(name) {
+            Some(value) => value.trim().parse().map_err(|_| invalid(name, &value)),
+            None => Ok(default),
+        };
+        match services.trim() {
+            "happy_path" => Ok(Self::HappyPath),
+            "http" => {
+                let base_url = var(BANK_ACCOUNT_SERVICES_URL_ENV_VAR).ok_or_else(|| {
+                    BankAccountServicesError::InvalidConfiguration(format!(
+                        "{} is required for the http services",
+                        BANK_ACCOUNT_SERVICES_URL_ENV_VAR
+                    ))
+                })?;
+                url::Url::parse(&base_url)
+                    .map_err(|_| invalid(BANK_ACCOUNT_SERVICES_URL_ENV_VAR, &base_url))?;
+                let retries = number(
+                    BANK_ACCOUNT_SERVICES_RETRIES_ENV_VAR,
+                    DEFAULT_BANK_ACCOUNT_SERVICES_RETRIES.into(),
+                )?;
+                Ok(Self::Http {
+                    base_url,
+                    timeout: Duration::from_millis(number(
+                        BANK_ACCOUNT_SERVICES_TIMEOUT_ENV_VAR,
+                        DEFAULT_BANK_ACCOUNT_SERVICES_TIMEOUT_MS,
+                    )?),
+                    retries: u32::try_from(retries).map_err(|_| {
+                        invalid(BANK_ACCOUNT_SERVICES_RETRIES_ENV_VAR, &retries.to_string())
+                    })?,
+                })
+            }
+            "rules" => {
+                let atm_daily_limit =
+                    number(ATM_DAILY_LIMIT_ENV_VAR, DEFAULT_ATM_DAILY_LIMIT as u64)?;
+                Ok(Self::Rules {
+                    atm_daily_limit: i64::try_from(atm_daily_limit).map_err(|_| {
+                        invalid(ATM_DAILY_LIMIT_ENV_VAR, &atm_daily_limit.to_string())
+                    })?,
+                })
+            }
+            other => Err(BankAccountServicesError::InvalidConfiguration(format!(
+                "unknown services {}, expected one of: happy_path, http, rules",
+                other
+            ))),
+        }
+    }
+
+    /// Builds the services, converting currencies with the rates of `fx_rate_provider`. The rules
+    /// limit ATM withdrawals by the committed withdrawals `atm_withdrawals` counts.
+    pub fn services(
+        &self,
+        fx_rate_provider: Box<dyn FxRateProvider>,
+        atm_withdrawals: AtmWithdrawals,
+    ) -> Box<dyn BankAccountApi> {
+        match self {
+            Self::HappyPath => Box::new(HappyPathBankAccountServices::new(fx_rate_provider)),
+            Self::Http {
+                base_url,
+                timeout,
+                retries,
+            } => Box::new(HttpBankAccountServices::new(
+                base_url,
+                *timeout,
+                *retries,
+                fx_rate_provider,
+            )),
+            Self::Rules { atm_daily_limit } => Box::new(RulesBankAccountServices::new(
+                *atm_daily_limit,
+                fx_rate_provider,
+                atm_withdrawals,
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+
+    fn parse(
+        services: &str,
+        vars: &[(&str, &str)],
+    ) -> Result<BankAccountServicesConfiguration, BankAccountServicesError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        BankAccountServicesConfiguration::parse(services, |name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn parses_the_http_services() {
+        assert_eq!(
+            parse(
+                "http",
+                &[
+                    (BANK_ACCOUNT_SERVICES_URL_ENV_VAR, "https://atm.example.com"),
+                    (BANK_ACCOUNT_SERVICES_TIMEOUT_ENV_VAR, "2500"),
+                ]
+            )
+            .unwrap(),
+            BankAccountServicesConfiguration::Http {
+                base_url: "https://atm.example.com".to_string(),
+                timeout: Duration::from_millis(2500),
+                retries: DEFAULT_BANK_ACCOUNT_SERVICES_RETRIES,
+            }
+        );
+        assert!(parse("http", &[]).is_err());
+        assert!(parse("http", &[(BANK_ACCOUNT_SERVICES_URL_ENV_VAR, "atm")]).is_err());
+    }
+
+    #[test]
+    fn parses_the_rules_services() {
+        assert_eq!(
+            parse("rules", &[]).unwrap(),
+            BankAccountServicesConfiguration::Rules {
+                atm_daily_limit: DEFAULT_ATM_DAILY_LIMIT
+            }
+        );
+        assert_eq!(
+            parse("rules", &[(ATM_DAILY_LIMIT_ENV_VAR, "50000")]).unwrap(),
+            BankAccountServicesConfiguration::Rules {
+                atm_daily_limit: 50000
+            }
+        );
+        assert!(parse("rules", &[(ATM_DAILY_LIMIT_ENV_VAR, "-1")]).is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_services() {
+        assert_eq!(
+            parse("happy_path", &[]).unwrap(),
+            BankAccountServicesConfiguration::HappyPath
+        );
+        assert!(matches!(
+            parse("mainframe", &[]),
+            Err(BankAccountServicesError::InvalidConfiguration(_))
+        ));
+    }
+}
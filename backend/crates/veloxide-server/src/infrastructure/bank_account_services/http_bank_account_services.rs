@@ -0,0 +1,298 @@
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::application::{
+    convert_currency, AtmError, BankAccountApi, CheckingError, FxError, FxRateProvider,
+};
+use crate::domain::{CurrencyConversion, Money};
+use crate::infrastructure::idempotency::IDEMPOTENCY_KEY_HEADER;
+
+/// How long to wait before the first retry, doubling for every retry after it.
+const INITIAL_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Serialize)]
+struct AtmWithdrawalRequest<'a> {
+    account_id: &'a str,
+    atm_id: &'a str,
+    amount: &'a Money,
+}
+
+#[derive(Debug, Serialize)]
+struct CheckValidationRequest<'a> {
+    account_id: &'a str,
+    check_number: &'a str,
+}
+
+/// The answer of the ATM network or check clearing service to a request.
+#[derive(Debug, PartialEq)]
+enum Approval {
+    Approved,
+    Declined(StatusCode),
+    Unavailable(String),
+}
+
+/// Asks an ATM network and a check clearing service over HTTP to approve withdrawals and
+/// checks. `POST <base URL>/atm-withdrawals` and `POST <base URL>/check-validations` approve
+/// with any 2xx response and decline with any 4xx response. Timeouts, 5xx, 408 and 429
+/// responses are retried, and declined once the retries run out. Every attempt at a request
+/// carries the same `Idempotency-Key` header, so that a withdrawal the service approved before
+/// its response was lost is not approved twice.
+pub struct HttpBankAccountServices {
+    client: reqwest::Client,
+    base_url: String,
+    retries: u32,
+    fx_rate_provider: Box<dyn FxRateProvider>,
+}
+
+impl HttpBankAccountServices {
+    pub fn new(
+        base_url: &str,
+        timeout: Duration,
+        retries: u32,
+        fx_rate_provider: Box<dyn FxRateProvider>,
+    ) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(timeout)
+            .build()
+            .expect("expected to be able to build the bank account services HTTP client");
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            retries,
+            fx_rate_provider,
+        }
+    }
+
+    async fn request_approval(&self, path: &str, body: &impl Serialize) -> Approval {
+        let url = format!("{}{}", self.base_url, path);
+        let idempotency_key = uuid::Uuid::new_v4().to_string();
+        let mut backoff = INITIAL_RETRY_BACKOFF;
+        let mut attempt = 0;
+        loop {
+            let request = self
+                .client
+                .post(&url)
+                .header(IDEMPOTENCY_KEY_HEADER, &idempotency_key)
+                .json(body);
+            let approval = match request.send().await {
+                Ok(response) if response.status().is_success() => Approval::Approved,
+                Ok(response) if is_retryable(response.status()) => {
+                    Approval::Unavailable(format!("{} responded with {}", url, response.status()))
+                }
+                Ok(response) => Approval::Declined(response.status()),
+                Err(err) => Approval::Unavailable(err.to_string()),
+            };
+            match approval {
+                Approval::Unavailable(reason) if attempt < self.retries => {
+                    tracing::warn!(attempt, "retrying {}: {}", url, reason);
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                approval => return approval,
+            }
+        }
+    }
+}
+
+fn is_retryable(status: StatusCode) -> bool {
+    status.is_server_error()
+        || status == StatusCode::REQUEST_TIMEOUT
+        || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+#[async_trait]
+impl BankAccountApi for HttpBankAccountServices {
+    #[tracing::instrument(skip(self))]
+    async fn atm_withdrawal(
+        &self,
+        account_id: &str,
+        atm_id: &str,
+        amount: &Money,
+    ) -> Result<(), AtmError> {
+        let request = AtmWithdrawalRequest {
+            account_id,
+            atm_id,
+            amount,
+        };
+        match self.request_approval("/atm-withdrawals", &request).await {
+            Approval::Approved => Ok(()),
+            Approval::Declined(status) => {
+                tracing::warn!("ATM withdrawal declined with {}", status);
+                Err(AtmError)
+            }
+            Approval::Unavailable(reason) => {
+                tracing::error!("ATM network unavailable: {}", reason);
+                Err(AtmError)
+            }
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    async fn validate_check(
+        &self,
+        account_id: &str,
+        check_number: &str,
+    ) -> Result<(), CheckingError> {
+        let request = CheckValidationRequest {
+            account_id,
+            check_number,
+        };
+        match self.request_approval("/check-validations", &request).await {
+            Approval::Approved => Ok(()),
+            Approval::Declined(status) => {
+                tracing::warn!("check declined with {}", status);
+                Err(CheckingError)
+            }
+            Approval::Unavailable(reason) => {
+                tracing::error!("check clearing unavailable: {}", reason);
+                Err(CheckingError)
+            }
+        }
+    }
+
+    async fn convert_currency(
+        &self,
+        amount: &Money,
+        to_currency: &str,
+    ) -> Result<CurrencyConversion, FxError> {
+        convert_currency(self.fx_rate_provider.as_ref(), amount, to_currency).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::StaticFxRateProvider;
+    use axum::{extract::State, http::HeaderMap, routing::post, Json, Router};
+    use pretty_assertions::assert_eq;
+    use std::net::{SocketAddr, TcpListener};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Stub {
+        // Responded with in order, the last one repeating.
+        statuses: Vec<StatusCode>,
+        requests: Mutex<Vec<serde_json::Value>>,
+        idempotency_keys: Mutex<Vec<String>>,
+    }
+
+    // A local ATM network and check clearing service that responds with `statuses`.
+    fn start_stub(statuses: Vec<StatusCode>) -> (SocketAddr, Arc<Stub>) {
+        let stub = Arc::new(Stub {
+            statuses,
+            ..Stub::default()
+        });
+        let respond = |State(stub): State<Arc<Stub>>,
+                       headers: HeaderMap,
+                       Json(request): Json<serde_json::Value>| async move {
+            if let Some(key) = headers.get(IDEMPOTENCY_KEY_HEADER) {
+                let key = key.to_str().unwrap().to_string();
+                stub.idempotency_keys.lock().unwrap().push(key);
+            }
+            let mut requests = stub.requests.lock().unwrap();
+            requests.push(request);
+            let index = (requests.len() - 1).min(stub.statuses.len() - 1);
+            stub.statuses[index]
+        };
+        let router = Router::new()
+            .route("/atm-withdrawals", post(respond))
+            .route("/check-validations", post(respond))
+            .with_state(stub.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(router.into_make_service()),
+        );
+        (address, stub)
+    }
+
+    fn services(address: SocketAddr, retries: u32) -> HttpBankAccountServices {
+        HttpBankAccountServices::new(
+            &format!("http://{}/", address),
+            Duration::from_secs(1),
+            retries,
+            Box::<StaticFxRateProvider>::default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn approves_withdrawals_the_atm_network_accepts() {
+        let (address, stub) = start_stub(vec![StatusCode::OK]);
+
+        let result = services(address, 2)
+            .atm_withdrawal("1234", "ATM34f1ba3c", &Money::new(2000, "USD"))
+            .await;
+
+        assert!(result.is_ok());
+        assert_eq!(
+            stub.requests.lock().unwrap()[0],
+            serde_json::json!({
+                "account_id": "1234",
+                "atm_id": "ATM34f1ba3c",
+                "amount": { "minor_units": 2000, "currency": "USD" },
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn declines_without_retrying_client_errors() {
+        let (address, stub) = start_stub(vec![StatusCode::UNPROCESSABLE_ENTITY]);
+
+        let result = services(address, 2).validate_check("1234", "1001").await;
+
+        assert!(result.is_err());
+        assert_eq!(stub.requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn retries_while_the_service_is_unavailable() {
+        let (address, stub) = start_stub(vec![
+            StatusCode::SERVICE_UNAVAILABLE,
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::NO_CONTENT,
+        ]);
+
+        let result = services(address, 2).validate_check("1234", "1001").await;
+
+        assert!(result.is_ok());
+        assert_eq!(stub.requests.lock().unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn declines_once_retries_run_out() {
+        let (address, stub) = start_stub(vec![StatusCode::BAD_GATEWAY]);
+
+        let result = services(address, 1)
+            .atm_withdrawal("1234", "ATM34f1ba3c", &Money::new(2000, "USD"))
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(stub.requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn retries_carry_the_same_idempotency_key() {
+        let (address, stub) = start_stub(vec![StatusCode::BAD_GATEWAY, StatusCode::OK]);
+        let services = services(address, 2);
+
+        let amount = Money::new(2000, "USD");
+        assert!(services
+            .atm_withdrawal("1234", "ATM34f1ba3c", &amount)
+            .await
+            .is_ok());
+        assert!(services
+            .atm_withdrawal("1234", "ATM34f1ba3c", &amount)
+            .await
+            .is_ok());
+
+        let keys = stub.idempotency_keys.lock().unwrap();
+        assert_eq!(keys.len(), 3);
+        assert_eq!(keys[0], keys[1]);
+        assert_ne!(keys[1], keys[2]);
+    }
+}
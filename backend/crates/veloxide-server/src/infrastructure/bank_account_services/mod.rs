@@ -0,0 +1,9 @@
+pub mod bank_account_services_configuration;
+pub mod http_bank_account_services;
+pub mod rules_bank_account_services;
+
+// Re-exports
+
+pub use bank_account_services_configuration::*;
+pub use http_bank_account_services::*;
+pub use rules_bank_account_services::*;
@@ -0,0 +1,187 @@
+use async_trait::async_trait;
+use chrono::{NaiveDate, Utc};
+use cqrs_es::{EventEnvelope, Query};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::application::{
+    convert_currency, AtmError, BankAccountApi, CheckingError, FxError, FxRateProvider,
+};
+use crate::domain::{BankAccount, BankAccountEvent, CurrencyConversion, Money};
+
+/// What each account withdrew at ATMs on the current day, per currency. As a query it counts
+/// the withdrawals as they are committed, so a withdrawal whose command fails never counts.
+///
+/// It is kept in memory, so it is lost on restart and not shared between instances.
+#[derive(Clone, Default)]
+pub struct AtmWithdrawals {
+    withdrawn: Arc<Mutex<HashMap<(String, String), (NaiveDate, i64)>>>,
+}
+
+impl AtmWithdrawals {
+    fn withdrawn_on(&self, day: NaiveDate, account_id: &str, currency: &str) -> i64 {
+        let withdrawn = self.withdrawn.lock().unwrap();
+        match withdrawn.get(&(account_id.to_string(), currency.to_string())) {
+            Some((withdrawn_on, withdrawn)) if *withdrawn_on == day => *withdrawn,
+            _ => 0,
+        }
+    }
+
+    fn record(&self, day: NaiveDate, account_id: &str, amount: &Money) {
+        let mut withdrawn = self.withdrawn.lock().unwrap();
+        let entry = withdrawn
+            .entry((account_id.to_string(), amount.currency.clone()))
+            .or_insert((day, 0));
+        if entry.0 != day {
+            *entry = (day, 0);
+        }
+        entry.1 = entry.1.saturating_add(amount.minor_units);
+    }
+}
+
+#[async_trait]
+impl Query<BankAccount> for AtmWithdrawals {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<BankAccount>]) {
+        for event in events {
+            if let BankAccountEvent::CustomerWithdrewCash { amount, .. } = &event.payload {
+                self.record(Utc::now().date_naive(), aggregate_id, amount);
+            }
+        }
+    }
+}
+
+/// Approves ATM withdrawals with rules evaluated in-process: each account may withdraw up to a
+/// daily limit per currency. Checks are left to the aggregate, which rejects check numbers that
+/// were used before.
+///
+/// Withdrawals only count once committed, see `AtmWithdrawals`, so withdrawals approved at the
+/// same time may together go over the limit.
+pub struct RulesBankAccountServices {
+    atm_daily_limit: i64,
+    fx_rate_provider: Box<dyn FxRateProvider>,
+    atm_withdrawals: AtmWithdrawals,
+}
+
+impl RulesBankAccountServices {
+    /// `atm_daily_limit` is in the minor units of whichever currency is withdrawn.
+    pub fn new(
+        atm_daily_limit: i64,
+        fx_rate_provider: Box<dyn FxRateProvider>,
+        atm_withdrawals: AtmWithdrawals,
+    ) -> Self {
+        Self {
+            atm_daily_limit,
+            fx_rate_provider,
+            atm_withdrawals,
+        }
+    }
+
+    fn approve_withdrawal_on(
+        &self,
+        day: NaiveDate,
+        account_id: &str,
+        amount: &Money,
+    ) -> Result<(), AtmError> {
+        self.atm_withdrawals
+            .withdrawn_on(day, account_id, &amount.currency)
+            .checked_add(amount.minor_units)
+            .filter(|withdrawn| *withdrawn <= self.atm_daily_limit)
+            .map(|_| ())
+            .ok_or_else(|| {
+                tracing::warn!(account_id, "daily ATM withdrawal limit exceeded");
+                AtmError
+            })
+    }
+}
+
+#[async_trait]
+impl BankAccountApi for RulesBankAccountServices {
+    async fn atm_withdrawal(
+        &self,
+        account_id: &str,
+        _atm_id: &str,
+        amount: &Money,
+    ) -> Result<(), AtmError> {
+        self.approve_withdrawal_on(Utc::now().date_naive(), account_id, amount)
+    }
+
+    async fn validate_check(
+        &self,
+        _account_id: &str,
+        _check_number: &str,
+    ) -> Result<(), CheckingError> {
+        Ok(())
+    }
+
+    async fn convert_currency(
+        &self,
+        amount: &Money,
+        to_currency: &str,
+    ) -> Result<CurrencyConversion, FxError> {
+        convert_currency(self.fx_rate_provider.as_ref(), amount, to_currency).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::application::StaticFxRateProvider;
+
+    fn services(atm_daily_limit: i64) -> RulesBankAccountServices {
+        RulesBankAccountServices::new(
+            atm_daily_limit,
+            Box::<StaticFxRateProvider>::default(),
+            AtmWithdrawals::default(),
+        )
+    }
+
+    fn day(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2023, 11, day).unwrap()
+    }
+
+    // Approves the withdrawal and counts it as if its command was committed.
+    fn withdraw_on(
+        services: &RulesBankAccountServices,
+        day: NaiveDate,
+        account_id: &str,
+        amount: Money,
+    ) -> Result<(), AtmError> {
+        services.approve_withdrawal_on(day, account_id, &amount)?;
+        services.atm_withdrawals.record(day, account_id, &amount);
+        Ok(())
+    }
+
+    #[test]
+    fn withdrawals_are_limited_per_account_and_day() {
+        let services = services(50000);
+        assert!(withdraw_on(&services, day(1), "1234", Money::new(30000, "USD")).is_ok());
+        assert!(withdraw_on(&services, day(1), "1234", Money::new(30000, "USD")).is_err());
+        assert!(withdraw_on(&services, day(1), "1234", Money::new(20000, "USD")).is_ok());
+        assert!(withdraw_on(&services, day(1), "5678", Money::new(50000, "USD")).is_ok());
+        assert!(withdraw_on(&services, day(2), "1234", Money::new(50000, "USD")).is_ok());
+    }
+
+    #[test]
+    fn withdrawals_are_limited_per_currency() {
+        let services = services(50000);
+        assert!(withdraw_on(&services, day(1), "1234", Money::new(50000, "USD")).is_ok());
+        assert!(withdraw_on(&services, day(1), "1234", Money::new(50000, "EUR")).is_ok());
+    }
+
+    #[test]
+    fn withdrawals_only_count_once_committed() {
+        let services = services(50000);
+        let amount = Money::new(50000, "USD");
+        assert!(services
+            .approve_withdrawal_on(day(1), "1234", &amount)
+            .is_ok());
+        assert!(services
+            .approve_withdrawal_on(day(1), "1234", &amount)
+            .is_ok());
+
+        services.atm_withdrawals.record(day(1), "1234", &amount);
+        assert!(services
+            .approve_withdrawal_on(day(1), "1234", &amount)
+            .is_err());
+    }
+}
@@ -11,6 +11,10 @@ fn get_database_environment_variable() -> String {
 cfg_if::cfg_if! {
     if #[cfg(feature = "postgres")] {
         use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
+
+        /// The pool of the database the server is built for.
+        pub type DbPool = Pool<Postgres>;
+
         #[instrument]
         pub async fn get_db_connection_postgres_sqlx() -> crate::prelude::Result<Pool<Postgres>> {
             let db_connection_url = get_database_environment_variable();
@@ -30,6 +34,9 @@ cfg_if::cfg_if! {
     } else if #[cfg(feature = "mysql")] {
         use sqlx::{mysql::MySqlPoolOptions, MySql, Pool};
 
+        /// The pool of the database the server is built for.
+        pub type DbPool = Pool<MySql>;
+
         #[instrument]
         pub async fn get_db_connection_mysql_sqlx() -> crate::prelude::Result<Pool<MySql>> {
             let db_connection_url = get_database_environment_variable();
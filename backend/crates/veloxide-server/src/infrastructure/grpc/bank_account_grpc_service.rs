@@ -1,8 +1,42 @@
-use crate::interfaces::bank_account::bank_account_views::AccountTransaction as DomainAccountTransaction;
+use crate::domain::AccessRole as DomainAccessRole;
+use crate::domain::AccountStatus as DomainAccountStatus;
+use crate::domain::ExchangeRate as DomainExchangeRate;
+use crate::domain::Money as DomainMoney;
+use crate::domain::StartTransferCommandData;
+use crate::domain::TransferStatus as DomainTransferStatus;
+use crate::domain::{
+    default_currency, BankAccountCancelCheckCommandData, BankAccountCommand,
+    BankAccountDepositMoneyCommandData, BankAccountError, BankAccountOpenAccountCommandData,
+    BankAccountWithdrawMoneyCommandData, BankAccountWriteCheckCommandData,
+};
+use crate::interfaces::bank_account::account_access::AccountAccess as DomainAccountAccess;
+use crate::interfaces::bank_account::account_events::{
+    view_access_check, AccountEvent as DomainAccountEvent, AccountEventBroadcaster,
+    AccountEventsError,
+};
+use crate::interfaces::bank_account::account_statements::AccountStatementError;
+use crate::interfaces::bank_account::account_transactions::AccountTransaction as DomainAccountTransaction;
+use crate::interfaces::bank_account::account_transactions::AccountTransactionFilter;
+use crate::interfaces::bank_account::account_transactions::AccountTransactionType as DomainAccountTransactionType;
+use crate::interfaces::bank_account::account_transactions::AccountTransactionsError;
 use crate::interfaces::bank_account::bank_account_views::BankAccountView as DomainBankAccountView;
+use crate::interfaces::transfer::transfer_views::TransferView as DomainTransferView;
 use bank_account_service::bank_account_service_server::BankAccountService;
+use bank_account_service::AccessRole as GrpcAccessRole;
+use bank_account_service::AccountAccess as GrpcAccountAccess;
+use bank_account_service::AccountEvent as GrpcAccountEvent;
+use bank_account_service::AccountStatus as GrpcAccountStatus;
 use bank_account_service::AccountTransaction as GrpcAccountTransaction;
+use bank_account_service::AccountTransactionType as GrpcAccountTransactionType;
 use bank_account_service::BankAccountView as GrpcBankAccountView;
+use bank_account_service::ExchangeRate as GrpcExchangeRate;
+use bank_account_service::Money as GrpcMoney;
+use bank_account_service::TransferStatus as GrpcTransferStatus;
+use bank_account_service::TransferView as GrpcTransferView;
+use cqrs_es::DomainEvent;
+use futures::stream::{BoxStream, StreamExt};
+use std::collections::HashMap;
+use std::sync::Arc;
 use tonic::{Request, Response, Status};
 
 pub mod bank_account_service {
@@ -10,33 +44,395 @@ pub mod bank_account_service {
 }
 pub use bank_account_service::*;
 
-use crate::application::{BankAccountApplicationService, BankAccountServiceError};
+use crate::application::{
+    Actor, BankAccountApplicationService, BankAccountServiceError, TransferApplicationService,
+    TransferServiceError,
+};
+use crate::infrastructure::grpc::auth_grpc_service::UserView;
+use crate::infrastructure::idempotency::{IdempotencyError, IDEMPOTENCY_KEY_METADATA};
 
 pub struct GRpcBankAccountService {
-    app_service: Box<dyn BankAccountApplicationService>,
+    app_service: Arc<dyn BankAccountApplicationService>,
+    transfer_service: Arc<dyn TransferApplicationService>,
+    account_events: AccountEventBroadcaster,
 }
 
 impl GRpcBankAccountService {
-    pub fn new(app_service: Box<dyn BankAccountApplicationService>) -> Self {
-        GRpcBankAccountService { app_service }
+    pub fn new(
+        app_service: Arc<dyn BankAccountApplicationService>,
+        transfer_service: Arc<dyn TransferApplicationService>,
+        account_events: AccountEventBroadcaster,
+    ) -> Self {
+        GRpcBankAccountService {
+            app_service,
+            transfer_service,
+            account_events,
+        }
+    }
+}
+
+// The user is resolved from the session cookie by `mw_authenticate`, like for the REST API.
+fn actor<T>(request: &Request<T>) -> Actor {
+    request
+        .extensions()
+        .get::<Option<UserView>>()
+        .cloned()
+        .flatten()
+        .into()
+}
+
+// The gRPC counterpart of the `Idempotency-Key` header of the REST API.
+fn idempotency_key<T>(request: &Request<T>) -> Result<Option<String>, Status> {
+    request
+        .metadata()
+        .get(IDEMPOTENCY_KEY_METADATA)
+        .map(|key| {
+            key.to_str()
+                .map(str::to_string)
+                .map_err(|_| Status::invalid_argument("the idempotency key must be visible ASCII"))
+        })
+        .transpose()
+}
+
+// Like the `MetadataExtension` of the REST API, records when and through which RPC a command
+// was issued.
+fn command_metadata<T>(request: &Request<T>, rpc: &str) -> HashMap<String, String> {
+    let mut metadata = HashMap::default();
+    metadata.insert("time".to_string(), chrono::Utc::now().to_rfc3339());
+    metadata.insert(
+        "path".to_string(),
+        format!("/bank_account_service.BankAccountService/{}", rpc),
+    );
+    if let Some(Ok(user_agent)) = request.metadata().get("user-agent").map(|v| v.to_str()) {
+        metadata.insert("User-Agent".to_string(), user_agent.to_string());
+    }
+    metadata
+}
+
+fn money(amount: Option<GrpcMoney>) -> Result<DomainMoney, Status> {
+    let amount = amount.ok_or_else(|| Status::invalid_argument("amount is required"))?;
+    Ok(DomainMoney::new(amount.minor_units, &amount.currency))
+}
+
+impl GRpcBankAccountService {
+    // Executes the command the same way the REST and GraphQL APIs do, and responds with the
+    // resulting view of the account.
+    async fn execute<T>(
+        &self,
+        request: &Request<T>,
+        rpc: &str,
+        account_id: String,
+        command: BankAccountCommand,
+        expected_version: u64,
+    ) -> Result<GrpcBankAccountView, Status> {
+        let actor = actor(request);
+        let expected_version = (expected_version > 0).then_some(expected_version as usize);
+        self.app_service
+            .execute(
+                &actor,
+                account_id.clone(),
+                command,
+                command_metadata(request, rpc),
+                expected_version,
+                idempotency_key(request)?,
+            )
+            .await?;
+        let account_view = self
+            .app_service
+            .get_bank_account(&actor, account_id)
+            .await?;
+        Ok(account_view.into())
     }
 }
 
 #[tonic::async_trait]
 impl BankAccountService for GRpcBankAccountService {
+    type SubscribeAccountEventsStream = BoxStream<'static, Result<GrpcAccountEvent, Status>>;
+
     #[tracing::instrument(skip(self), ret, err)]
     async fn get_bank_account(
         &self,
         request: Request<GetBankAccountRequest>,
     ) -> Result<Response<GetBankAccountResponse>, Status> {
+        let actor = actor(&request);
         let account_id = request.into_inner().id;
 
-        let account_view = self.app_service.get_bank_account(account_id).await?;
+        let account_view = self
+            .app_service
+            .get_bank_account(&actor, account_id)
+            .await?;
         let reply = bank_account_service::GetBankAccountResponse {
             account_view: Some(account_view.into()),
         };
         Ok(Response::new(reply))
     }
+
+    #[tracing::instrument(skip(self), ret, err)]
+    async fn open_account(
+        &self,
+        request: Request<OpenAccountRequest>,
+    ) -> Result<Response<OpenAccountResponse>, Status> {
+        let OpenAccountRequest {
+            account_id,
+            currency,
+        } = request.get_ref().clone();
+        let command = BankAccountCommand::OpenAccount(BankAccountOpenAccountCommandData {
+            account_id: account_id.clone(),
+            currency: if currency.is_empty() {
+                default_currency()
+            } else {
+                currency
+            },
+            owner_id: None,
+        });
+
+        let account_view = self
+            .execute(&request, "OpenAccount", account_id, command, 0)
+            .await?;
+        let reply = bank_account_service::OpenAccountResponse {
+            account_view: Some(account_view),
+        };
+        Ok(Response::new(reply))
+    }
+
+    #[tracing::instrument(skip(self), ret, err)]
+    async fn deposit_money(
+        &self,
+        request: Request<DepositMoneyRequest>,
+    ) -> Result<Response<DepositMoneyResponse>, Status> {
+        let DepositMoneyRequest {
+            account_id,
+            amount,
+            expected_version,
+        } = request.get_ref().clone();
+        let command = BankAccountCommand::DepositMoney(BankAccountDepositMoneyCommandData {
+            amount: money(amount)?,
+        });
+
+        let account_view = self
+            .execute(
+                &request,
+                "DepositMoney",
+                account_id,
+                command,
+                expected_version,
+            )
+            .await?;
+        let reply = bank_account_service::DepositMoneyResponse {
+            account_view: Some(account_view),
+        };
+        Ok(Response::new(reply))
+    }
+
+    #[tracing::instrument(skip(self), ret, err)]
+    async fn withdraw_money(
+        &self,
+        request: Request<WithdrawMoneyRequest>,
+    ) -> Result<Response<WithdrawMoneyResponse>, Status> {
+        let WithdrawMoneyRequest {
+            account_id,
+            amount,
+            atm_id,
+            expected_version,
+        } = request.get_ref().clone();
+        let command = BankAccountCommand::WithdrawMoney(BankAccountWithdrawMoneyCommandData {
+            amount: money(amount)?,
+            atm_id,
+        });
+
+        let account_view = self
+            .execute(
+                &request,
+                "WithdrawMoney",
+                account_id,
+                command,
+                expected_version,
+            )
+            .await?;
+        let reply = bank_account_service::WithdrawMoneyResponse {
+            account_view: Some(account_view),
+        };
+        Ok(Response::new(reply))
+    }
+
+    #[tracing::instrument(skip(self), ret, err)]
+    async fn write_check(
+        &self,
+        request: Request<WriteCheckRequest>,
+    ) -> Result<Response<WriteCheckResponse>, Status> {
+        let WriteCheckRequest {
+            account_id,
+            check_number,
+            amount,
+            expected_version,
+        } = request.get_ref().clone();
+        let command = BankAccountCommand::WriteCheck(BankAccountWriteCheckCommandData {
+            check_number,
+            amount: money(amount)?,
+        });
+
+        let account_view = self
+            .execute(
+                &request,
+                "WriteCheck",
+                account_id,
+                command,
+                expected_version,
+            )
+            .await?;
+        let reply = bank_account_service::WriteCheckResponse {
+            account_view: Some(account_view),
+        };
+        Ok(Response::new(reply))
+    }
+
+    #[tracing::instrument(skip(self), ret, err)]
+    async fn cancel_check(
+        &self,
+        request: Request<CancelCheckRequest>,
+    ) -> Result<Response<CancelCheckResponse>, Status> {
+        let CancelCheckRequest {
+            account_id,
+            check_number,
+            expected_version,
+        } = request.get_ref().clone();
+        let command =
+            BankAccountCommand::CancelCheck(BankAccountCancelCheckCommandData { check_number });
+
+        let account_view = self
+            .execute(
+                &request,
+                "CancelCheck",
+                account_id,
+                command,
+                expected_version,
+            )
+            .await?;
+        let reply = bank_account_service::CancelCheckResponse {
+            account_view: Some(account_view),
+        };
+        Ok(Response::new(reply))
+    }
+
+    #[tracing::instrument(skip(self), ret, err)]
+    async fn transfer_money(
+        &self,
+        request: Request<TransferMoneyRequest>,
+    ) -> Result<Response<TransferMoneyResponse>, Status> {
+        let actor = actor(&request);
+        let idempotency_key = idempotency_key(&request)?;
+        let request = request.into_inner();
+        let command = StartTransferCommandData {
+            source_account_id: request.source_account_id,
+            destination_account_id: request.destination_account_id,
+            amount: money(request.amount)?,
+        };
+
+        let transfer_view = self
+            .transfer_service
+            .transfer_money(&actor, command, idempotency_key)
+            .await?;
+        let reply = bank_account_service::TransferMoneyResponse {
+            transfer_view: Some(transfer_view.into()),
+        };
+        Ok(Response::new(reply))
+    }
+
+    #[tracing::instrument(skip(self), ret, err)]
+    async fn get_transfer(
+        &self,
+        request: Request<GetTransferRequest>,
+    ) -> Result<Response<GetTransferResponse>, Status> {
+        let actor = actor(&request);
+        let transfer_id = request.into_inner().id;
+
+        let transfer_view = self
+            .transfer_service
+            .get_transfer(&actor, transfer_id)
+            .await?;
+        let reply = bank_account_service::GetTransferResponse {
+            transfer_view: Some(transfer_view.into()),
+        };
+        Ok(Response::new(reply))
+    }
+
+    #[tracing::instrument(skip(self), ret, err)]
+    async fn list_account_transactions(
+        &self,
+        request: Request<ListAccountTransactionsRequest>,
+    ) -> Result<Response<ListAccountTransactionsResponse>, Status> {
+        let actor = actor(&request);
+        let request = request.into_inner();
+        let transaction_type = transaction_type_filter(request.transaction_type());
+        // Unset proto3 fields arrive as their zero value rather than as absent.
+        let non_empty = |value: String| (!value.is_empty()).then_some(value);
+        let filter = AccountTransactionFilter::parse(
+            non_empty(request.from).as_deref(),
+            non_empty(request.to).as_deref(),
+            transaction_type,
+        )
+        .map_err(BankAccountServiceError::from)?;
+
+        let page = self
+            .app_service
+            .get_account_transactions(
+                &actor,
+                request.account_id,
+                filter,
+                non_empty(request.after),
+                (request.limit > 0).then_some(request.limit as usize),
+            )
+            .await?;
+        let reply = bank_account_service::ListAccountTransactionsResponse {
+            transactions: page.transactions.into_iter().map(Into::into).collect(),
+            next_cursor: page.next_cursor.unwrap_or_default(),
+        };
+        Ok(Response::new(reply))
+    }
+
+    // Access to the account is checked when subscribing, and again whenever access to it is
+    // revoked, ending the stream with `PERMISSION_DENIED` once the caller lost it.
+    #[tracing::instrument(skip(self), err)]
+    async fn subscribe_account_events(
+        &self,
+        request: Request<SubscribeAccountEventsRequest>,
+    ) -> Result<Response<Self::SubscribeAccountEventsStream>, Status> {
+        let actor = actor(&request);
+        let request = request.into_inner();
+        self.app_service
+            .get_bank_account(&actor, request.account_id.clone())
+            .await?;
+
+        let access_check =
+            view_access_check(self.app_service.clone(), actor, request.account_id.clone());
+        let events = self
+            .account_events
+            .subscribe(
+                request.account_id,
+                request.from_sequence as usize,
+                access_check,
+            )
+            .map(|event| event.map(GrpcAccountEvent::from).map_err(Status::from));
+        Ok(Response::new(events.boxed()))
+    }
+
+    #[tracing::instrument(skip(self), ret, err)]
+    async fn list_account_access(
+        &self,
+        request: Request<ListAccountAccessRequest>,
+    ) -> Result<Response<ListAccountAccessResponse>, Status> {
+        let actor = actor(&request);
+        let account_id = request.into_inner().account_id;
+
+        let access = self
+            .app_service
+            .get_account_access(&actor, account_id)
+            .await?;
+        let reply = bank_account_service::ListAccountAccessResponse {
+            access: access.into_iter().map(Into::into).collect(),
+        };
+        Ok(Response::new(reply))
+    }
 }
 
 const GENERIC_ERROR: &str = "An internal error occurred";
@@ -45,6 +441,120 @@ impl From<BankAccountServiceError> for tonic::Status {
         match error {
             BankAccountServiceError::Persistence(_) => Status::internal(GENERIC_ERROR),
             BankAccountServiceError::BankAccountNotFound(err) => Status::not_found(err),
+            err @ BankAccountServiceError::AccessDenied(_) => {
+                Status::permission_denied(err.to_string())
+            }
+            err @ BankAccountServiceError::Unauthenticated => {
+                Status::unauthenticated(err.to_string())
+            }
+            BankAccountServiceError::CommandRejected(err) => err.into(),
+            BankAccountServiceError::CommandFailed(_) => Status::internal(GENERIC_ERROR),
+            BankAccountServiceError::Conflict(err) => Status::aborted(err),
+            BankAccountServiceError::Transactions(AccountTransactionsError::Sqlx(_)) => {
+                Status::internal(GENERIC_ERROR)
+            }
+            BankAccountServiceError::Transactions(err) => Status::invalid_argument(err.to_string()),
+            BankAccountServiceError::Statements(AccountStatementError::Replay { .. }) => {
+                Status::internal(GENERIC_ERROR)
+            }
+            err @ BankAccountServiceError::Statements(AccountStatementError::NotOpen { .. }) => {
+                Status::not_found(err.to_string())
+            }
+            BankAccountServiceError::Statements(err) => Status::invalid_argument(err.to_string()),
+            BankAccountServiceError::Access(_) => Status::internal(GENERIC_ERROR),
+            BankAccountServiceError::Idempotency(err) => err.into(),
+        }
+    }
+}
+
+impl From<TransferServiceError> for tonic::Status {
+    fn from(error: TransferServiceError) -> Self {
+        match error {
+            TransferServiceError::Persistence(_) => Status::internal(GENERIC_ERROR),
+            TransferServiceError::TransferNotFound(err) => Status::not_found(err),
+            TransferServiceError::TransferRejected(err) => Status::failed_precondition(err),
+            err @ TransferServiceError::AccessDenied(_) => {
+                Status::permission_denied(err.to_string())
+            }
+            TransferServiceError::Idempotency(err) => err.into(),
+        }
+    }
+}
+
+impl From<BankAccountError> for tonic::Status {
+    fn from(error: BankAccountError) -> Self {
+        match error {
+            err @ (BankAccountError::AccountAlreadyOpen
+            | BankAccountError::AccessAlreadyGranted
+            | BankAccountError::OwnerAlreadyAssigned
+            | BankAccountError::InterestAlreadyAccrued
+            | BankAccountError::TransferAlreadyApplied) => Status::already_exists(err.to_string()),
+            err @ (BankAccountError::AccessNotGranted
+            | BankAccountError::CheckNotFound
+            | BankAccountError::HoldNotFound) => Status::not_found(err.to_string()),
+            err @ (BankAccountError::CannotDepositNegativeAmount
+            | BankAccountError::CannotWithdrawNegativeAmount
+            | BankAccountError::CannotWriteNegativeCheckAmount
+            | BankAccountError::InvalidAmount
+            | BankAccountError::InvalidCheckNumber
+            | BankAccountError::InvalidAccountId
+            | BankAccountError::InvalidCheck
+            | BankAccountError::InvalidCurrency
+            | BankAccountError::CurrencyMismatch
+            | BankAccountError::InvalidOverdraftLimit
+            | BankAccountError::InvalidAccessRole
+            | BankAccountError::CannotGrantOwnerRole
+            | BankAccountError::InvalidInterestDate
+            | BankAccountError::InvalidInterestRate
+            | BankAccountError::InvalidHoldId
+            | BankAccountError::InvalidHoldExpiry
+            | BankAccountError::InvalidCaptureAmount) => Status::invalid_argument(err.to_string()),
+            err @ (BankAccountError::InsufficientFunds
+            | BankAccountError::AtmRuleViolation
+            | BankAccountError::AccountNotOpen
+            | BankAccountError::AccountFrozen
+            | BankAccountError::AccountAlreadyFrozen
+            | BankAccountError::AccountNotFrozen
+            | BankAccountError::AccountClosed
+            | BankAccountError::CannotCloseAccountWithNonZeroBalance
+            | BankAccountError::CannotCloseAccountWithActiveHolds
+            | BankAccountError::CannotCloseAccountWithAccruedInterest
+            | BankAccountError::CannotChangeOwnerAccess
+            | BankAccountError::CheckAlreadyCancelled
+            | BankAccountError::CheckAlreadyCleared
+            | BankAccountError::TransferNotDebited) => Status::failed_precondition(err.to_string()),
+            err @ BankAccountError::AmountOverflow => Status::out_of_range(err.to_string()),
+            err @ BankAccountError::ExchangeRateUnavailable => Status::unavailable(err.to_string()),
+            BankAccountError::UnexpectedError(_) => Status::internal(GENERIC_ERROR),
+        }
+    }
+}
+
+impl From<AccountEventsError> for tonic::Status {
+    fn from(error: AccountEventsError) -> Self {
+        match error {
+            err @ AccountEventsError::AccessDenied(_) => Status::permission_denied(err.to_string()),
+            AccountEventsError::Replay { .. } | AccountEventsError::AccessCheck { .. } => {
+                Status::internal(GENERIC_ERROR)
+            }
+        }
+    }
+}
+
+impl From<IdempotencyError> for tonic::Status {
+    fn from(error: IdempotencyError) -> Self {
+        match error {
+            err @ (IdempotencyError::InvalidKey | IdempotencyError::KeyReused(_)) => {
+                Status::invalid_argument(err.to_string())
+            }
+            err @ IdempotencyError::InProgress(_) => Status::aborted(err.to_string()),
+            err @ IdempotencyError::OutcomeUnknown(_) => {
+                Status::failed_precondition(err.to_string())
+            }
+            err @ IdempotencyError::Unauthenticated => Status::unauthenticated(err.to_string()),
+            IdempotencyError::InvalidConfiguration(_)
+            | IdempotencyError::Serialization(_)
+            | IdempotencyError::Sqlx(_) => Status::internal(GENERIC_ERROR),
         }
     }
 }
@@ -53,13 +563,59 @@ impl From<DomainBankAccountView> for GrpcBankAccountView {
     fn from(src: DomainBankAccountView) -> Self {
         GrpcBankAccountView {
             account_id: src.account_id.unwrap_or_default(), //TODO: Investigate why this is an option type, ideally removing it
-            balance: src.balance,
+            balance: Some(src.balance.into()),
+            available_balance: Some(src.available_balance.into()),
+            status: GrpcAccountStatus::from(src.status) as i32,
+            overdraft_limit: Some(src.overdraft_limit.into()),
             written_checks: src.written_checks,
-            account_transactions: src
-                .account_transactions
-                .into_iter()
-                .map(Into::into)
-                .collect(),
+            cancelled_checks: src.cancelled_checks,
+            owner_id: src.owner_id.unwrap_or_default(),
+            version: src.version as u64,
+        }
+    }
+}
+
+impl From<DomainAccountEvent> for GrpcAccountEvent {
+    fn from(src: DomainAccountEvent) -> Self {
+        GrpcAccountEvent {
+            account_id: src.account_id,
+            sequence: src.sequence as u64,
+            event_type: src.payload.event_type(),
+            event_version: src.payload.event_version(),
+            balance: src.payload.balance().cloned().map(Into::into),
+            payload: serde_json::to_string(&src.payload)
+                .expect("bank account events serialize to JSON"),
+        }
+    }
+}
+
+impl From<DomainAccountAccess> for GrpcAccountAccess {
+    fn from(src: DomainAccountAccess) -> Self {
+        GrpcAccountAccess {
+            user_id: src.user_id,
+            role: GrpcAccessRole::from(src.role) as i32,
+        }
+    }
+}
+
+impl From<DomainAccessRole> for GrpcAccessRole {
+    fn from(src: DomainAccessRole) -> Self {
+        match src {
+            DomainAccessRole::Owner => GrpcAccessRole::Owner,
+            DomainAccessRole::CoOwner => GrpcAccessRole::CoOwner,
+            DomainAccessRole::ViewOnly => GrpcAccessRole::ViewOnly,
+            DomainAccessRole::PaymentsOnly => GrpcAccessRole::PaymentsOnly,
+        }
+    }
+}
+
+impl From<DomainAccountStatus> for GrpcAccountStatus {
+    fn from(src: DomainAccountStatus) -> Self {
+        match src {
+            DomainAccountStatus::NotOpen => GrpcAccountStatus::Unspecified,
+            DomainAccountStatus::Open => GrpcAccountStatus::Open,
+            DomainAccountStatus::Frozen => GrpcAccountStatus::Frozen,
+            DomainAccountStatus::Closed => GrpcAccountStatus::Closed,
         }
     }
 }
@@ -67,8 +623,101 @@ impl From<DomainBankAccountView> for GrpcBankAccountView {
 impl From<DomainAccountTransaction> for GrpcAccountTransaction {
     fn from(src: DomainAccountTransaction) -> Self {
         GrpcAccountTransaction {
-            amount: src.amount,
+            amount: Some(src.amount.into()),
             description: src.description,
+            original_amount: src.original_amount.map(Into::into),
+            exchange_rate: src.exchange_rate.map(Into::into),
+            transaction_type: GrpcAccountTransactionType::from(src.transaction_type) as i32,
+            occurred_at: src.occurred_at,
+            cursor: src.cursor,
+        }
+    }
+}
+
+impl From<DomainAccountTransactionType> for GrpcAccountTransactionType {
+    fn from(src: DomainAccountTransactionType) -> Self {
+        match src {
+            DomainAccountTransactionType::Deposit => GrpcAccountTransactionType::Deposit,
+            DomainAccountTransactionType::Withdrawal => GrpcAccountTransactionType::Withdrawal,
+            DomainAccountTransactionType::Check => GrpcAccountTransactionType::Check,
+            DomainAccountTransactionType::CheckCancelled => {
+                GrpcAccountTransactionType::CheckCancelled
+            }
+            DomainAccountTransactionType::TransferOut => GrpcAccountTransactionType::TransferOut,
+            DomainAccountTransactionType::TransferIn => GrpcAccountTransactionType::TransferIn,
+            DomainAccountTransactionType::TransferRefund => {
+                GrpcAccountTransactionType::TransferRefund
+            }
+            DomainAccountTransactionType::Interest => GrpcAccountTransactionType::Interest,
+            DomainAccountTransactionType::HoldCapture => GrpcAccountTransactionType::HoldCapture,
+        }
+    }
+}
+
+// `Unspecified` is the zero value of an unset filter, so it lists transactions of every type.
+fn transaction_type_filter(
+    src: GrpcAccountTransactionType,
+) -> Option<DomainAccountTransactionType> {
+    match src {
+        GrpcAccountTransactionType::Unspecified => None,
+        GrpcAccountTransactionType::Deposit => Some(DomainAccountTransactionType::Deposit),
+        GrpcAccountTransactionType::Withdrawal => Some(DomainAccountTransactionType::Withdrawal),
+        GrpcAccountTransactionType::Check => Some(DomainAccountTransactionType::Check),
+        GrpcAccountTransactionType::CheckCancelled => {
+            Some(DomainAccountTransactionType::CheckCancelled)
+        }
+        GrpcAccountTransactionType::TransferOut => Some(DomainAccountTransactionType::TransferOut),
+        GrpcAccountTransactionType::TransferIn => Some(DomainAccountTransactionType::TransferIn),
+        GrpcAccountTransactionType::TransferRefund => {
+            Some(DomainAccountTransactionType::TransferRefund)
+        }
+        GrpcAccountTransactionType::Interest => Some(DomainAccountTransactionType::Interest),
+        GrpcAccountTransactionType::HoldCapture => Some(DomainAccountTransactionType::HoldCapture),
+    }
+}
+
+impl From<DomainMoney> for GrpcMoney {
+    fn from(src: DomainMoney) -> Self {
+        GrpcMoney {
+            minor_units: src.minor_units,
+            currency: src.currency,
+        }
+    }
+}
+
+impl From<DomainExchangeRate> for GrpcExchangeRate {
+    fn from(src: DomainExchangeRate) -> Self {
+        GrpcExchangeRate {
+            from_currency: src.from_currency,
+            to_currency: src.to_currency,
+            scaled_rate: src.scaled_rate,
+        }
+    }
+}
+
+impl From<DomainTransferView> for GrpcTransferView {
+    fn from(src: DomainTransferView) -> Self {
+        GrpcTransferView {
+            transfer_id: src.transfer_id,
+            source_account_id: src.source_account_id,
+            destination_account_id: src.destination_account_id,
+            amount: Some(src.amount.into()),
+            status: GrpcTransferStatus::from(src.status) as i32,
+            failure_reason: src.failure_reason.unwrap_or_default(),
+        }
+    }
+}
+
+impl From<DomainTransferStatus> for GrpcTransferStatus {
+    fn from(src: DomainTransferStatus) -> Self {
+        match src {
+            DomainTransferStatus::NotStarted => GrpcTransferStatus::Unspecified,
+            DomainTransferStatus::Started => GrpcTransferStatus::Started,
+            DomainTransferStatus::SourceDebited => GrpcTransferStatus::SourceDebited,
+            DomainTransferStatus::Completed => GrpcTransferStatus::Completed,
+            DomainTransferStatus::Compensating => GrpcTransferStatus::Compensating,
+            DomainTransferStatus::Compensated => GrpcTransferStatus::Compensated,
+            DomainTransferStatus::Failed => GrpcTransferStatus::Failed,
         }
     }
 }
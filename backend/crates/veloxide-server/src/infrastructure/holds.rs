@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use cqrs_es::persist::ViewRepository;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use crate::application::Clock;
+use crate::domain::{BankAccount, BankAccountCommand, BankAccountExpireHoldsCommandData};
+use crate::infrastructure::outbox::OutboxCqrs;
+use crate::infrastructure::projections::aggregate_ids;
+use crate::infrastructure::DbPool;
+use crate::interfaces::bank_account::BankAccountView;
+
+/// How often expired holds are looked for.
+const HOLD_EXPIRY_POLL_INTERVAL: StdDuration = StdDuration::from_secs(60);
+
+/// Releases the holds that expired without being captured, returning their funds to the
+/// available balance.
+///
+/// Holds are released within a minute or so of expiring, until then they can still be captured.
+/// Run the job on a single instance; a hold only ever expires once, but jobs on several
+/// instances would race to expire it.
+pub struct HoldExpiryJob<R>
+where
+    R: ViewRepository<BankAccountView, BankAccount>,
+{
+    pool: DbPool,
+    cqrs: Arc<OutboxCqrs<BankAccount>>,
+    view_repository: Arc<R>,
+    clock: Arc<dyn Clock>,
+}
+
+impl<R> HoldExpiryJob<R>
+where
+    R: ViewRepository<BankAccountView, BankAccount>,
+{
+    pub fn new(
+        pool: DbPool,
+        cqrs: Arc<OutboxCqrs<BankAccount>>,
+        view_repository: Arc<R>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        Self {
+            pool,
+            cqrs,
+            view_repository,
+            clock,
+        }
+    }
+
+    /// Expires holds until the process exits.
+    pub async fn run(self) {
+        loop {
+            if let Err(err) = self.expire().await {
+                tracing::error!("failed to expire holds: {}", err);
+            }
+            tokio::time::sleep(HOLD_EXPIRY_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Expires the holds that are past their expiry, returning on how many accounts they were.
+    #[tracing::instrument(skip(self), err)]
+    pub async fn expire(&self) -> Result<usize, sqlx::Error> {
+        let now = self.clock.now();
+        let mut expired = 0;
+        for account_id in aggregate_ids::<BankAccount>(&self.pool).await? {
+            match self.expire_account(&account_id, now).await {
+                Ok(true) => expired += 1,
+                Ok(false) => {}
+                Err(err) => tracing::error!(
+                    "failed to expire the holds of bank account {}: {}",
+                    account_id,
+                    err
+                ),
+            }
+        }
+        Ok(expired)
+    }
+
+    async fn expire_account(&self, account_id: &str, now: DateTime<Utc>) -> Result<bool, String> {
+        let view = self
+            .view_repository
+            .load(account_id)
+            .await
+            .map_err(|err| err.to_string())?;
+        let has_expired_holds = view.is_some_and(|view| {
+            view.holds.iter().any(|hold| {
+                DateTime::parse_from_rfc3339(&hold.expires_at)
+                    .is_ok_and(|expires_at| expires_at <= now)
+            })
+        });
+        if !has_expired_holds {
+            return Ok(false);
+        }
+        let command = BankAccountCommand::ExpireHolds(BankAccountExpireHoldsCommandData {
+            now: now.to_rfc3339(),
+        });
+        let metadata = HashMap::from([("time".to_string(), now.to_rfc3339())]);
+        self.cqrs
+            .execute_with_metadata(account_id, command, metadata)
+            .await
+            .map_err(|err| err.to_string())?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,452 @@
+use chrono::Duration;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use cfg_if::cfg_if;
+
+pub const IDEMPOTENCY_KEY_TTL_ENV_VAR: &str = "IDEMPOTENCY_KEY_TTL_SECONDS";
+pub const IDEMPOTENCY_KEY_LEASE_ENV_VAR: &str = "IDEMPOTENCY_KEY_LEASE_SECONDS";
+
+/// The header REST requests pass their idempotency key in.
+pub const IDEMPOTENCY_KEY_HEADER: &str = "Idempotency-Key";
+
+/// The metadata key gRPC requests pass their idempotency key in.
+pub const IDEMPOTENCY_KEY_METADATA: &str = "idempotency-key";
+
+/// How long a key is remembered when no TTL is configured.
+pub const DEFAULT_IDEMPOTENCY_KEY_TTL_SECONDS: i64 = 24 * 60 * 60;
+
+/// How long a claimed key is held for its request when no lease is configured.
+pub const DEFAULT_IDEMPOTENCY_KEY_LEASE_SECONDS: i64 = 60;
+
+/// The longest idempotency key accepted, matching the width of its column.
+pub const MAX_IDEMPOTENCY_KEY_LENGTH: usize = 255;
+
+#[derive(thiserror::Error, Debug)]
+pub enum IdempotencyError {
+    #[error("invalid idempotency key TTL or lease: {0}")]
+    InvalidConfiguration(String),
+
+    #[error("idempotency keys can only be used by signed in users")]
+    Unauthenticated,
+
+    #[error("idempotency keys must be between 1 and 255 characters long")]
+    InvalidKey,
+
+    #[error("idempotency key {0} was already used for a different request")]
+    KeyReused(String),
+
+    #[error("a request with idempotency key {0} is still in progress")]
+    InProgress(String),
+
+    #[error("the request with idempotency key {0} did not complete, its outcome is unknown")]
+    OutcomeUnknown(String),
+
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// How long idempotency keys are remembered. Once a key expires, a request reusing it is
+/// executed again as if it were new.
+///
+/// A key is remembered for the `ttl` once its outcome is stored. While its request runs, the key
+/// is leased for the shorter `lease`. A key still without an outcome once its lease runs out
+/// belongs to a request that was cut short, e.g. by a crash, after which it may or may not have
+/// taken effect. Such a key is never claimed again until the `ttl` after its lease has passed
+/// too, so that the request is not executed twice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdempotencyConfiguration {
+    pub ttl: Duration,
+    pub lease: Duration,
+}
+
+impl Default for IdempotencyConfiguration {
+    fn default() -> Self {
+        Self {
+            ttl: Duration::seconds(DEFAULT_IDEMPOTENCY_KEY_TTL_SECONDS),
+            lease: Duration::seconds(DEFAULT_IDEMPOTENCY_KEY_LEASE_SECONDS),
+        }
+    }
+}
+
+impl IdempotencyConfiguration {
+    /// Reads the TTL from the `IDEMPOTENCY_KEY_TTL_SECONDS` env var, defaulting to a day, and the
+    /// lease from `IDEMPOTENCY_KEY_LEASE_SECONDS`, defaulting to a minute.
+    pub fn from_env() -> Result<Self, IdempotencyError> {
+        Self::parse(
+            dotenvy::var(IDEMPOTENCY_KEY_TTL_ENV_VAR).ok().as_deref(),
+            dotenvy::var(IDEMPOTENCY_KEY_LEASE_ENV_VAR).ok().as_deref(),
+        )
+    }
+
+    /// Parses the TTL and lease in seconds. Unset or empty values use the defaults.
+    pub fn parse(ttl: Option<&str>, lease: Option<&str>) -> Result<Self, IdempotencyError> {
+        let defaults = Self::default();
+        Ok(Self {
+            ttl: parse_seconds(ttl)?.unwrap_or(defaults.ttl),
+            lease: parse_seconds(lease)?.unwrap_or(defaults.lease),
+        })
+    }
+}
+
+fn parse_seconds(value: Option<&str>) -> Result<Option<Duration>, IdempotencyError> {
+    match value.map(str::trim).filter(|value| !value.is_empty()) {
+        None => Ok(None),
+        Some(value) => value
+            .parse()
+            .ok()
+            .filter(|seconds| *seconds > 0)
+            .map(|seconds| Some(Duration::seconds(seconds)))
+            .ok_or_else(|| IdempotencyError::InvalidConfiguration(value.to_string())),
+    }
+}
+
+/// The scope of the idempotency keys of `user_id`. Keys can only be used by signed in users, as
+/// anonymous callers cannot be told apart and would replay each other's outcomes.
+pub fn idempotency_scope(user_id: Option<&str>) -> Result<&str, IdempotencyError> {
+    user_id.ok_or(IdempotencyError::Unauthenticated)
+}
+
+/// The result of claiming an idempotency key.
+#[derive(Debug, PartialEq)]
+pub enum Claim<T> {
+    /// The key is new, the request should be executed and its outcome completed.
+    New,
+    /// The request was executed before, with this outcome.
+    Completed(T),
+}
+
+fn validate_key(key: &str) -> Result<(), IdempotencyError> {
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LENGTH {
+        return Err(IdempotencyError::InvalidKey);
+    }
+    Ok(())
+}
+
+// Decides what an existing key means for a request: a key may only ever be replayed for the
+// exact request it was first used for, and has no outcome while that request still runs or once
+// it was cut short.
+fn settle<T: DeserializeOwned>(
+    key: &str,
+    request: &str,
+    stored_request: &str,
+    outcome: Option<&str>,
+    lease_expired: bool,
+) -> Result<Claim<T>, IdempotencyError> {
+    if request != stored_request {
+        return Err(IdempotencyError::KeyReused(key.to_string()));
+    }
+    match outcome {
+        Some(outcome) => Ok(Claim::Completed(serde_json::from_str(outcome)?)),
+        None if lease_expired => Err(IdempotencyError::OutcomeUnknown(key.to_string())),
+        None => Err(IdempotencyError::InProgress(key.to_string())),
+    }
+}
+
+fn serialize_outcome<T: Serialize>(outcome: &T) -> Result<String, IdempotencyError> {
+    Ok(serde_json::to_string(outcome)?)
+}
+
+cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use chrono::{NaiveDateTime, Utc};
+        use sqlx::{Pool, Postgres, Row};
+
+        /// Remembers the outcome of requests by their idempotency key, so that a retried request
+        /// returns the original outcome instead of being executed twice. Keys are scoped, e.g. to
+        /// the user making the request, so that users cannot replay each other's requests.
+        #[derive(Clone)]
+        pub struct IdempotencyKeyStore {
+            pool: Pool<Postgres>,
+            configuration: IdempotencyConfiguration,
+        }
+
+        impl IdempotencyKeyStore {
+            pub fn new(pool: Pool<Postgres>, configuration: IdempotencyConfiguration) -> Self {
+                Self { pool, configuration }
+            }
+
+            /// Claims the key for `request`, the serialized request it is used for. Returns the
+            /// stored outcome when the same request was made with the key before.
+            pub async fn claim<T: DeserializeOwned>(
+                &self,
+                scope: &str,
+                key: &str,
+                request: &str,
+            ) -> Result<Claim<T>, IdempotencyError> {
+                validate_key(key)?;
+                let now = Utc::now().naive_utc();
+                sqlx::query("DELETE FROM idempotency_keys WHERE scope = $1 AND idempotency_key = $2 AND ((outcome IS NOT NULL AND expires_at <= $3) OR expires_at <= $4)")
+                    .bind(scope)
+                    .bind(key)
+                    .bind(now)
+                    .bind(now - self.configuration.ttl)
+                    .execute(&self.pool)
+                    .await?;
+                let claimed = sqlx::query(
+                    "INSERT INTO idempotency_keys (scope, idempotency_key, request, expires_at) VALUES ($1, $2, $3, $4) ON CONFLICT (scope, idempotency_key) DO NOTHING",
+                )
+                .bind(scope)
+                .bind(key)
+                .bind(request)
+                .bind(now + self.configuration.lease)
+                .execute(&self.pool)
+                .await?
+                .rows_affected()
+                    == 1;
+                if claimed {
+                    return Ok(Claim::New);
+                }
+
+                let row = sqlx::query("SELECT request, outcome, expires_at FROM idempotency_keys WHERE scope = $1 AND idempotency_key = $2")
+                    .bind(scope)
+                    .bind(key)
+                    .fetch_one(&self.pool)
+                    .await?;
+                let stored_request: String = row.try_get("request")?;
+                let outcome: Option<String> = row.try_get("outcome")?;
+                let expires_at: NaiveDateTime = row.try_get("expires_at")?;
+                settle(key, request, &stored_request, outcome.as_deref(), expires_at <= now)
+            }
+
+            /// Stores the outcome of the request the key was claimed for, and remembers it for the
+            /// TTL from now on.
+            pub async fn complete<T: Serialize>(&self, scope: &str, key: &str, outcome: &T) -> Result<(), IdempotencyError> {
+                sqlx::query("UPDATE idempotency_keys SET outcome = $1, expires_at = $2 WHERE scope = $3 AND idempotency_key = $4")
+                    .bind(serialize_outcome(outcome)?)
+                    .bind(Utc::now().naive_utc() + self.configuration.ttl)
+                    .bind(scope)
+                    .bind(key)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            /// Forgets a claimed key whose request failed before it had an outcome worth
+            /// replaying, e.g. on a lost database connection, so that it can be retried.
+            pub async fn release(&self, scope: &str, key: &str) -> Result<(), IdempotencyError> {
+                sqlx::query("DELETE FROM idempotency_keys WHERE scope = $1 AND idempotency_key = $2 AND outcome IS NULL")
+                    .bind(scope)
+                    .bind(key)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            /// Deletes every expired key, returning how many were deleted. Keys of requests that
+            /// were cut short are kept for the TTL after their lease.
+            pub async fn purge_expired(&self) -> Result<u64, IdempotencyError> {
+                let now = Utc::now().naive_utc();
+                let purged = sqlx::query("DELETE FROM idempotency_keys WHERE (outcome IS NOT NULL AND expires_at <= $1) OR expires_at <= $2")
+                    .bind(now)
+                    .bind(now - self.configuration.ttl)
+                    .execute(&self.pool)
+                    .await?
+                    .rows_affected();
+                Ok(purged)
+            }
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use chrono::{NaiveDateTime, Utc};
+        use sqlx::{MySql, Pool, Row};
+
+        /// Remembers the outcome of requests by their idempotency key, so that a retried request
+        /// returns the original outcome instead of being executed twice. Keys are scoped, e.g. to
+        /// the user making the request, so that users cannot replay each other's requests.
+        #[derive(Clone)]
+        pub struct IdempotencyKeyStore {
+            pool: Pool<MySql>,
+            configuration: IdempotencyConfiguration,
+        }
+
+        impl IdempotencyKeyStore {
+            pub fn new(pool: Pool<MySql>, configuration: IdempotencyConfiguration) -> Self {
+                Self { pool, configuration }
+            }
+
+            /// Claims the key for `request`, the serialized request it is used for. Returns the
+            /// stored outcome when the same request was made with the key before.
+            pub async fn claim<T: DeserializeOwned>(
+                &self,
+                scope: &str,
+                key: &str,
+                request: &str,
+            ) -> Result<Claim<T>, IdempotencyError> {
+                validate_key(key)?;
+                let now = Utc::now().naive_utc();
+                sqlx::query("DELETE FROM idempotency_keys WHERE scope = ? AND idempotency_key = ? AND ((outcome IS NOT NULL AND expires_at <= ?) OR expires_at <= ?)")
+                    .bind(scope)
+                    .bind(key)
+                    .bind(now)
+                    .bind(now - self.configuration.ttl)
+                    .execute(&self.pool)
+                    .await?;
+                let claimed = sqlx::query(
+                    "INSERT IGNORE INTO idempotency_keys (scope, idempotency_key, request, expires_at) VALUES (?, ?, ?, ?)",
+                )
+                .bind(scope)
+                .bind(key)
+                .bind(request)
+                .bind(now + self.configuration.lease)
+                .execute(&self.pool)
+                .await?
+                .rows_affected()
+                    == 1;
+                if claimed {
+                    return Ok(Claim::New);
+                }
+
+                let row = sqlx::query("SELECT request, outcome, expires_at FROM idempotency_keys WHERE scope = ? AND idempotency_key = ?")
+                    .bind(scope)
+                    .bind(key)
+                    .fetch_one(&self.pool)
+                    .await?;
+                let stored_request: String = row.try_get("request")?;
+                let outcome: Option<String> = row.try_get("outcome")?;
+                let expires_at: NaiveDateTime = row.try_get("expires_at")?;
+                settle(key, request, &stored_request, outcome.as_deref(), expires_at <= now)
+            }
+
+            /// Stores the outcome of the request the key was claimed for, and remembers it for the
+            /// TTL from now on.
+            pub async fn complete<T: Serialize>(&self, scope: &str, key: &str, outcome: &T) -> Result<(), IdempotencyError> {
+                sqlx::query("UPDATE idempotency_keys SET outcome = ?, expires_at = ? WHERE scope = ? AND idempotency_key = ?")
+                    .bind(serialize_outcome(outcome)?)
+                    .bind(Utc::now().naive_utc() + self.configuration.ttl)
+                    .bind(scope)
+                    .bind(key)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            /// Forgets a claimed key whose request failed before it had an outcome worth
+            /// replaying, e.g. on a lost database connection, so that it can be retried.
+            pub async fn release(&self, scope: &str, key: &str) -> Result<(), IdempotencyError> {
+                sqlx::query("DELETE FROM idempotency_keys WHERE scope = ? AND idempotency_key = ? AND outcome IS NULL")
+                    .bind(scope)
+                    .bind(key)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            /// Deletes every expired key, returning how many were deleted. Keys of requests that
+            /// were cut short are kept for the TTL after their lease.
+            pub async fn purge_expired(&self) -> Result<u64, IdempotencyError> {
+                let now = Utc::now().naive_utc();
+                let purged = sqlx::query("DELETE FROM idempotency_keys WHERE (outcome IS NOT NULL AND expires_at <= ?) OR expires_at <= ?")
+                    .bind(now)
+                    .bind(now - self.configuration.ttl)
+                    .execute(&self.pool)
+                    .await?
+                    .rows_affected();
+                Ok(purged)
+            }
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn ttl_defaults_to_a_day() {
+        assert_eq!(IdempotencyConfiguration::default().ttl, Duration::hours(24));
+    }
+
+    #[test]
+    fn in_progress_keys_are_leased_for_a_minute() {
+        assert_eq!(
+            IdempotencyConfiguration::default().lease,
+            Duration::minutes(1)
+        );
+    }
+
+    #[test]
+    fn parses_ttl_and_lease_in_seconds() {
+        assert_eq!(
+            IdempotencyConfiguration::parse(Some(" 600 "), Some("30")).unwrap(),
+            IdempotencyConfiguration {
+                ttl: Duration::minutes(10),
+                lease: Duration::seconds(30),
+            }
+        );
+        assert_eq!(
+            IdempotencyConfiguration::parse(None, Some("")).unwrap(),
+            IdempotencyConfiguration::default()
+        );
+        assert!(matches!(
+            IdempotencyConfiguration::parse(Some("0"), None),
+            Err(IdempotencyError::InvalidConfiguration(_))
+        ));
+        assert!(matches!(
+            IdempotencyConfiguration::parse(None, Some("a minute")),
+            Err(IdempotencyError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn anonymous_callers_cannot_use_idempotency_keys() {
+        assert_eq!(idempotency_scope(Some("1234")).unwrap(), "1234");
+        assert!(matches!(
+            idempotency_scope(None),
+            Err(IdempotencyError::Unauthenticated)
+        ));
+    }
+
+    #[test]
+    fn rejects_empty_and_overlong_keys() {
+        assert!(validate_key("3f0c2b9e").is_ok());
+        assert!(matches!(
+            validate_key(""),
+            Err(IdempotencyError::InvalidKey)
+        ));
+        assert!(matches!(
+            validate_key(&"k".repeat(MAX_IDEMPOTENCY_KEY_LENGTH + 1)),
+            Err(IdempotencyError::InvalidKey)
+        ));
+    }
+
+    #[test]
+    fn replays_the_outcome_of_the_same_request() {
+        let outcome = serialize_outcome(&Ok::<(), String>(())).unwrap();
+        let claim: Claim<Result<(), String>> =
+            settle("3f0c2b9e", "request", "request", Some(&outcome), false).unwrap();
+        assert_eq!(claim, Claim::Completed(Ok(())));
+    }
+
+    #[test]
+    fn rejects_a_key_reused_for_another_request() {
+        let claim =
+            settle::<Result<(), String>>("3f0c2b9e", "request", "other request", None, false);
+        assert!(matches!(claim, Err(IdempotencyError::KeyReused(key)) if key == "3f0c2b9e"));
+    }
+
+    #[test]
+    fn a_key_without_outcome_is_in_progress() {
+        let claim = settle::<Result<(), String>>("3f0c2b9e", "request", "request", None, false);
+        assert!(matches!(claim, Err(IdempotencyError::InProgress(key)) if key == "3f0c2b9e"));
+    }
+
+    #[test]
+    fn a_key_left_without_outcome_after_its_lease_is_never_claimed_again() {
+        let claim = settle::<Result<(), String>>("3f0c2b9e", "request", "request", None, true);
+        assert!(matches!(claim, Err(IdempotencyError::OutcomeUnknown(key)) if key == "3f0c2b9e"));
+    }
+
+    #[test]
+    fn replays_the_outcome_of_a_request_that_completed_after_its_lease() {
+        let outcome = serialize_outcome(&Ok::<(), String>(())).unwrap();
+        let claim: Claim<Result<(), String>> =
+            settle("3f0c2b9e", "request", "request", Some(&outcome), true).unwrap();
+        assert_eq!(claim, Claim::Completed(Ok(())));
+    }
+}
@@ -0,0 +1,210 @@
+use chrono::{Duration, NaiveDate};
+use cqrs_es::persist::ViewRepository;
+use cqrs_es::AggregateError;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use crate::application::Clock;
+use crate::domain::{
+    AccountStatus, BankAccount, BankAccountAccrueInterestCommandData, BankAccountCommand,
+    BankAccountError, MAX_ANNUAL_INTEREST_RATE_BASIS_POINTS,
+};
+use crate::infrastructure::outbox::OutboxCqrs;
+use crate::infrastructure::projections::aggregate_ids;
+use crate::infrastructure::DbPool;
+use crate::interfaces::bank_account::BankAccountView;
+
+pub const INTEREST_RATES_ENV_VAR: &str = "INTEREST_RATES";
+
+/// How often the job looks whether a day has passed that interest is yet to be accrued for.
+const INTEREST_POLL_INTERVAL: StdDuration = StdDuration::from_secs(60 * 60);
+
+#[derive(thiserror::Error, Debug)]
+pub enum InterestConfigurationError {
+    #[error("invalid {0}: {1}")]
+    InvalidConfiguration(&'static str, String),
+}
+
+/// The annual interest rate paid on accounts of each currency, in basis points. Accounts in a
+/// currency without a rate earn no interest.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InterestConfiguration {
+    pub rates: HashMap<String, u32>,
+}
+
+impl InterestConfiguration {
+    /// Reads the `INTEREST_RATES` env var, a comma separated list of currencies and their rates
+    /// in basis points such as `USD=425,EUR=300`. Interest is not paid when it is unset.
+    pub fn from_env() -> Result<Self, InterestConfigurationError> {
+        Self::parse(dotenvy::var(INTEREST_RATES_ENV_VAR).ok().as_deref())
+    }
+
+    pub fn parse(rates: Option<&str>) -> Result<Self, InterestConfigurationError> {
+        let mut configuration = Self::default();
+        let rates = rates.unwrap_or_default();
+        for rate in rates.split(',').filter(|rate| !rate.trim().is_empty()) {
+            let invalid_rate = || {
+                InterestConfigurationError::InvalidConfiguration(
+                    INTEREST_RATES_ENV_VAR,
+                    rate.to_string(),
+                )
+            };
+            let (currency, basis_points) = rate.split_once('=').ok_or_else(invalid_rate)?;
+            let currency = currency.trim().to_uppercase();
+            if currency.is_empty() {
+                return Err(invalid_rate());
+            }
+            let basis_points = basis_points
+                .trim()
+                .parse()
+                .ok()
+                .filter(|basis_points| *basis_points <= MAX_ANNUAL_INTEREST_RATE_BASIS_POINTS)
+                .ok_or_else(invalid_rate)?;
+            configuration.rates.insert(currency, basis_points);
+        }
+        Ok(configuration)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.rates.is_empty()
+    }
+
+    pub fn rate(&self, currency: &str) -> Option<u32> {
+        self.rates.get(currency).copied()
+    }
+}
+
+/// Accrues a day of interest on every open or frozen account once the day is over, which also
+/// posts the interest of the month on its last day.
+///
+/// Interest is accrued on the balance the account has when the job runs, shortly after midnight
+/// UTC. Days the job did not run for, such as while the server was down, are not caught up on.
+/// Run the job on a single instance; a day is only ever accrued once, but jobs on several
+/// instances would race to accrue it.
+pub struct InterestJob<R>
+where
+    R: ViewRepository<BankAccountView, BankAccount>,
+{
+    pool: DbPool,
+    cqrs: Arc<OutboxCqrs<BankAccount>>,
+    view_repository: Arc<R>,
+    clock: Arc<dyn Clock>,
+    configuration: InterestConfiguration,
+}
+
+impl<R> InterestJob<R>
+where
+    R: ViewRepository<BankAccountView, BankAccount>,
+{
+    pub fn new(
+        pool: DbPool,
+        cqrs: Arc<OutboxCqrs<BankAccount>>,
+        view_repository: Arc<R>,
+        clock: Arc<dyn Clock>,
+        configuration: InterestConfiguration,
+    ) -> Self {
+        Self {
+            pool,
+            cqrs,
+            view_repository,
+            clock,
+            configuration,
+        }
+    }
+
+    /// Accrues interest for the previous day whenever the date changes, until the process exits.
+    pub async fn run(self) {
+        let mut accrued_for: Option<NaiveDate> = None;
+        loop {
+            let date = (self.clock.now() - Duration::days(1)).date_naive();
+            if accrued_for != Some(date) {
+                match self.accrue(date).await {
+                    Ok(_) => accrued_for = Some(date),
+                    Err(err) => tracing::error!("failed to accrue interest for {}: {}", date, err),
+                }
+            }
+            tokio::time::sleep(INTEREST_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Accrues a day of interest on every account that earns it, returning on how many accounts
+    /// it was accrued.
+    #[tracing::instrument(skip(self), err)]
+    pub async fn accrue(&self, date: NaiveDate) -> Result<usize, sqlx::Error> {
+        let mut accrued = 0;
+        for account_id in aggregate_ids::<BankAccount>(&self.pool).await? {
+            match self.accrue_account(&account_id, date).await {
+                Ok(true) => accrued += 1,
+                Ok(false) => {}
+                Err(err) => tracing::error!(
+                    "failed to accrue interest on bank account {}: {}",
+                    account_id,
+                    err
+                ),
+            }
+        }
+        Ok(accrued)
+    }
+
+    async fn accrue_account(&self, account_id: &str, date: NaiveDate) -> Result<bool, String> {
+        let view = self
+            .view_repository
+            .load(account_id)
+            .await
+            .map_err(|err| err.to_string())?;
+        let Some(view) = view else {
+            return Ok(false);
+        };
+        if !matches!(view.status, AccountStatus::Open | AccountStatus::Frozen) {
+            return Ok(false);
+        }
+        let Some(annual_rate_basis_points) = self.configuration.rate(&view.balance.currency) else {
+            return Ok(false);
+        };
+        let command = BankAccountCommand::AccrueInterest(BankAccountAccrueInterestCommandData {
+            date: date.format("%Y-%m-%d").to_string(),
+            annual_rate_basis_points,
+        });
+        let metadata = HashMap::from([("time".to_string(), self.clock.now().to_rfc3339())]);
+        match self
+            .cqrs
+            .execute_with_metadata(account_id, command, metadata)
+            .await
+        {
+            Ok(()) => Ok(true),
+            // Accrued before the job was last restarted.
+            Err(AggregateError::UserError(BankAccountError::InterestAlreadyAccrued)) => Ok(false),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_rates_per_currency() {
+        let configuration = InterestConfiguration::parse(Some(" usd=425, EUR = 300 ,")).unwrap();
+        assert_eq!(configuration.rate("USD"), Some(425));
+        assert_eq!(configuration.rate("EUR"), Some(300));
+        assert_eq!(configuration.rate("GBP"), None);
+        assert!(configuration.is_enabled());
+    }
+
+    #[test]
+    fn interest_is_disabled_without_rates() {
+        assert!(!InterestConfiguration::parse(None).unwrap().is_enabled());
+        assert!(!InterestConfiguration::parse(Some("")).unwrap().is_enabled());
+    }
+
+    #[test]
+    fn rejects_invalid_rates() {
+        assert!(InterestConfiguration::parse(Some("USD")).is_err());
+        assert!(InterestConfiguration::parse(Some("USD=4.25")).is_err());
+        assert!(InterestConfiguration::parse(Some("=425")).is_err());
+        assert!(InterestConfiguration::parse(Some("USD=10001")).is_err());
+    }
+}
@@ -216,6 +216,12 @@ pub async fn mw_authorise<B>(
         .iter()
         .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
         .collect();
+    // Resolved by `mw_authenticate`, which runs first.
+    let user_id = request
+        .extensions()
+        .get::<Option<UserView>>()
+        .and_then(|user| user.as_ref())
+        .map(|user| user.id.clone());
     let input: serde_json::Value = match auth_token_result {
         Some(auth_token) => {
             tracing::debug!(?auth_token.identifier);
@@ -224,6 +230,7 @@ pub async fn mw_authorise<B>(
                 "method": method.as_str(),
                 "path": path,
                 "user": {
+                "id": user_id,
                 "email": auth_token.identifier.as_str(),
                 "token_expiry": auth_token.expiration.to_rfc3339(),
             },
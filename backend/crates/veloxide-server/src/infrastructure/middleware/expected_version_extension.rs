@@ -0,0 +1,72 @@
+use async_trait::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::{header, request::Parts, HeaderValue, StatusCode};
+
+// This is a custom Axum extension that takes the version a command expects the account to be at
+// from the `If-Match` header, as returned in the `ETag` header of the account. `*` matches any
+// version.
+pub struct ExpectedVersionExtension(pub Option<usize>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ExpectedVersionExtension
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.headers.get(header::IF_MATCH) {
+            Some(value) => parse_if_match(value).map(ExpectedVersionExtension).ok_or((
+                StatusCode::BAD_REQUEST,
+                "the If-Match header must be a single version as returned in the ETag header",
+            )),
+            None => Ok(ExpectedVersionExtension(None)),
+        }
+    }
+}
+
+/// The `ETag` header value of an account at `version`.
+pub fn version_etag(version: usize) -> String {
+    format!("\"{}\"", version)
+}
+
+fn parse_if_match(value: &HeaderValue) -> Option<Option<usize>> {
+    let value = value.to_str().ok()?.trim();
+    if value == "*" {
+        return Some(None);
+    }
+    let version = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value);
+    version.parse().ok().map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coverage_helper::test;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_the_etag_of_a_version() {
+        let etag = HeaderValue::from_str(&version_etag(42)).unwrap();
+        assert_eq!(parse_if_match(&etag), Some(Some(42)));
+        assert_eq!(
+            parse_if_match(&HeaderValue::from_static("7")),
+            Some(Some(7))
+        );
+    }
+
+    #[test]
+    fn any_version_matches_a_wildcard() {
+        assert_eq!(parse_if_match(&HeaderValue::from_static("*")), Some(None));
+    }
+
+    #[test]
+    fn rejects_anything_else() {
+        for value in ["\"abc\"", "\"1\", \"2\"", "W/\"1\"", "\"-1\""] {
+            assert_eq!(parse_if_match(&HeaderValue::from_static(value)), None);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+use async_trait::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::{request::Parts, StatusCode};
+
+use crate::infrastructure::idempotency::{IdempotencyError, IDEMPOTENCY_KEY_HEADER};
+
+// This is a custom Axum extension that takes the optional idempotency key of a command from the
+// `Idempotency-Key` header.
+pub struct IdempotencyKeyExtension(pub Option<String>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for IdempotencyKeyExtension
+where
+    S: Send + Sync,
+{
+    type Rejection = (StatusCode, &'static str);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.headers.get(IDEMPOTENCY_KEY_HEADER) {
+            Some(key) => key
+                .to_str()
+                .map(|key| IdempotencyKeyExtension(Some(key.to_string())))
+                .map_err(|_| {
+                    (
+                        StatusCode::BAD_REQUEST,
+                        "the Idempotency-Key header must be visible ASCII",
+                    )
+                }),
+            None => Ok(IdempotencyKeyExtension(None)),
+        }
+    }
+}
+
+/// The status a REST command responds with when its idempotency key cannot be used.
+pub fn idempotency_error_status(err: &IdempotencyError) -> StatusCode {
+    match err {
+        IdempotencyError::InvalidKey => StatusCode::BAD_REQUEST,
+        IdempotencyError::KeyReused(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        IdempotencyError::InProgress(_) | IdempotencyError::OutcomeUnknown(_) => {
+            StatusCode::CONFLICT
+        }
+        IdempotencyError::Unauthenticated => StatusCode::UNAUTHORIZED,
+        IdempotencyError::InvalidConfiguration(_)
+        | IdempotencyError::Serialization(_)
+        | IdempotencyError::Sqlx(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
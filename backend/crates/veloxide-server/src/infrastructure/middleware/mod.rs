@@ -1,6 +1,10 @@
 pub mod auth;
 pub mod error;
+pub mod expected_version_extension;
+pub mod idempotency_key_extension;
 pub mod metadata_extension;
 
 pub use error::*;
+pub use expected_version_extension::*;
+pub use idempotency_key_extension::*;
 pub use metadata_extension::*;
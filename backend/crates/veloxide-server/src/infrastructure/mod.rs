@@ -1,11 +1,23 @@
 pub mod auth_utils;
+pub mod bank_account_services;
 pub mod cryptography;
 pub mod db;
 pub mod grpc;
+pub mod holds;
+pub mod idempotency;
+pub mod interest;
 pub mod logging;
 pub mod middleware;
 pub mod observability;
+pub mod outbox;
+pub mod projections;
+pub mod reconciliation;
 pub mod repositories;
+pub mod scheduled_payments;
+pub mod snapshots;
+pub mod statements;
+pub mod transfers;
 pub mod web_server;
+pub mod webhooks;
 
 pub use db::*;
@@ -0,0 +1,286 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+
+pub const OUTBOX_PUBLISHER_ENV_VAR: &str = "OUTBOX_PUBLISHER";
+pub const OUTBOX_FILE_PATH_ENV_VAR: &str = "OUTBOX_FILE_PATH";
+pub const NATS_URL_ENV_VAR: &str = "NATS_URL";
+pub const NATS_SUBJECT_PREFIX_ENV_VAR: &str = "NATS_SUBJECT_PREFIX";
+pub const KAFKA_BROKERS_ENV_VAR: &str = "KAFKA_BROKERS";
+pub const KAFKA_TOPIC_ENV_VAR: &str = "KAFKA_TOPIC";
+
+/// The NATS subject prefix and Kafka topic used when none is configured.
+pub const DEFAULT_EVENTS_TOPIC: &str = "veloxide.events";
+
+#[derive(thiserror::Error, Debug)]
+pub enum OutboxError {
+    #[error("invalid outbox configuration: {0}")]
+    InvalidConfiguration(String),
+
+    #[error("failed to publish event {message_id}: {reason}")]
+    Publish { message_id: String, reason: String },
+
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// An event committed on an aggregate, as published to external brokers.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OutboxEvent {
+    pub aggregate_type: String,
+    pub aggregate_id: String,
+    pub sequence: usize,
+    pub event_type: String,
+    pub event_version: String,
+    pub payload: serde_json::Value,
+    pub metadata: serde_json::Value,
+    pub recorded_at: DateTime<Utc>,
+}
+
+impl OutboxEvent {
+    /// Identifies the event, so that brokers and consumers can drop the duplicates that
+    /// at-least-once delivery causes.
+    pub fn message_id(&self) -> String {
+        format!(
+            "{}:{}:{}",
+            self.aggregate_type, self.aggregate_id, self.sequence
+        )
+    }
+
+    pub(crate) fn publish_error(&self, reason: impl ToString) -> OutboxError {
+        OutboxError::Publish {
+            message_id: self.message_id(),
+            reason: reason.to_string(),
+        }
+    }
+}
+
+/// Publishes committed events to a broker. The outbox relay publishes one event at a time, and
+/// the events of an aggregate in order, so a publisher only has to keep the order in which it
+/// is called.
+#[async_trait]
+pub trait EventPublisher: Send + Sync {
+    /// Returns once the broker has accepted the event, after which it is removed from the outbox.
+    async fn publish(&self, event: &OutboxEvent) -> Result<(), OutboxError>;
+}
+
+/// Writes each event as a line of JSON to a file, or to stdout without one. Meant for local
+/// development and tests.
+#[derive(Debug, Default)]
+pub struct FileEventPublisher {
+    path: Option<PathBuf>,
+}
+
+impl FileEventPublisher {
+    pub fn stdout() -> Self {
+        Self { path: None }
+    }
+
+    /// Appends to the file, creating it when it does not exist.
+    pub fn file(path: PathBuf) -> Self {
+        Self { path: Some(path) }
+    }
+}
+
+#[async_trait]
+impl EventPublisher for FileEventPublisher {
+    async fn publish(&self, event: &OutboxEvent) -> Result<(), OutboxError> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        match &self.path {
+            Some(path) => {
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await?;
+                file.write_all(&line).await?;
+                file.flush().await?;
+            }
+            None => {
+                let mut stdout = tokio::io::stdout();
+                stdout.write_all(&line).await?;
+                stdout.flush().await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Which broker the outbox relay publishes to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EventPublisherConfiguration {
+    Stdout,
+    File(PathBuf),
+    /// Publishes to JetStream on `<subject_prefix>.<aggregate type>`.
+    Nats {
+        url: String,
+        subject_prefix: String,
+    },
+    /// Publishes to the topic keyed by aggregate id, so that the events of an aggregate share a
+    /// partition and keep their order.
+    Kafka {
+        brokers: String,
+        topic: String,
+    },
+}
+
+impl EventPublisherConfiguration {
+    /// Reads the publisher from the `OUTBOX_PUBLISHER` env var, one of `stdout`, `file`, `nats`
+    /// and `kafka`. Unset or empty disables the relay, events are still written to the outbox.
+    pub fn from_env() -> Result<Option<Self>, OutboxError> {
+        match dotenvy::var(OUTBOX_PUBLISHER_ENV_VAR) {
+            Ok(publisher) if !publisher.trim().is_empty() => {
+                Self::parse(&publisher, |name| dotenvy::var(name).ok()).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    pub fn parse(
+        publisher: &str,
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, OutboxError> {
+        let required = |name: &str| {
+            var(name).ok_or_else(|| {
+                OutboxError::InvalidConfiguration(format!(
+                    "{} is required for the {} publisher",
+                    name, publisher
+                ))
+            })
+        };
+        let topic = |name: &str| var(name).unwrap_or_else(|| DEFAULT_EVENTS_TOPIC.to_string());
+        match publisher.trim() {
+            "stdout" => Ok(Self::Stdout),
+            "file" => Ok(Self::File(required(OUTBOX_FILE_PATH_ENV_VAR)?.into())),
+            "nats" => Ok(Self::Nats {
+                url: required(NATS_URL_ENV_VAR)?,
+                subject_prefix: topic(NATS_SUBJECT_PREFIX_ENV_VAR),
+            }),
+            "kafka" => Ok(Self::Kafka {
+                brokers: required(KAFKA_BROKERS_ENV_VAR)?,
+                topic: topic(KAFKA_TOPIC_ENV_VAR),
+            }),
+            other => Err(OutboxError::InvalidConfiguration(format!(
+                "unknown publisher {}, expected one of: stdout, file, nats, kafka",
+                other
+            ))),
+        }
+    }
+
+    /// Connects to the broker. NATS and Kafka are only available when built with the `nats`
+    /// and `kafka` features.
+    pub async fn publisher(&self) -> Result<Box<dyn EventPublisher>, OutboxError> {
+        match self {
+            Self::Stdout => Ok(Box::new(FileEventPublisher::stdout())),
+            Self::File(path) => Ok(Box::new(FileEventPublisher::file(path.clone()))),
+            #[cfg(feature = "nats")]
+            Self::Nats {
+                url,
+                subject_prefix,
+            } => Ok(Box::new(
+                super::NatsEventPublisher::connect(url, subject_prefix).await?,
+            )),
+            #[cfg(feature = "kafka")]
+            Self::Kafka { brokers, topic } => {
+                Ok(Box::new(super::KafkaEventPublisher::new(brokers, topic)?))
+            }
+            #[allow(unreachable_patterns)]
+            unavailable => Err(OutboxError::InvalidConfiguration(format!(
+                "{:?} requires building with its feature enabled",
+                unavailable
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coverage_helper::test;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+
+    fn parse(
+        publisher: &str,
+        vars: &[(&str, &str)],
+    ) -> Result<EventPublisherConfiguration, OutboxError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        EventPublisherConfiguration::parse(publisher, |name| vars.get(name).cloned())
+    }
+
+    #[test]
+    fn parses_the_file_and_stdout_publishers() {
+        assert_eq!(
+            parse("stdout", &[]).unwrap(),
+            EventPublisherConfiguration::Stdout
+        );
+        assert_eq!(
+            parse("file", &[(OUTBOX_FILE_PATH_ENV_VAR, "events.jsonl")]).unwrap(),
+            EventPublisherConfiguration::File("events.jsonl".into())
+        );
+        assert!(matches!(
+            parse("file", &[]),
+            Err(OutboxError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn broker_topics_default_to_veloxide_events() {
+        assert_eq!(
+            parse("nats", &[(NATS_URL_ENV_VAR, "nats://localhost:4222")]).unwrap(),
+            EventPublisherConfiguration::Nats {
+                url: "nats://localhost:4222".to_string(),
+                subject_prefix: DEFAULT_EVENTS_TOPIC.to_string(),
+            }
+        );
+        assert_eq!(
+            parse(
+                "kafka",
+                &[
+                    (KAFKA_BROKERS_ENV_VAR, "localhost:9092"),
+                    (KAFKA_TOPIC_ENV_VAR, "bank-account-events")
+                ]
+            )
+            .unwrap(),
+            EventPublisherConfiguration::Kafka {
+                brokers: "localhost:9092".to_string(),
+                topic: "bank-account-events".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_publishers() {
+        assert!(matches!(
+            parse("rabbitmq", &[]),
+            Err(OutboxError::InvalidConfiguration(_))
+        ));
+    }
+
+    #[test]
+    fn message_ids_identify_the_event() {
+        let event = OutboxEvent {
+            aggregate_type: "account".to_string(),
+            aggregate_id: "1234".to_string(),
+            sequence: 3,
+            event_type: "CustomerDepositedMoney".to_string(),
+            event_version: "2.0".to_string(),
+            payload: serde_json::json!({}),
+            metadata: serde_json::json!({}),
+            recorded_at: Utc::now(),
+        };
+        assert_eq!(event.message_id(), "account:1234:3");
+    }
+}
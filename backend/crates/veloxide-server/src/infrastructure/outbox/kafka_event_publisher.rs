@@ -0,0 +1,53 @@
+use super::*;
+use async_trait::async_trait;
+use rdkafka::config::ClientConfig;
+use rdkafka::message::{Header, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::util::Timeout;
+use std::time::Duration;
+
+/// How long the producer may take to have an event acknowledged, retries included.
+const KAFKA_PUBLISH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Publishes events to a Kafka topic keyed by aggregate id, so that the events of an aggregate
+/// land on the same partition in order. The idempotent producer keeps retries from reordering
+/// or duplicating them, and each event carries its message id in a `message-id` header.
+pub struct KafkaEventPublisher {
+    producer: FutureProducer,
+    topic: String,
+}
+
+impl KafkaEventPublisher {
+    pub fn new(brokers: &str, topic: &str) -> Result<Self, OutboxError> {
+        let producer = ClientConfig::new()
+            .set("bootstrap.servers", brokers)
+            .set("enable.idempotence", "true")
+            .set("acks", "all")
+            .create()
+            .map_err(|err| OutboxError::InvalidConfiguration(format!("{}: {}", brokers, err)))?;
+        Ok(Self {
+            producer,
+            topic: topic.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl EventPublisher for KafkaEventPublisher {
+    async fn publish(&self, event: &OutboxEvent) -> Result<(), OutboxError> {
+        let payload = serde_json::to_vec(event)?;
+        let message_id = event.message_id();
+        let record = FutureRecord::to(&self.topic)
+            .key(event.aggregate_id.as_str())
+            .payload(payload.as_slice())
+            .headers(OwnedHeaders::new().insert(Header {
+                key: "message-id",
+                value: Some(message_id.as_str()),
+            }));
+        self.producer
+            .send(record, Timeout::After(KAFKA_PUBLISH_TIMEOUT))
+            .await
+            .map_err(|(err, _)| event.publish_error(err))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,17 @@
+pub mod event_publisher;
+#[cfg(feature = "kafka")]
+pub mod kafka_event_publisher;
+#[cfg(feature = "nats")]
+pub mod nats_event_publisher;
+pub mod outbox_event_repository;
+pub mod outbox_relay;
+
+// Re-exports
+
+pub use event_publisher::*;
+#[cfg(feature = "kafka")]
+pub use kafka_event_publisher::*;
+#[cfg(feature = "nats")]
+pub use nats_event_publisher::*;
+pub use outbox_event_repository::*;
+pub use outbox_relay::*;
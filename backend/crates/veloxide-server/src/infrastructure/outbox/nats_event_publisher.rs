@@ -0,0 +1,41 @@
+use super::*;
+use async_nats::jetstream;
+use async_nats::HeaderMap;
+use async_trait::async_trait;
+
+/// Publishes events to NATS JetStream on `<subject prefix>.<aggregate type>`, waiting for the
+/// stream to acknowledge each one. The `Nats-Msg-Id` header lets JetStream drop events that are
+/// published twice within its duplicate window.
+pub struct NatsEventPublisher {
+    jetstream: jetstream::Context,
+    subject_prefix: String,
+}
+
+impl NatsEventPublisher {
+    pub async fn connect(url: &str, subject_prefix: &str) -> Result<Self, OutboxError> {
+        let client = async_nats::connect(url)
+            .await
+            .map_err(|err| OutboxError::InvalidConfiguration(format!("{}: {}", url, err)))?;
+        Ok(Self {
+            jetstream: jetstream::new(client),
+            subject_prefix: subject_prefix.to_string(),
+        })
+    }
+}
+
+#[async_trait]
+impl EventPublisher for NatsEventPublisher {
+    async fn publish(&self, event: &OutboxEvent) -> Result<(), OutboxError> {
+        let subject = format!("{}.{}", self.subject_prefix, event.aggregate_type);
+        let mut headers = HeaderMap::new();
+        headers.insert("Nats-Msg-Id", event.message_id().as_str());
+        let payload = serde_json::to_vec(event)?;
+        self.jetstream
+            .publish_with_headers(subject, headers, payload.into())
+            .await
+            .map_err(|err| event.publish_error(err))?
+            .await
+            .map_err(|err| event.publish_error(err))?;
+        Ok(())
+    }
+}
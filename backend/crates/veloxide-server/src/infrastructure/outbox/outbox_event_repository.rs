@@ -0,0 +1,362 @@
+use async_trait::async_trait;
+use cqrs_es::persist::PersistedEventStore;
+use cqrs_es::persist::{
+    PersistedEventRepository, PersistenceError, ReplayStream, SerializedEvent, SerializedSnapshot,
+};
+use cqrs_es::{Aggregate, CqrsFramework};
+use serde_json::Value;
+
+use cfg_if::cfg_if;
+
+/// A framework whose committed events are also written to the outbox.
+pub type OutboxCqrs<A> = CqrsFramework<A, PersistedEventStore<OutboxEventRepository, A>>;
+
+// A duplicate event or snapshot means another command committed on the aggregate first.
+fn persistence_error(err: sqlx::Error) -> PersistenceError {
+    match err {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            PersistenceError::OptimisticLockError
+        }
+        err => PersistenceError::ConnectionError(Box::new(err)),
+    }
+}
+
+/// The metadata key a command passes the sequence number it expects the aggregate to be at in.
+/// The command is only committed while the aggregate is still at that sequence number, and the
+/// key is not stored with its events.
+pub const EXPECTED_VERSION_METADATA: &str = "expected_version";
+
+// The events of a command continue from the sequence number the aggregate was loaded at, which
+// must be the one the command expected.
+fn ensure_expected_version(events: &[SerializedEvent]) -> Result<(), PersistenceError> {
+    let Some(first) = events.first() else {
+        return Ok(());
+    };
+    let Some(expected_version) = first.metadata.get(EXPECTED_VERSION_METADATA) else {
+        return Ok(());
+    };
+    let expected_version = expected_version
+        .as_str()
+        .and_then(|version| version.parse::<usize>().ok())
+        .ok_or_else(|| {
+            PersistenceError::UnknownError(
+                format!(
+                    "invalid {}: {}",
+                    EXPECTED_VERSION_METADATA, expected_version
+                )
+                .into(),
+            )
+        })?;
+    if first.sequence != expected_version + 1 {
+        return Err(PersistenceError::OptimisticLockError);
+    }
+    Ok(())
+}
+
+fn stored_metadata(event: &SerializedEvent) -> Value {
+    let mut metadata = event.metadata.clone();
+    if let Value::Object(entries) = &mut metadata {
+        entries.remove(EXPECTED_VERSION_METADATA);
+    }
+    metadata
+}
+
+cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use postgres_es::PostgresEventRepository;
+        use sqlx::{Pool, Postgres};
+
+        /// An event repository that writes every committed event to the outbox in the same
+        /// transaction as it is written to the events table, so that exactly the committed
+        /// events are published. Reads are served by the postgres-es repository.
+        pub struct OutboxEventRepository {
+            pool: Pool<Postgres>,
+            events: PostgresEventRepository,
+        }
+
+        impl OutboxEventRepository {
+            pub fn new(pool: Pool<Postgres>) -> Self {
+                Self {
+                    events: PostgresEventRepository::new(pool.clone()),
+                    pool,
+                }
+            }
+        }
+
+        #[async_trait]
+        impl PersistedEventRepository for OutboxEventRepository {
+            async fn get_events<A: Aggregate>(
+                &self,
+                aggregate_id: &str,
+            ) -> Result<Vec<SerializedEvent>, PersistenceError> {
+                self.events.get_events::<A>(aggregate_id).await
+            }
+
+            async fn get_last_events<A: Aggregate>(
+                &self,
+                aggregate_id: &str,
+                last_sequence: usize,
+            ) -> Result<Vec<SerializedEvent>, PersistenceError> {
+                self.events.get_last_events::<A>(aggregate_id, last_sequence).await
+            }
+
+            async fn get_snapshot<A: Aggregate>(
+                &self,
+                aggregate_id: &str,
+            ) -> Result<Option<SerializedSnapshot>, PersistenceError> {
+                self.events.get_snapshot::<A>(aggregate_id).await
+            }
+
+            async fn persist<A: Aggregate>(
+                &self,
+                events: &[SerializedEvent],
+                snapshot_update: Option<(String, Value, usize)>,
+            ) -> Result<(), PersistenceError> {
+                ensure_expected_version(events)?;
+                let mut transaction = self.pool.begin().await.map_err(persistence_error)?;
+                for event in events {
+                    let metadata = stored_metadata(event);
+                    sqlx::query("INSERT INTO events (aggregate_type, aggregate_id, sequence, event_type, event_version, payload, metadata) VALUES ($1, $2, $3, $4, $5, $6, $7)")
+                        .bind(&event.aggregate_type)
+                        .bind(&event.aggregate_id)
+                        .bind(event.sequence as i64)
+                        .bind(&event.event_type)
+                        .bind(&event.event_version)
+                        .bind(&event.payload)
+                        .bind(&metadata)
+                        .execute(&mut *transaction)
+                        .await
+                        .map_err(persistence_error)?;
+                    sqlx::query("INSERT INTO outbox (aggregate_type, aggregate_id, sequence, event_type, event_version, payload, metadata) VALUES ($1, $2, $3, $4, $5, $6, $7)")
+                        .bind(&event.aggregate_type)
+                        .bind(&event.aggregate_id)
+                        .bind(event.sequence as i64)
+                        .bind(&event.event_type)
+                        .bind(&event.event_version)
+                        .bind(&event.payload)
+                        .bind(&metadata)
+                        .execute(&mut *transaction)
+                        .await
+                        .map_err(persistence_error)?;
+                }
+
+                // Same as the postgres-es repository: the first snapshot is inserted, later ones
+                // replace the previous snapshot only if no other command replaced it first.
+                if let Some((aggregate_id, payload, current_snapshot)) = snapshot_update {
+                    let last_sequence = events.last().map_or(0, |event| event.sequence) as i64;
+                    let snapshotted = if current_snapshot == 1 {
+                        sqlx::query("INSERT INTO snapshots (aggregate_type, aggregate_id, last_sequence, current_snapshot, payload) VALUES ($1, $2, $3, $4, $5)")
+                            .bind(A::aggregate_type())
+                            .bind(&aggregate_id)
+                            .bind(last_sequence)
+                            .bind(current_snapshot as i64)
+                            .bind(&payload)
+                            .execute(&mut *transaction)
+                            .await
+                    } else {
+                        sqlx::query("UPDATE snapshots SET last_sequence = $1, payload = $2, current_snapshot = $3 WHERE aggregate_type = $4 AND aggregate_id = $5 AND current_snapshot = $6")
+                            .bind(last_sequence)
+                            .bind(&payload)
+                            .bind(current_snapshot as i64)
+                            .bind(A::aggregate_type())
+                            .bind(&aggregate_id)
+                            .bind(current_snapshot as i64 - 1)
+                            .execute(&mut *transaction)
+                            .await
+                    };
+                    if snapshotted.map_err(persistence_error)?.rows_affected() != 1 {
+                        return Err(PersistenceError::OptimisticLockError);
+                    }
+                }
+                transaction.commit().await.map_err(persistence_error)
+            }
+
+            async fn stream_events<A: Aggregate>(
+                &self,
+                aggregate_id: &str,
+            ) -> Result<ReplayStream, PersistenceError> {
+                self.events.stream_events::<A>(aggregate_id).await
+            }
+
+            async fn stream_all_events<A: Aggregate>(
+                &self,
+            ) -> Result<ReplayStream, PersistenceError> {
+                self.events.stream_all_events::<A>().await
+            }
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use mysql_es::MysqlEventRepository;
+        use sqlx::{MySql, Pool};
+
+        /// An event repository that writes every committed event to the outbox in the same
+        /// transaction as it is written to the events table, so that exactly the committed
+        /// events are published. Reads are served by the mysql-es repository.
+        pub struct OutboxEventRepository {
+            pool: Pool<MySql>,
+            events: MysqlEventRepository,
+        }
+
+        impl OutboxEventRepository {
+            pub fn new(pool: Pool<MySql>) -> Self {
+                Self {
+                    events: MysqlEventRepository::new(pool.clone()),
+                    pool,
+                }
+            }
+        }
+
+        #[async_trait]
+        impl PersistedEventRepository for OutboxEventRepository {
+            async fn get_events<A: Aggregate>(
+                &self,
+                aggregate_id: &str,
+            ) -> Result<Vec<SerializedEvent>, PersistenceError> {
+                self.events.get_events::<A>(aggregate_id).await
+            }
+
+            async fn get_last_events<A: Aggregate>(
+                &self,
+                aggregate_id: &str,
+                last_sequence: usize,
+            ) -> Result<Vec<SerializedEvent>, PersistenceError> {
+                self.events.get_last_events::<A>(aggregate_id, last_sequence).await
+            }
+
+            async fn get_snapshot<A: Aggregate>(
+                &self,
+                aggregate_id: &str,
+            ) -> Result<Option<SerializedSnapshot>, PersistenceError> {
+                self.events.get_snapshot::<A>(aggregate_id).await
+            }
+
+            async fn persist<A: Aggregate>(
+                &self,
+                events: &[SerializedEvent],
+                snapshot_update: Option<(String, Value, usize)>,
+            ) -> Result<(), PersistenceError> {
+                ensure_expected_version(events)?;
+                let mut transaction = self.pool.begin().await.map_err(persistence_error)?;
+                for event in events {
+                    let metadata = stored_metadata(event);
+                    sqlx::query("INSERT INTO events (aggregate_type, aggregate_id, sequence, event_type, event_version, payload, metadata) VALUES (?, ?, ?, ?, ?, ?, ?)")
+                        .bind(&event.aggregate_type)
+                        .bind(&event.aggregate_id)
+                        .bind(event.sequence as i64)
+                        .bind(&event.event_type)
+                        .bind(&event.event_version)
+                        .bind(&event.payload)
+                        .bind(&metadata)
+                        .execute(&mut *transaction)
+                        .await
+                        .map_err(persistence_error)?;
+                    sqlx::query("INSERT INTO outbox (aggregate_type, aggregate_id, sequence, event_type, event_version, payload, metadata) VALUES (?, ?, ?, ?, ?, ?, ?)")
+                        .bind(&event.aggregate_type)
+                        .bind(&event.aggregate_id)
+                        .bind(event.sequence as i64)
+                        .bind(&event.event_type)
+                        .bind(&event.event_version)
+                        .bind(&event.payload)
+                        .bind(&metadata)
+                        .execute(&mut *transaction)
+                        .await
+                        .map_err(persistence_error)?;
+                }
+
+                // Same as the mysql-es repository: the first snapshot is inserted, later ones
+                // replace the previous snapshot only if no other command replaced it first.
+                if let Some((aggregate_id, payload, current_snapshot)) = snapshot_update {
+                    let last_sequence = events.last().map_or(0, |event| event.sequence) as i64;
+                    let snapshotted = if current_snapshot == 1 {
+                        sqlx::query("INSERT INTO snapshots (aggregate_type, aggregate_id, last_sequence, current_snapshot, payload) VALUES (?, ?, ?, ?, ?)")
+                            .bind(A::aggregate_type())
+                            .bind(&aggregate_id)
+                            .bind(last_sequence)
+                            .bind(current_snapshot as i64)
+                            .bind(&payload)
+                            .execute(&mut *transaction)
+                            .await
+                    } else {
+                        sqlx::query("UPDATE snapshots SET last_sequence = ?, payload = ?, current_snapshot = ? WHERE aggregate_type = ? AND aggregate_id = ? AND current_snapshot = ?")
+                            .bind(last_sequence)
+                            .bind(&payload)
+                            .bind(current_snapshot as i64)
+                            .bind(A::aggregate_type())
+                            .bind(&aggregate_id)
+                            .bind(current_snapshot as i64 - 1)
+                            .execute(&mut *transaction)
+                            .await
+                    };
+                    if snapshotted.map_err(persistence_error)?.rows_affected() != 1 {
+                        return Err(PersistenceError::OptimisticLockError);
+                    }
+                }
+                transaction.commit().await.map_err(persistence_error)
+            }
+
+            async fn stream_events<A: Aggregate>(
+                &self,
+                aggregate_id: &str,
+            ) -> Result<ReplayStream, PersistenceError> {
+                self.events.stream_events::<A>(aggregate_id).await
+            }
+
+            async fn stream_all_events<A: Aggregate>(
+                &self,
+            ) -> Result<ReplayStream, PersistenceError> {
+                self.events.stream_all_events::<A>().await
+            }
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    fn event(sequence: usize, metadata: Value) -> SerializedEvent {
+        SerializedEvent {
+            aggregate_id: "1234".to_string(),
+            sequence,
+            aggregate_type: "account".to_string(),
+            event_type: "CustomerDepositedMoney".to_string(),
+            event_version: "1.0".to_string(),
+            payload: json!({}),
+            metadata,
+        }
+    }
+
+    #[test]
+    fn commits_while_the_aggregate_is_at_the_expected_version() {
+        let events = [
+            event(4, json!({ "expected_version": "3" })),
+            event(5, json!({})),
+        ];
+        assert!(ensure_expected_version(&events).is_ok());
+        assert!(ensure_expected_version(&[event(4, json!({}))]).is_ok());
+    }
+
+    #[test]
+    fn conflicts_once_the_aggregate_moved_past_the_expected_version() {
+        let events = [event(5, json!({ "expected_version": "3" }))];
+        assert!(matches!(
+            ensure_expected_version(&events),
+            Err(PersistenceError::OptimisticLockError)
+        ));
+    }
+
+    #[test]
+    fn does_not_store_the_expected_version() {
+        let event = event(
+            4,
+            json!({ "expected_version": "3", "time": "2023-11-14T09:00:00Z" }),
+        );
+        assert_eq!(
+            stored_metadata(&event),
+            json!({ "time": "2023-11-14T09:00:00Z" })
+        );
+    }
+}
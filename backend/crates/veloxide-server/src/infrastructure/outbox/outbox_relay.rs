@@ -0,0 +1,222 @@
+use super::*;
+use chrono::NaiveDateTime;
+use sqlx::Row;
+use std::collections::HashSet;
+use std::time::Duration;
+
+use cfg_if::cfg_if;
+
+pub const OUTBOX_POLL_INTERVAL_ENV_VAR: &str = "OUTBOX_POLL_INTERVAL_MS";
+
+/// How long the relay waits for new events when the outbox is empty, when no interval is
+/// configured.
+pub const DEFAULT_OUTBOX_POLL_INTERVAL_MS: u64 = 1000;
+
+/// How many events the relay reads from the outbox at a time.
+pub const OUTBOX_BATCH_SIZE: usize = 100;
+
+/// Reads the poll interval from the `OUTBOX_POLL_INTERVAL_MS` env var, defaulting to a second.
+pub fn poll_interval_from_env() -> Result<Duration, OutboxError> {
+    match dotenvy::var(OUTBOX_POLL_INTERVAL_ENV_VAR) {
+        Ok(poll_interval) => parse_poll_interval(&poll_interval),
+        Err(_) => Ok(Duration::from_millis(DEFAULT_OUTBOX_POLL_INTERVAL_MS)),
+    }
+}
+
+pub fn parse_poll_interval(poll_interval: &str) -> Result<Duration, OutboxError> {
+    poll_interval
+        .trim()
+        .parse()
+        .ok()
+        .filter(|millis| *millis > 0)
+        .map(Duration::from_millis)
+        .ok_or_else(|| {
+            OutboxError::InvalidConfiguration(format!(
+                "{} must be a positive number of milliseconds, got {}",
+                OUTBOX_POLL_INTERVAL_ENV_VAR, poll_interval
+            ))
+        })
+}
+
+impl OutboxRelay {
+    /// Publishes until the process exits.
+    pub async fn run(self) {
+        loop {
+            match self.relay().await {
+                // A full batch likely means more events are waiting.
+                Ok(published) if published == OUTBOX_BATCH_SIZE => continue,
+                Ok(_) => {}
+                Err(err) => tracing::error!("failed to relay the outbox: {}", err),
+            }
+            tokio::time::sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Publishes the oldest events in the outbox, returning how many were published.
+    #[tracing::instrument(skip(self), err)]
+    pub async fn relay(&self) -> Result<usize, OutboxError> {
+        let events = self.unpublished().await?;
+        let mut blocked_aggregates = HashSet::new();
+        let mut published = 0;
+        for event in events {
+            let aggregate = (event.aggregate_type.clone(), event.aggregate_id.clone());
+            if blocked_aggregates.contains(&aggregate) {
+                continue;
+            }
+            match self.publisher.publish(&event).await {
+                Ok(()) => {
+                    self.remove(&event).await?;
+                    published += 1;
+                }
+                Err(err) => {
+                    tracing::warn!("{}, retrying on the next poll", err);
+                    blocked_aggregates.insert(aggregate);
+                }
+            }
+        }
+        Ok(published)
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use sqlx::{Pool, Postgres};
+        use sqlx::postgres::PgRow;
+
+        fn outbox_event(row: &PgRow) -> Result<OutboxEvent, sqlx::Error> {
+            let sequence: i64 = row.try_get("sequence")?;
+            let recorded_at: NaiveDateTime = row.try_get("recorded_at")?;
+            Ok(OutboxEvent {
+                aggregate_type: row.try_get("aggregate_type")?,
+                aggregate_id: row.try_get("aggregate_id")?,
+                sequence: sequence as usize,
+                event_type: row.try_get("event_type")?,
+                event_version: row.try_get("event_version")?,
+                payload: row.try_get("payload")?,
+                metadata: row.try_get("metadata")?,
+                recorded_at: recorded_at.and_utc(),
+            })
+        }
+
+        /// Publishes the events written to the outbox, removing each one once it was published.
+        /// An event that fails to publish is retried on the next poll, and the later events of
+        /// its aggregate wait until it succeeds, so delivery is at-least-once and in order per
+        /// aggregate. Run the relay on a single instance, relays on several instances could
+        /// publish the events of an aggregate out of order.
+        pub struct OutboxRelay {
+            pool: Pool<Postgres>,
+            publisher: Box<dyn EventPublisher>,
+            poll_interval: Duration,
+        }
+
+        impl OutboxRelay {
+            pub fn new(
+                pool: Pool<Postgres>,
+                publisher: Box<dyn EventPublisher>,
+                poll_interval: Duration,
+            ) -> Self {
+                Self { pool, publisher, poll_interval }
+            }
+
+            async fn unpublished(&self) -> Result<Vec<OutboxEvent>, OutboxError> {
+                let rows = sqlx::query("SELECT aggregate_type, aggregate_id, sequence, event_type, event_version, payload, metadata, recorded_at FROM outbox ORDER BY recorded_at, aggregate_type, aggregate_id, sequence LIMIT $1")
+                    .bind(OUTBOX_BATCH_SIZE as i64)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows.iter().map(outbox_event).collect::<Result<_, _>>()?)
+            }
+
+            async fn remove(&self, event: &OutboxEvent) -> Result<(), OutboxError> {
+                sqlx::query("DELETE FROM outbox WHERE aggregate_type = $1 AND aggregate_id = $2 AND sequence = $3")
+                    .bind(&event.aggregate_type)
+                    .bind(&event.aggregate_id)
+                    .bind(event.sequence as i64)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use sqlx::{MySql, Pool};
+        use sqlx::mysql::MySqlRow;
+
+        fn outbox_event(row: &MySqlRow) -> Result<OutboxEvent, sqlx::Error> {
+            let sequence: i64 = row.try_get("sequence")?;
+            let recorded_at: NaiveDateTime = row.try_get("recorded_at")?;
+            Ok(OutboxEvent {
+                aggregate_type: row.try_get("aggregate_type")?,
+                aggregate_id: row.try_get("aggregate_id")?,
+                sequence: sequence as usize,
+                event_type: row.try_get("event_type")?,
+                event_version: row.try_get("event_version")?,
+                payload: row.try_get("payload")?,
+                metadata: row.try_get("metadata")?,
+                recorded_at: recorded_at.and_utc(),
+            })
+        }
+
+        /// Publishes the events written to the outbox, removing each one once it was published.
+        /// An event that fails to publish is retried on the next poll, and the later events of
+        /// its aggregate wait until it succeeds, so delivery is at-least-once and in order per
+        /// aggregate. Run the relay on a single instance, relays on several instances could
+        /// publish the events of an aggregate out of order.
+        pub struct OutboxRelay {
+            pool: Pool<MySql>,
+            publisher: Box<dyn EventPublisher>,
+            poll_interval: Duration,
+        }
+
+        impl OutboxRelay {
+            pub fn new(
+                pool: Pool<MySql>,
+                publisher: Box<dyn EventPublisher>,
+                poll_interval: Duration,
+            ) -> Self {
+                Self { pool, publisher, poll_interval }
+            }
+
+            async fn unpublished(&self) -> Result<Vec<OutboxEvent>, OutboxError> {
+                let rows = sqlx::query("SELECT aggregate_type, aggregate_id, sequence, event_type, event_version, payload, metadata, recorded_at FROM outbox ORDER BY recorded_at, aggregate_type, aggregate_id, sequence LIMIT ?")
+                    .bind(OUTBOX_BATCH_SIZE as i64)
+                    .fetch_all(&self.pool)
+                    .await?;
+                Ok(rows.iter().map(outbox_event).collect::<Result<_, _>>()?)
+            }
+
+            async fn remove(&self, event: &OutboxEvent) -> Result<(), OutboxError> {
+                sqlx::query("DELETE FROM outbox WHERE aggregate_type = ? AND aggregate_id = ? AND sequence = ?")
+                    .bind(&event.aggregate_type)
+                    .bind(&event.aggregate_id)
+                    .bind(event.sequence as i64)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coverage_helper::test;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_the_poll_interval_in_milliseconds() {
+        assert_eq!(
+            parse_poll_interval(" 250 ").unwrap(),
+            Duration::from_millis(250)
+        );
+        assert!(matches!(
+            parse_poll_interval("0"),
+            Err(OutboxError::InvalidConfiguration(_))
+        ));
+        assert!(matches!(
+            parse_poll_interval("a second"),
+            Err(OutboxError::InvalidConfiguration(_))
+        ));
+    }
+}
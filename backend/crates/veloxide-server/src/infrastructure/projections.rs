@@ -0,0 +1,548 @@
+#[cfg(feature = "openapi")]
+use utoipa::ToSchema;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use cqrs_es::persist::{EventUpcaster, PersistedEventStore};
+use cqrs_es::{Aggregate, EventEnvelope, EventStore, Query, View};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use cfg_if::cfg_if;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ProjectionError {
+    #[error("unknown projection: {0}")]
+    UnknownProjection(String),
+
+    #[error("failed to replay aggregate {aggregate_id}: {reason}")]
+    Replay {
+        aggregate_id: String,
+        reason: String,
+    },
+
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// Reported after each aggregate has been replayed into a projection.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectionProgress {
+    pub aggregate_id: String,
+    pub replayed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct ProjectionRebuildReport {
+    pub projection: String,
+    pub aggregates_replayed: usize,
+    pub events_replayed: usize,
+}
+
+/// A view table that can be thrown away and rebuilt from the events it was projected from.
+#[async_trait]
+pub trait Projection: Send + Sync {
+    fn name(&self) -> &str;
+
+    /// Clears the view table, or only the view of `aggregate_id` when given, and replays the
+    /// events of the matching aggregates into it, all in one transaction so that readers never
+    /// see the table half rebuilt.
+    async fn rebuild(
+        &self,
+        aggregate_id: Option<&str>,
+        on_progress: &(dyn Fn(&ProjectionProgress) + Send + Sync),
+    ) -> Result<ProjectionRebuildReport, ProjectionError>;
+}
+
+/// The projections that can be rebuilt, looked up by the name of their view table.
+#[derive(Clone, Default)]
+pub struct ProjectionRebuilder {
+    projections: Vec<Arc<dyn Projection>>,
+}
+
+impl ProjectionRebuilder {
+    pub fn new(projections: Vec<Arc<dyn Projection>>) -> Self {
+        Self { projections }
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.projections
+            .iter()
+            .map(|projection| projection.name())
+            .collect()
+    }
+
+    #[tracing::instrument(skip(self, on_progress), err)]
+    pub async fn rebuild(
+        &self,
+        projection: &str,
+        aggregate_id: Option<&str>,
+        on_progress: &(dyn Fn(&ProjectionProgress) + Send + Sync),
+    ) -> Result<ProjectionRebuildReport, ProjectionError> {
+        let projection = self
+            .projections
+            .iter()
+            .find(|candidate| candidate.name() == projection)
+            .ok_or_else(|| ProjectionError::UnknownProjection(projection.to_string()))?;
+        projection.rebuild(aggregate_id, on_progress).await
+    }
+}
+
+// Replays the events of a single aggregate into a fresh view the same way the `GenericQuery` that
+// projects them at runtime does, so a rebuilt view is exactly what the live query would have
+// produced.
+fn replayed_view<A: Aggregate, V: View<A>>(events: &[EventEnvelope<A>]) -> V {
+    let mut view = V::default();
+    for event in events {
+        view.update(event);
+    }
+    view
+}
+
+/// The metadata key events record the time they happened at in, as an RFC 3339 timestamp.
+pub const EVENT_TIME_METADATA: &str = "time";
+
+/// Records the time each event was committed at, as `created_at` has it by sequence number, on
+/// the events without a time of their own, such as those of commands issued by the server.
+pub fn fill_event_times<A: Aggregate>(
+    events: &mut [EventEnvelope<A>],
+    created_at: &HashMap<usize, DateTime<Utc>>,
+) {
+    for event in events {
+        if let Some(created_at) = created_at.get(&event.sequence) {
+            event
+                .metadata
+                .entry(EVENT_TIME_METADATA.to_string())
+                .or_insert_with(|| created_at.to_rfc3339());
+        }
+    }
+}
+
+fn event_times(rows: Vec<(i64, NaiveDateTime)>) -> HashMap<usize, DateTime<Utc>> {
+    rows.into_iter()
+        .filter_map(|(sequence, created_at)| {
+            Some((usize::try_from(sequence).ok()?, created_at.and_utc()))
+        })
+        .collect()
+}
+
+cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use postgres_es::PostgresEventRepository;
+        use sqlx::{Pool, Postgres};
+
+        /// The ids of every aggregate of type `A` that has events in the event store.
+        pub async fn aggregate_ids<A: Aggregate>(pool: &Pool<Postgres>) -> Result<Vec<String>, sqlx::Error> {
+            sqlx::query_scalar("SELECT DISTINCT aggregate_id FROM events WHERE aggregate_type = $1 ORDER BY aggregate_id")
+                .bind(A::aggregate_type())
+                .fetch_all(pool)
+                .await
+        }
+
+        /// When each event of the aggregate was committed, by sequence number.
+        pub async fn event_created_at<A: Aggregate>(pool: &Pool<Postgres>, aggregate_id: &str) -> Result<HashMap<usize, DateTime<Utc>>, sqlx::Error> {
+            let rows = sqlx::query_as(r#"SELECT sequence, "createdAt" FROM events WHERE aggregate_type = $1 AND aggregate_id = $2"#)
+                .bind(A::aggregate_type())
+                .bind(aggregate_id)
+                .fetch_all(pool)
+                .await?;
+            Ok(event_times(rows))
+        }
+
+        pub struct ViewProjection<A, V>
+        where
+            A: Aggregate + Send + Sync,
+            V: View<A>,
+        {
+            view_table: String,
+            pool: Pool<Postgres>,
+            event_store: PersistedEventStore<PostgresEventRepository, A>,
+            view: PhantomData<V>,
+        }
+
+        impl<A, V> ViewProjection<A, V>
+        where
+            A: Aggregate + Send + Sync,
+            V: View<A>,
+        {
+            pub fn new(view_table: &str, pool: Pool<Postgres>, upcasters: Vec<Box<dyn EventUpcaster>>) -> Self {
+                Self {
+                    view_table: view_table.to_string(),
+                    event_store: PersistedEventStore::new_event_store(PostgresEventRepository::new(pool.clone()))
+                        .with_upcasters(upcasters),
+                    view: PhantomData,
+                    pool,
+                }
+            }
+        }
+
+        #[async_trait]
+        impl<A, V> Projection for ViewProjection<A, V>
+        where
+            A: Aggregate + Send + Sync,
+            V: View<A>,
+        {
+            fn name(&self) -> &str {
+                &self.view_table
+            }
+
+            async fn rebuild(
+                &self,
+                aggregate_id: Option<&str>,
+                on_progress: &(dyn Fn(&ProjectionProgress) + Send + Sync),
+            ) -> Result<ProjectionRebuildReport, ProjectionError> {
+                let mut transaction = self.pool.begin().await?;
+                let aggregate_ids: Vec<String> = match aggregate_id {
+                    Some(aggregate_id) => {
+                        sqlx::query(&format!("DELETE FROM {} WHERE view_id = $1", self.view_table))
+                            .bind(aggregate_id)
+                            .execute(&mut *transaction)
+                            .await?;
+                        vec![aggregate_id.to_string()]
+                    }
+                    None => {
+                        sqlx::query(&format!("TRUNCATE TABLE {}", self.view_table))
+                            .execute(&mut *transaction)
+                            .await?;
+                        aggregate_ids::<A>(&self.pool).await?
+                    }
+                };
+
+                let total = aggregate_ids.len();
+                let mut events_replayed = 0;
+                for (index, aggregate_id) in aggregate_ids.into_iter().enumerate() {
+                    let replay_error = |reason: String| ProjectionError::Replay {
+                        aggregate_id: aggregate_id.clone(),
+                        reason,
+                    };
+                    let events = self
+                        .event_store
+                        .load_events(&aggregate_id)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    if !events.is_empty() {
+                        let view: V = replayed_view(&events);
+                        let payload = serde_json::to_value(&view).map_err(|err| replay_error(err.to_string()))?;
+                        // A view is at version 1 once the `GenericQuery` first stored it.
+                        sqlx::query(&format!("INSERT INTO {} (payload, version, view_id) VALUES ($1, $2, $3)", self.view_table))
+                            .bind(payload)
+                            .bind(1_i64)
+                            .bind(&aggregate_id)
+                            .execute(&mut *transaction)
+                            .await?;
+                    }
+                    events_replayed += events.len();
+                    on_progress(&ProjectionProgress {
+                        aggregate_id,
+                        replayed: index + 1,
+                        total,
+                    });
+                }
+                transaction.commit().await?;
+                Ok(ProjectionRebuildReport {
+                    projection: self.view_table.clone(),
+                    aggregates_replayed: total,
+                    events_replayed,
+                })
+            }
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use mysql_es::MysqlEventRepository;
+        use sqlx::{MySql, Pool};
+
+        /// The ids of every aggregate of type `A` that has events in the event store.
+        pub async fn aggregate_ids<A: Aggregate>(pool: &Pool<MySql>) -> Result<Vec<String>, sqlx::Error> {
+            sqlx::query_scalar("SELECT DISTINCT aggregate_id FROM events WHERE aggregate_type = ? ORDER BY aggregate_id")
+                .bind(A::aggregate_type())
+                .fetch_all(pool)
+                .await
+        }
+
+        /// When each event of the aggregate was committed, by sequence number.
+        pub async fn event_created_at<A: Aggregate>(pool: &Pool<MySql>, aggregate_id: &str) -> Result<HashMap<usize, DateTime<Utc>>, sqlx::Error> {
+            let rows = sqlx::query_as("SELECT sequence, createdAt FROM events WHERE aggregate_type = ? AND aggregate_id = ?")
+                .bind(A::aggregate_type())
+                .bind(aggregate_id)
+                .fetch_all(pool)
+                .await?;
+            Ok(event_times(rows))
+        }
+
+        pub struct ViewProjection<A, V>
+        where
+            A: Aggregate + Send + Sync,
+            V: View<A>,
+        {
+            view_table: String,
+            pool: Pool<MySql>,
+            event_store: PersistedEventStore<MysqlEventRepository, A>,
+            view: PhantomData<V>,
+        }
+
+        impl<A, V> ViewProjection<A, V>
+        where
+            A: Aggregate + Send + Sync,
+            V: View<A>,
+        {
+            pub fn new(view_table: &str, pool: Pool<MySql>, upcasters: Vec<Box<dyn EventUpcaster>>) -> Self {
+                Self {
+                    view_table: view_table.to_string(),
+                    event_store: PersistedEventStore::new_event_store(MysqlEventRepository::new(pool.clone()))
+                        .with_upcasters(upcasters),
+                    view: PhantomData,
+                    pool,
+                }
+            }
+        }
+
+        #[async_trait]
+        impl<A, V> Projection for ViewProjection<A, V>
+        where
+            A: Aggregate + Send + Sync,
+            V: View<A>,
+        {
+            fn name(&self) -> &str {
+                &self.view_table
+            }
+
+            async fn rebuild(
+                &self,
+                aggregate_id: Option<&str>,
+                on_progress: &(dyn Fn(&ProjectionProgress) + Send + Sync),
+            ) -> Result<ProjectionRebuildReport, ProjectionError> {
+                let mut transaction = self.pool.begin().await?;
+                let aggregate_ids: Vec<String> = match aggregate_id {
+                    Some(aggregate_id) => {
+                        sqlx::query(&format!("DELETE FROM {} WHERE view_id = ?", self.view_table))
+                            .bind(aggregate_id)
+                            .execute(&mut *transaction)
+                            .await?;
+                        vec![aggregate_id.to_string()]
+                    }
+                    None => {
+                        // `TRUNCATE` would commit the transaction on MySQL.
+                        sqlx::query(&format!("DELETE FROM {}", self.view_table))
+                            .execute(&mut *transaction)
+                            .await?;
+                        aggregate_ids::<A>(&self.pool).await?
+                    }
+                };
+
+                let total = aggregate_ids.len();
+                let mut events_replayed = 0;
+                for (index, aggregate_id) in aggregate_ids.into_iter().enumerate() {
+                    let replay_error = |reason: String| ProjectionError::Replay {
+                        aggregate_id: aggregate_id.clone(),
+                        reason,
+                    };
+                    let events = self
+                        .event_store
+                        .load_events(&aggregate_id)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    if !events.is_empty() {
+                        let view: V = replayed_view(&events);
+                        let payload = serde_json::to_value(&view).map_err(|err| replay_error(err.to_string()))?;
+                        // A view is at version 1 once the `GenericQuery` first stored it.
+                        sqlx::query(&format!("INSERT INTO {} (payload, version, view_id) VALUES (?, ?, ?)", self.view_table))
+                            .bind(payload)
+                            .bind(1_i64)
+                            .bind(&aggregate_id)
+                            .execute(&mut *transaction)
+                            .await?;
+                    }
+                    events_replayed += events.len();
+                    on_progress(&ProjectionProgress {
+                        aggregate_id,
+                        replayed: index + 1,
+                        total,
+                    });
+                }
+                transaction.commit().await?;
+                Ok(ProjectionRebuildReport {
+                    projection: self.view_table.clone(),
+                    aggregates_replayed: total,
+                    events_replayed,
+                })
+            }
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::sync::Mutex;
+
+    struct StubProjection;
+
+    #[async_trait]
+    impl Projection for StubProjection {
+        fn name(&self) -> &str {
+            "stub_query"
+        }
+
+        async fn rebuild(
+            &self,
+            aggregate_id: Option<&str>,
+            on_progress: &(dyn Fn(&ProjectionProgress) + Send + Sync),
+        ) -> Result<ProjectionRebuildReport, ProjectionError> {
+            let aggregate_id = aggregate_id.unwrap_or("1234").to_string();
+            on_progress(&ProjectionProgress {
+                aggregate_id,
+                replayed: 1,
+                total: 1,
+            });
+            Ok(ProjectionRebuildReport {
+                projection: self.name().to_string(),
+                aggregates_replayed: 1,
+                events_replayed: 3,
+            })
+        }
+    }
+
+    fn rebuilder() -> ProjectionRebuilder {
+        ProjectionRebuilder::new(vec![Arc::new(StubProjection)])
+    }
+
+    #[tokio::test]
+    async fn rebuilds_projection_by_name_and_reports_progress() {
+        let progress = Mutex::new(Vec::new());
+        let report = rebuilder()
+            .rebuild("stub_query", Some("5678"), &|p| {
+                progress.lock().unwrap().push(p.clone())
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(report.events_replayed, 3);
+        assert_eq!(
+            progress.into_inner().unwrap(),
+            vec![ProjectionProgress {
+                aggregate_id: "5678".to_string(),
+                replayed: 1,
+                total: 1,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_unknown_projection() {
+        let result = rebuilder().rebuild("missing_query", None, &|_| {}).await;
+        assert!(matches!(
+            result,
+            Err(ProjectionError::UnknownProjection(name)) if name == "missing_query"
+        ));
+    }
+
+    #[test]
+    fn lists_projection_names() {
+        assert_eq!(rebuilder().names(), vec!["stub_query"]);
+    }
+
+    #[test]
+    fn dates_events_without_a_time_by_when_they_were_committed() {
+        use crate::domain::{BankAccount, BankAccountEvent, Money};
+
+        let event = |sequence: usize, metadata: HashMap<String, String>| EventEnvelope {
+            aggregate_id: "1234".to_string(),
+            sequence,
+            payload: BankAccountEvent::CustomerDepositedMoney {
+                amount: Money::new(100, "USD"),
+                balance: Money::new(100 * sequence as i64, "USD"),
+            },
+            metadata,
+        };
+        let requested_at = HashMap::from([(
+            EVENT_TIME_METADATA.to_string(),
+            "2023-11-01T09:00:00+00:00".to_string(),
+        )]);
+        let mut events: Vec<EventEnvelope<BankAccount>> =
+            vec![event(1, requested_at), event(2, HashMap::new())];
+        let created_at = event_times(vec![
+            (1, "2023-11-01T09:00:01".parse().unwrap()),
+            (2, "2023-11-01T09:05:00".parse().unwrap()),
+        ]);
+
+        fill_event_times(&mut events, &created_at);
+
+        assert_eq!(
+            events[0].metadata[EVENT_TIME_METADATA],
+            "2023-11-01T09:00:00+00:00"
+        );
+        assert_eq!(
+            events[1].metadata[EVENT_TIME_METADATA],
+            "2023-11-01T09:05:00+00:00"
+        );
+    }
+
+    fn account_events() -> Vec<EventEnvelope<crate::domain::BankAccount>> {
+        use crate::domain::{BankAccountEvent, Money};
+
+        let payloads = vec![
+            BankAccountEvent::AccountOpened {
+                account_id: "1234".to_string(),
+                currency: "USD".to_string(),
+                owner_id: Some("5f2b4e1c".to_string()),
+            },
+            BankAccountEvent::CustomerDepositedMoney {
+                amount: Money::new(10000, "USD"),
+                balance: Money::new(10000, "USD"),
+            },
+            BankAccountEvent::CustomerWithdrewCash {
+                amount: Money::new(2500, "USD"),
+                balance: Money::new(7500, "USD"),
+            },
+        ];
+        payloads
+            .into_iter()
+            .enumerate()
+            .map(|(index, payload)| EventEnvelope {
+                aggregate_id: "1234".to_string(),
+                sequence: index + 1,
+                payload,
+                metadata: HashMap::new(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn replays_events_into_a_fresh_view() {
+        use crate::domain::{AccountStatus, Money};
+        use crate::interfaces::bank_account::BankAccountView;
+
+        let view: BankAccountView = replayed_view(&account_events());
+
+        assert_eq!(view.account_id.as_deref(), Some("1234"));
+        assert_eq!(view.owner_id.as_deref(), Some("5f2b4e1c"));
+        assert_eq!(view.status, AccountStatus::Open);
+        assert_eq!(view.balance, Money::new(7500, "USD"));
+        assert_eq!(view.available_balance, Money::new(7500, "USD"));
+        assert_eq!(view.version, 3);
+    }
+
+    #[test]
+    fn replaying_restores_a_view_that_missed_an_event() {
+        use crate::domain::Money;
+        use crate::interfaces::bank_account::BankAccountView;
+
+        let events = account_events();
+        // The view failed to update when the withdrawal was committed.
+        let mut drifted = BankAccountView::default();
+        for event in &events[..2] {
+            drifted.update(event);
+        }
+        assert_eq!(drifted.balance, Money::new(10000, "USD"));
+
+        let replayed: BankAccountView = replayed_view(&events);
+        assert_eq!(replayed.balance, Money::new(7500, "USD"));
+        assert_eq!(replayed.version, 3);
+    }
+}
@@ -0,0 +1,409 @@
+use cqrs_es::persist::{PersistedEventStore, ViewRepository};
+use cqrs_es::{Aggregate, EventStore};
+use serde::Serialize;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use crate::domain::{bank_account_upcasters, BankAccount, Money};
+use crate::infrastructure::projections::{aggregate_ids, ProjectionError, ProjectionRebuilder};
+use crate::infrastructure::DbPool;
+use crate::interfaces::bank_account::{
+    BankAccountView, GeneralLedgerError, GeneralLedgerQuery, ACCOUNT_QUERY_TABLE,
+    LEDGER_POSTINGS_TABLE,
+};
+
+pub const RECONCILIATION_INTERVAL_ENV_VAR: &str = "RECONCILIATION_INTERVAL_SECONDS";
+pub const RECONCILIATION_REPAIR_ENV_VAR: &str = "RECONCILIATION_REPAIR";
+
+/// How long a mismatched account is left before it is compared again, ahead of being repaired.
+const RECOMPARE_DELAY: StdDuration = StdDuration::from_secs(5);
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReconciliationConfigurationError {
+    #[error("invalid {0}: {1}")]
+    InvalidConfiguration(&'static str, String),
+}
+
+/// How often the server reconciles every account, and whether it rebuilds the projections of
+/// the accounts that drifted. Accounts are not reconciled while no interval is set.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReconciliationConfiguration {
+    pub interval: Option<StdDuration>,
+    pub repair: bool,
+}
+
+impl ReconciliationConfiguration {
+    /// Reads the `RECONCILIATION_INTERVAL_SECONDS` and `RECONCILIATION_REPAIR` env vars,
+    /// defaulting to never reconciling and to only reporting mismatches.
+    pub fn from_env() -> Result<Self, ReconciliationConfigurationError> {
+        Self::parse(
+            dotenvy::var(RECONCILIATION_INTERVAL_ENV_VAR)
+                .ok()
+                .as_deref(),
+            dotenvy::var(RECONCILIATION_REPAIR_ENV_VAR).ok().as_deref(),
+        )
+    }
+
+    pub fn parse(
+        interval: Option<&str>,
+        repair: Option<&str>,
+    ) -> Result<Self, ReconciliationConfigurationError> {
+        let mut configuration = Self::default();
+        if let Some(interval) = interval.filter(|value| !value.trim().is_empty()) {
+            let invalid_interval = || {
+                ReconciliationConfigurationError::InvalidConfiguration(
+                    RECONCILIATION_INTERVAL_ENV_VAR,
+                    interval.to_string(),
+                )
+            };
+            let seconds: u64 = interval.trim().parse().map_err(|_| invalid_interval())?;
+            if seconds == 0 {
+                return Err(invalid_interval());
+            }
+            configuration.interval = Some(StdDuration::from_secs(seconds));
+        }
+        if let Some(repair) = repair.filter(|value| !value.trim().is_empty()) {
+            configuration.repair = repair.trim().parse().map_err(|_| {
+                ReconciliationConfigurationError::InvalidConfiguration(
+                    RECONCILIATION_REPAIR_ENV_VAR,
+                    repair.to_string(),
+                )
+            })?;
+        }
+        Ok(configuration)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.interval.is_some()
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ReconciliationError {
+    #[error("failed to replay the events of account {account_id}: {reason}")]
+    Replay { account_id: String, reason: String },
+
+    #[error("failed to load the view of account {account_id}: {reason}")]
+    View { account_id: String, reason: String },
+
+    #[error(transparent)]
+    Ledger(#[from] GeneralLedgerError),
+
+    #[error(transparent)]
+    Projection(#[from] ProjectionError),
+
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// An account whose view or ledger postings disagree with the balance its events leave it at.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BalanceMismatch {
+    pub account_id: String,
+    pub aggregate_balance: Money,
+    /// The balance in the view of the account, `None` if it has no view
+    pub view_balance: Option<Money>,
+    /// The customer deposits of the account in the general ledger
+    pub ledger_balance: Money,
+    pub view_drifted: bool,
+    pub ledger_drifted: bool,
+    /// Whether the projections that drifted were rebuilt, which they only are once the account
+    /// still mismatches when compared again
+    pub repaired: bool,
+}
+
+impl BalanceMismatch {
+    /// Compares the balances of the account, returning `None` when they all agree. An account
+    /// without ledger postings has nothing in customer deposits.
+    pub fn compare(
+        account_id: &str,
+        aggregate_balance: &Money,
+        view_balance: Option<&Money>,
+        ledger_balance: Option<&Money>,
+    ) -> Option<Self> {
+        let ledger_balance = ledger_balance
+            .cloned()
+            .unwrap_or_else(|| Money::zero(&aggregate_balance.currency));
+        let view_drifted = view_balance != Some(aggregate_balance);
+        let ledger_drifted = &ledger_balance != aggregate_balance;
+        if !view_drifted && !ledger_drifted {
+            return None;
+        }
+        Some(Self {
+            account_id: account_id.to_string(),
+            aggregate_balance: aggregate_balance.clone(),
+            view_balance: view_balance.cloned(),
+            ledger_balance,
+            view_drifted,
+            ledger_drifted,
+            repaired: false,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ReconciliationReport {
+    pub accounts_checked: usize,
+    /// Accounts that could not be reconciled, e.g. because their events failed to load
+    pub accounts_failed: usize,
+    pub mismatches: Vec<BalanceMismatch>,
+}
+
+impl ReconciliationReport {
+    fn record_metrics(&self) {
+        let view_mismatches = self
+            .mismatches
+            .iter()
+            .filter(|mismatch| mismatch.view_drifted)
+            .count();
+        let ledger_mismatches = self
+            .mismatches
+            .iter()
+            .filter(|mismatch| mismatch.ledger_drifted)
+            .count();
+        let repaired = self
+            .mismatches
+            .iter()
+            .filter(|mismatch| mismatch.repaired)
+            .count();
+        metrics::gauge!(
+            "reconciliation_accounts_checked",
+            self.accounts_checked as f64
+        );
+        metrics::gauge!(
+            "reconciliation_accounts_failed",
+            self.accounts_failed as f64
+        );
+        metrics::gauge!("reconciliation_view_mismatches", view_mismatches as f64);
+        metrics::gauge!("reconciliation_ledger_mismatches", ledger_mismatches as f64);
+        metrics::counter!("reconciliation_repairs_total", repaired as u64);
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use postgres_es::PostgresEventRepository;
+
+        type ReconciliationEventStore = PersistedEventStore<PostgresEventRepository, BankAccount>;
+
+        fn reconciliation_event_store(pool: DbPool) -> ReconciliationEventStore {
+            PersistedEventStore::new_event_store(PostgresEventRepository::new(pool))
+                .with_upcasters(bank_account_upcasters())
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use mysql_es::MysqlEventRepository;
+
+        type ReconciliationEventStore = PersistedEventStore<MysqlEventRepository, BankAccount>;
+
+        fn reconciliation_event_store(pool: DbPool) -> ReconciliationEventStore {
+            PersistedEventStore::new_event_store(MysqlEventRepository::new(pool))
+                .with_upcasters(bank_account_upcasters())
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+/// Replays every account from the event store and compares the balance it arrives at with the
+/// balance in its view and its customer deposits in the general ledger, catching projections
+/// that silently failed to update. Drifted projections can be rebuilt for the account.
+///
+/// Events committed while an account is being reconciled may be in one balance and not yet in
+/// another, so a single mismatch on a busy account is not necessarily a drift. An account is
+/// only repaired when it is compared again a moment later and still mismatches the same way.
+pub struct Reconciler<R>
+where
+    R: ViewRepository<BankAccountView, BankAccount>,
+{
+    pool: DbPool,
+    event_store: ReconciliationEventStore,
+    view_repository: Arc<R>,
+    general_ledger: GeneralLedgerQuery,
+    projection_rebuilder: Arc<ProjectionRebuilder>,
+}
+
+impl<R> Reconciler<R>
+where
+    R: ViewRepository<BankAccountView, BankAccount>,
+{
+    pub fn new(
+        pool: DbPool,
+        view_repository: Arc<R>,
+        projection_rebuilder: Arc<ProjectionRebuilder>,
+    ) -> Self {
+        Self {
+            event_store: reconciliation_event_store(pool.clone()),
+            general_ledger: GeneralLedgerQuery::new(pool.clone()),
+            pool,
+            view_repository,
+            projection_rebuilder,
+        }
+    }
+
+    /// Reconciles every account at the configured interval, until the process exits.
+    pub async fn run(self, configuration: ReconciliationConfiguration) {
+        let Some(interval) = configuration.interval else {
+            return;
+        };
+        loop {
+            if let Err(err) = self.reconcile(configuration.repair).await {
+                tracing::error!("failed to reconcile the bank accounts: {}", err);
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Reconciles every account, rebuilding the drifted projections of an account if `repair`
+    /// is set. Each mismatch is logged and the totals are recorded as metrics.
+    #[tracing::instrument(skip(self), err)]
+    pub async fn reconcile(
+        &self,
+        repair: bool,
+    ) -> Result<ReconciliationReport, ReconciliationError> {
+        let mut report = ReconciliationReport::default();
+        for account_id in aggregate_ids::<BankAccount>(&self.pool).await? {
+            match self.reconcile_account(&account_id, repair).await {
+                Ok(mismatch) => {
+                    report.accounts_checked += 1;
+                    if let Some(mismatch) = mismatch {
+                        tracing::warn!(
+                            account_id = %mismatch.account_id,
+                            aggregate_balance = %mismatch.aggregate_balance,
+                            view_balance = ?mismatch.view_balance,
+                            ledger_balance = %mismatch.ledger_balance,
+                            repaired = mismatch.repaired,
+                            "balance mismatch"
+                        );
+                        report.mismatches.push(mismatch);
+                    }
+                }
+                Err(err) => {
+                    report.accounts_failed += 1;
+                    tracing::error!("failed to reconcile bank account {}: {}", account_id, err);
+                }
+            }
+        }
+        report.record_metrics();
+        Ok(report)
+    }
+
+    async fn reconcile_account(
+        &self,
+        account_id: &str,
+        repair: bool,
+    ) -> Result<Option<BalanceMismatch>, ReconciliationError> {
+        let (sequence, mismatch) = self.compare(account_id).await?;
+        let Some(mut mismatch) = mismatch else {
+            return Ok(None);
+        };
+        // The projections of an account are updated just after its events are committed, so
+        // only a mismatch that is still there, with no events committed in between, is a drift.
+        if repair {
+            tokio::time::sleep(RECOMPARE_DELAY).await;
+            if self.compare(account_id).await? == (sequence, Some(mismatch.clone())) {
+                if mismatch.view_drifted {
+                    self.rebuild(ACCOUNT_QUERY_TABLE, account_id).await?;
+                }
+                if mismatch.ledger_drifted {
+                    self.rebuild(LEDGER_POSTINGS_TABLE, account_id).await?;
+                }
+                mismatch.repaired = true;
+            }
+        }
+        Ok(Some(mismatch))
+    }
+
+    // Compares the balances of the account, along with the sequence number of the last event
+    // its balance was replayed to.
+    async fn compare(
+        &self,
+        account_id: &str,
+    ) -> Result<(usize, Option<BalanceMismatch>), ReconciliationError> {
+        let events = self
+            .event_store
+            .load_events(account_id)
+            .await
+            .map_err(|err| ReconciliationError::Replay {
+                account_id: account_id.to_string(),
+                reason: err.to_string(),
+            })?;
+        let sequence = events.last().map_or(0, |event| event.sequence);
+        let mut account = BankAccount::default();
+        for event in events {
+            account.apply(event.payload);
+        }
+        let view = self.view_repository.load(account_id).await.map_err(|err| {
+            ReconciliationError::View {
+                account_id: account_id.to_string(),
+                reason: err.to_string(),
+            }
+        })?;
+        let ledger_balance = self.general_ledger.customer_deposits(account_id).await?;
+
+        let mismatch = BalanceMismatch::compare(
+            account_id,
+            account.balance(),
+            view.as_ref().map(|view| &view.balance),
+            ledger_balance.as_ref(),
+        );
+        Ok((sequence, mismatch))
+    }
+
+    // Each projection is cleared and replayed for the account in a single transaction.
+    async fn rebuild(&self, projection: &str, account_id: &str) -> Result<(), ProjectionError> {
+        self.projection_rebuilder
+            .rebuild(projection, Some(account_id), &|_| {})
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn usd(minor_units: i64) -> Money {
+        Money::new(minor_units, "USD")
+    }
+
+    #[test]
+    fn balances_that_agree_are_no_mismatch() {
+        assert_eq!(
+            BalanceMismatch::compare("1234", &usd(1000), Some(&usd(1000)), Some(&usd(1000))),
+            None
+        );
+        // Nothing was posted for an account that never moved money.
+        assert_eq!(
+            BalanceMismatch::compare("1234", &usd(0), Some(&usd(0)), None),
+            None
+        );
+    }
+
+    #[test]
+    fn reports_which_projection_drifted() {
+        let mismatch =
+            BalanceMismatch::compare("1234", &usd(1000), Some(&usd(800)), Some(&usd(1000)))
+                .unwrap();
+        assert!(mismatch.view_drifted);
+        assert!(!mismatch.ledger_drifted);
+
+        let mismatch = BalanceMismatch::compare("1234", &usd(1000), None, None).unwrap();
+        assert_eq!(mismatch.view_balance, None);
+        assert_eq!(mismatch.ledger_balance, usd(0));
+        assert!(mismatch.view_drifted);
+        assert!(mismatch.ledger_drifted);
+    }
+
+    #[test]
+    fn parses_configuration() {
+        let configuration = ReconciliationConfiguration::parse(Some("3600"), Some("true")).unwrap();
+        assert_eq!(configuration.interval, Some(StdDuration::from_secs(3600)));
+        assert!(configuration.repair);
+        assert!(!ReconciliationConfiguration::parse(Some(""), None)
+            .unwrap()
+            .is_enabled());
+        assert!(ReconciliationConfiguration::parse(Some("0"), None).is_err());
+        assert!(ReconciliationConfiguration::parse(None, Some("yes")).is_err());
+    }
+}
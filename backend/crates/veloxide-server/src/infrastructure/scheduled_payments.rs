@@ -0,0 +1,194 @@
+use chrono::Duration;
+use cqrs_es::EventStore;
+use std::time::Duration as StdDuration;
+
+use crate::application::ScheduledPaymentScheduler;
+use crate::domain::ScheduledPayment;
+use crate::interfaces::scheduled_payment::{ScheduledPaymentQuery, ScheduledPaymentQueryError};
+
+pub const SCHEDULED_PAYMENT_MAX_ATTEMPTS_ENV_VAR: &str = "SCHEDULED_PAYMENT_MAX_ATTEMPTS";
+pub const SCHEDULED_PAYMENT_INITIAL_BACKOFF_ENV_VAR: &str =
+    "SCHEDULED_PAYMENT_INITIAL_BACKOFF_SECONDS";
+
+pub const DEFAULT_SCHEDULED_PAYMENT_MAX_ATTEMPTS: u32 = 5;
+pub const DEFAULT_SCHEDULED_PAYMENT_INITIAL_BACKOFF_SECONDS: i64 = 60;
+
+/// The longest a failed run waits before it is retried, however often it failed.
+pub const MAX_SCHEDULED_PAYMENT_BACKOFF_SECONDS: i64 = 60 * 60;
+
+/// How often due payments are looked for.
+const SCHEDULED_PAYMENT_POLL_INTERVAL: StdDuration = StdDuration::from_secs(5);
+
+/// How many due payments are run at a time.
+const SCHEDULED_PAYMENT_BATCH_SIZE: usize = 50;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ScheduledPaymentConfigurationError {
+    #[error("invalid {0}: {1}")]
+    InvalidConfiguration(&'static str, String),
+}
+
+/// How often a run that failed for a reason that may pass, such as a conflicting command or an
+/// unavailable database, is attempted and how long to wait in between. The wait doubles after
+/// every failed attempt, up to `MAX_SCHEDULED_PAYMENT_BACKOFF_SECONDS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduledPaymentConfiguration {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+}
+
+impl Default for ScheduledPaymentConfiguration {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_SCHEDULED_PAYMENT_MAX_ATTEMPTS,
+            initial_backoff: Duration::seconds(DEFAULT_SCHEDULED_PAYMENT_INITIAL_BACKOFF_SECONDS),
+        }
+    }
+}
+
+impl ScheduledPaymentConfiguration {
+    /// Reads the `SCHEDULED_PAYMENT_MAX_ATTEMPTS` and `SCHEDULED_PAYMENT_INITIAL_BACKOFF_SECONDS`
+    /// env vars, defaulting to 5 attempts starting a minute apart.
+    pub fn from_env() -> Result<Self, ScheduledPaymentConfigurationError> {
+        Self::parse(
+            dotenvy::var(SCHEDULED_PAYMENT_MAX_ATTEMPTS_ENV_VAR)
+                .ok()
+                .as_deref(),
+            dotenvy::var(SCHEDULED_PAYMENT_INITIAL_BACKOFF_ENV_VAR)
+                .ok()
+                .as_deref(),
+        )
+    }
+
+    pub fn parse(
+        max_attempts: Option<&str>,
+        initial_backoff: Option<&str>,
+    ) -> Result<Self, ScheduledPaymentConfigurationError> {
+        let mut configuration = Self::default();
+        if let Some(max_attempts) = max_attempts.filter(|value| !value.trim().is_empty()) {
+            configuration.max_attempts =
+                parse_positive(SCHEDULED_PAYMENT_MAX_ATTEMPTS_ENV_VAR, max_attempts)?;
+        }
+        if let Some(initial_backoff) = initial_backoff.filter(|value| !value.trim().is_empty()) {
+            configuration.initial_backoff = Duration::seconds(parse_positive(
+                SCHEDULED_PAYMENT_INITIAL_BACKOFF_ENV_VAR,
+                initial_backoff,
+            )?);
+        }
+        Ok(configuration)
+    }
+
+    /// How long to wait before retrying a run that failed `attempts` times, or `None` once it
+    /// ran out of attempts.
+    pub fn backoff(&self, attempts: u32) -> Option<Duration> {
+        if attempts == 0 || attempts >= self.max_attempts {
+            return None;
+        }
+        let max_backoff = Duration::seconds(MAX_SCHEDULED_PAYMENT_BACKOFF_SECONDS);
+        let backoff = 2_i32
+            .checked_pow(attempts - 1)
+            .and_then(|factor| self.initial_backoff.checked_mul(factor))
+            .unwrap_or(max_backoff);
+        Some(backoff.min(max_backoff))
+    }
+}
+
+fn parse_positive<T: std::str::FromStr + PartialOrd + Default>(
+    name: &'static str,
+    value: &str,
+) -> Result<T, ScheduledPaymentConfigurationError> {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|value| *value > T::default())
+        .ok_or_else(|| {
+            ScheduledPaymentConfigurationError::InvalidConfiguration(name, value.to_string())
+        })
+}
+
+/// Looks for due payments and has the scheduler run them.
+///
+/// Run the worker on a single instance. Runs are keyed so that a payment is not made twice for
+/// the same run, but workers on several instances would still race to record its outcome.
+pub struct ScheduledPaymentWorker<PaymentStore>
+where
+    PaymentStore: EventStore<ScheduledPayment>,
+{
+    query: ScheduledPaymentQuery,
+    scheduler: ScheduledPaymentScheduler<PaymentStore>,
+}
+
+impl<PaymentStore> ScheduledPaymentWorker<PaymentStore>
+where
+    PaymentStore: EventStore<ScheduledPayment>,
+{
+    pub fn new(
+        query: ScheduledPaymentQuery,
+        scheduler: ScheduledPaymentScheduler<PaymentStore>,
+    ) -> Self {
+        Self { query, scheduler }
+    }
+
+    /// Runs payments until the process exits.
+    pub async fn run(self) {
+        loop {
+            match self.run_due().await {
+                // A full batch likely means more payments are due.
+                Ok(ran) if ran == SCHEDULED_PAYMENT_BATCH_SIZE => continue,
+                Ok(_) => {}
+                Err(err) => tracing::error!("failed to run scheduled payments: {}", err),
+            }
+            tokio::time::sleep(SCHEDULED_PAYMENT_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Runs the payments that are due, returning how many were run.
+    #[tracing::instrument(skip(self), err)]
+    pub async fn run_due(&self) -> Result<usize, ScheduledPaymentQueryError> {
+        let payments = self
+            .query
+            .due(self.scheduler.now(), SCHEDULED_PAYMENT_BATCH_SIZE)
+            .await?;
+        for payment in &payments {
+            self.scheduler.run(payment).await;
+        }
+        Ok(payments.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn backoff_doubles_until_attempts_run_out() {
+        let configuration = ScheduledPaymentConfiguration::default();
+        assert_eq!(configuration.backoff(1), Some(Duration::seconds(60)));
+        assert_eq!(configuration.backoff(3), Some(Duration::seconds(240)));
+        assert_eq!(
+            configuration.backoff(DEFAULT_SCHEDULED_PAYMENT_MAX_ATTEMPTS),
+            None
+        );
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let configuration = ScheduledPaymentConfiguration::parse(Some("40"), Some("600")).unwrap();
+        assert_eq!(
+            configuration.backoff(30),
+            Some(Duration::seconds(MAX_SCHEDULED_PAYMENT_BACKOFF_SECONDS))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_configuration() {
+        assert!(ScheduledPaymentConfiguration::parse(Some("0"), None).is_err());
+        assert!(ScheduledPaymentConfiguration::parse(None, Some("soon")).is_err());
+        assert_eq!(
+            ScheduledPaymentConfiguration::parse(Some(""), None).unwrap(),
+            ScheduledPaymentConfiguration::default()
+        );
+    }
+}
@@ -0,0 +1,266 @@
+use cqrs_es::persist::{EventUpcaster, PersistedEventRepository, PersistedEventStore};
+use cqrs_es::{Aggregate, EventStore};
+
+use cfg_if::cfg_if;
+
+pub const SNAPSHOT_SIZE_ENV_VAR: &str = "BANK_ACCOUNT_SNAPSHOT_SIZE";
+
+#[derive(thiserror::Error, Debug)]
+pub enum SnapshotError {
+    #[error("invalid snapshot size: {0}")]
+    InvalidConfiguration(String),
+
+    #[error("failed to replay aggregate {aggregate_id}: {reason}")]
+    Replay {
+        aggregate_id: String,
+        reason: String,
+    },
+
+    #[error(transparent)]
+    Serialization(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// How often an aggregate is snapshotted. Without a snapshot size every command replays the
+/// full event history of the aggregate, with one a snapshot is taken every `snapshot_size`
+/// events and only the events committed since are replayed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotConfiguration {
+    pub snapshot_size: Option<usize>,
+}
+
+impl SnapshotConfiguration {
+    /// Reads the snapshot size from the `BANK_ACCOUNT_SNAPSHOT_SIZE` env var, where unset or `0`
+    /// disables snapshotting.
+    pub fn from_env() -> Result<Self, SnapshotError> {
+        match dotenvy::var(SNAPSHOT_SIZE_ENV_VAR) {
+            Ok(snapshot_size) => Self::parse(&snapshot_size),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn parse(snapshot_size: &str) -> Result<Self, SnapshotError> {
+        let snapshot_size: usize = snapshot_size
+            .trim()
+            .parse()
+            .map_err(|_| SnapshotError::InvalidConfiguration(snapshot_size.to_string()))?;
+        Ok(Self {
+            snapshot_size: (snapshot_size > 0).then_some(snapshot_size),
+        })
+    }
+
+    pub fn event_store<R, A>(&self, repo: R) -> PersistedEventStore<R, A>
+    where
+        R: PersistedEventRepository,
+        A: Aggregate + Send + Sync,
+    {
+        match self.snapshot_size {
+            Some(snapshot_size) => PersistedEventStore::new_snapshot_store(repo, snapshot_size),
+            None => PersistedEventStore::new_event_store(repo),
+        }
+    }
+
+    // Mirrors how the event store numbers snapshots, so that the next snapshot it takes
+    // after a rebuild replaces the rebuilt one rather than conflicting with it.
+    fn snapshot_number(&self, last_sequence: usize) -> Option<usize> {
+        let snapshot_size = self.snapshot_size?;
+        let snapshot_number = last_sequence / snapshot_size;
+        (snapshot_number > 0).then_some(snapshot_number)
+    }
+}
+
+cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use postgres_es::PostgresEventRepository;
+        use sqlx::{Pool, Postgres};
+
+        /// Replaces the snapshots of an aggregate type with ones taken from the full event
+        /// history. Needed when snapshotting is first enabled or its size changes, and whenever
+        /// the shape of the aggregate changes so that old snapshots no longer deserialize.
+        /// Commands committed while a rebuild runs may be snapshotted twice, so run it while
+        /// the server is stopped.
+        pub struct SnapshotRebuilder {
+            pool: Pool<Postgres>,
+            configuration: SnapshotConfiguration,
+        }
+
+        impl SnapshotRebuilder {
+            pub fn new(pool: Pool<Postgres>, configuration: SnapshotConfiguration) -> Self {
+                Self { pool, configuration }
+            }
+
+            /// Returns the number of snapshots taken. With snapshotting disabled the existing
+            /// snapshots are only removed.
+            #[tracing::instrument(skip(self, upcasters), err)]
+            pub async fn rebuild<A>(
+                &self,
+                upcasters: Vec<Box<dyn EventUpcaster>>,
+            ) -> Result<usize, SnapshotError>
+            where
+                A: Aggregate + Send + Sync,
+            {
+                let aggregate_type = A::aggregate_type();
+                let event_store: PersistedEventStore<PostgresEventRepository, A> =
+                    PersistedEventStore::new_event_store(PostgresEventRepository::new(self.pool.clone()))
+                        .with_upcasters(upcasters);
+                let aggregate_ids: Vec<String> =
+                    sqlx::query_scalar("SELECT DISTINCT aggregate_id FROM events WHERE aggregate_type = $1")
+                        .bind(&aggregate_type)
+                        .fetch_all(&self.pool)
+                        .await?;
+
+                let mut transaction = self.pool.begin().await?;
+                sqlx::query("DELETE FROM snapshots WHERE aggregate_type = $1")
+                    .bind(&aggregate_type)
+                    .execute(&mut *transaction)
+                    .await?;
+
+                let mut rebuilt = 0;
+                for aggregate_id in aggregate_ids {
+                    let context = event_store.load_aggregate(&aggregate_id).await.map_err(|err| {
+                        SnapshotError::Replay {
+                            aggregate_id: aggregate_id.clone(),
+                            reason: err.to_string(),
+                        }
+                    })?;
+                    let Some(snapshot_number) = self.configuration.snapshot_number(context.current_sequence) else {
+                        continue;
+                    };
+                    sqlx::query(
+                        "INSERT INTO snapshots (aggregate_type, aggregate_id, last_sequence, current_snapshot, payload) VALUES ($1, $2, $3, $4, $5)",
+                    )
+                    .bind(&aggregate_type)
+                    .bind(&aggregate_id)
+                    .bind(context.current_sequence as i64)
+                    .bind(snapshot_number as i64)
+                    .bind(serde_json::to_value(&context.aggregate)?)
+                    .execute(&mut *transaction)
+                    .await?;
+                    rebuilt += 1;
+                }
+                transaction.commit().await?;
+
+                tracing::info!("rebuilt {} {} snapshots", rebuilt, aggregate_type);
+                Ok(rebuilt)
+            }
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use mysql_es::MysqlEventRepository;
+        use sqlx::{MySql, Pool};
+
+        /// Replaces the snapshots of an aggregate type with ones taken from the full event
+        /// history. Needed when snapshotting is first enabled or its size changes, and whenever
+        /// the shape of the aggregate changes so that old snapshots no longer deserialize.
+        /// Commands committed while a rebuild runs may be snapshotted twice, so run it while
+        /// the server is stopped.
+        pub struct SnapshotRebuilder {
+            pool: Pool<MySql>,
+            configuration: SnapshotConfiguration,
+        }
+
+        impl SnapshotRebuilder {
+            pub fn new(pool: Pool<MySql>, configuration: SnapshotConfiguration) -> Self {
+                Self { pool, configuration }
+            }
+
+            /// Returns the number of snapshots taken. With snapshotting disabled the existing
+            /// snapshots are only removed.
+            #[tracing::instrument(skip(self, upcasters), err)]
+            pub async fn rebuild<A>(
+                &self,
+                upcasters: Vec<Box<dyn EventUpcaster>>,
+            ) -> Result<usize, SnapshotError>
+            where
+                A: Aggregate + Send + Sync,
+            {
+                let aggregate_type = A::aggregate_type();
+                let event_store: PersistedEventStore<MysqlEventRepository, A> =
+                    PersistedEventStore::new_event_store(MysqlEventRepository::new(self.pool.clone()))
+                        .with_upcasters(upcasters);
+                let aggregate_ids: Vec<String> =
+                    sqlx::query_scalar("SELECT DISTINCT aggregate_id FROM events WHERE aggregate_type = ?")
+                        .bind(&aggregate_type)
+                        .fetch_all(&self.pool)
+                        .await?;
+
+                let mut transaction = self.pool.begin().await?;
+                sqlx::query("DELETE FROM snapshots WHERE aggregate_type = ?")
+                    .bind(&aggregate_type)
+                    .execute(&mut *transaction)
+                    .await?;
+
+                let mut rebuilt = 0;
+                for aggregate_id in aggregate_ids {
+                    let context = event_store.load_aggregate(&aggregate_id).await.map_err(|err| {
+                        SnapshotError::Replay {
+                            aggregate_id: aggregate_id.clone(),
+                            reason: err.to_string(),
+                        }
+                    })?;
+                    let Some(snapshot_number) = self.configuration.snapshot_number(context.current_sequence) else {
+                        continue;
+                    };
+                    sqlx::query(
+                        "INSERT INTO snapshots (aggregate_type, aggregate_id, last_sequence, current_snapshot, payload) VALUES (?, ?, ?, ?, ?)",
+                    )
+                    .bind(&aggregate_type)
+                    .bind(&aggregate_id)
+                    .bind(context.current_sequence as i64)
+                    .bind(snapshot_number as i64)
+                    .bind(serde_json::to_value(&context.aggregate)?)
+                    .execute(&mut *transaction)
+                    .await?;
+                    rebuilt += 1;
+                }
+                transaction.commit().await?;
+
+                tracing::info!("rebuilt {} {} snapshots", rebuilt, aggregate_type);
+                Ok(rebuilt)
+            }
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_snapshot_size() {
+        let configuration = SnapshotConfiguration::parse("100").unwrap();
+        assert_eq!(configuration.snapshot_size, Some(100));
+    }
+
+    #[test]
+    fn zero_snapshot_size_disables_snapshotting() {
+        let configuration = SnapshotConfiguration::parse("0").unwrap();
+        assert_eq!(configuration, SnapshotConfiguration::default());
+    }
+
+    #[test]
+    fn rejects_invalid_snapshot_size() {
+        let result = SnapshotConfiguration::parse("often");
+        assert!(matches!(
+            result,
+            Err(SnapshotError::InvalidConfiguration(size)) if size == "often"
+        ));
+    }
+
+    #[test]
+    fn numbers_snapshots_by_completed_multiples_of_the_snapshot_size() {
+        let configuration = SnapshotConfiguration::parse("100").unwrap();
+        assert_eq!(configuration.snapshot_number(99), None);
+        assert_eq!(configuration.snapshot_number(100), Some(1));
+        assert_eq!(configuration.snapshot_number(250), Some(2));
+    }
+
+    #[test]
+    fn takes_no_snapshots_when_disabled() {
+        assert_eq!(SnapshotConfiguration::default().snapshot_number(1000), None);
+    }
+}
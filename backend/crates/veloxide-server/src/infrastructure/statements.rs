@@ -0,0 +1,242 @@
+use std::fmt::Write as _;
+
+use crate::domain::Money;
+use crate::interfaces::bank_account::{AccountStatement, AccountStatementLine};
+
+/// How many lines of text fit on a page of a PDF statement.
+const PDF_LINES_PER_PAGE: usize = 60;
+
+/// The header row of a CSV statement.
+pub const STATEMENT_CSV_HEADER: &str = "date,type,description,amount,balance,currency";
+
+/// Renders the statement as CSV, one row per transaction between an opening and a closing
+/// balance row. Amounts are in major units without the currency, which has a column of its own.
+pub fn render_statement_csv(statement: &AccountStatement) -> String {
+    let mut csv = format!("{}\r\n", STATEMENT_CSV_HEADER);
+    let mut row = |fields: [&str; 6]| {
+        let fields: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    };
+    row([
+        &statement.starts_at,
+        "OpeningBalance",
+        &format!("opening balance for {}", statement.period),
+        "",
+        &major_units(&statement.opening_balance),
+        &statement.opening_balance.currency,
+    ]);
+    for line in &statement.transactions {
+        row([
+            &line.occurred_at,
+            &line.transaction_type.to_string(),
+            &line.description,
+            &major_units(&line.amount),
+            &major_units(&line.balance),
+            &line.balance.currency,
+        ]);
+    }
+    row([
+        &statement.ends_at,
+        "ClosingBalance",
+        &format!("closing balance for {}", statement.period),
+        "",
+        &major_units(&statement.closing_balance),
+        &statement.closing_balance.currency,
+    ]);
+    csv
+}
+
+/// Quotes a field that holds a comma, quote or line break, doubling any quotes in it. Text that
+/// a spreadsheet would take for a formula, such as a description starting with `=`, is prefixed
+/// with `'` so that it is shown as text instead. Amounts are left alone, negative ones included.
+fn csv_field(field: &str) -> String {
+    let field =
+        if field.starts_with(['=', '+', '-', '@', '\t', '\r']) && field.parse::<f64>().is_err() {
+            format!("'{}", field)
+        } else {
+            field.to_string()
+        };
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field
+    }
+}
+
+/// The amount without its currency, e.g. "-10.50".
+fn major_units(money: &Money) -> String {
+    let amount = money.to_string();
+    match amount.split_once(' ') {
+        Some((major_units, _)) => major_units.to_string(),
+        None => amount,
+    }
+}
+
+/// Renders the statement as a plain, paginated PDF in a monospaced font.
+pub fn render_statement_pdf(statement: &AccountStatement) -> Vec<u8> {
+    let lines = statement_text(statement);
+    let pages: Vec<&[String]> = lines.chunks(PDF_LINES_PER_PAGE).collect();
+
+    // Objects 1 to 3 are the catalog, the page tree and the font, followed by a page and its
+    // contents for every page.
+    let page_ids: Vec<usize> = (0..pages.len()).map(|page| 4 + page * 2).collect();
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            page_ids
+                .iter()
+                .map(|id| format!("{} 0 R", id))
+                .collect::<Vec<_>>()
+                .join(" "),
+            pages.len()
+        ),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier >>".to_string(),
+    ];
+    for (page, page_id) in pages.iter().zip(&page_ids) {
+        let mut content = String::from("BT\n/F1 9 Tf\n11 TL\n40 800 Td\n");
+        for line in page.iter() {
+            let _ = writeln!(content, "({}) Tj T*", pdf_text(line));
+        }
+        content.push_str("ET");
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] \
+             /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            page_id + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}\nendstream",
+            content.len(),
+            content
+        ));
+    }
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        let _ = write!(pdf, "{} 0 obj\n{}\nendobj\n", index + 1, object);
+    }
+    let xref_offset = pdf.len();
+    let _ = write!(pdf, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = write!(pdf, "{:010} 00000 n \n", offset);
+    }
+    let _ = write!(
+        pdf,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref_offset
+    );
+    pdf.into_bytes()
+}
+
+fn statement_text(statement: &AccountStatement) -> Vec<String> {
+    let mut lines = vec![
+        format!("Statement of account {}", statement.account_id),
+        format!(
+            "Period {} ({} to {})",
+            statement.period, statement.starts_at, statement.ends_at
+        ),
+        String::new(),
+        format!("Opening balance {:>20}", statement.opening_balance),
+        String::new(),
+    ];
+    lines.extend(statement.transactions.iter().map(statement_text_line));
+    lines.push(String::new());
+    lines.push(format!("Closing balance {:>20}", statement.closing_balance));
+    lines
+}
+
+fn statement_text_line(line: &AccountStatementLine) -> String {
+    let date = line.occurred_at.get(..10).unwrap_or(&line.occurred_at);
+    let description: String = line.description.chars().take(34).collect();
+    format!(
+        "{} {:<34} {:>16} {:>16}",
+        date,
+        description,
+        major_units(&line.amount),
+        major_units(&line.balance)
+    )
+}
+
+/// Escapes text for a PDF string, replacing what the standard fonts cannot show.
+fn pdf_text(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\\' | '(' | ')' => format!("\\{}", c),
+            ' '..='~' => c.to_string(),
+            _ => "?".to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interfaces::bank_account::AccountTransactionType;
+    use pretty_assertions::assert_eq;
+
+    fn statement(transactions: usize) -> AccountStatement {
+        AccountStatement {
+            account_id: "1234".to_string(),
+            period: "2023-11".to_string(),
+            starts_at: "2023-11-01T00:00:00+00:00".to_string(),
+            ends_at: "2023-12-01T00:00:00+00:00".to_string(),
+            opening_balance: Money::new(10000, "USD"),
+            transactions: (0..transactions)
+                .map(|_| AccountStatementLine {
+                    occurred_at: "2023-11-03T12:00:00+00:00".to_string(),
+                    transaction_type: AccountTransactionType::Withdrawal,
+                    description: "check \"42\", (rent)".to_string(),
+                    amount: Money::new(-2550, "USD"),
+                    balance: Money::new(7450, "USD"),
+                })
+                .collect(),
+            closing_balance: Money::new(7450, "USD"),
+        }
+    }
+
+    #[test]
+    fn csv_statement_has_opening_and_closing_rows() {
+        let csv = render_statement_csv(&statement(1));
+        assert_eq!(
+            csv.lines().collect::<Vec<_>>(),
+            vec![
+                STATEMENT_CSV_HEADER,
+                "2023-11-01T00:00:00+00:00,OpeningBalance,opening balance for 2023-11,,100.00,USD",
+                "2023-11-03T12:00:00+00:00,Withdrawal,\"check \"\"42\"\", (rent)\",-25.50,74.50,USD",
+                "2023-12-01T00:00:00+00:00,ClosingBalance,closing balance for 2023-11,,74.50,USD",
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_fields_are_never_read_as_formulas() {
+        assert_eq!(
+            csv_field("=HYPERLINK(\"http://evil\")"),
+            "\"'=HYPERLINK(\"\"http://evil\"\")\""
+        );
+        assert_eq!(csv_field("+1 refund"), "'+1 refund");
+        assert_eq!(csv_field("@SUM(A1)"), "'@SUM(A1)");
+        assert_eq!(csv_field("-25.50"), "-25.50");
+    }
+
+    #[test]
+    fn pdf_statement_is_paginated() {
+        let pdf = String::from_utf8(render_statement_pdf(&statement(100))).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/Count 2"));
+        assert!(pdf.contains("(check \"42\", \\(rent\\))"));
+
+        let xref_offset: usize = pdf
+            .rsplit("startxref\n")
+            .next()
+            .and_then(|tail| tail.lines().next())
+            .and_then(|offset| offset.parse().ok())
+            .unwrap();
+        assert!(pdf[xref_offset..].starts_with("xref\n0 8\n"));
+    }
+}
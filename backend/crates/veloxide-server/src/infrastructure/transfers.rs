@@ -0,0 +1,97 @@
+use cqrs_es::persist::ViewRepository;
+use cqrs_es::EventStore;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use crate::application::TransferProcessManager;
+use crate::domain::{Transfer, TransferStatus};
+use crate::infrastructure::projections::aggregate_ids;
+use crate::infrastructure::DbPool;
+use crate::interfaces::bank_account::BankAccountEventStore;
+use crate::interfaces::transfer::TransferView;
+
+/// How often transfers that stopped part way are looked for.
+const PENDING_TRANSFER_POLL_INTERVAL: StdDuration = StdDuration::from_secs(60);
+
+/// Resumes the transfers whose saga stopped part way, such as when the server restarted between
+/// debiting the source and crediting the destination, so that every transfer is eventually
+/// completed, failed or compensated.
+///
+/// A transfer is only resumed once it was pending at the same step on the previous poll as
+/// well, so that the job keeps out of the way of the transfers still in flight. Run the job on a
+/// single instance; the accounts never move the money of a transfer twice, but jobs on several
+/// instances would race to resume it.
+pub struct PendingTransferJob<TransferStore, R>
+where
+    TransferStore: EventStore<Transfer>,
+    R: ViewRepository<TransferView, Transfer>,
+{
+    pool: DbPool,
+    process_manager: TransferProcessManager<BankAccountEventStore, TransferStore>,
+    view_repository: Arc<R>,
+}
+
+impl<TransferStore, R> PendingTransferJob<TransferStore, R>
+where
+    TransferStore: EventStore<Transfer>,
+    R: ViewRepository<TransferView, Transfer>,
+{
+    pub fn new(
+        pool: DbPool,
+        process_manager: TransferProcessManager<BankAccountEventStore, TransferStore>,
+        view_repository: Arc<R>,
+    ) -> Self {
+        Self {
+            pool,
+            process_manager,
+            view_repository,
+        }
+    }
+
+    /// Resumes pending transfers until the process exits.
+    pub async fn run(self) {
+        let mut pending = HashMap::new();
+        loop {
+            match self.resume(&pending).await {
+                Ok(still_pending) => pending = still_pending,
+                Err(err) => tracing::error!("failed to resume pending transfers: {}", err),
+            }
+            tokio::time::sleep(PENDING_TRANSFER_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Resumes the transfers that are at the step `previously_pending` has them at, returning
+    /// the step every pending transfer was found at.
+    #[tracing::instrument(skip(self, previously_pending), err)]
+    pub async fn resume(
+        &self,
+        previously_pending: &HashMap<String, TransferStatus>,
+    ) -> Result<HashMap<String, TransferStatus>, sqlx::Error> {
+        let mut pending = HashMap::new();
+        for transfer_id in aggregate_ids::<Transfer>(&self.pool).await? {
+            let view = match self.view_repository.load(&transfer_id).await {
+                Ok(Some(view)) => view,
+                Ok(None) => continue,
+                Err(err) => {
+                    tracing::error!("failed to load transfer {}: {}", transfer_id, err);
+                    continue;
+                }
+            };
+            if !matches!(
+                view.status,
+                TransferStatus::Started
+                    | TransferStatus::SourceDebited
+                    | TransferStatus::Compensating
+            ) {
+                continue;
+            }
+            if previously_pending.get(&transfer_id) == Some(&view.status) {
+                tracing::warn!("resuming transfer {} at {}", transfer_id, view.status);
+                self.process_manager.resume(&view).await;
+            }
+            pending.insert(transfer_id, view.status);
+        }
+        Ok(pending)
+    }
+}
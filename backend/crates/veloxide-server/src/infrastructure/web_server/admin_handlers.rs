@@ -0,0 +1,145 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    response::Response,
+    Extension, Json,
+};
+use cqrs_es::AggregateError;
+use serde::Deserialize;
+use tracing::instrument;
+use utoipa::IntoParams;
+
+use crate::domain::{
+    BankAccount, BankAccountAssignOwnerCommandData, BankAccountCommand, BankAccountError,
+};
+use crate::infrastructure::middleware::MetadataExtension;
+use crate::infrastructure::outbox::OutboxCqrs;
+use crate::infrastructure::projections::{
+    ProjectionError, ProjectionRebuildReport, ProjectionRebuilder,
+};
+use crate::interfaces::{GeneralLedgerQuery, TrialBalance};
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct RebuildProjectionParams {
+    /// Only rebuild the view of this aggregate
+    pub aggregate_id: Option<String>,
+}
+
+// Clears a view table and replays the events it is projected from, responding once the
+// rebuild has finished. Progress is logged as each aggregate is replayed.
+#[utoipa::path(
+    post,
+    tag = "Admin",
+    path = "/api/admin/projections/{name}/rebuild",
+    params(
+        ("name" = String, Path, description = "The view table to rebuild, e.g. account_query"),
+        RebuildProjectionParams
+    ),
+    responses(
+        (status = 200, description = "Projection rebuilt", body = ProjectionRebuildReport),
+        (status = 404, description = "Unknown projection"),
+        (status = 500, description = "Rebuild failed", body = String)
+    )
+)]
+#[instrument(skip(projection_rebuilder))]
+pub async fn rebuild_projection_handler(
+    Path(name): Path<String>,
+    Query(params): Query<RebuildProjectionParams>,
+    Extension(projection_rebuilder): Extension<Arc<ProjectionRebuilder>>,
+) -> Response {
+    let result: Result<ProjectionRebuildReport, ProjectionError> = projection_rebuilder
+        .rebuild(&name, params.aggregate_id.as_deref(), &|progress| {
+            tracing::info!(
+                "rebuilding {}: replayed {}/{} ({})",
+                name,
+                progress.replayed,
+                progress.total,
+                progress.aggregate_id
+            )
+        })
+        .await;
+    match result {
+        Ok(report) => (StatusCode::OK, Json(report)).into_response(),
+        Err(err @ ProjectionError::UnknownProjection(_)) => {
+            (StatusCode::NOT_FOUND, err.to_string()).into_response()
+        }
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+// Sums the postings of every ledger account. `balanced` is false if the debits and credits of a
+// currency differ, which the ledger should never allow.
+#[utoipa::path(
+    get,
+    tag = "Admin",
+    path = "/api/admin/ledger/trial-balance",
+    responses(
+        (status = 200, description = "Trial balance of the general ledger", body = TrialBalance),
+        (status = 500, description = "Failed to read the ledger", body = String)
+    )
+)]
+#[instrument(skip(general_ledger))]
+pub async fn trial_balance_handler(
+    Extension(general_ledger): Extension<GeneralLedgerQuery>,
+) -> Response {
+    match general_ledger.trial_balance().await {
+        Ok(trial_balance) => {
+            if !trial_balance.balanced {
+                tracing::error!(
+                    "the general ledger does not balance: {:?}",
+                    trial_balance.totals
+                );
+            }
+            (StatusCode::OK, Json(trial_balance)).into_response()
+        }
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
+
+// Records the owner of a bank account opened before accounts had owners, which no user can
+// access until then. Issued through the CQRS framework directly, as no user has a role on the
+// account to issue it with.
+#[utoipa::path(
+    post,
+    tag = "Admin",
+    path = "/api/admin/bank-accounts/{id}/owner",
+    params(
+        ("id" = String, Path, description = "Bank account ID")
+    ),
+    request_body(content = BankAccountAssignOwnerCommandData, description = "The user that owns the account", content_type = "application/json"),
+    responses(
+        (status = 204, description = "Owner assigned"),
+        (status = 400, description = "No owner given", body = String),
+        (status = 404, description = "Bank account not found", body = String),
+        (status = 409, description = "The account already has an owner", body = String),
+        (status = 500, description = "Failed to assign the owner", body = String)
+    )
+)]
+#[instrument(skip(bank_account_cqrs))]
+pub async fn assign_owner_handler(
+    Path(id): Path<String>,
+    Extension(bank_account_cqrs): Extension<Arc<OutboxCqrs<BankAccount>>>,
+    MetadataExtension(metadata): MetadataExtension,
+    Json(command): Json<BankAccountAssignOwnerCommandData>,
+) -> Response {
+    if command.owner_id.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "owner_id is required").into_response();
+    }
+    match bank_account_cqrs
+        .execute_with_metadata(&id, BankAccountCommand::AssignOwner(command), metadata)
+        .await
+    {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(AggregateError::UserError(err @ BankAccountError::AccountNotOpen)) => {
+            (StatusCode::NOT_FOUND, err.to_string()).into_response()
+        }
+        Err(AggregateError::UserError(err)) => {
+            (StatusCode::CONFLICT, err.to_string()).into_response()
+        }
+        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response(),
+    }
+}
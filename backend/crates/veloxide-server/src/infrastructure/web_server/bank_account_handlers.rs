@@ -1,22 +1,28 @@
-use cqrs_es::persist::ViewRepository;
-
-use postgres_es::{PostgresCqrs, PostgresViewRepository};
-
+use crate::application::{Actor, BankAccountApplicationService, BankAccountServiceError};
 use crate::domain::bank_account::*;
-use crate::infrastructure::middleware::MetadataExtension;
+use crate::infrastructure::grpc::auth_grpc_service::UserView;
+use crate::infrastructure::middleware::{
+    idempotency_error_status, version_etag, ExpectedVersionExtension, IdempotencyKeyExtension,
+    MetadataExtension,
+};
+use crate::infrastructure::statements::{render_statement_csv, render_statement_pdf};
 use std::sync::Arc;
 
 use axum::{
-    extract::Path, http::StatusCode, response::IntoResponse, response::Response, Extension, Json,
+    extract::{Path, Query},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    response::Response,
+    Extension, Json,
 };
 
+use serde::Deserialize;
 use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
 
 pub use crate::interfaces::bank_account::*;
 
-cfg_if::cfg_if! {
-    if #[cfg(feature = "postgres")] {
-        // Serves as our query endpoint to respond with the materialized `BankAccountView`
+// Serves as our query endpoint to respond with the materialized `BankAccountView`
 // for the requested account.
 #[utoipa::path(
     get,
@@ -26,108 +32,265 @@ cfg_if::cfg_if! {
         ("id" = i32, Path, description = "Bank account ID")
     ),
     responses(
-        (status = 200, description = "Get bank account details", body = [BankAccountView])
+        (status = 200, description = "Get bank account details", body = [BankAccountView],
+            headers(("ETag" = String, description = "The version of the account, to pass as If-Match to a command"))),
+        (status = 403, description = "The user has no access to the account"),
+        (status = 404, description = "Bank account not found")
     )
   )]
-  #[instrument(skip(view_repo))]
-  pub async fn query_handler(
-      Path(id): Path<String>,
-      Extension(view_repo): Extension<Arc<PostgresViewRepository<BankAccountView, BankAccount>>>,
-  ) -> Response {
-      let view = match view_repo.load(&id).await {
-          Ok(view) => view,
-          Err(err) => {
-              return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
-          }
-      };
-      match view {
-          None => StatusCode::NOT_FOUND.into_response(),
-          Some(account_view) => (StatusCode::OK, Json(account_view)).into_response(),
-      }
-  }
+#[instrument(skip(bank_account_service))]
+pub async fn query_handler(
+    Path(id): Path<String>,
+    Extension(bank_account_service): Extension<Arc<dyn BankAccountApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+) -> Response {
+    let actor: Actor = user.into();
+    match bank_account_service.get_bank_account(&actor, id).await {
+        Ok(account_view) => (
+            StatusCode::OK,
+            [(header::ETAG, version_etag(account_view.version))],
+            Json(account_view),
+        )
+            .into_response(),
+        Err(err) => bank_account_error_response(err),
+    }
+}
 
-  // Serves as our command endpoint to make changes in a `BankAccount` aggregate.
-  #[utoipa::path(
+// Serves as our command endpoint to make changes in a `BankAccount` aggregate.
+#[utoipa::path(
     post,
     tag = "Bank Accounts",
     path = "/api/bank-accounts/{id}",
     responses(
       (status = 204, description = "Command issued successfully"),
-      (status = 400, description = "Command failed", body = [String])
+      (status = 400, description = "Command failed", body = [String]),
+      (status = 401, description = "Opening an account requires a signed in user"),
+      (status = 403, description = "The role of the user on the account does not permit the command"),
+      (status = 404, description = "Bank account not found"),
+      (status = 409, description = "The account is no longer at the version of If-Match, or a command with the same idempotency key is still in progress or did not complete", body = [String]),
+      (status = 422, description = "The idempotency key was used for a different command")
     ),
     request_body(content = BankAccountCommand, description = "Bank account command to execute, see the Bank Account Command schema at the bottom of the page for details", content_type = "application/json"),
     params(
       ("id" = i32, Path, description = "Bank account ID"),
+      ("If-Match" = Option<String>, Header, description = "The ETag of the account, to only execute the command if the account has not changed since"),
+      ("Idempotency-Key" = Option<String>, Header, description = "Retrying with the same key returns the original outcome instead of executing the command again"),
     ),
   )]
-  #[instrument(skip(cqrs))]
-  pub async fn command_handler(
-      Path(id): Path<String>,
-      Extension(cqrs): Extension<Arc<PostgresCqrs<BankAccount>>>,
-      MetadataExtension(metadata): crate::infrastructure::middleware::MetadataExtension,
-      Json(command): Json<BankAccountCommand>,
-  ) -> Response {
-      match cqrs.execute_with_metadata(&id, command, metadata).await {
-          Ok(_) => StatusCode::NO_CONTENT.into_response(),
-          Err(err) => (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
-      }
-  }
+#[instrument(skip(bank_account_service))]
+pub async fn command_handler(
+    Path(id): Path<String>,
+    Extension(bank_account_service): Extension<Arc<dyn BankAccountApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+    MetadataExtension(metadata): MetadataExtension,
+    ExpectedVersionExtension(expected_version): ExpectedVersionExtension,
+    IdempotencyKeyExtension(idempotency_key): IdempotencyKeyExtension,
+    Json(command): Json<BankAccountCommand>,
+) -> Response {
+    let actor: Actor = user.into();
+    match bank_account_service
+        .execute(
+            &actor,
+            id,
+            command,
+            metadata,
+            expected_version,
+            idempotency_key,
+        )
+        .await
+    {
+        Ok(_) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => bank_account_error_response(err),
+    }
+}
 
-    } else if #[cfg(feature = "mysql")] {
-// for the requested account.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AccountTransactionsParams {
+    /// The `next_cursor` of the previous page
+    pub after: Option<String>,
+    /// Page size, at most 200
+    pub limit: Option<usize>,
+    /// Only transactions at or after this RFC 3339 timestamp
+    pub from: Option<String>,
+    /// Only transactions before this RFC 3339 timestamp
+    pub to: Option<String>,
+    /// Only transactions of this type
+    #[serde(rename = "type")]
+    pub transaction_type: Option<AccountTransactionType>,
+}
+
+// Serves the transaction history of an account a page at a time, newest first.
 #[utoipa::path(
     get,
     tag = "Bank Accounts",
-    path = "/api/bank-accounts/{id}",
+    path = "/api/bank-accounts/{id}/transactions",
     params(
-        ("id" = i32, Path, description = "Bank account ID")
+        ("id" = i32, Path, description = "Bank account ID"),
+        AccountTransactionsParams
     ),
     responses(
-        (status = 200, description = "Get bank account details", body = [BankAccountView])
+        (status = 200, description = "A page of the account's transactions", body = AccountTransactionPage),
+        (status = 400, description = "Invalid cursor or date", body = String),
+        (status = 403, description = "The user has no access to the account"),
+        (status = 404, description = "Bank account not found")
     )
-  )]
-  #[instrument(skip(view_repo))]
-  pub async fn query_handler(
-      Path(id): Path<String>,
-      Extension(view_repo): Extension<Arc<MysqlViewRepository<BankAccountView, BankAccount>>>,
-  ) -> Response {
-      let view = match view_repo.load(&id).await {
-          Ok(view) => view,
-          Err(err) => {
-              return (StatusCode::INTERNAL_SERVER_ERROR, err.to_string()).into_response();
-          }
-      };
-      match view {
-          None => StatusCode::NOT_FOUND.into_response(),
-          Some(account_view) => (StatusCode::OK, Json(account_view)).into_response(),
-      }
-  }
+)]
+#[instrument(skip(bank_account_service))]
+pub async fn transactions_handler(
+    Path(id): Path<String>,
+    Query(params): Query<AccountTransactionsParams>,
+    Extension(bank_account_service): Extension<Arc<dyn BankAccountApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+) -> Response {
+    let filter = match AccountTransactionFilter::parse(
+        params.from.as_deref(),
+        params.to.as_deref(),
+        params.transaction_type,
+    ) {
+        Ok(filter) => filter,
+        Err(err) => return (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
+    };
+    let actor: Actor = user.into();
+    let page: Result<AccountTransactionPage, BankAccountServiceError> = bank_account_service
+        .get_account_transactions(&actor, id, filter, params.after, params.limit)
+        .await;
+    match page {
+        Ok(page) => (StatusCode::OK, Json(page)).into_response(),
+        Err(err) => bank_account_error_response(err),
+    }
+}
 
-  // Serves as our command endpoint to make changes in a `BankAccount` aggregate.
-  #[utoipa::path(
-    post,
+// Serves the users with access to an account and their roles.
+#[utoipa::path(
+    get,
     tag = "Bank Accounts",
-    path = "/api/bank-accounts/{id}",
-    responses(
-      (status = 204, description = "Command issued successfully"),
-      (status = 400, description = "Command failed", body = [String])
+    path = "/api/bank-accounts/{id}/access",
+    params(
+        ("id" = i32, Path, description = "Bank account ID")
     ),
-    request_body(content = BankAccountCommand, description = "Bank account command to execute, see the Bank Account Command schema at the bottom of the page for details", content_type = "application/json"),
+    responses(
+        (status = 200, description = "The users with access to the account", body = [AccountAccess]),
+        (status = 403, description = "The user has no access to the account"),
+        (status = 404, description = "Bank account not found")
+    )
+)]
+#[instrument(skip(bank_account_service))]
+pub async fn access_handler(
+    Path(id): Path<String>,
+    Extension(bank_account_service): Extension<Arc<dyn BankAccountApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+) -> Response {
+    let actor: Actor = user.into();
+    match bank_account_service.get_account_access(&actor, id).await {
+        Ok(access) => (StatusCode::OK, Json(access)).into_response(),
+        Err(err) => bank_account_error_response(err),
+    }
+}
+
+/// The format a statement is downloaded in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum StatementFormat {
+    #[default]
+    Json,
+    Csv,
+    Pdf,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AccountStatementParams {
+    /// json, csv or pdf, json by default
+    pub format: Option<StatementFormat>,
+}
+
+// Serves the statement of an account for a month, drawn up from its events.
+#[utoipa::path(
+    get,
+    tag = "Bank Accounts",
+    path = "/api/bank-accounts/{id}/statements/{period}",
     params(
-      ("id" = i32, Path, description = "Bank account ID"),
+        ("id" = i32, Path, description = "Bank account ID"),
+        ("period" = String, Path, description = "The month of the statement, e.g. 2023-11"),
+        AccountStatementParams
     ),
-  )]
-  #[instrument(skip(cqrs))]
-  pub async fn command_handler(
-      Path(id): Path<String>,
-      Extension(cqrs): Extension<Arc<MysqlCqrs<BankAccount>>>,
-      MetadataExtension(metadata): MetadataExtension,
-      Json(command): Json<BankAccountCommand>,
-  ) -> Response {
-      match cqrs.execute_with_metadata(&id, command, metadata).await {
-          Ok(_) => StatusCode::NO_CONTENT.into_response(),
-          Err(err) => (StatusCode::BAD_REQUEST, err.to_string()).into_response(),
-      }
-  }
+    responses(
+        (status = 200, description = "The statement of the account for the month", body = AccountStatement,
+            content_type = ["application/json", "text/csv", "application/pdf"]),
+        (status = 400, description = "Invalid period", body = String),
+        (status = 403, description = "The user has no access to the account"),
+        (status = 404, description = "Bank account not found, or not open in the period")
+    )
+)]
+#[instrument(skip(bank_account_service))]
+pub async fn statement_handler(
+    Path((id, period)): Path<(String, String)>,
+    Query(params): Query<AccountStatementParams>,
+    Extension(bank_account_service): Extension<Arc<dyn BankAccountApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+) -> Response {
+    let actor: Actor = user.into();
+    let statement = match bank_account_service
+        .get_account_statement(&actor, id, period)
+        .await
+    {
+        Ok(statement) => statement,
+        Err(err) => return bank_account_error_response(err),
+    };
+    let attachment = |extension: &str| {
+        format!(
+            "attachment; filename=\"statement-{}-{}.{}\"",
+            statement.account_id, statement.period, extension
+        )
+    };
+    match params.format.unwrap_or_default() {
+        StatementFormat::Json => (StatusCode::OK, Json(statement)).into_response(),
+        StatementFormat::Csv => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (header::CONTENT_DISPOSITION, attachment("csv")),
+            ],
+            render_statement_csv(&statement),
+        )
+            .into_response(),
+        StatementFormat::Pdf => (
+            StatusCode::OK,
+            [
+                (header::CONTENT_TYPE, "application/pdf".to_string()),
+                (header::CONTENT_DISPOSITION, attachment("pdf")),
+            ],
+            render_statement_pdf(&statement),
+        )
+            .into_response(),
     }
 }
+
+fn bank_account_error_response(err: BankAccountServiceError) -> Response {
+    let status = match err {
+        BankAccountServiceError::BankAccountNotFound(_) => StatusCode::NOT_FOUND,
+        BankAccountServiceError::AccessDenied(_) => StatusCode::FORBIDDEN,
+        BankAccountServiceError::Unauthenticated => StatusCode::UNAUTHORIZED,
+        BankAccountServiceError::CommandRejected(_) => StatusCode::BAD_REQUEST,
+        BankAccountServiceError::Conflict(_) => StatusCode::CONFLICT,
+        BankAccountServiceError::Transactions(AccountTransactionsError::Sqlx(_)) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+        BankAccountServiceError::Transactions(_) => StatusCode::BAD_REQUEST,
+        BankAccountServiceError::Statements(AccountStatementError::InvalidPeriod(_)) => {
+            StatusCode::BAD_REQUEST
+        }
+        BankAccountServiceError::Statements(AccountStatementError::NotOpen { .. }) => {
+            StatusCode::NOT_FOUND
+        }
+        BankAccountServiceError::Statements(AccountStatementError::Replay { .. }) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+        BankAccountServiceError::Persistence(_)
+        | BankAccountServiceError::Access(_)
+        | BankAccountServiceError::CommandFailed(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        BankAccountServiceError::Idempotency(ref err) => idempotency_error_status(err),
+    };
+    (status, err.to_string()).into_response()
+}
@@ -1,5 +1,5 @@
 use hyper::{
-    header::{ACCEPT, ACCEPT_ENCODING, AUTHORIZATION, CONTENT_TYPE},
+    header::{ACCEPT, ACCEPT_ENCODING, AUTHORIZATION, CONTENT_TYPE, ETAG, IF_MATCH},
     http::{HeaderName, HeaderValue},
     Method,
 };
@@ -11,15 +11,18 @@ pub fn new_cors_layer() -> CorsLayer {
     let frontend_client_origin: String =
         dotenvy::var(FRONTEND_CLIENT_ORIGIN_ENV_VAR).unwrap_or("http://localhost:5173".to_string());
     CorsLayer::new()
-        .allow_methods([Method::GET, Method::POST])
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
         .allow_credentials(true)
         .allow_headers(vec![
             CONTENT_TYPE,
             ACCEPT,
             ACCEPT_ENCODING,
             AUTHORIZATION,
+            IF_MATCH,
+            "idempotency-key".parse::<HeaderName>().unwrap(),
             "x-grpc-web".parse::<HeaderName>().unwrap(),
             "x-user-agent".parse::<HeaderName>().unwrap(),
         ])
+        .expose_headers([ETAG])
         .allow_origin(frontend_client_origin.parse::<HeaderValue>().unwrap())
 }
@@ -1,10 +1,10 @@
 use std::{net::SocketAddr, sync::Arc};
 
 use async_graphql::{http::*, *};
-use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use async_graphql_axum::{GraphQLProtocol, GraphQLRequest, GraphQLResponse, GraphQLWebSocket};
 
 use axum::{
-    extract::Extension,
+    extract::{Extension, WebSocketUpgrade},
     response::{self, IntoResponse},
     routing::get,
     Router,
@@ -13,21 +13,17 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
-use crate::domain::BankAccount;
-
-use crate::interfaces::{BankAccountGraphQlMutation, BankAccountGraphQlQuery, BankAccountView};
-
-use cfg_if::cfg_if;
+use crate::application::{
+    Actor, BankAccountApplicationService, ScheduledPaymentApplicationService,
+    TransferApplicationService,
+};
+use crate::infrastructure::grpc::auth_grpc_service::UserView;
 
-cfg_if! {
-    if #[cfg(feature = "postgres")] {
-        use postgres_es::{PostgresCqrs, PostgresViewRepository};
-    } else if #[cfg(feature = "mysql")] {
-        use mysql_es::{MysqlCqrs, MysqlViewRepository};
-    } else {
-        compile_error!("Must specify either mysql or postgres feature");
-    }
-}
+use crate::interfaces::{
+    AccountEventBroadcaster, BankAccountGraphQlMutation, BankAccountGraphQlQuery,
+    BankAccountGraphQlSubscription, ScheduledPaymentGraphQlMutation, ScheduledPaymentGraphQlQuery,
+    TransferGraphQlMutation, TransferGraphQlQuery,
+};
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct GraphQlConfiguration {
@@ -50,66 +46,84 @@ async fn graphql_playground() -> impl IntoResponse {
     response::Html(playground_source(GraphQLPlaygroundConfig::new("/")))
 }
 
+type GraphQlSchema = Schema<QueryRoot, MutationRoot, SubscriptionRoot>;
+
+// Resolvers act on behalf of the user resolved by `mw_authenticate`.
 #[instrument(skip(schema, req))]
 async fn graphql_handler(
-    schema: Extension<Schema<QueryRoot, MutationRoot, EmptySubscription>>,
+    schema: Extension<GraphQlSchema>,
+    Extension(user): Extension<Option<UserView>>,
     req: GraphQLRequest,
 ) -> GraphQLResponse {
-    schema.execute(req.into_inner()).await.into()
+    let actor: Actor = user.into();
+    schema.execute(req.into_inner().data(actor)).await.into()
 }
 
 #[derive(MergedObject, Default)]
-struct QueryRoot(BankAccountGraphQlQuery);
+struct QueryRoot(
+    BankAccountGraphQlQuery,
+    TransferGraphQlQuery,
+    ScheduledPaymentGraphQlQuery,
+);
+
+// Subscriptions are served over a WebSocket, on behalf of the user that opened it.
+#[instrument(skip(schema, protocol, websocket))]
+async fn graphql_ws_handler(
+    Extension(schema): Extension<GraphQlSchema>,
+    Extension(user): Extension<Option<UserView>>,
+    protocol: GraphQLProtocol,
+    websocket: WebSocketUpgrade,
+) -> impl IntoResponse {
+    let actor: Actor = user.into();
+    websocket
+        .protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |stream| {
+            let mut data = Data::default();
+            data.insert(actor);
+            GraphQLWebSocket::new(stream, schema, protocol)
+                .with_data(data)
+                .serve()
+        })
+}
 
 #[derive(MergedObject, Default)]
-struct MutationRoot(BankAccountGraphQlMutation);
-
-cfg_if! {
-    if #[cfg(feature = "postgres")] {
-        #[instrument(skip(bank_account_view_repsitory, bank_account_cqrs_framework))]
-        pub fn new_graphql_router(
-            bank_account_cqrs_framework: Arc<PostgresCqrs<BankAccount>>,
-            bank_account_view_repsitory: Arc<PostgresViewRepository<BankAccountView, BankAccount>>,
-        ) -> Router {
-            tracing::debug!("Starting graphql server");
-
-            // create the schema
-            let schema = Schema::build(
-                QueryRoot::default(),
-                MutationRoot::default(),
-                EmptySubscription,
-            )
-            .data(bank_account_view_repsitory)
-            .data(bank_account_cqrs_framework)
-            .finish();
-
-            Router::new()
-                .route("/", get(graphql_playground).post(graphql_handler))
-                .layer(Extension(schema))
-        }
-    } else if #[cfg(feature = "mysql")] {
-        #[instrument(skip(bank_account_view_repsitory, bank_account_cqrs_framework))]
-        pub fn new_graphql_router(
-            bank_account_cqrs_framework: Arc<MysqlCqrs<BankAccount>>,
-            bank_account_view_repsitory: Arc<MysqlViewRepository<BankAccountView, BankAccount>>,
-        ) -> Router {
-            tracing::debug!("Starting graphql server");
-
-            // create the schema
-            let schema = Schema::build(
-                QueryRoot::default(),
-                MutationRoot::default(),
-                EmptySubscription,
-            )
-            .data(bank_account_view_repsitory)
-            .data(bank_account_cqrs_framework)
-            .finish();
-
-            Router::new()
-                .route("/", get(graphql_playground).post(graphql_handler))
-                .layer(Extension(schema))
-        }
-    } else {
-        compile_error!("Must specify either mysql or postgres feature");
-    }
+struct MutationRoot(
+    BankAccountGraphQlMutation,
+    TransferGraphQlMutation,
+    ScheduledPaymentGraphQlMutation,
+);
+
+#[derive(MergedSubscription, Default)]
+struct SubscriptionRoot(BankAccountGraphQlSubscription);
+
+#[instrument(skip(
+    bank_account_service,
+    transfer_service,
+    scheduled_payment_service,
+    account_events
+))]
+pub fn new_graphql_router(
+    bank_account_service: Arc<dyn BankAccountApplicationService>,
+    transfer_service: Arc<dyn TransferApplicationService>,
+    scheduled_payment_service: Arc<dyn ScheduledPaymentApplicationService>,
+    account_events: AccountEventBroadcaster,
+) -> Router {
+    tracing::debug!("Starting graphql server");
+
+    // create the schema
+    let schema = Schema::build(
+        QueryRoot::default(),
+        MutationRoot::default(),
+        SubscriptionRoot::default(),
+    )
+    .data(bank_account_service)
+    .data(transfer_service)
+    .data(scheduled_payment_service)
+    .data(account_events)
+    .finish();
+
+    Router::new()
+        .route("/", get(graphql_playground).post(graphql_handler))
+        .route("/ws", get(graphql_ws_handler))
+        .layer(Extension(schema))
 }
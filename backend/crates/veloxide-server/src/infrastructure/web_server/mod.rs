@@ -1,8 +1,12 @@
+pub mod admin_handlers;
 pub mod bank_account_handlers;
 pub mod configuration;
 pub mod cors;
 pub mod graphql;
 pub mod oauth;
 pub mod openapi;
+pub mod scheduled_payment_handlers;
+pub mod transfer_handlers;
+pub mod webhook_handlers;
 
 pub use cors::*;
@@ -2,8 +2,18 @@ use utoipa::OpenApi;
 
 //TODO: Remove reaching into domain from here
 use crate::domain::bank_account::*;
-use crate::infrastructure::web_server::bank_account_handlers;
+use crate::domain::{
+    CreateScheduledPaymentCommandData, ExchangeRate, Money, PaymentFrequency, ScheduledPaymentKind,
+    ScheduledPaymentStatus, StartTransferCommandData, TransferStatus,
+    UpdateScheduledPaymentCommandData,
+};
+use crate::infrastructure::projections::ProjectionRebuildReport;
+use crate::infrastructure::web_server::bank_account_handlers::StatementFormat;
 use crate::infrastructure::web_server::oauth::*;
+use crate::infrastructure::web_server::{
+    admin_handlers, bank_account_handlers, scheduled_payment_handlers, transfer_handlers,
+    webhook_handlers,
+};
 use crate::interfaces::*;
 
 #[derive(OpenApi)]
@@ -11,6 +21,25 @@ use crate::interfaces::*;
       paths(
           bank_account_handlers::query_handler,
           bank_account_handlers::command_handler,
+          bank_account_handlers::transactions_handler,
+          bank_account_handlers::access_handler,
+          bank_account_handlers::statement_handler,
+          transfer_handlers::query_handler,
+          transfer_handlers::command_handler,
+          webhook_handlers::list_handler,
+          webhook_handlers::register_handler,
+          webhook_handlers::query_handler,
+          webhook_handlers::update_handler,
+          webhook_handlers::delete_handler,
+          webhook_handlers::deliveries_handler,
+          scheduled_payment_handlers::list_handler,
+          scheduled_payment_handlers::create_handler,
+          scheduled_payment_handlers::query_handler,
+          scheduled_payment_handlers::update_handler,
+          scheduled_payment_handlers::cancel_handler,
+          admin_handlers::rebuild_projection_handler,
+          admin_handlers::trial_balance_handler,
+          admin_handlers::assign_owner_handler,
           login,
           logout,
           protected,
@@ -23,10 +52,60 @@ use crate::interfaces::*;
             BankAccountDepositMoneyCommandData,
             BankAccountWithdrawMoneyCommandData,
             BankAccountWriteCheckCommandData,
-            AccountTransaction),
+            BankAccountCancelCheckCommandData,
+            BankAccountDebitTransferCommandData,
+            BankAccountCreditTransferCommandData,
+            BankAccountRefundTransferCommandData,
+            BankAccountFreezeAccountCommandData,
+            BankAccountUnfreezeAccountCommandData,
+            BankAccountCloseAccountCommandData,
+            BankAccountSetOverdraftLimitCommandData,
+            BankAccountGrantAccessCommandData,
+            BankAccountRevokeAccessCommandData,
+            BankAccountAccrueInterestCommandData,
+            BankAccountPlaceHoldCommandData,
+            BankAccountReleaseHoldCommandData,
+            BankAccountCaptureHoldCommandData,
+            BankAccountExpireHoldsCommandData,
+            BankAccountAssignOwnerCommandData,
+            AccessRole,
+            AccountAccess,
+            AccountHold,
+            AccountStatement,
+            AccountStatementLine,
+            AccountStatus,
+            AccountTransaction,
+            AccountTransactionPage,
+            AccountTransactionType,
+            ExchangeRate,
+            Money,
+            StatementFormat,
+            StartTransferCommandData,
+            TransferStatus,
+            TransferView,
+            WebhookSubscription,
+            WebhookSubscriptionRequest,
+            WebhookDelivery,
+            WebhookDeliveryStatus,
+            CreateScheduledPaymentCommandData,
+            UpdateScheduledPaymentCommandData,
+            ScheduledPaymentView,
+            ScheduledPaymentKind,
+            ScheduledPaymentStatus,
+            PaymentFrequency,
+            ProjectionRebuildReport,
+            LedgerAccount,
+            LedgerAccountType,
+            TrialBalance,
+            TrialBalanceLine,
+            TrialBalanceTotal),
     ),
       tags(
-          (name = "Bank Accounts", description = "Bank Account Management API")
+          (name = "Bank Accounts", description = "Bank Account Management API"),
+          (name = "Transfers", description = "Money transfers between bank accounts"),
+          (name = "Webhooks", description = "Callbacks with the events of bank accounts"),
+          (name = "Scheduled payments", description = "One-off and recurring deposits and withdrawals"),
+          (name = "Admin", description = "Operational tasks, restricted to administrators")
       ),
         info(
             title = "Bank Account API: built with Veloxide",
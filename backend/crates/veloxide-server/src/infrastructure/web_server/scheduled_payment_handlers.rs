@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    response::Response,
+    Extension, Json,
+};
+use serde::Deserialize;
+use tracing::instrument;
+use utoipa::IntoParams;
+
+use crate::application::{Actor, ScheduledPaymentApplicationService, ScheduledPaymentServiceError};
+use crate::domain::{CreateScheduledPaymentCommandData, UpdateScheduledPaymentCommandData};
+use crate::infrastructure::grpc::auth_grpc_service::UserView;
+pub use crate::interfaces::scheduled_payment::*;
+
+// Schedules a deposit or withdrawal, made on behalf of the signed in user from its start on.
+#[utoipa::path(
+    post,
+    tag = "Scheduled payments",
+    path = "/api/scheduled-payments",
+    request_body(content = CreateScheduledPaymentCommandData, description = "The account, amount and schedule of the payment", content_type = "application/json"),
+    responses(
+        (status = 201, description = "Payment scheduled", body = ScheduledPaymentView),
+        (status = 400, description = "Invalid amount or schedule", body = String),
+        (status = 401, description = "Scheduling payments requires a signed in user"),
+        (status = 403, description = "The user cannot pay from the account"),
+        (status = 404, description = "Bank account not found")
+    )
+)]
+#[instrument(skip(scheduled_payment_service))]
+pub async fn create_handler(
+    Extension(scheduled_payment_service): Extension<Arc<dyn ScheduledPaymentApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+    Json(command): Json<CreateScheduledPaymentCommandData>,
+) -> Response {
+    let actor: Actor = user.into();
+    match scheduled_payment_service
+        .create_scheduled_payment(&actor, command)
+        .await
+    {
+        Ok(payment) => (StatusCode::CREATED, Json(payment)).into_response(),
+        Err(err) => scheduled_payment_error_response(err),
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ScheduledPaymentsParams {
+    /// Bank account ID
+    pub account_id: String,
+}
+
+// Lists the payments scheduled on an account.
+#[utoipa::path(
+    get,
+    tag = "Scheduled payments",
+    path = "/api/scheduled-payments",
+    params(ScheduledPaymentsParams),
+    responses(
+        (status = 200, description = "The payments scheduled on the account, oldest first", body = [ScheduledPaymentView]),
+        (status = 403, description = "The user cannot view the account"),
+        (status = 404, description = "Bank account not found")
+    )
+)]
+#[instrument(skip(scheduled_payment_service))]
+pub async fn list_handler(
+    Query(params): Query<ScheduledPaymentsParams>,
+    Extension(scheduled_payment_service): Extension<Arc<dyn ScheduledPaymentApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+) -> Response {
+    let actor: Actor = user.into();
+    match scheduled_payment_service
+        .list_scheduled_payments(&actor, params.account_id)
+        .await
+    {
+        Ok(payments) => (StatusCode::OK, Json(payments)).into_response(),
+        Err(err) => scheduled_payment_error_response(err),
+    }
+}
+
+#[utoipa::path(
+    get,
+    tag = "Scheduled payments",
+    path = "/api/scheduled-payments/{id}",
+    params(
+        ("id" = String, Path, description = "Scheduled payment ID")
+    ),
+    responses(
+        (status = 200, description = "Get scheduled payment details", body = ScheduledPaymentView),
+        (status = 403, description = "The user cannot view the account"),
+        (status = 404, description = "Scheduled payment not found")
+    )
+)]
+#[instrument(skip(scheduled_payment_service))]
+pub async fn query_handler(
+    Path(id): Path<String>,
+    Extension(scheduled_payment_service): Extension<Arc<dyn ScheduledPaymentApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+) -> Response {
+    let actor: Actor = user.into();
+    match scheduled_payment_service
+        .get_scheduled_payment(&actor, id)
+        .await
+    {
+        Ok(payment) => (StatusCode::OK, Json(payment)).into_response(),
+        Err(err) => scheduled_payment_error_response(err),
+    }
+}
+
+// Replaces the amount and schedule of a payment, which next runs at the new start.
+#[utoipa::path(
+    put,
+    tag = "Scheduled payments",
+    path = "/api/scheduled-payments/{id}",
+    params(
+        ("id" = String, Path, description = "Scheduled payment ID")
+    ),
+    request_body(content = UpdateScheduledPaymentCommandData, description = "The new amount and schedule of the payment", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Scheduled payment updated", body = ScheduledPaymentView),
+        (status = 400, description = "Invalid amount or schedule, or the payment is no longer active", body = String),
+        (status = 403, description = "The user cannot pay from the account"),
+        (status = 404, description = "Scheduled payment not found")
+    )
+)]
+#[instrument(skip(scheduled_payment_service))]
+pub async fn update_handler(
+    Path(id): Path<String>,
+    Extension(scheduled_payment_service): Extension<Arc<dyn ScheduledPaymentApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+    Json(command): Json<UpdateScheduledPaymentCommandData>,
+) -> Response {
+    let actor: Actor = user.into();
+    match scheduled_payment_service
+        .update_scheduled_payment(&actor, id, command)
+        .await
+    {
+        Ok(payment) => (StatusCode::OK, Json(payment)).into_response(),
+        Err(err) => scheduled_payment_error_response(err),
+    }
+}
+
+// Cancels a payment, responding with it so that its history stays visible.
+#[utoipa::path(
+    delete,
+    tag = "Scheduled payments",
+    path = "/api/scheduled-payments/{id}",
+    params(
+        ("id" = String, Path, description = "Scheduled payment ID")
+    ),
+    responses(
+        (status = 200, description = "Scheduled payment cancelled", body = ScheduledPaymentView),
+        (status = 400, description = "The payment is no longer active", body = String),
+        (status = 403, description = "The user cannot pay from the account"),
+        (status = 404, description = "Scheduled payment not found")
+    )
+)]
+#[instrument(skip(scheduled_payment_service))]
+pub async fn cancel_handler(
+    Path(id): Path<String>,
+    Extension(scheduled_payment_service): Extension<Arc<dyn ScheduledPaymentApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+) -> Response {
+    let actor: Actor = user.into();
+    match scheduled_payment_service
+        .cancel_scheduled_payment(&actor, id)
+        .await
+    {
+        Ok(payment) => (StatusCode::OK, Json(payment)).into_response(),
+        Err(err) => scheduled_payment_error_response(err),
+    }
+}
+
+fn scheduled_payment_error_response(err: ScheduledPaymentServiceError) -> Response {
+    let status = match err {
+        ScheduledPaymentServiceError::ScheduledPaymentNotFound(_)
+        | ScheduledPaymentServiceError::BankAccountNotFound(_) => StatusCode::NOT_FOUND,
+        ScheduledPaymentServiceError::Unauthenticated => StatusCode::UNAUTHORIZED,
+        ScheduledPaymentServiceError::AccessDenied(_) => StatusCode::FORBIDDEN,
+        ScheduledPaymentServiceError::CommandRejected(_) => StatusCode::BAD_REQUEST,
+        ScheduledPaymentServiceError::CommandFailed(_)
+        | ScheduledPaymentServiceError::BankAccount(_)
+        | ScheduledPaymentServiceError::Query(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, err.to_string()).into_response()
+}
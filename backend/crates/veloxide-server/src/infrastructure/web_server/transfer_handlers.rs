@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::Path, http::StatusCode, response::IntoResponse, response::Response, Extension, Json,
+};
+use tracing::instrument;
+
+use crate::application::{Actor, TransferApplicationService, TransferServiceError};
+use crate::domain::StartTransferCommandData;
+use crate::infrastructure::grpc::auth_grpc_service::UserView;
+use crate::infrastructure::middleware::{idempotency_error_status, IdempotencyKeyExtension};
+pub use crate::interfaces::transfer::*;
+
+// Serves as our query endpoint to respond with the materialized `TransferView`
+// for the requested transfer.
+#[utoipa::path(
+    get,
+    tag = "Transfers",
+    path = "/api/transfers/{id}",
+    params(
+        ("id" = String, Path, description = "Transfer ID")
+    ),
+    responses(
+        (status = 200, description = "Get transfer details", body = TransferView),
+        (status = 403, description = "Neither account of the transfer is owned by the user"),
+        (status = 404, description = "Transfer not found")
+    )
+)]
+#[instrument(skip(transfer_service))]
+pub async fn query_handler(
+    Path(id): Path<String>,
+    Extension(transfer_service): Extension<Arc<dyn TransferApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+) -> Response {
+    let actor: Actor = user.into();
+    match transfer_service.get_transfer(&actor, id).await {
+        Ok(view) => (StatusCode::OK, Json(view)).into_response(),
+        Err(err) => transfer_error_response(err),
+    }
+}
+
+// Starts a transfer between two bank accounts, responding with the transfer once the
+// saga has completed, failed or been compensated.
+#[utoipa::path(
+    post,
+    tag = "Transfers",
+    path = "/api/transfers",
+    request_body(content = StartTransferCommandData, description = "The accounts and amount to transfer", content_type = "application/json"),
+    params(
+        ("Idempotency-Key" = Option<String>, Header, description = "Retrying with the same key returns the transfer started the first time")
+    ),
+    responses(
+        (status = 201, description = "Transfer processed, see its status for the outcome", body = TransferView),
+        (status = 400, description = "Transfer rejected", body = String),
+        (status = 403, description = "The source account is not owned by the user"),
+        (status = 409, description = "A transfer with the same idempotency key is still in progress or did not complete"),
+        (status = 422, description = "The idempotency key was used for a different transfer")
+    )
+)]
+#[instrument(skip(transfer_service))]
+pub async fn command_handler(
+    Extension(transfer_service): Extension<Arc<dyn TransferApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+    IdempotencyKeyExtension(idempotency_key): IdempotencyKeyExtension,
+    Json(command): Json<StartTransferCommandData>,
+) -> Response {
+    let actor: Actor = user.into();
+    match transfer_service
+        .transfer_money(&actor, command, idempotency_key)
+        .await
+    {
+        Ok(view) => (StatusCode::CREATED, Json(view)).into_response(),
+        Err(err) => transfer_error_response(err),
+    }
+}
+
+fn transfer_error_response(err: TransferServiceError) -> Response {
+    let status = match err {
+        TransferServiceError::TransferNotFound(_) => StatusCode::NOT_FOUND,
+        TransferServiceError::TransferRejected(_) => StatusCode::BAD_REQUEST,
+        TransferServiceError::AccessDenied(_) => StatusCode::FORBIDDEN,
+        TransferServiceError::Persistence(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        TransferServiceError::Idempotency(ref err) => idempotency_error_status(err),
+    };
+    (status, err.to_string()).into_response()
+}
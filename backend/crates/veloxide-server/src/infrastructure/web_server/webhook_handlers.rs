@@ -0,0 +1,200 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    response::Response,
+    Extension, Json,
+};
+use serde::Deserialize;
+use tracing::instrument;
+use utoipa::IntoParams;
+
+use crate::application::{Actor, WebhookApplicationService, WebhookServiceError};
+use crate::infrastructure::grpc::auth_grpc_service::UserView;
+pub use crate::interfaces::webhooks::*;
+
+// Registers a webhook for the signed in user, responding with the secret its payloads are
+// signed with.
+#[utoipa::path(
+    post,
+    tag = "Webhooks",
+    path = "/api/webhooks",
+    request_body(content = WebhookSubscriptionRequest, description = "The URL to call back, and the account and event types to call it back for", content_type = "application/json"),
+    responses(
+        (status = 201, description = "Webhook registered", body = WebhookSubscription),
+        (status = 400, description = "Invalid URL or event type", body = String),
+        (status = 401, description = "Registering webhooks requires a signed in user"),
+        (status = 403, description = "The user cannot view the account"),
+        (status = 404, description = "Bank account not found")
+    )
+)]
+#[instrument(skip(webhook_service))]
+pub async fn register_handler(
+    Extension(webhook_service): Extension<Arc<dyn WebhookApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+    Json(request): Json<WebhookSubscriptionRequest>,
+) -> Response {
+    let actor: Actor = user.into();
+    match webhook_service.register_webhook(&actor, request).await {
+        Ok(subscription) => (StatusCode::CREATED, Json(subscription)).into_response(),
+        Err(err) => webhook_error_response(err),
+    }
+}
+
+// Lists the webhooks registered by the signed in user.
+#[utoipa::path(
+    get,
+    tag = "Webhooks",
+    path = "/api/webhooks",
+    responses(
+        (status = 200, description = "The webhooks of the user, oldest first", body = [WebhookSubscription]),
+        (status = 401, description = "Listing webhooks requires a signed in user")
+    )
+)]
+#[instrument(skip(webhook_service))]
+pub async fn list_handler(
+    Extension(webhook_service): Extension<Arc<dyn WebhookApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+) -> Response {
+    let actor: Actor = user.into();
+    match webhook_service.list_webhooks(&actor).await {
+        Ok(subscriptions) => (StatusCode::OK, Json(subscriptions)).into_response(),
+        Err(err) => webhook_error_response(err),
+    }
+}
+
+#[utoipa::path(
+    get,
+    tag = "Webhooks",
+    path = "/api/webhooks/{id}",
+    params(
+        ("id" = String, Path, description = "Webhook ID")
+    ),
+    responses(
+        (status = 200, description = "Get webhook details", body = WebhookSubscription),
+        (status = 404, description = "The user has no webhook with the ID")
+    )
+)]
+#[instrument(skip(webhook_service))]
+pub async fn query_handler(
+    Path(id): Path<String>,
+    Extension(webhook_service): Extension<Arc<dyn WebhookApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+) -> Response {
+    let actor: Actor = user.into();
+    match webhook_service.get_webhook(&actor, id).await {
+        Ok(subscription) => (StatusCode::OK, Json(subscription)).into_response(),
+        Err(err) => webhook_error_response(err),
+    }
+}
+
+// Changes the URL, account and event types of a webhook, keeping its secret.
+#[utoipa::path(
+    put,
+    tag = "Webhooks",
+    path = "/api/webhooks/{id}",
+    params(
+        ("id" = String, Path, description = "Webhook ID")
+    ),
+    request_body(content = WebhookSubscriptionRequest, description = "The URL to call back, and the account and event types to call it back for", content_type = "application/json"),
+    responses(
+        (status = 200, description = "Webhook updated", body = WebhookSubscription),
+        (status = 400, description = "Invalid URL or event type", body = String),
+        (status = 403, description = "The user cannot view the account"),
+        (status = 404, description = "The user has no webhook with the ID, or the bank account was not found")
+    )
+)]
+#[instrument(skip(webhook_service))]
+pub async fn update_handler(
+    Path(id): Path<String>,
+    Extension(webhook_service): Extension<Arc<dyn WebhookApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+    Json(request): Json<WebhookSubscriptionRequest>,
+) -> Response {
+    let actor: Actor = user.into();
+    match webhook_service.update_webhook(&actor, id, request).await {
+        Ok(subscription) => (StatusCode::OK, Json(subscription)).into_response(),
+        Err(err) => webhook_error_response(err),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    tag = "Webhooks",
+    path = "/api/webhooks/{id}",
+    params(
+        ("id" = String, Path, description = "Webhook ID")
+    ),
+    responses(
+        (status = 204, description = "Webhook deleted along with its pending deliveries"),
+        (status = 404, description = "The user has no webhook with the ID")
+    )
+)]
+#[instrument(skip(webhook_service))]
+pub async fn delete_handler(
+    Path(id): Path<String>,
+    Extension(webhook_service): Extension<Arc<dyn WebhookApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+) -> Response {
+    let actor: Actor = user.into();
+    match webhook_service.delete_webhook(&actor, id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(err) => webhook_error_response(err),
+    }
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct WebhookDeliveriesParams {
+    /// Number of deliveries, at most 200
+    pub limit: Option<usize>,
+}
+
+// Responds with the delivery log of a webhook, newest first, including the attempts made and
+// the last error of each delivery.
+#[utoipa::path(
+    get,
+    tag = "Webhooks",
+    path = "/api/webhooks/{id}/deliveries",
+    params(
+        ("id" = String, Path, description = "Webhook ID"),
+        WebhookDeliveriesParams
+    ),
+    responses(
+        (status = 200, description = "The most recent deliveries to the webhook", body = [WebhookDelivery]),
+        (status = 404, description = "The user has no webhook with the ID")
+    )
+)]
+#[instrument(skip(webhook_service))]
+pub async fn deliveries_handler(
+    Path(id): Path<String>,
+    Query(params): Query<WebhookDeliveriesParams>,
+    Extension(webhook_service): Extension<Arc<dyn WebhookApplicationService>>,
+    Extension(user): Extension<Option<UserView>>,
+) -> Response {
+    let actor: Actor = user.into();
+    match webhook_service
+        .get_webhook_deliveries(&actor, id, params.limit)
+        .await
+    {
+        Ok(deliveries) => (StatusCode::OK, Json(deliveries)).into_response(),
+        Err(err) => webhook_error_response(err),
+    }
+}
+
+fn webhook_error_response(err: WebhookServiceError) -> Response {
+    let status = match err {
+        WebhookServiceError::WebhookNotFound(_) | WebhookServiceError::BankAccountNotFound(_) => {
+            StatusCode::NOT_FOUND
+        }
+        WebhookServiceError::Unauthenticated => StatusCode::UNAUTHORIZED,
+        WebhookServiceError::AccessDenied(_) => StatusCode::FORBIDDEN,
+        WebhookServiceError::InvalidWebhook(_) => StatusCode::BAD_REQUEST,
+        WebhookServiceError::BankAccount(_) | WebhookServiceError::Store(_) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+    };
+    (status, err.to_string()).into_response()
+}
@@ -0,0 +1,517 @@
+use chrono::{Duration, Utc};
+use std::error::Error as StdError;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration as StdDuration;
+
+use crate::infrastructure::cryptography::{encrypt_content_to_base64_urlsafe, EncryptionContent};
+use crate::interfaces::webhooks::{DueWebhookDelivery, WebhookError, WebhookStore};
+
+pub const WEBHOOK_MAX_ATTEMPTS_ENV_VAR: &str = "WEBHOOK_MAX_ATTEMPTS";
+pub const WEBHOOK_INITIAL_BACKOFF_ENV_VAR: &str = "WEBHOOK_INITIAL_BACKOFF_SECONDS";
+
+pub const DEFAULT_WEBHOOK_MAX_ATTEMPTS: u32 = 8;
+pub const DEFAULT_WEBHOOK_INITIAL_BACKOFF_SECONDS: i64 = 30;
+
+/// The longest a failed delivery waits before it is retried, however often it failed.
+pub const MAX_WEBHOOK_BACKOFF_SECONDS: i64 = 6 * 60 * 60;
+
+/// HMAC-SHA512 of the body followed by the timestamp, keyed with the secret of the webhook and
+/// base64url encoded without padding.
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Veloxide-Signature";
+/// Unix timestamp of the attempt, in seconds.
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "X-Veloxide-Timestamp";
+/// Id of the delivery, the same across its attempts.
+pub const WEBHOOK_DELIVERY_HEADER: &str = "X-Veloxide-Delivery";
+pub const WEBHOOK_EVENT_HEADER: &str = "X-Veloxide-Event";
+
+/// How long a webhook may take to respond before the attempt counts as failed.
+const WEBHOOK_TIMEOUT: StdDuration = StdDuration::from_secs(10);
+
+/// How often due deliveries are looked for.
+const WEBHOOK_POLL_INTERVAL: StdDuration = StdDuration::from_secs(1);
+
+/// How many due deliveries are made at a time.
+const WEBHOOK_BATCH_SIZE: usize = 50;
+
+#[derive(thiserror::Error, Debug)]
+pub enum WebhookConfigurationError {
+    #[error("invalid {0}: {1}")]
+    InvalidConfiguration(&'static str, String),
+}
+
+/// How often a delivery is attempted and how long to wait in between. The wait doubles after
+/// every failed attempt, up to `MAX_WEBHOOK_BACKOFF_SECONDS`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WebhookConfiguration {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+}
+
+impl Default for WebhookConfiguration {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_WEBHOOK_MAX_ATTEMPTS,
+            initial_backoff: Duration::seconds(DEFAULT_WEBHOOK_INITIAL_BACKOFF_SECONDS),
+        }
+    }
+}
+
+impl WebhookConfiguration {
+    /// Reads the `WEBHOOK_MAX_ATTEMPTS` and `WEBHOOK_INITIAL_BACKOFF_SECONDS` env vars,
+    /// defaulting to 8 attempts starting 30 seconds apart.
+    pub fn from_env() -> Result<Self, WebhookConfigurationError> {
+        Self::parse(
+            dotenvy::var(WEBHOOK_MAX_ATTEMPTS_ENV_VAR).ok().as_deref(),
+            dotenvy::var(WEBHOOK_INITIAL_BACKOFF_ENV_VAR)
+                .ok()
+                .as_deref(),
+        )
+    }
+
+    pub fn parse(
+        max_attempts: Option<&str>,
+        initial_backoff: Option<&str>,
+    ) -> Result<Self, WebhookConfigurationError> {
+        let mut configuration = Self::default();
+        if let Some(max_attempts) = max_attempts {
+            configuration.max_attempts =
+                parse_positive(WEBHOOK_MAX_ATTEMPTS_ENV_VAR, max_attempts)?;
+        }
+        if let Some(initial_backoff) = initial_backoff {
+            configuration.initial_backoff = Duration::seconds(parse_positive(
+                WEBHOOK_INITIAL_BACKOFF_ENV_VAR,
+                initial_backoff,
+            )?);
+        }
+        Ok(configuration)
+    }
+
+    /// How long to wait before retrying a delivery that failed `attempts` times, or `None` once
+    /// it ran out of attempts.
+    pub fn backoff(&self, attempts: u32) -> Option<Duration> {
+        if attempts == 0 || attempts >= self.max_attempts {
+            return None;
+        }
+        let max_backoff = Duration::seconds(MAX_WEBHOOK_BACKOFF_SECONDS);
+        let backoff = 2_i32
+            .checked_pow(attempts - 1)
+            .and_then(|factor| self.initial_backoff.checked_mul(factor))
+            .unwrap_or(max_backoff);
+        Some(backoff.min(max_backoff))
+    }
+}
+
+fn parse_positive<T: std::str::FromStr + PartialOrd + Default>(
+    name: &'static str,
+    value: &str,
+) -> Result<T, WebhookConfigurationError> {
+    value
+        .trim()
+        .parse()
+        .ok()
+        .filter(|value| *value > T::default())
+        .ok_or_else(|| WebhookConfigurationError::InvalidConfiguration(name, value.to_string()))
+}
+
+/// Signs a webhook body the way receivers verify it, see `WEBHOOK_SIGNATURE_HEADER`.
+pub fn sign_webhook_payload(secret: &str, timestamp: &str, body: &str) -> Option<String> {
+    encrypt_content_to_base64_urlsafe(
+        secret.as_bytes(),
+        &EncryptionContent {
+            content: body.to_string(),
+            salt: timestamp.to_string(),
+        },
+    )
+    .ok()
+}
+
+/// Whether webhooks may be delivered to the address. Loopback, private and link-local addresses,
+/// the last including the `169.254.169.254` metadata endpoint of cloud providers, and the other
+/// reserved ranges are refused, so that webhooks cannot reach into the server's own network.
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, _] = ip.octets();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || ip.is_multicast()
+                // "This network", shared address space, IETF protocol assignments, benchmarking
+                // and reserved for future use.
+                || a == 0
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 198 && (18..20).contains(&b))
+                || a >= 240)
+        }
+        IpAddr::V6(ip) => {
+            if let Some(ip) = ip.to_ipv4_mapped() {
+                return is_public_address(IpAddr::V4(ip));
+            }
+            let [first, second, ..] = ip.segments();
+            !(ip.is_unspecified()
+                || ip.is_loopback()
+                || ip.is_multicast()
+                // Unique local, link-local and documentation addresses.
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && second == 0x0db8))
+        }
+    }
+}
+
+/// Checks that the host of a webhook URL only resolves to public addresses.
+pub async fn ensure_public_destination(url: &str) -> Result<(), String> {
+    let parsed = url::Url::parse(url).map_err(|_| format!("invalid URL {}", url))?;
+    let addresses: Vec<IpAddr> = match parsed.host() {
+        Some(url::Host::Ipv4(ip)) => vec![IpAddr::V4(ip)],
+        Some(url::Host::Ipv6(ip)) => vec![IpAddr::V6(ip)],
+        Some(url::Host::Domain(domain)) => {
+            let port = parsed.port_or_known_default().unwrap_or_default();
+            tokio::net::lookup_host((domain, port))
+                .await
+                .map_err(|err| format!("failed to resolve {}: {}", domain, err))?
+                .map(|address| address.ip())
+                .collect()
+        }
+        None => return Err(format!("{} has no host", url)),
+    };
+    if addresses.is_empty() {
+        return Err(format!("{} resolves to no address", url));
+    }
+    match addresses.into_iter().find(|ip| !is_public_address(*ip)) {
+        Some(ip) => Err(format!("{} resolves to the non-public address {}", url, ip)),
+        None => Ok(()),
+    }
+}
+
+/// Resolves webhook hosts to their public addresses only. Connections are made to the addresses
+/// it resolves, so a host cannot be rebound to a private address after it was checked.
+struct PublicAddressResolver;
+
+impl reqwest::dns::Resolve for PublicAddressResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        Box::pin(resolve_public_addresses(name.as_str().to_string()))
+    }
+}
+
+async fn resolve_public_addresses(
+    host: String,
+) -> Result<reqwest::dns::Addrs, Box<dyn StdError + Send + Sync>> {
+    let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+        .await?
+        .filter(|address| is_public_address(address.ip()))
+        .collect();
+    if addresses.is_empty() {
+        return Err(format!("{} resolves to no public address", host).into());
+    }
+    Ok(Box::new(addresses.into_iter()))
+}
+
+/// The outcome of calling a webhook once.
+#[derive(Debug, PartialEq)]
+pub enum DeliveryAttempt {
+    Delivered {
+        response_status: u16,
+    },
+    Failed {
+        response_status: Option<u16>,
+        error: String,
+    },
+}
+
+/// Calls the webhook with the delivery, signed with its secret. Any 2xx response counts as
+/// delivered.
+pub async fn attempt_delivery(
+    client: &reqwest::Client,
+    delivery: &DueWebhookDelivery,
+) -> DeliveryAttempt {
+    let timestamp = Utc::now().timestamp().to_string();
+    let Some(signature) = sign_webhook_payload(&delivery.secret, &timestamp, &delivery.payload)
+    else {
+        return DeliveryAttempt::Failed {
+            response_status: None,
+            error: "failed to sign the payload".to_string(),
+        };
+    };
+    let response = client
+        .post(&delivery.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(WEBHOOK_SIGNATURE_HEADER, signature)
+        .header(WEBHOOK_TIMESTAMP_HEADER, timestamp)
+        .header(WEBHOOK_DELIVERY_HEADER, &delivery.delivery_id)
+        .header(WEBHOOK_EVENT_HEADER, &delivery.event_type)
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+    match response {
+        Ok(response) if response.status().is_success() => DeliveryAttempt::Delivered {
+            response_status: response.status().as_u16(),
+        },
+        Ok(response) => DeliveryAttempt::Failed {
+            response_status: Some(response.status().as_u16()),
+            error: format!("webhook responded with {}", response.status()),
+        },
+        Err(err) => DeliveryAttempt::Failed {
+            response_status: None,
+            error: err.to_string(),
+        },
+    }
+}
+
+/// Makes the webhook deliveries queued by the `WebhookQuery`, retrying failed ones with
+/// exponential backoff and dead-lettering the ones that run out of attempts. Delivery is
+/// at-least-once, receivers should drop deliveries whose `X-Veloxide-Delivery` they have seen.
+/// Run the sender on a single instance, senders on several instances could deliver twice.
+pub struct WebhookSender {
+    store: WebhookStore,
+    client: reqwest::Client,
+    configuration: WebhookConfiguration,
+}
+
+impl WebhookSender {
+    pub fn new(store: WebhookStore, configuration: WebhookConfiguration) -> Self {
+        // Redirects are not followed, as they could lead anywhere, private addresses included.
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(PublicAddressResolver))
+            .build()
+            .expect("expected to be able to build the webhook HTTP client");
+        Self {
+            store,
+            client,
+            configuration,
+        }
+    }
+
+    /// Delivers until the process exits.
+    pub async fn run(self) {
+        loop {
+            match self.send_due().await {
+                // A full batch likely means more deliveries are due.
+                Ok(sent) if sent == WEBHOOK_BATCH_SIZE => continue,
+                Ok(_) => {}
+                Err(err) => tracing::error!("failed to make webhook deliveries: {}", err),
+            }
+            tokio::time::sleep(WEBHOOK_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Attempts the deliveries that are due, returning how many were attempted.
+    #[tracing::instrument(skip(self), err)]
+    pub async fn send_due(&self) -> Result<usize, WebhookError> {
+        let deliveries = self.store.due(WEBHOOK_BATCH_SIZE).await?;
+        for delivery in &deliveries {
+            let attempts = delivery.attempts + 1;
+            // Checked on every attempt, as where the host resolves to may have changed since
+            // the webhook was registered.
+            let attempt = match ensure_public_destination(&delivery.url).await {
+                Ok(()) => attempt_delivery(&self.client, delivery).await,
+                Err(error) => DeliveryAttempt::Failed {
+                    response_status: None,
+                    error,
+                },
+            };
+            match attempt {
+                DeliveryAttempt::Delivered { response_status } => {
+                    self.store
+                        .record_delivered(delivery, attempts, response_status)
+                        .await?
+                }
+                DeliveryAttempt::Failed {
+                    response_status,
+                    error,
+                } => match self.configuration.backoff(attempts) {
+                    Some(backoff) => {
+                        let next_attempt_at = (Utc::now() + backoff).naive_utc();
+                        self.store
+                            .record_retry(
+                                delivery,
+                                attempts,
+                                response_status,
+                                &error,
+                                next_attempt_at,
+                            )
+                            .await?
+                    }
+                    None => {
+                        tracing::warn!(
+                            "dead-lettering webhook delivery {} after {} attempts: {}",
+                            delivery.delivery_id,
+                            attempts,
+                            error
+                        );
+                        self.store
+                            .record_dead_letter(delivery, attempts, response_status, &error)
+                            .await?
+                    }
+                },
+            }
+        }
+        Ok(deliveries.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{extract::State, http::HeaderMap, http::StatusCode, routing::post, Router};
+    use pretty_assertions::assert_eq;
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+
+    type Received = Arc<Mutex<Vec<(HeaderMap, String)>>>;
+
+    // A local receiver that records the calls it gets and responds with `status`.
+    fn start_receiver(status: StatusCode) -> (SocketAddr, Received) {
+        let received: Received = Arc::default();
+        let router =
+            Router::new()
+                .route(
+                    "/hooks",
+                    post(
+                        move |State(received): State<Received>,
+                              headers: HeaderMap,
+                              body: String| async move {
+                            received.lock().unwrap().push((headers, body));
+                            status
+                        },
+                    ),
+                )
+                .with_state(received.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(
+            axum::Server::from_tcp(listener)
+                .unwrap()
+                .serve(router.into_make_service()),
+        );
+        (address, received)
+    }
+
+    fn delivery(url: String) -> DueWebhookDelivery {
+        DueWebhookDelivery {
+            delivery_id: "7d9f1e04".to_string(),
+            subscription_id: "0b6a3c52".to_string(),
+            event_type: "CustomerDepositedMoney".to_string(),
+            url,
+            secret: "whsec_3f0c2b9e".to_string(),
+            payload: r#"{"account_id":"1234","sequence":2}"#.to_string(),
+            attempts: 0,
+        }
+    }
+
+    #[test]
+    fn backoff_doubles_until_attempts_run_out() {
+        let configuration = WebhookConfiguration::default();
+        assert_eq!(configuration.backoff(1), Some(Duration::seconds(30)));
+        assert_eq!(configuration.backoff(2), Some(Duration::seconds(60)));
+        assert_eq!(configuration.backoff(4), Some(Duration::seconds(240)));
+        assert_eq!(configuration.backoff(DEFAULT_WEBHOOK_MAX_ATTEMPTS), None);
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        let configuration = WebhookConfiguration::parse(Some("100"), Some("3600")).unwrap();
+        assert_eq!(
+            configuration.backoff(40),
+            Some(Duration::seconds(MAX_WEBHOOK_BACKOFF_SECONDS))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_configuration() {
+        assert!(WebhookConfiguration::parse(Some("0"), None).is_err());
+        assert!(WebhookConfiguration::parse(None, Some("soon")).is_err());
+        assert_eq!(
+            WebhookConfiguration::parse(None, None).unwrap(),
+            WebhookConfiguration::default()
+        );
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_payloads() {
+        let (address, received) = start_receiver(StatusCode::NO_CONTENT);
+        let delivery = delivery(format!("http://{}/hooks", address));
+
+        let attempt = attempt_delivery(&reqwest::Client::new(), &delivery).await;
+
+        assert_eq!(
+            attempt,
+            DeliveryAttempt::Delivered {
+                response_status: 204
+            }
+        );
+        let received = received.lock().unwrap();
+        let (headers, body) = &received[0];
+        assert_eq!(body, &delivery.payload);
+        assert_eq!(headers[WEBHOOK_DELIVERY_HEADER], "7d9f1e04");
+        assert_eq!(headers[WEBHOOK_EVENT_HEADER], "CustomerDepositedMoney");
+        let timestamp = headers[WEBHOOK_TIMESTAMP_HEADER].to_str().unwrap();
+        assert_eq!(
+            headers[WEBHOOK_SIGNATURE_HEADER].to_str().unwrap(),
+            sign_webhook_payload("whsec_3f0c2b9e", timestamp, body).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn error_responses_fail_the_attempt() {
+        let (address, _) = start_receiver(StatusCode::SERVICE_UNAVAILABLE);
+        let delivery = delivery(format!("http://{}/hooks", address));
+
+        let attempt = attempt_delivery(&reqwest::Client::new(), &delivery).await;
+
+        assert!(matches!(
+            attempt,
+            DeliveryAttempt::Failed {
+                response_status: Some(503),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn refuses_addresses_within_private_networks() {
+        for address in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_address(address.parse().unwrap()), "{}", address);
+        }
+        for address in ["93.184.216.34", "2606:2800:220:1:248:1893:25c8:1946"] {
+            assert!(is_public_address(address.parse().unwrap()), "{}", address);
+        }
+    }
+
+    #[tokio::test]
+    async fn refuses_destinations_within_private_networks() {
+        assert!(ensure_public_destination("http://127.0.0.1:9000/hooks")
+            .await
+            .is_err());
+        assert!(
+            ensure_public_destination("http://169.254.169.254/latest/meta-data")
+                .await
+                .is_err()
+        );
+        assert!(ensure_public_destination("http://localhost:9000/hooks")
+            .await
+            .is_err());
+        assert!(ensure_public_destination("https://93.184.216.34/hooks")
+            .await
+            .is_ok());
+    }
+}
@@ -0,0 +1,436 @@
+#[cfg(feature = "graphql")]
+use async_graphql::SimpleObject;
+
+#[cfg(feature = "frontend")]
+use ts_rs::TS;
+
+use super::*;
+
+/// The table the users of every account and their roles are projected into.
+pub const ACCOUNT_ACCESS_TABLE: &str = "account_access";
+
+#[derive(thiserror::Error, Debug)]
+pub enum AccountAccessError {
+    #[error("invalid access role: {0}")]
+    InvalidRole(String),
+
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// A user with access to a bank account.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+#[cfg_attr(feature = "graphql", derive(SimpleObject))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct AccountAccess {
+    pub user_id: String,
+    pub role: AccessRole,
+}
+
+// How a bank account event changes who may access the account, if at all.
+#[derive(Debug, PartialEq)]
+enum AccessChange {
+    Grant { user_id: String, role: AccessRole },
+    Revoke { user_id: String },
+}
+
+impl AccessChange {
+    fn from_event(event: &BankAccountEvent) -> Option<Self> {
+        match event {
+            BankAccountEvent::AccountOpened {
+                owner_id: Some(owner_id),
+                ..
+            }
+            | BankAccountEvent::OwnerAssigned { owner_id } => Some(AccessChange::Grant {
+                user_id: owner_id.clone(),
+                role: AccessRole::Owner,
+            }),
+            BankAccountEvent::AccessGranted { user_id, role } => Some(AccessChange::Grant {
+                user_id: user_id.clone(),
+                role: *role,
+            }),
+            BankAccountEvent::AccessRevoked { user_id } => Some(AccessChange::Revoke {
+                user_id: user_id.clone(),
+            }),
+            _ => None,
+        }
+    }
+}
+
+fn parse_role(role: &str) -> Result<AccessRole, AccountAccessError> {
+    role.parse()
+        .map_err(|_| AccountAccessError::InvalidRole(role.to_string()))
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use async_trait::async_trait;
+        use cqrs_es::persist::PersistedEventStore;
+        use cqrs_es::EventStore;
+        use postgres_es::PostgresEventRepository;
+        use sqlx::postgres::PgRow;
+        use sqlx::{PgConnection, Pool, Postgres, Row};
+
+        use crate::infrastructure::projections::{
+            aggregate_ids, Projection, ProjectionError, ProjectionProgress, ProjectionRebuildReport,
+        };
+
+        /// Projects the owner of each account and the users it was shared with into the
+        /// `account_access` table, one row per user, so that the role of a user on an account
+        /// can be looked up without replaying its events.
+        #[derive(Clone)]
+        pub struct AccountAccessQuery {
+            pool: Pool<Postgres>,
+        }
+
+        impl AccountAccessQuery {
+            pub fn new(pool: Pool<Postgres>) -> Self {
+                Self { pool }
+            }
+
+            /// Every user with access to the account.
+            pub async fn list(&self, account_id: &str) -> Result<Vec<AccountAccess>, AccountAccessError> {
+                let rows = sqlx::query("SELECT user_id, role FROM account_access WHERE account_id = $1 ORDER BY user_id")
+                    .bind(account_id)
+                    .fetch_all(&self.pool)
+                    .await?;
+                rows.iter().map(account_access_from_row).collect()
+            }
+
+            // Granting upserts, so that the same events can be dispatched again, e.g. by a rebuild.
+            async fn record(&self, connection: &mut PgConnection, account_id: &str, events: &[EventEnvelope<BankAccount>]) -> Result<(), sqlx::Error> {
+                for event in events {
+                    match AccessChange::from_event(&event.payload) {
+                        Some(AccessChange::Grant { user_id, role }) => {
+                            sqlx::query(
+                                "INSERT INTO account_access (account_id, user_id, role) VALUES ($1, $2, $3) ON CONFLICT (account_id, user_id) DO UPDATE SET role = EXCLUDED.role",
+                            )
+                            .bind(account_id)
+                            .bind(user_id)
+                            .bind(role.to_string())
+                            .execute(&mut *connection)
+                            .await?;
+                        }
+                        Some(AccessChange::Revoke { user_id }) => {
+                            sqlx::query("DELETE FROM account_access WHERE account_id = $1 AND user_id = $2")
+                                .bind(account_id)
+                                .bind(user_id)
+                                .execute(&mut *connection)
+                                .await?;
+                        }
+                        None => {}
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        fn account_access_from_row(row: &PgRow) -> Result<AccountAccess, AccountAccessError> {
+            let role: String = row.try_get("role")?;
+            Ok(AccountAccess {
+                user_id: row.try_get("user_id")?,
+                role: parse_role(&role)?,
+            })
+        }
+
+        #[async_trait]
+        impl Query<BankAccount> for AccountAccessQuery {
+            async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<BankAccount>]) {
+                let recorded = match self.pool.acquire().await {
+                    Ok(mut connection) => self.record(&mut connection, aggregate_id, events).await,
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = recorded {
+                    tracing::error!("failed to record access to account {}: {}", aggregate_id, err);
+                }
+            }
+        }
+
+        #[async_trait]
+        impl Projection for AccountAccessQuery {
+            fn name(&self) -> &str {
+                ACCOUNT_ACCESS_TABLE
+            }
+
+            async fn rebuild(
+                &self,
+                aggregate_id: Option<&str>,
+                on_progress: &(dyn Fn(&ProjectionProgress) + Send + Sync),
+            ) -> Result<ProjectionRebuildReport, ProjectionError> {
+                let mut transaction = self.pool.begin().await?;
+                let aggregate_ids: Vec<String> = match aggregate_id {
+                    Some(aggregate_id) => {
+                        sqlx::query("DELETE FROM account_access WHERE account_id = $1")
+                            .bind(aggregate_id)
+                            .execute(&mut *transaction)
+                            .await?;
+                        vec![aggregate_id.to_string()]
+                    }
+                    None => {
+                        sqlx::query("TRUNCATE TABLE account_access")
+                            .execute(&mut *transaction)
+                            .await?;
+                        aggregate_ids::<BankAccount>(&self.pool).await?
+                    }
+                };
+
+                let event_store: PersistedEventStore<PostgresEventRepository, BankAccount> =
+                    PersistedEventStore::new_event_store(PostgresEventRepository::new(self.pool.clone()))
+                        .with_upcasters(bank_account_upcasters());
+                let total = aggregate_ids.len();
+                let mut events_replayed = 0;
+                for (index, aggregate_id) in aggregate_ids.into_iter().enumerate() {
+                    let replay_error = |reason: String| ProjectionError::Replay {
+                        aggregate_id: aggregate_id.clone(),
+                        reason,
+                    };
+                    let events = event_store
+                        .load_events(&aggregate_id)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    self.record(&mut transaction, &aggregate_id, &events)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    events_replayed += events.len();
+                    on_progress(&ProjectionProgress {
+                        aggregate_id,
+                        replayed: index + 1,
+                        total,
+                    });
+                }
+                transaction.commit().await?;
+                Ok(ProjectionRebuildReport {
+                    projection: ACCOUNT_ACCESS_TABLE.to_string(),
+                    aggregates_replayed: total,
+                    events_replayed,
+                })
+            }
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use async_trait::async_trait;
+        use cqrs_es::persist::PersistedEventStore;
+        use cqrs_es::EventStore;
+        use mysql_es::MysqlEventRepository;
+        use sqlx::mysql::MySqlRow;
+        use sqlx::{MySql, MySqlConnection, Pool, Row};
+
+        use crate::infrastructure::projections::{
+            aggregate_ids, Projection, ProjectionError, ProjectionProgress, ProjectionRebuildReport,
+        };
+
+        /// Projects the owner of each account and the users it was shared with into the
+        /// `account_access` table, one row per user, so that the role of a user on an account
+        /// can be looked up without replaying its events.
+        #[derive(Clone)]
+        pub struct AccountAccessQuery {
+            pool: Pool<MySql>,
+        }
+
+        impl AccountAccessQuery {
+            pub fn new(pool: Pool<MySql>) -> Self {
+                Self { pool }
+            }
+
+            /// Every user with access to the account.
+            pub async fn list(&self, account_id: &str) -> Result<Vec<AccountAccess>, AccountAccessError> {
+                let rows = sqlx::query("SELECT user_id, role FROM account_access WHERE account_id = ? ORDER BY user_id")
+                    .bind(account_id)
+                    .fetch_all(&self.pool)
+                    .await?;
+                rows.iter().map(account_access_from_row).collect()
+            }
+
+            // Granting upserts, so that the same events can be dispatched again, e.g. by a rebuild.
+            async fn record(&self, connection: &mut MySqlConnection, account_id: &str, events: &[EventEnvelope<BankAccount>]) -> Result<(), sqlx::Error> {
+                for event in events {
+                    match AccessChange::from_event(&event.payload) {
+                        Some(AccessChange::Grant { user_id, role }) => {
+                            sqlx::query(
+                                "INSERT INTO account_access (account_id, user_id, role) VALUES (?, ?, ?) ON DUPLICATE KEY UPDATE role = VALUES(role)",
+                            )
+                            .bind(account_id)
+                            .bind(user_id)
+                            .bind(role.to_string())
+                            .execute(&mut *connection)
+                            .await?;
+                        }
+                        Some(AccessChange::Revoke { user_id }) => {
+                            sqlx::query("DELETE FROM account_access WHERE account_id = ? AND user_id = ?")
+                                .bind(account_id)
+                                .bind(user_id)
+                                .execute(&mut *connection)
+                                .await?;
+                        }
+                        None => {}
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        fn account_access_from_row(row: &MySqlRow) -> Result<AccountAccess, AccountAccessError> {
+            let role: String = row.try_get("role")?;
+            Ok(AccountAccess {
+                user_id: row.try_get("user_id")?,
+                role: parse_role(&role)?,
+            })
+        }
+
+        #[async_trait]
+        impl Query<BankAccount> for AccountAccessQuery {
+            async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<BankAccount>]) {
+                let recorded = match self.pool.acquire().await {
+                    Ok(mut connection) => self.record(&mut connection, aggregate_id, events).await,
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = recorded {
+                    tracing::error!("failed to record access to account {}: {}", aggregate_id, err);
+                }
+            }
+        }
+
+        #[async_trait]
+        impl Projection for AccountAccessQuery {
+            fn name(&self) -> &str {
+                ACCOUNT_ACCESS_TABLE
+            }
+
+            async fn rebuild(
+                &self,
+                aggregate_id: Option<&str>,
+                on_progress: &(dyn Fn(&ProjectionProgress) + Send + Sync),
+            ) -> Result<ProjectionRebuildReport, ProjectionError> {
+                let mut transaction = self.pool.begin().await?;
+                let aggregate_ids: Vec<String> = match aggregate_id {
+                    Some(aggregate_id) => {
+                        sqlx::query("DELETE FROM account_access WHERE account_id = ?")
+                            .bind(aggregate_id)
+                            .execute(&mut *transaction)
+                            .await?;
+                        vec![aggregate_id.to_string()]
+                    }
+                    None => {
+                        sqlx::query("DELETE FROM account_access")
+                            .execute(&mut *transaction)
+                            .await?;
+                        aggregate_ids::<BankAccount>(&self.pool).await?
+                    }
+                };
+
+                let event_store: PersistedEventStore<MysqlEventRepository, BankAccount> =
+                    PersistedEventStore::new_event_store(MysqlEventRepository::new(self.pool.clone()))
+                        .with_upcasters(bank_account_upcasters());
+                let total = aggregate_ids.len();
+                let mut events_replayed = 0;
+                for (index, aggregate_id) in aggregate_ids.into_iter().enumerate() {
+                    let replay_error = |reason: String| ProjectionError::Replay {
+                        aggregate_id: aggregate_id.clone(),
+                        reason,
+                    };
+                    let events = event_store
+                        .load_events(&aggregate_id)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    self.record(&mut transaction, &aggregate_id, &events)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    events_replayed += events.len();
+                    on_progress(&ProjectionProgress {
+                        aggregate_id,
+                        replayed: index + 1,
+                        total,
+                    });
+                }
+                transaction.commit().await?;
+                Ok(ProjectionRebuildReport {
+                    projection: ACCOUNT_ACCESS_TABLE.to_string(),
+                    aggregates_replayed: total,
+                    events_replayed,
+                })
+            }
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn the_user_opening_an_account_becomes_its_owner() {
+        let event = BankAccountEvent::AccountOpened {
+            account_id: "1234".to_string(),
+            currency: "USD".to_string(),
+            owner_id: Some("1111".to_string()),
+        };
+        assert_eq!(
+            AccessChange::from_event(&event),
+            Some(AccessChange::Grant {
+                user_id: "1111".to_string(),
+                role: AccessRole::Owner,
+            })
+        );
+    }
+
+    #[test]
+    fn accounts_opened_without_owner_grant_no_access() {
+        let event = BankAccountEvent::AccountOpened {
+            account_id: "1234".to_string(),
+            currency: "USD".to_string(),
+            owner_id: None,
+        };
+        assert_eq!(AccessChange::from_event(&event), None);
+    }
+
+    #[test]
+    fn the_user_assigned_to_an_account_without_owner_becomes_its_owner() {
+        let event = BankAccountEvent::OwnerAssigned {
+            owner_id: "1111".to_string(),
+        };
+        assert_eq!(
+            AccessChange::from_event(&event),
+            Some(AccessChange::Grant {
+                user_id: "1111".to_string(),
+                role: AccessRole::Owner,
+            })
+        );
+    }
+
+    #[test]
+    fn grants_and_revokes_access() {
+        let granted = BankAccountEvent::AccessGranted {
+            user_id: "2222".to_string(),
+            role: AccessRole::PaymentsOnly,
+        };
+        assert_eq!(
+            AccessChange::from_event(&granted),
+            Some(AccessChange::Grant {
+                user_id: "2222".to_string(),
+                role: AccessRole::PaymentsOnly,
+            })
+        );
+
+        let revoked = BankAccountEvent::AccessRevoked {
+            user_id: "2222".to_string(),
+        };
+        assert_eq!(
+            AccessChange::from_event(&revoked),
+            Some(AccessChange::Revoke {
+                user_id: "2222".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_roles() {
+        assert!(matches!(
+            parse_role("Admin"),
+            Err(AccountAccessError::InvalidRole(role)) if role == "Admin"
+        ));
+    }
+}
@@ -0,0 +1,329 @@
+use super::*;
+use async_trait::async_trait;
+use cqrs_es::persist::{EventUpcaster, PersistedEventRepository, SerializedEvent};
+use futures::future::BoxFuture;
+use futures::stream::{self, BoxStream, StreamExt};
+use std::collections::VecDeque;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::application::{Actor, BankAccountApplicationService, BankAccountServiceError};
+use crate::domain::AccountPermission;
+
+/// How many events may be waiting for the slowest subscriber before it falls behind, after
+/// which it catches up from the event store.
+pub const ACCOUNT_EVENTS_CAPACITY: usize = 1024;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AccountEventsError {
+    #[error("failed to replay the events of account {account_id}: {reason}")]
+    Replay { account_id: String, reason: String },
+
+    #[error("access denied to bank account {0}")]
+    AccessDenied(String),
+
+    #[error("failed to check access to account {account_id}: {reason}")]
+    AccessCheck { account_id: String, reason: String },
+}
+
+/// Checks whether the subscriber may still see the account, failing with `AccessDenied` once
+/// it may not.
+pub type AccessCheck =
+    Box<dyn Fn() -> BoxFuture<'static, Result<(), AccountEventsError>> + Send + Sync>;
+
+/// Checks that the role of `actor` on the account still lets them view it.
+pub fn view_access_check(
+    bank_account_service: Arc<dyn BankAccountApplicationService>,
+    actor: Actor,
+    account_id: String,
+) -> AccessCheck {
+    Box::new(move || {
+        let bank_account_service = bank_account_service.clone();
+        let actor = actor.clone();
+        let account_id = account_id.clone();
+        Box::pin(async move {
+            match bank_account_service
+                .authorize(&actor, account_id.clone(), AccountPermission::View)
+                .await
+            {
+                Ok(_) => Ok(()),
+                Err(BankAccountServiceError::AccessDenied(_)) => {
+                    Err(AccountEventsError::AccessDenied(account_id))
+                }
+                Err(err) => Err(AccountEventsError::AccessCheck {
+                    account_id,
+                    reason: err.to_string(),
+                }),
+            }
+        })
+    })
+}
+
+/// An event committed on a bank account, as sent to subscribers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccountEvent {
+    pub account_id: String,
+    pub sequence: usize,
+    pub payload: BankAccountEvent,
+}
+
+impl From<&EventEnvelope<BankAccount>> for AccountEvent {
+    fn from(event: &EventEnvelope<BankAccount>) -> Self {
+        Self {
+            account_id: event.aggregate_id.clone(),
+            sequence: event.sequence,
+            payload: event.payload.clone(),
+        }
+    }
+}
+
+// What a subscriber that has received every event before `next_sequence` does with a live event.
+#[derive(Debug, PartialEq)]
+enum Delivery {
+    Skip,
+    Send,
+    CatchUp,
+}
+
+fn delivery(account_id: &str, next_sequence: usize, event: &AccountEvent) -> Delivery {
+    if event.account_id != account_id || event.sequence < next_sequence {
+        Delivery::Skip
+    } else if event.sequence == next_sequence {
+        Delivery::Send
+    } else {
+        // The events in between were missed, they are read back from the event store.
+        Delivery::CatchUp
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use postgres_es::PostgresEventRepository;
+        use sqlx::{Pool, Postgres};
+
+        type AccountEventRepository = PostgresEventRepository;
+
+        impl AccountEventBroadcaster {
+            pub fn new(pool: Pool<Postgres>) -> Self {
+                Self::with_repository(PostgresEventRepository::new(pool), bank_account_upcasters())
+            }
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use mysql_es::MysqlEventRepository;
+        use sqlx::{MySql, Pool};
+
+        type AccountEventRepository = MysqlEventRepository;
+
+        impl AccountEventBroadcaster {
+            pub fn new(pool: Pool<MySql>) -> Self {
+                Self::with_repository(MysqlEventRepository::new(pool), bank_account_upcasters())
+            }
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+/// Broadcasts the events of every bank account to its subscribers as they are committed. A
+/// subscription first replays the history of the account from the event store, then switches
+/// over to the live events.
+#[derive(Clone)]
+pub struct AccountEventBroadcaster {
+    sender: broadcast::Sender<AccountEvent>,
+    repository: Arc<AccountEventRepository>,
+    upcasters: Arc<Vec<Box<dyn EventUpcaster>>>,
+}
+
+impl AccountEventBroadcaster {
+    fn with_repository(
+        repository: AccountEventRepository,
+        upcasters: Vec<Box<dyn EventUpcaster>>,
+    ) -> Self {
+        let (sender, _) = broadcast::channel(ACCOUNT_EVENTS_CAPACITY);
+        Self {
+            sender,
+            repository: Arc::new(repository),
+            upcasters: Arc::new(upcasters),
+        }
+    }
+
+    /// The events of the account from `from_sequence` onwards, the history first. Every time
+    /// access to the account is revoked from anyone, `access_check` is run again before the
+    /// revocation is sent, so that a subscriber who lost access gets an `AccessDenied` error
+    /// instead. The stream ends after the first error.
+    pub fn subscribe(
+        &self,
+        account_id: String,
+        from_sequence: usize,
+        access_check: AccessCheck,
+    ) -> BoxStream<'static, Result<AccountEvent, AccountEventsError>> {
+        // Subscribed before the history is loaded, so no event committed in between is missed.
+        let subscription = Subscription {
+            account_id,
+            next_sequence: from_sequence,
+            catching_up: true,
+            backlog: VecDeque::new(),
+            receiver: self.sender.subscribe(),
+            repository: self.repository.clone(),
+            upcasters: self.upcasters.clone(),
+            access_check,
+        };
+        stream::unfold(Some(subscription), |subscription| async move {
+            let mut subscription = subscription?;
+            match subscription.next().await {
+                Ok(Some(event)) => Some((Ok(event), Some(subscription))),
+                Ok(None) => None,
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+        .boxed()
+    }
+}
+
+#[async_trait]
+impl Query<BankAccount> for AccountEventBroadcaster {
+    async fn dispatch(&self, _aggregate_id: &str, events: &[EventEnvelope<BankAccount>]) {
+        for event in events {
+            // Sending only fails while nobody is subscribed.
+            let _ = self.sender.send(AccountEvent::from(event));
+        }
+    }
+}
+
+struct Subscription {
+    account_id: String,
+    next_sequence: usize,
+    catching_up: bool,
+    backlog: VecDeque<AccountEvent>,
+    receiver: broadcast::Receiver<AccountEvent>,
+    repository: Arc<AccountEventRepository>,
+    upcasters: Arc<Vec<Box<dyn EventUpcaster>>>,
+    access_check: AccessCheck,
+}
+
+impl Subscription {
+    async fn next(&mut self) -> Result<Option<AccountEvent>, AccountEventsError> {
+        let Some(event) = self.next_event().await? else {
+            return Ok(None);
+        };
+        if matches!(event.payload, BankAccountEvent::AccessRevoked { .. }) {
+            (self.access_check)().await?;
+        }
+        Ok(Some(event))
+    }
+
+    async fn next_event(&mut self) -> Result<Option<AccountEvent>, AccountEventsError> {
+        loop {
+            if self.catching_up {
+                self.backlog = self.load().await?;
+                self.catching_up = false;
+            }
+            if let Some(event) = self.backlog.pop_front() {
+                self.next_sequence = event.sequence + 1;
+                return Ok(Some(event));
+            }
+            match self.receiver.recv().await {
+                Ok(event) => match delivery(&self.account_id, self.next_sequence, &event) {
+                    Delivery::Skip => {}
+                    Delivery::Send => {
+                        self.next_sequence = event.sequence + 1;
+                        return Ok(Some(event));
+                    }
+                    Delivery::CatchUp => self.catching_up = true,
+                },
+                Err(RecvError::Lagged(_)) => self.catching_up = true,
+                Err(RecvError::Closed) => return Ok(None),
+            }
+        }
+    }
+
+    // Only the events from the next sequence onwards are read back, not the whole stream.
+    async fn load(&self) -> Result<VecDeque<AccountEvent>, AccountEventsError> {
+        let replay_error = |reason: String| AccountEventsError::Replay {
+            account_id: self.account_id.clone(),
+            reason,
+        };
+        let events = self
+            .repository
+            .get_last_events::<BankAccount>(&self.account_id, self.next_sequence.saturating_sub(1))
+            .await
+            .map_err(|err| replay_error(err.to_string()))?;
+        events
+            .into_iter()
+            .map(|event| {
+                let event = upcast(&self.upcasters, event);
+                Ok(AccountEvent {
+                    account_id: event.aggregate_id,
+                    sequence: event.sequence,
+                    payload: serde_json::from_value(event.payload)
+                        .map_err(|err| replay_error(err.to_string()))?,
+                })
+            })
+            .collect()
+    }
+}
+
+fn upcast(upcasters: &[Box<dyn EventUpcaster>], event: SerializedEvent) -> SerializedEvent {
+    upcasters.iter().fold(event, |event, upcaster| {
+        if upcaster.can_upcast(&event.event_type, &event.event_version) {
+            upcaster.upcast(event)
+        } else {
+            event
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coverage_helper::test;
+    use pretty_assertions::assert_eq;
+
+    fn event(account_id: &str, sequence: usize) -> AccountEvent {
+        AccountEvent {
+            account_id: account_id.to_string(),
+            sequence,
+            payload: BankAccountEvent::AccountFrozen {
+                reason: "suspected fraud".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn sends_the_next_event_of_the_account() {
+        assert_eq!(delivery("1234", 4, &event("1234", 4)), Delivery::Send);
+    }
+
+    #[test]
+    fn skips_other_accounts_and_events_already_sent() {
+        assert_eq!(delivery("1234", 4, &event("5678", 4)), Delivery::Skip);
+        assert_eq!(delivery("1234", 4, &event("1234", 3)), Delivery::Skip);
+    }
+
+    #[test]
+    fn catches_up_after_missing_events() {
+        assert_eq!(delivery("1234", 4, &event("1234", 6)), Delivery::CatchUp);
+    }
+
+    #[test]
+    fn upcasts_the_events_read_back_from_the_event_store() {
+        let event = SerializedEvent {
+            aggregate_id: "1234".to_string(),
+            sequence: 5,
+            aggregate_type: "account".to_string(),
+            event_type: "CustomerDepositedMoney".to_string(),
+            event_version: "1.0".to_string(),
+            payload: serde_json::json!({"CustomerDepositedMoney": {"amount": 100.0, "balance": 250.0}}),
+            metadata: serde_json::json!({}),
+        };
+
+        let event = upcast(&bank_account_upcasters(), event);
+        assert_eq!(
+            serde_json::from_value::<BankAccountEvent>(event.payload).unwrap(),
+            BankAccountEvent::CustomerDepositedMoney {
+                amount: Money::new(10000, "USD"),
+                balance: Money::new(25000, "USD"),
+            }
+        );
+    }
+}
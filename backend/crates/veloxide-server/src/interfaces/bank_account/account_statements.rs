@@ -0,0 +1,360 @@
+#[cfg(feature = "graphql")]
+use async_graphql::SimpleObject;
+
+#[cfg(feature = "frontend")]
+use ts_rs::TS;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use cqrs_es::persist::PersistedEventStore;
+use cqrs_es::EventStore;
+
+use super::*;
+use crate::infrastructure::projections::{event_created_at, fill_event_times, EVENT_TIME_METADATA};
+use crate::infrastructure::DbPool;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AccountStatementError {
+    #[error("invalid statement period, expected a month such as 2023-11: {0}")]
+    InvalidPeriod(String),
+
+    #[error("account {account_id} was not open in {period}")]
+    NotOpen { account_id: String, period: String },
+
+    #[error("failed to replay the events of account {account_id}: {reason}")]
+    Replay { account_id: String, reason: String },
+}
+
+/// The month a statement covers, from the start of its first day up to the start of the next
+/// month, in UTC.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementPeriod {
+    pub period: String,
+    pub starts_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+}
+
+impl StatementPeriod {
+    /// Parses a month such as "2023-11".
+    pub fn parse(period: &str) -> Result<Self, AccountStatementError> {
+        let invalid_period = || AccountStatementError::InvalidPeriod(period.to_string());
+        let first_day = NaiveDate::parse_from_str(&format!("{}-01", period.trim()), "%Y-%m-%d")
+            .map_err(|_| invalid_period())?;
+        let next_first_day = first_day_of_next_month(first_day).ok_or_else(invalid_period)?;
+        Ok(Self {
+            period: interest_period(first_day),
+            starts_at: first_day
+                .and_hms_opt(0, 0, 0)
+                .ok_or_else(invalid_period)?
+                .and_utc(),
+            ends_at: next_first_day
+                .and_hms_opt(0, 0, 0)
+                .ok_or_else(invalid_period)?
+                .and_utc(),
+        })
+    }
+}
+
+/// A transaction on a statement, with the balance it left the account at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "graphql", derive(SimpleObject))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct AccountStatementLine {
+    /// When the transaction was made, as an RFC 3339 timestamp
+    pub occurred_at: String,
+    pub transaction_type: AccountTransactionType,
+    pub description: String,
+    /// What the transaction changed the balance by, negative for money going out
+    pub amount: Money,
+    pub balance: Money,
+}
+
+/// The transactions of an account over a month, between the balance it started and ended at.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+#[cfg_attr(feature = "graphql", derive(SimpleObject))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct AccountStatement {
+    pub account_id: String,
+    /// The month covered, e.g. "2023-11"
+    pub period: String,
+    /// RFC 3339 timestamp the statement starts at
+    pub starts_at: String,
+    /// RFC 3339 timestamp the statement ends before
+    pub ends_at: String,
+    pub opening_balance: Money,
+    pub transactions: Vec<AccountStatementLine>,
+    pub closing_balance: Money,
+}
+
+impl AccountStatement {
+    /// Draws up the statement from every event of the account, or returns `None` if the
+    /// account had not been opened by the end of the period. The `AccountStatementGenerator`
+    /// gives every event a time, events without one are taken to have happened at the time of
+    /// the event before them.
+    pub fn from_events(
+        account_id: &str,
+        period: &StatementPeriod,
+        events: &[EventEnvelope<BankAccount>],
+    ) -> Option<Self> {
+        let mut balance: Option<Money> = None;
+        let mut opening_balance: Option<Money> = None;
+        let mut transactions = vec![];
+        let mut occurred_at = DateTime::<Utc>::MIN_UTC;
+        for event in events {
+            occurred_at = event_time(event).map_or(occurred_at, |time| time.max(occurred_at));
+            if occurred_at >= period.ends_at {
+                break;
+            }
+            if let BankAccountEvent::AccountOpened { currency, .. } = &event.payload {
+                balance = Some(Money::zero(currency));
+            }
+            if occurred_at >= period.starts_at && opening_balance.is_none() {
+                opening_balance = balance.clone();
+            }
+            let Some(new_balance) = event.payload.balance() else {
+                continue;
+            };
+            if occurred_at >= period.starts_at {
+                if let Some(transaction) = NewAccountTransaction::from_event(&event.payload) {
+                    let previous_balance = balance
+                        .clone()
+                        .unwrap_or_else(|| Money::zero(&new_balance.currency));
+                    transactions.push(AccountStatementLine {
+                        occurred_at: occurred_at.to_rfc3339(),
+                        transaction_type: transaction.transaction_type,
+                        description: transaction.description,
+                        amount: new_balance.checked_sub(&previous_balance).ok()?,
+                        balance: new_balance.clone(),
+                    });
+                }
+            }
+            balance = Some(new_balance.clone());
+        }
+        let closing_balance = balance?;
+        Some(Self {
+            account_id: account_id.to_string(),
+            period: period.period.clone(),
+            starts_at: period.starts_at.to_rfc3339(),
+            ends_at: period.ends_at.to_rfc3339(),
+            opening_balance: opening_balance.unwrap_or_else(|| closing_balance.clone()),
+            transactions,
+            closing_balance,
+        })
+    }
+}
+
+fn event_time(event: &EventEnvelope<BankAccount>) -> Option<DateTime<Utc>> {
+    event
+        .metadata
+        .get(EVENT_TIME_METADATA)
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map(|time| time.with_timezone(&Utc))
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use postgres_es::PostgresEventRepository;
+        use sqlx::{Pool, Postgres};
+
+        type StatementEventStore = PersistedEventStore<PostgresEventRepository, BankAccount>;
+
+        impl AccountStatementGenerator {
+            pub fn new(pool: Pool<Postgres>) -> Self {
+                Self::with_event_store(
+                    pool.clone(),
+                    PersistedEventStore::new_event_store(PostgresEventRepository::new(pool))
+                        .with_upcasters(bank_account_upcasters()),
+                )
+            }
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use mysql_es::MysqlEventRepository;
+        use sqlx::{MySql, Pool};
+
+        type StatementEventStore = PersistedEventStore<MysqlEventRepository, BankAccount>;
+
+        impl AccountStatementGenerator {
+            pub fn new(pool: Pool<MySql>) -> Self {
+                Self::with_event_store(
+                    pool.clone(),
+                    PersistedEventStore::new_event_store(MysqlEventRepository::new(pool))
+                        .with_upcasters(bank_account_upcasters()),
+                )
+            }
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+/// Draws up account statements from the event stream of the account, so that they show the
+/// balances as they were at the time rather than as the views have them now.
+#[derive(Clone)]
+pub struct AccountStatementGenerator {
+    pool: DbPool,
+    event_store: Arc<StatementEventStore>,
+}
+
+impl AccountStatementGenerator {
+    fn with_event_store(pool: DbPool, event_store: StatementEventStore) -> Self {
+        Self {
+            pool,
+            event_store: Arc::new(event_store),
+        }
+    }
+
+    pub async fn generate(
+        &self,
+        account_id: &str,
+        period: &StatementPeriod,
+    ) -> Result<AccountStatement, AccountStatementError> {
+        let replay_error = |reason: String| AccountStatementError::Replay {
+            account_id: account_id.to_string(),
+            reason,
+        };
+        let mut events = self
+            .event_store
+            .load_events(account_id)
+            .await
+            .map_err(|err| replay_error(err.to_string()))?;
+        // Commands issued by the server, such as crediting a transfer, record no time of their
+        // own, so they are dated by when their events were committed.
+        let created_at = event_created_at::<BankAccount>(&self.pool, account_id)
+            .await
+            .map_err(|err| replay_error(err.to_string()))?;
+        fill_event_times(&mut events, &created_at);
+        AccountStatement::from_events(account_id, period, &events).ok_or_else(|| {
+            AccountStatementError::NotOpen {
+                account_id: account_id.to_string(),
+                period: period.period.clone(),
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coverage_helper::test;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+
+    fn usd(minor_units: i64) -> Money {
+        Money::new(minor_units, "USD")
+    }
+
+    fn event(
+        sequence: usize,
+        time: Option<&str>,
+        payload: BankAccountEvent,
+    ) -> EventEnvelope<BankAccount> {
+        EventEnvelope {
+            aggregate_id: "1234".to_string(),
+            sequence,
+            payload,
+            metadata: time
+                .map(|time| HashMap::from([("time".to_string(), time.to_string())]))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn events() -> Vec<EventEnvelope<BankAccount>> {
+        vec![
+            event(
+                1,
+                Some("2023-10-02T09:00:00Z"),
+                BankAccountEvent::AccountOpened {
+                    account_id: "1234".to_string(),
+                    currency: "USD".to_string(),
+                    owner_id: None,
+                },
+            ),
+            event(
+                2,
+                Some("2023-10-02T09:05:00Z"),
+                BankAccountEvent::CustomerDepositedMoney {
+                    amount: usd(10000),
+                    balance: usd(10000),
+                },
+            ),
+            event(
+                3,
+                Some("2023-11-03T12:00:00Z"),
+                BankAccountEvent::CustomerWithdrewCash {
+                    amount: usd(2500),
+                    balance: usd(7500),
+                },
+            ),
+            // Credited by the transfer process manager, which records no time.
+            event(
+                4,
+                None,
+                BankAccountEvent::TransferCredited {
+                    transfer_id: "T-1".to_string(),
+                    source_account_id: "5678".to_string(),
+                    amount: usd(1000),
+                    balance: usd(8500),
+                },
+            ),
+            event(
+                5,
+                Some("2023-12-01T00:00:00Z"),
+                BankAccountEvent::InterestPosted {
+                    period: "2023-11".to_string(),
+                    amount: usd(12),
+                    balance: usd(8512),
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn statement_runs_from_the_opening_to_the_closing_balance() {
+        let period = StatementPeriod::parse("2023-11").unwrap();
+        let statement = AccountStatement::from_events("1234", &period, &events()).unwrap();
+        assert_eq!(statement.opening_balance, usd(10000));
+        assert_eq!(statement.closing_balance, usd(8500));
+        assert_eq!(
+            statement
+                .transactions
+                .iter()
+                .map(|line| (line.amount.clone(), line.occurred_at.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                (usd(-2500), "2023-11-03T12:00:00+00:00"),
+                (usd(1000), "2023-11-03T12:00:00+00:00"),
+            ]
+        );
+    }
+
+    #[test]
+    fn statement_without_transactions_keeps_the_balance() {
+        let period = StatementPeriod::parse("2024-01").unwrap();
+        let statement = AccountStatement::from_events("1234", &period, &events()).unwrap();
+        assert_eq!(statement.opening_balance, usd(8512));
+        assert_eq!(statement.closing_balance, usd(8512));
+        assert!(statement.transactions.is_empty());
+    }
+
+    #[test]
+    fn no_statement_before_the_account_was_opened() {
+        let period = StatementPeriod::parse("2023-09").unwrap();
+        assert_eq!(
+            AccountStatement::from_events("1234", &period, &events()),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_months() {
+        let period = StatementPeriod::parse("2023-12").unwrap();
+        assert_eq!(period.ends_at.to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        assert!(matches!(
+            StatementPeriod::parse("2023-13"),
+            Err(AccountStatementError::InvalidPeriod(period)) if period == "2023-13"
+        ));
+    }
+}
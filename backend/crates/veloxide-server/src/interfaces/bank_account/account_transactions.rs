@@ -0,0 +1,868 @@
+#[cfg(feature = "graphql")]
+use async_graphql::{Enum, SimpleObject};
+
+#[cfg(feature = "frontend")]
+use ts_rs::TS;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use super::*;
+use crate::infrastructure::projections::{event_created_at, EVENT_TIME_METADATA};
+use crate::infrastructure::DbPool;
+
+/// The table the transaction history of every account is projected into.
+pub const ACCOUNT_TRANSACTIONS_TABLE: &str = "account_transactions";
+
+/// How many transactions are returned when no limit is given.
+pub const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// The most transactions returned in a single page, whatever limit is given.
+pub const MAX_PAGE_SIZE: usize = 200;
+
+#[derive(thiserror::Error, Debug)]
+pub enum AccountTransactionsError {
+    #[error("invalid cursor: {0}")]
+    InvalidCursor(String),
+
+    #[error("invalid date, expected an RFC 3339 timestamp: {0}")]
+    InvalidDate(String),
+
+    #[error("unknown transaction type: {0}")]
+    UnknownTransactionType(String),
+
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "graphql", derive(Enum))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub enum AccountTransactionType {
+    Deposit,
+    Withdrawal,
+    Check,
+    CheckCancelled,
+    TransferOut,
+    TransferIn,
+    TransferRefund,
+    Interest,
+    HoldCapture,
+}
+
+impl Display for AccountTransactionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> core::result::Result<(), std::fmt::Error> {
+        let name = match self {
+            AccountTransactionType::Deposit => "Deposit",
+            AccountTransactionType::Withdrawal => "Withdrawal",
+            AccountTransactionType::Check => "Check",
+            AccountTransactionType::CheckCancelled => "CheckCancelled",
+            AccountTransactionType::TransferOut => "TransferOut",
+            AccountTransactionType::TransferIn => "TransferIn",
+            AccountTransactionType::TransferRefund => "TransferRefund",
+            AccountTransactionType::Interest => "Interest",
+            AccountTransactionType::HoldCapture => "HoldCapture",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for AccountTransactionType {
+    type Err = AccountTransactionsError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "Deposit" => Ok(AccountTransactionType::Deposit),
+            "Withdrawal" => Ok(AccountTransactionType::Withdrawal),
+            "Check" => Ok(AccountTransactionType::Check),
+            "CheckCancelled" => Ok(AccountTransactionType::CheckCancelled),
+            "TransferOut" => Ok(AccountTransactionType::TransferOut),
+            "TransferIn" => Ok(AccountTransactionType::TransferIn),
+            "TransferRefund" => Ok(AccountTransactionType::TransferRefund),
+            "Interest" => Ok(AccountTransactionType::Interest),
+            "HoldCapture" => Ok(AccountTransactionType::HoldCapture),
+            _ => Err(AccountTransactionsError::UnknownTransactionType(
+                name.to_string(),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+#[cfg_attr(feature = "graphql", derive(SimpleObject))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct AccountTransaction {
+    /// Pass as `after` to continue with the transactions that came before this one
+    pub cursor: String,
+    pub transaction_type: AccountTransactionType,
+    pub description: String,
+    pub amount: Money,
+
+    /// The amount as it was received, when it was converted from another currency
+    #[serde(default)]
+    pub original_amount: Option<Money>,
+
+    /// The rate applied when converting `original_amount` into `amount`
+    #[serde(default)]
+    pub exchange_rate: Option<ExchangeRate>,
+
+    /// When the transaction was made, as an RFC 3339 timestamp
+    pub occurred_at: String,
+}
+
+/// A page of an account's transactions, newest first.
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct AccountTransactionPage {
+    pub transactions: Vec<AccountTransaction>,
+
+    /// Set when there are older transactions, pass as `after` to fetch them
+    pub next_cursor: Option<String>,
+}
+
+/// Narrows down the transactions of an account. `from` is inclusive and `to` exclusive.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AccountTransactionFilter {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    pub transaction_type: Option<AccountTransactionType>,
+}
+
+impl AccountTransactionFilter {
+    pub fn parse(
+        from: Option<&str>,
+        to: Option<&str>,
+        transaction_type: Option<AccountTransactionType>,
+    ) -> Result<Self, AccountTransactionsError> {
+        Ok(Self {
+            from: from.map(parse_date).transpose()?,
+            to: to.map(parse_date).transpose()?,
+            transaction_type,
+        })
+    }
+}
+
+fn parse_date(date: &str) -> Result<DateTime<Utc>, AccountTransactionsError> {
+    DateTime::parse_from_rfc3339(date)
+        .map(|date| date.with_timezone(&Utc))
+        .map_err(|_| AccountTransactionsError::InvalidDate(date.to_string()))
+}
+
+/// Clamps a requested page size to between 1 and `MAX_PAGE_SIZE`.
+pub fn page_size(limit: Option<usize>) -> usize {
+    limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE)
+}
+
+// Cursors are the opaque encoding of the event sequence the transaction was recorded by.
+fn encode_cursor(sequence: i64) -> String {
+    URL_SAFE_NO_PAD.encode(sequence.to_string())
+}
+
+fn decode_cursor(cursor: &str) -> Result<i64, AccountTransactionsError> {
+    URL_SAFE_NO_PAD
+        .decode(cursor)
+        .ok()
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .and_then(|sequence| sequence.parse().ok())
+        .ok_or_else(|| AccountTransactionsError::InvalidCursor(cursor.to_string()))
+}
+
+// What a bank account event adds to the transaction history of the account, if anything.
+#[derive(Debug, PartialEq)]
+pub(super) struct NewAccountTransaction {
+    pub(super) transaction_type: AccountTransactionType,
+    pub(super) description: String,
+    amount: Money,
+    original_amount: Option<Money>,
+    exchange_rate: Option<ExchangeRate>,
+}
+
+impl NewAccountTransaction {
+    fn new(transaction_type: AccountTransactionType, description: &str, amount: &Money) -> Self {
+        Self {
+            transaction_type,
+            description: description.to_string(),
+            amount: amount.clone(),
+            original_amount: None,
+            exchange_rate: None,
+        }
+    }
+
+    pub(super) fn from_event(event: &BankAccountEvent) -> Option<Self> {
+        let transaction = match event {
+            BankAccountEvent::CustomerDepositedMoney { amount, .. } => {
+                Self::new(AccountTransactionType::Deposit, "deposit", amount)
+            }
+
+            BankAccountEvent::CustomerDepositedForeignCurrency {
+                original_amount,
+                exchange_rate,
+                amount,
+                ..
+            } => Self {
+                transaction_type: AccountTransactionType::Deposit,
+                description: format!("deposit ({} @ {})", original_amount, exchange_rate),
+                amount: amount.clone(),
+                original_amount: Some(original_amount.clone()),
+                exchange_rate: Some(exchange_rate.clone()),
+            },
+
+            BankAccountEvent::CustomerWithdrewCash { amount, .. } => {
+                Self::new(AccountTransactionType::Withdrawal, "atm withdrawal", amount)
+            }
+
+            BankAccountEvent::CustomerWroteCheck {
+                check_number,
+                amount,
+                ..
+            } => Self::new(AccountTransactionType::Check, check_number, amount),
+
+            BankAccountEvent::CheckCancelled {
+                check_number,
+                amount,
+                ..
+            } => Self::new(
+                AccountTransactionType::CheckCancelled,
+                &format!("stopped payment of check {}", check_number),
+                amount,
+            ),
+
+            BankAccountEvent::TransferDebited {
+                destination_account_id,
+                amount,
+                ..
+            } => Self::new(
+                AccountTransactionType::TransferOut,
+                &format!("transfer to {}", destination_account_id),
+                amount,
+            ),
+
+            BankAccountEvent::TransferCredited {
+                source_account_id,
+                amount,
+                ..
+            } => Self::new(
+                AccountTransactionType::TransferIn,
+                &format!("transfer from {}", source_account_id),
+                amount,
+            ),
+
+            BankAccountEvent::TransferRefunded {
+                transfer_id,
+                amount,
+                ..
+            } => Self::new(
+                AccountTransactionType::TransferRefund,
+                &format!("refund of transfer {}", transfer_id),
+                amount,
+            ),
+
+            BankAccountEvent::InterestPosted { period, amount, .. } => Self::new(
+                AccountTransactionType::Interest,
+                &format!("interest for {}", period),
+                amount,
+            ),
+
+            BankAccountEvent::HoldCaptured {
+                hold_id, amount, ..
+            } => Self::new(
+                AccountTransactionType::HoldCapture,
+                &format!("capture of hold {}", hold_id),
+                amount,
+            ),
+
+            BankAccountEvent::AccountOpened { .. }
+            | BankAccountEvent::AccountFrozen { .. }
+            | BankAccountEvent::AccountUnfrozen { .. }
+            | BankAccountEvent::AccountClosed { .. }
+            | BankAccountEvent::OverdraftLimitSet { .. }
+            | BankAccountEvent::OverdraftEntered { .. }
+            | BankAccountEvent::AccessGranted { .. }
+            | BankAccountEvent::AccessRevoked { .. }
+            | BankAccountEvent::OwnerAssigned { .. }
+            | BankAccountEvent::InterestAccrued { .. }
+            | BankAccountEvent::HoldPlaced { .. }
+            | BankAccountEvent::HoldReleased { .. }
+            | BankAccountEvent::HoldExpired { .. } => return None,
+        };
+        Some(transaction)
+    }
+}
+
+// Events issued through the REST API carry the time they were issued at, any other is dated by
+// when it was committed, as `committed_at` has it by sequence number.
+pub(super) fn occurred_at(
+    event: &EventEnvelope<BankAccount>,
+    committed_at: &HashMap<usize, DateTime<Utc>>,
+) -> NaiveDateTime {
+    event
+        .metadata
+        .get(EVENT_TIME_METADATA)
+        .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+        .map(|time| time.naive_utc())
+        .or_else(|| {
+            committed_at
+                .get(&event.sequence)
+                .map(|time| time.naive_utc())
+        })
+        // Only an event that was never committed has neither.
+        .unwrap_or_else(|| Utc::now().naive_utc())
+}
+
+// When the events without a time of their own were committed, by sequence number. Commands
+// issued by the server, such as crediting a transfer, record no time.
+pub(super) async fn commit_times(
+    pool: &DbPool,
+    account_id: &str,
+    events: &[EventEnvelope<BankAccount>],
+) -> Result<HashMap<usize, DateTime<Utc>>, sqlx::Error> {
+    if events
+        .iter()
+        .all(|event| event.metadata.contains_key(EVENT_TIME_METADATA))
+    {
+        return Ok(HashMap::new());
+    }
+    event_created_at::<BankAccount>(pool, account_id).await
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use async_trait::async_trait;
+        use cqrs_es::persist::PersistedEventStore;
+        use cqrs_es::EventStore;
+        use postgres_es::PostgresEventRepository;
+        use sqlx::postgres::PgRow;
+        use sqlx::types::Json;
+        use sqlx::{PgConnection, Pool, Postgres, QueryBuilder, Row};
+
+        use crate::infrastructure::projections::{
+            aggregate_ids, Projection, ProjectionError, ProjectionProgress, ProjectionRebuildReport,
+        };
+
+        /// Projects each deposit, withdrawal, check and transfer into its own row of the
+        /// `account_transactions` table, so that the history of an account can be paged through
+        /// instead of being loaded in full with its view.
+        #[derive(Clone)]
+        pub struct AccountTransactionsQuery {
+            pool: Pool<Postgres>,
+        }
+
+        impl AccountTransactionsQuery {
+            pub fn new(pool: Pool<Postgres>) -> Self {
+                Self { pool }
+            }
+
+            /// Returns up to `limit` transactions of the account, newest first, that came before
+            /// the `after` cursor.
+            pub async fn find(
+                &self,
+                account_id: &str,
+                filter: &AccountTransactionFilter,
+                after: Option<&str>,
+                limit: usize,
+            ) -> Result<AccountTransactionPage, AccountTransactionsError> {
+                let mut query = QueryBuilder::<Postgres>::new(
+                    "SELECT sequence, transaction_type, description, amount_minor_units, currency, original_amount, exchange_rate, occurred_at FROM account_transactions WHERE account_id = ",
+                );
+                query.push_bind(account_id);
+                if let Some(after) = after {
+                    query.push(" AND sequence < ").push_bind(decode_cursor(after)?);
+                }
+                if let Some(from) = filter.from {
+                    query.push(" AND occurred_at >= ").push_bind(from.naive_utc());
+                }
+                if let Some(to) = filter.to {
+                    query.push(" AND occurred_at < ").push_bind(to.naive_utc());
+                }
+                if let Some(transaction_type) = filter.transaction_type {
+                    query.push(" AND transaction_type = ").push_bind(transaction_type.to_string());
+                }
+                // One more than asked for tells whether there is a next page.
+                query.push(" ORDER BY sequence DESC LIMIT ").push_bind(limit as i64 + 1);
+
+                let rows = query.build().fetch_all(&self.pool).await?;
+                let mut transactions = rows
+                    .iter()
+                    .map(account_transaction_from_row)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let next_cursor = if transactions.len() > limit {
+                    transactions.truncate(limit);
+                    transactions.last().map(|transaction| transaction.cursor.clone())
+                } else {
+                    None
+                };
+                Ok(AccountTransactionPage { transactions, next_cursor })
+            }
+
+            // Rows are keyed on the event sequence, so events that are dispatched again, e.g. by
+            // a rebuild that overlaps with live traffic, are only recorded once.
+            async fn record(&self, connection: &mut PgConnection, account_id: &str, events: &[EventEnvelope<BankAccount>]) -> Result<(), sqlx::Error> {
+                let committed_at = commit_times(&self.pool, account_id, events).await?;
+                for event in events {
+                    let Some(transaction) = NewAccountTransaction::from_event(&event.payload) else {
+                        continue;
+                    };
+                    sqlx::query(
+                        "INSERT INTO account_transactions (account_id, sequence, transaction_type, description, amount_minor_units, currency, original_amount, exchange_rate, occurred_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) ON CONFLICT (account_id, sequence) DO NOTHING",
+                    )
+                    .bind(account_id)
+                    .bind(event.sequence as i64)
+                    .bind(transaction.transaction_type.to_string())
+                    .bind(transaction.description)
+                    .bind(transaction.amount.minor_units)
+                    .bind(transaction.amount.currency)
+                    .bind(transaction.original_amount.map(Json))
+                    .bind(transaction.exchange_rate.map(Json))
+                    .bind(occurred_at(event, &committed_at))
+                    .execute(&mut *connection)
+                    .await?;
+                }
+                Ok(())
+            }
+        }
+
+        fn account_transaction_from_row(row: &PgRow) -> Result<AccountTransaction, AccountTransactionsError> {
+            let transaction_type: String = row.try_get("transaction_type")?;
+            let original_amount: Option<Json<Money>> = row.try_get("original_amount")?;
+            let exchange_rate: Option<Json<ExchangeRate>> = row.try_get("exchange_rate")?;
+            let occurred_at: NaiveDateTime = row.try_get("occurred_at")?;
+            Ok(AccountTransaction {
+                cursor: encode_cursor(row.try_get("sequence")?),
+                transaction_type: transaction_type.parse()?,
+                description: row.try_get("description")?,
+                amount: Money::new(row.try_get("amount_minor_units")?, row.try_get("currency")?),
+                original_amount: original_amount.map(|amount| amount.0),
+                exchange_rate: exchange_rate.map(|rate| rate.0),
+                occurred_at: occurred_at.and_utc().to_rfc3339(),
+            })
+        }
+
+        #[async_trait]
+        impl Query<BankAccount> for AccountTransactionsQuery {
+            async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<BankAccount>]) {
+                let recorded = match self.pool.acquire().await {
+                    Ok(mut connection) => self.record(&mut connection, aggregate_id, events).await,
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = recorded {
+                    tracing::error!("failed to record transactions of account {}: {}", aggregate_id, err);
+                }
+            }
+        }
+
+        #[async_trait]
+        impl Projection for AccountTransactionsQuery {
+            fn name(&self) -> &str {
+                ACCOUNT_TRANSACTIONS_TABLE
+            }
+
+            async fn rebuild(
+                &self,
+                aggregate_id: Option<&str>,
+                on_progress: &(dyn Fn(&ProjectionProgress) + Send + Sync),
+            ) -> Result<ProjectionRebuildReport, ProjectionError> {
+                let mut transaction = self.pool.begin().await?;
+                let aggregate_ids: Vec<String> = match aggregate_id {
+                    Some(aggregate_id) => {
+                        sqlx::query("DELETE FROM account_transactions WHERE account_id = $1")
+                            .bind(aggregate_id)
+                            .execute(&mut *transaction)
+                            .await?;
+                        vec![aggregate_id.to_string()]
+                    }
+                    None => {
+                        sqlx::query("TRUNCATE TABLE account_transactions")
+                            .execute(&mut *transaction)
+                            .await?;
+                        aggregate_ids::<BankAccount>(&self.pool).await?
+                    }
+                };
+
+                let event_store: PersistedEventStore<PostgresEventRepository, BankAccount> =
+                    PersistedEventStore::new_event_store(PostgresEventRepository::new(self.pool.clone()))
+                        .with_upcasters(bank_account_upcasters());
+                let total = aggregate_ids.len();
+                let mut events_replayed = 0;
+                for (index, aggregate_id) in aggregate_ids.into_iter().enumerate() {
+                    let replay_error = |reason: String| ProjectionError::Replay {
+                        aggregate_id: aggregate_id.clone(),
+                        reason,
+                    };
+                    let events = event_store
+                        .load_events(&aggregate_id)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    self.record(&mut transaction, &aggregate_id, &events)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    events_replayed += events.len();
+                    on_progress(&ProjectionProgress {
+                        aggregate_id,
+                        replayed: index + 1,
+                        total,
+                    });
+                }
+                transaction.commit().await?;
+                Ok(ProjectionRebuildReport {
+                    projection: ACCOUNT_TRANSACTIONS_TABLE.to_string(),
+                    aggregates_replayed: total,
+                    events_replayed,
+                })
+            }
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use async_trait::async_trait;
+        use cqrs_es::persist::PersistedEventStore;
+        use cqrs_es::EventStore;
+        use mysql_es::MysqlEventRepository;
+        use sqlx::mysql::MySqlRow;
+        use sqlx::types::Json;
+        use sqlx::{MySql, MySqlConnection, Pool, QueryBuilder, Row};
+
+        use crate::infrastructure::projections::{
+            aggregate_ids, Projection, ProjectionError, ProjectionProgress, ProjectionRebuildReport,
+        };
+
+        /// Projects each deposit, withdrawal, check and transfer into its own row of the
+        /// `account_transactions` table, so that the history of an account can be paged through
+        /// instead of being loaded in full with its view.
+        #[derive(Clone)]
+        pub struct AccountTransactionsQuery {
+            pool: Pool<MySql>,
+        }
+
+        impl AccountTransactionsQuery {
+            pub fn new(pool: Pool<MySql>) -> Self {
+                Self { pool }
+            }
+
+            /// Returns up to `limit` transactions of the account, newest first, that came before
+            /// the `after` cursor.
+            pub async fn find(
+                &self,
+                account_id: &str,
+                filter: &AccountTransactionFilter,
+                after: Option<&str>,
+                limit: usize,
+            ) -> Result<AccountTransactionPage, AccountTransactionsError> {
+                let mut query = QueryBuilder::<MySql>::new(
+                    "SELECT sequence, transaction_type, description, amount_minor_units, currency, original_amount, exchange_rate, occurred_at FROM account_transactions WHERE account_id = ",
+                );
+                query.push_bind(account_id);
+                if let Some(after) = after {
+                    query.push(" AND sequence < ").push_bind(decode_cursor(after)?);
+                }
+                if let Some(from) = filter.from {
+                    query.push(" AND occurred_at >= ").push_bind(from.naive_utc());
+                }
+                if let Some(to) = filter.to {
+                    query.push(" AND occurred_at < ").push_bind(to.naive_utc());
+                }
+                if let Some(transaction_type) = filter.transaction_type {
+                    query.push(" AND transaction_type = ").push_bind(transaction_type.to_string());
+                }
+                // One more than asked for tells whether there is a next page.
+                query.push(" ORDER BY sequence DESC LIMIT ").push_bind(limit as i64 + 1);
+
+                let rows = query.build().fetch_all(&self.pool).await?;
+                let mut transactions = rows
+                    .iter()
+                    .map(account_transaction_from_row)
+                    .collect::<Result<Vec<_>, _>>()?;
+                let next_cursor = if transactions.len() > limit {
+                    transactions.truncate(limit);
+                    transactions.last().map(|transaction| transaction.cursor.clone())
+                } else {
+                    None
+                };
+                Ok(AccountTransactionPage { transactions, next_cursor })
+            }
+
+            // Rows are keyed on the event sequence, so events that are dispatched again, e.g. by
+            // a rebuild that overlaps with live traffic, are only recorded once.
+            async fn record(&self, connection: &mut MySqlConnection, account_id: &str, events: &[EventEnvelope<BankAccount>]) -> Result<(), sqlx::Error> {
+                let committed_at = commit_times(&self.pool, account_id, events).await?;
+                for event in events {
+                    let Some(transaction) = NewAccountTransaction::from_event(&event.payload) else {
+                        continue;
+                    };
+                    sqlx::query(
+                        "INSERT IGNORE INTO account_transactions (account_id, sequence, transaction_type, description, amount_minor_units, currency, original_amount, exchange_rate, occurred_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                    )
+                    .bind(account_id)
+                    .bind(event.sequence as i64)
+                    .bind(transaction.transaction_type.to_string())
+                    .bind(transaction.description)
+                    .bind(transaction.amount.minor_units)
+                    .bind(transaction.amount.currency)
+                    .bind(transaction.original_amount.map(Json))
+                    .bind(transaction.exchange_rate.map(Json))
+                    .bind(occurred_at(event, &committed_at))
+                    .execute(&mut *connection)
+                    .await?;
+                }
+                Ok(())
+            }
+        }
+
+        fn account_transaction_from_row(row: &MySqlRow) -> Result<AccountTransaction, AccountTransactionsError> {
+            let transaction_type: String = row.try_get("transaction_type")?;
+            let original_amount: Option<Json<Money>> = row.try_get("original_amount")?;
+            let exchange_rate: Option<Json<ExchangeRate>> = row.try_get("exchange_rate")?;
+            let occurred_at: NaiveDateTime = row.try_get("occurred_at")?;
+            Ok(AccountTransaction {
+                cursor: encode_cursor(row.try_get("sequence")?),
+                transaction_type: transaction_type.parse()?,
+                description: row.try_get("description")?,
+                amount: Money::new(row.try_get("amount_minor_units")?, row.try_get("currency")?),
+                original_amount: original_amount.map(|amount| amount.0),
+                exchange_rate: exchange_rate.map(|rate| rate.0),
+                occurred_at: occurred_at.and_utc().to_rfc3339(),
+            })
+        }
+
+        #[async_trait]
+        impl Query<BankAccount> for AccountTransactionsQuery {
+            async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<BankAccount>]) {
+                let recorded = match self.pool.acquire().await {
+                    Ok(mut connection) => self.record(&mut connection, aggregate_id, events).await,
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = recorded {
+                    tracing::error!("failed to record transactions of account {}: {}", aggregate_id, err);
+                }
+            }
+        }
+
+        #[async_trait]
+        impl Projection for AccountTransactionsQuery {
+            fn name(&self) -> &str {
+                ACCOUNT_TRANSACTIONS_TABLE
+            }
+
+            async fn rebuild(
+                &self,
+                aggregate_id: Option<&str>,
+                on_progress: &(dyn Fn(&ProjectionProgress) + Send + Sync),
+            ) -> Result<ProjectionRebuildReport, ProjectionError> {
+                let mut transaction = self.pool.begin().await?;
+                let aggregate_ids: Vec<String> = match aggregate_id {
+                    Some(aggregate_id) => {
+                        sqlx::query("DELETE FROM account_transactions WHERE account_id = ?")
+                            .bind(aggregate_id)
+                            .execute(&mut *transaction)
+                            .await?;
+                        vec![aggregate_id.to_string()]
+                    }
+                    None => {
+                        sqlx::query("DELETE FROM account_transactions")
+                            .execute(&mut *transaction)
+                            .await?;
+                        aggregate_ids::<BankAccount>(&self.pool).await?
+                    }
+                };
+
+                let event_store: PersistedEventStore<MysqlEventRepository, BankAccount> =
+                    PersistedEventStore::new_event_store(MysqlEventRepository::new(self.pool.clone()))
+                        .with_upcasters(bank_account_upcasters());
+                let total = aggregate_ids.len();
+                let mut events_replayed = 0;
+                for (index, aggregate_id) in aggregate_ids.into_iter().enumerate() {
+                    let replay_error = |reason: String| ProjectionError::Replay {
+                        aggregate_id: aggregate_id.clone(),
+                        reason,
+                    };
+                    let events = event_store
+                        .load_events(&aggregate_id)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    self.record(&mut transaction, &aggregate_id, &events)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    events_replayed += events.len();
+                    on_progress(&ProjectionProgress {
+                        aggregate_id,
+                        replayed: index + 1,
+                        total,
+                    });
+                }
+                transaction.commit().await?;
+                Ok(ProjectionRebuildReport {
+                    projection: ACCOUNT_TRANSACTIONS_TABLE.to_string(),
+                    aggregates_replayed: total,
+                    events_replayed,
+                })
+            }
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn describes_deposits_and_withdrawals() {
+        let deposit =
+            NewAccountTransaction::from_event(&BankAccountEvent::CustomerDepositedMoney {
+                amount: Money::new(20000, "USD"),
+                balance: Money::new(20000, "USD"),
+            });
+        assert_eq!(
+            deposit,
+            Some(NewAccountTransaction::new(
+                AccountTransactionType::Deposit,
+                "deposit",
+                &Money::new(20000, "USD")
+            ))
+        );
+
+        let withdrawal =
+            NewAccountTransaction::from_event(&BankAccountEvent::CustomerWithdrewCash {
+                amount: Money::new(5000, "USD"),
+                balance: Money::new(15000, "USD"),
+            });
+        assert_eq!(
+            withdrawal.map(|transaction| transaction.transaction_type),
+            Some(AccountTransactionType::Withdrawal)
+        );
+    }
+
+    #[test]
+    fn describes_checks_by_their_number() {
+        let check = NewAccountTransaction::from_event(&BankAccountEvent::CustomerWroteCheck {
+            check_number: "1170".to_string(),
+            amount: Money::new(25628, "USD"),
+            balance: Money::new(74372, "USD"),
+            written_at: None,
+        })
+        .unwrap();
+        assert_eq!(check.transaction_type, AccountTransactionType::Check);
+        assert_eq!(check.description, "1170");
+
+        let cancelled = NewAccountTransaction::from_event(&BankAccountEvent::CheckCancelled {
+            check_number: "1170".to_string(),
+            amount: Money::new(25628, "USD"),
+            balance: Money::new(100000, "USD"),
+        })
+        .unwrap();
+        assert_eq!(
+            cancelled.transaction_type,
+            AccountTransactionType::CheckCancelled
+        );
+        assert_eq!(cancelled.description, "stopped payment of check 1170");
+    }
+
+    #[test]
+    fn events_that_move_no_money_are_not_transactions() {
+        let event = BankAccountEvent::AccountOpened {
+            account_id: "1234".to_string(),
+            currency: "USD".to_string(),
+            owner_id: None,
+        };
+        assert_eq!(NewAccountTransaction::from_event(&event), None);
+    }
+
+    #[test]
+    fn round_trips_cursors() {
+        assert_eq!(decode_cursor(&encode_cursor(42)).unwrap(), 42);
+    }
+
+    #[test]
+    fn rejects_invalid_cursor() {
+        assert!(matches!(
+            decode_cursor("not a cursor"),
+            Err(AccountTransactionsError::InvalidCursor(cursor)) if cursor == "not a cursor"
+        ));
+    }
+
+    #[test]
+    fn round_trips_transaction_types() {
+        for transaction_type in [
+            AccountTransactionType::Deposit,
+            AccountTransactionType::Withdrawal,
+            AccountTransactionType::Check,
+            AccountTransactionType::CheckCancelled,
+            AccountTransactionType::TransferOut,
+            AccountTransactionType::TransferIn,
+            AccountTransactionType::TransferRefund,
+            AccountTransactionType::Interest,
+            AccountTransactionType::HoldCapture,
+        ] {
+            assert_eq!(
+                transaction_type
+                    .to_string()
+                    .parse::<AccountTransactionType>()
+                    .unwrap(),
+                transaction_type
+            );
+        }
+    }
+
+    #[test]
+    fn parses_date_range_filter() {
+        let filter = AccountTransactionFilter::parse(
+            Some("2023-10-01T00:00:00Z"),
+            Some("2023-11-01T00:00:00+01:00"),
+            None,
+        )
+        .unwrap();
+        assert_eq!(filter.to.unwrap().to_rfc3339(), "2023-10-31T23:00:00+00:00");
+        assert!(matches!(
+            AccountTransactionFilter::parse(Some("yesterday"), None, None),
+            Err(AccountTransactionsError::InvalidDate(date)) if date == "yesterday"
+        ));
+    }
+
+    #[test]
+    fn clamps_page_size() {
+        assert_eq!(page_size(None), DEFAULT_PAGE_SIZE);
+        assert_eq!(page_size(Some(0)), 1);
+        assert_eq!(page_size(Some(10_000)), MAX_PAGE_SIZE);
+    }
+
+    #[test]
+    fn dates_events_by_when_they_were_issued_or_else_committed() {
+        let event = |sequence: usize, metadata: HashMap<String, String>| EventEnvelope {
+            aggregate_id: "1234".to_string(),
+            sequence,
+            payload: BankAccountEvent::CustomerDepositedMoney {
+                amount: Money::new(100, "USD"),
+                balance: Money::new(100 * sequence as i64, "USD"),
+            },
+            metadata,
+        };
+        let committed_at = HashMap::from([
+            (1, "2023-11-01T09:00:01Z".parse().unwrap()),
+            (2, "2023-11-01T09:05:00Z".parse().unwrap()),
+        ]);
+        let issued = event(
+            1,
+            HashMap::from([(
+                EVENT_TIME_METADATA.to_string(),
+                "2023-11-01T09:00:00+00:00".to_string(),
+            )]),
+        );
+
+        assert_eq!(
+            occurred_at(&issued, &committed_at),
+            "2023-11-01T09:00:00".parse::<NaiveDateTime>().unwrap()
+        );
+        assert_eq!(
+            occurred_at(&event(2, HashMap::new()), &committed_at),
+            "2023-11-01T09:05:00".parse::<NaiveDateTime>().unwrap()
+        );
+    }
+}
@@ -1,8 +1,12 @@
 use super::*;
 
-use async_graphql::{Context, Object};
+use async_graphql::connection::{Connection, Edge};
+use async_graphql::{Context, ErrorExtensions, Object, Subscription};
+use futures::{Stream, StreamExt};
+use std::collections::HashMap;
 
-use cfg_if::cfg_if;
+use crate::application::{Actor, BankAccountApplicationService, BankAccountServiceError};
+use crate::interfaces::bank_account::account_events::view_access_check;
 
 #[derive(Default)]
 pub struct BankAccountGraphQlQuery {}
@@ -10,82 +14,81 @@ pub struct BankAccountGraphQlQuery {}
 #[derive(Default)]
 pub struct BankAccountGraphQlMutation {}
 
-cfg_if! {
-    if #[cfg(feature = "postgres")] {
-        #[Object]
-        impl BankAccountGraphQlQuery {
-            #[instrument(skip(self, ctx))]
-            /// Get a bank account by its ID
-            async fn bank_account_query<'ctx>(
-                &self,
-                ctx: &Context<'ctx>,
-                id: String,
-            ) -> async_graphql::Result<BankAccountView> {
-                let view_repo = ctx.data::<Arc<PostgresViewRepository<BankAccountView, BankAccount>>>()?;
-                let view = match view_repo.load(&id).await? {
-                    Some(view) => view,
-                    None => {
-                        return Err(async_graphql::Error::new("Bank account not found"));
-                    }
-                };
-                tracing::debug!("Loaded view in GraphQL response: {:?}", view);
-                Ok(view)
-            }
-        }
+#[derive(Default)]
+pub struct BankAccountGraphQlSubscription {}
 
-        #[Object]
-impl BankAccountGraphQlMutation {
+#[Object]
+impl BankAccountGraphQlQuery {
     #[instrument(skip(self, ctx))]
-    /// Issue a command on the bank account aggregate
-    async fn bank_account_mutation<'ctx>(
+    /// Get a bank account by its ID
+    async fn bank_account_query<'ctx>(
         &self,
         ctx: &Context<'ctx>,
         id: String,
-        command: BankAccountCommand,
     ) -> async_graphql::Result<BankAccountView> {
-        let cqrs = ctx.data::<Arc<PostgresCqrs<BankAccount>>>()?;
-        let view_repo = ctx.data::<Arc<PostgresViewRepository<BankAccountView, BankAccount>>>()?;
-
-        match cqrs.execute(&id, command).await {
-            Ok(_) => {}
-            Err(err) => {
-                return Err(async_graphql::Error::new(err.to_string()));
-            }
-        }
-        let view = match view_repo.load(&id).await {
-            Ok(view) => match view {
-                Some(view) => view,
-                None => {
-                    return Err(async_graphql::Error::new("Bank account not found"));
-                }
-            },
-            Err(err) => {
-                return Err(async_graphql::Error::new(err.to_string()));
-            }
-        };
+        let bank_account_service = ctx.data::<Arc<dyn BankAccountApplicationService>>()?;
+        let view = bank_account_service
+            .get_bank_account(ctx.data::<Actor>()?, id)
+            .await?;
+        tracing::debug!("Loaded view in GraphQL response: {:?}", view);
         Ok(view)
     }
-}
 
-    } else if #[cfg(feature = "mysql")] {
-#[Object]
-impl BankAccountGraphQlQuery {
     #[instrument(skip(self, ctx))]
-    /// Get a bank account by its ID
-    async fn bank_account_query<'ctx>(
+    /// Page through the transactions of a bank account, newest first
+    async fn account_transactions<'ctx>(
         &self,
         ctx: &Context<'ctx>,
         id: String,
-    ) -> async_graphql::Result<BankAccountView> {
-        let view_repo = ctx.data::<Arc<MysqlViewRepository<BankAccountView, BankAccount>>>()?;
-        let view = match view_repo.load(&id).await? {
-            Some(view) => view,
-            None => {
-                return Err(async_graphql::Error::new("Bank account not found"));
-            }
-        };
-        tracing::debug!("Loaded view in GraphQL response: {:?}", view);
-        Ok(view)
+        after: Option<String>,
+        first: Option<usize>,
+        #[graphql(desc = "Only transactions at or after this RFC 3339 timestamp")] from: Option<
+            String,
+        >,
+        #[graphql(desc = "Only transactions before this RFC 3339 timestamp")] to: Option<String>,
+        transaction_type: Option<AccountTransactionType>,
+    ) -> async_graphql::Result<Connection<String, AccountTransaction>> {
+        let bank_account_service = ctx.data::<Arc<dyn BankAccountApplicationService>>()?;
+        let filter =
+            AccountTransactionFilter::parse(from.as_deref(), to.as_deref(), transaction_type)?;
+        let page = bank_account_service
+            .get_account_transactions(ctx.data::<Actor>()?, id, filter, after, first)
+            .await?;
+
+        let mut connection = Connection::new(false, page.next_cursor.is_some());
+        connection.edges.extend(
+            page.transactions
+                .into_iter()
+                .map(|transaction| Edge::new(transaction.cursor.clone(), transaction)),
+        );
+        Ok(connection)
+    }
+
+    #[instrument(skip(self, ctx))]
+    /// The users with access to a bank account and their roles
+    async fn account_access<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: String,
+    ) -> async_graphql::Result<Vec<AccountAccess>> {
+        let bank_account_service = ctx.data::<Arc<dyn BankAccountApplicationService>>()?;
+        Ok(bank_account_service
+            .get_account_access(ctx.data::<Actor>()?, id)
+            .await?)
+    }
+
+    #[instrument(skip(self, ctx))]
+    /// The statement of a bank account for a month such as "2023-11"
+    async fn account_statement<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: String,
+        period: String,
+    ) -> async_graphql::Result<AccountStatement> {
+        let bank_account_service = ctx.data::<Arc<dyn BankAccountApplicationService>>()?;
+        Ok(bank_account_service
+            .get_account_statement(ctx.data::<Actor>()?, id, period)
+            .await?)
     }
 }
 
@@ -98,31 +101,70 @@ impl BankAccountGraphQlMutation {
         ctx: &Context<'ctx>,
         id: String,
         command: BankAccountCommand,
+        #[graphql(
+            desc = "Only execute the command if the account is still at this version, as returned in its view"
+        )]
+        expected_version: Option<usize>,
+        #[graphql(
+            desc = "Retrying with the same key returns the original outcome instead of executing the command again"
+        )]
+        idempotency_key: Option<String>,
     ) -> async_graphql::Result<BankAccountView> {
-        let cqrs = ctx.data::<Arc<MysqlCqrs<BankAccount>>>()?;
-        let view_repo = ctx.data::<Arc<MysqlViewRepository<BankAccountView, BankAccount>>>()?;
+        let bank_account_service = ctx.data::<Arc<dyn BankAccountApplicationService>>()?;
+        let actor = ctx.data::<Actor>()?;
 
-        match cqrs.execute(&id, command).await {
-            Ok(_) => {}
-            Err(err) => {
-                return Err(async_graphql::Error::new(err.to_string()));
-            }
-        }
-        let view = match view_repo.load(&id).await {
-            Ok(view) => match view {
-                Some(view) => view,
-                None => {
-                    return Err(async_graphql::Error::new("Bank account not found"));
+        bank_account_service
+            .execute(
+                actor,
+                id.clone(),
+                command,
+                HashMap::new(),
+                expected_version,
+                idempotency_key,
+            )
+            .await
+            .map_err(|err| match err {
+                // Lets clients tell a stale version apart from a rejected command.
+                BankAccountServiceError::Conflict(_) => {
+                    err.extend_with(|_, extensions| extensions.set("code", "CONFLICT"))
                 }
-            },
-            Err(err) => {
-                return Err(async_graphql::Error::new(err.to_string()));
-            }
-        };
-        Ok(view)
+                err => err.into(),
+            })?;
+        Ok(bank_account_service.get_bank_account(actor, id).await?)
     }
 }
-    } else {
-        compile_error!("Must specify either mysql or postgres feature");
+
+#[Subscription]
+impl BankAccountGraphQlSubscription {
+    /// The view of a bank account, pushed again whenever an event is committed on it. Access
+    /// to the account is checked when subscribing, and again whenever access to it is revoked,
+    /// ending the subscription with an error once the subscriber lost it.
+    async fn bank_account_updated<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: String,
+    ) -> async_graphql::Result<impl Stream<Item = async_graphql::Result<BankAccountView>>> {
+        let bank_account_service = ctx.data::<Arc<dyn BankAccountApplicationService>>()?;
+        let account_events = ctx.data::<AccountEventBroadcaster>()?;
+        let actor = ctx.data::<Actor>()?;
+        let mut view = bank_account_service
+            .get_bank_account(actor, id.clone())
+            .await?;
+
+        let access_check =
+            view_access_check(bank_account_service.clone(), actor.clone(), id.clone());
+
+        // The view is brought up to date with each event the same way `AccountQuery` does.
+        let events = account_events.subscribe(id, view.version + 1, access_check);
+        Ok(events.map(move |event| {
+            let event = event?;
+            view.update(&EventEnvelope {
+                aggregate_id: event.account_id,
+                sequence: event.sequence,
+                payload: event.payload,
+                metadata: HashMap::new(),
+            });
+            Ok(view.clone())
+        }))
     }
 }
@@ -31,19 +31,52 @@ cfg_if::cfg_if! {
     }
 }
 
+/// The table the `BankAccountView`s are stored in.
+pub const ACCOUNT_QUERY_TABLE: &str = "account_query";
+
+/// Funds reserved on an account by a hold that is yet to be settled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "graphql", derive(SimpleObject))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct AccountHold {
+    pub hold_id: String,
+    pub amount: Money,
+    /// RFC 3339 timestamp after which the hold is released if it was not captured
+    pub expires_at: String,
+}
+
 // The view for a BankAccount query, for a standard http application this should
 // be designed to reflect the response dto that will be returned to a user.
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
 #[cfg_attr(feature = "graphql", derive(SimpleObject))]
 #[cfg_attr(feature = "frontend", derive(TS))]
 #[ts(export, export_to = "frontend/src/bindings/")]
 pub struct BankAccountView {
     pub account_id: Option<String>, //TODO: Investigate why this is an option.
-    pub balance: f64,
+    /// Id of the `User` that owns the account
+    pub owner_id: Option<String>,
+    pub status: AccountStatus,
+    /// The ledger balance, what has been paid into and out of the account
+    pub balance: Money,
+    /// The balance less the funds reserved by holds, what may still be paid out
+    #[serde(default)]
+    pub available_balance: Money,
+    /// The holds that are yet to be captured, released or expire
+    #[serde(default)]
+    pub holds: Vec<AccountHold>,
+    pub overdraft_limit: Money,
     pub written_checks: Vec<String>,
-    pub account_transactions: Vec<AccountTransaction>,
+    /// The written checks whose payment was stopped
+    #[serde(default)]
+    pub cancelled_checks: Vec<String>,
+    /// Sequence number of the last event applied to the account, to be passed back as the
+    /// expected version of a command
+    #[serde(default)]
+    pub version: usize,
 }
 
 // This updates the view with events as they are committed.
@@ -51,51 +84,132 @@ pub struct BankAccountView {
 // design the events to carry the balance information instead.
 impl View<BankAccount> for BankAccountView {
     fn update(&mut self, event: &EventEnvelope<BankAccount>) {
+        self.version = event.sequence;
         match &event.payload {
-            BankAccountEvent::AccountOpened { account_id } => {
+            BankAccountEvent::AccountOpened {
+                account_id,
+                currency,
+                owner_id,
+            } => {
                 self.account_id = Some(account_id.clone());
+                self.owner_id = owner_id.clone();
+                self.status = AccountStatus::Open;
+                self.balance = Money::zero(currency);
+                self.available_balance = Money::zero(currency);
+                self.overdraft_limit = Money::zero(currency);
             }
 
-            BankAccountEvent::CustomerDepositedMoney { amount, balance } => {
-                self.account_transactions
-                    .push(AccountTransaction::new("deposit", *amount));
-                self.balance = *balance;
+            // The transactions themselves are recorded by the `AccountTransactionsQuery`.
+            BankAccountEvent::CustomerDepositedMoney { balance, .. }
+            | BankAccountEvent::CustomerDepositedForeignCurrency { balance, .. }
+            | BankAccountEvent::CustomerWithdrewCash { balance, .. }
+            | BankAccountEvent::TransferDebited { balance, .. }
+            | BankAccountEvent::TransferCredited { balance, .. }
+            | BankAccountEvent::TransferRefunded { balance, .. }
+            | BankAccountEvent::InterestPosted { balance, .. } => {
+                self.set_balance(balance);
             }
 
-            BankAccountEvent::CustomerWithdrewCash { amount, balance } => {
-                self.account_transactions
-                    .push(AccountTransaction::new("atm withdrawal", *amount));
-                self.balance = *balance;
+            BankAccountEvent::CustomerWroteCheck {
+                check_number,
+                balance,
+                ..
+            } => {
+                self.written_checks.push(check_number.clone());
+                self.set_balance(balance);
             }
 
-            BankAccountEvent::CustomerWroteCheck {
+            BankAccountEvent::CheckCancelled {
                 check_number,
+                balance,
+                ..
+            } => {
+                self.cancelled_checks.push(check_number.clone());
+                self.set_balance(balance);
+            }
+
+            BankAccountEvent::AccountFrozen { .. } => {
+                self.status = AccountStatus::Frozen;
+            }
+
+            BankAccountEvent::AccountUnfrozen { .. } => {
+                self.status = AccountStatus::Open;
+            }
+
+            BankAccountEvent::AccountClosed { .. } => {
+                self.status = AccountStatus::Closed;
+            }
+
+            BankAccountEvent::OwnerAssigned { owner_id } => {
+                self.owner_id = Some(owner_id.clone());
+            }
+
+            BankAccountEvent::OverdraftLimitSet { limit } => {
+                self.overdraft_limit = limit.clone();
+            }
+
+            BankAccountEvent::HoldPlaced {
+                hold_id,
                 amount,
+                expires_at,
+                available_balance,
+            } => {
+                self.holds.push(AccountHold {
+                    hold_id: hold_id.clone(),
+                    amount: amount.clone(),
+                    expires_at: expires_at.to_rfc3339(),
+                });
+                self.available_balance = available_balance.clone();
+            }
+
+            BankAccountEvent::HoldReleased {
+                hold_id,
+                available_balance,
+                ..
+            }
+            | BankAccountEvent::HoldExpired {
+                hold_id,
+                available_balance,
+                ..
+            } => {
+                self.holds.retain(|hold| &hold.hold_id != hold_id);
+                self.available_balance = available_balance.clone();
+            }
+
+            BankAccountEvent::HoldCaptured {
+                hold_id,
                 balance,
+                available_balance,
+                ..
             } => {
-                self.account_transactions
-                    .push(AccountTransaction::new(check_number, *amount));
-                self.written_checks.push(check_number.clone());
-                self.balance = *balance;
+                self.holds.retain(|hold| &hold.hold_id != hold_id);
+                self.balance = balance.clone();
+                self.available_balance = available_balance.clone();
             }
+
+            // Who may access the account is projected into `account_access` instead, and interest
+            // only changes the balance once it is posted.
+            BankAccountEvent::OverdraftEntered { .. }
+            | BankAccountEvent::AccessGranted { .. }
+            | BankAccountEvent::AccessRevoked { .. }
+            | BankAccountEvent::InterestAccrued { .. } => {}
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
-#[cfg_attr(feature = "graphql", derive(SimpleObject))]
-#[cfg_attr(feature = "frontend", derive(TS))]
-#[ts(export, export_to = "frontend/src/bindings/")]
-pub struct AccountTransaction {
-    pub description: String,
-    pub amount: f64,
-}
-impl AccountTransaction {
-    fn new(description: &str, amount: f64) -> Self {
-        Self {
-            description: description.to_string(),
-            amount,
-        }
+impl BankAccountView {
+    // Money moving in or out of the account leaves what is held as it is, so the available
+    // balance moves along with the balance.
+    fn set_balance(&mut self, balance: &Money) {
+        let held = self
+            .holds
+            .iter()
+            .try_fold(Money::zero(&balance.currency), |held, hold| {
+                held.checked_add(&hold.amount)
+            });
+        self.available_balance = held
+            .and_then(|held| balance.checked_sub(&held))
+            .unwrap_or_else(|_| balance.clone());
+        self.balance = balance.clone();
     }
 }
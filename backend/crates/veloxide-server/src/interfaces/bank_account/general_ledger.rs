@@ -0,0 +1,857 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use super::*;
+
+/// The table the double-entry postings of every account are projected into.
+pub const LEDGER_POSTINGS_TABLE: &str = "ledger_postings";
+
+#[derive(thiserror::Error, Debug)]
+pub enum GeneralLedgerError {
+    #[error("unknown ledger account: {0}")]
+    UnknownLedgerAccount(String),
+
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub enum LedgerAccountType {
+    Asset,
+    Liability,
+    Expense,
+}
+
+/// The chart of accounts, as seeded into the `ledger_accounts` table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub enum LedgerAccount {
+    /// Money held by the bank, paid in by deposits and out by withdrawals
+    Cash,
+    /// What the bank owes its customers, the balances of their accounts
+    CustomerDeposits,
+    /// Checks written but not yet presented for payment
+    ChequesClearing,
+    /// Transfers debited from one account and not yet credited to the other or refunded
+    TransfersInTransit,
+    /// Captured holds, owed to whoever the hold was placed for
+    CardSettlements,
+    /// Interest paid to customers
+    InterestExpense,
+}
+
+impl LedgerAccount {
+    pub fn code(&self) -> &'static str {
+        match self {
+            LedgerAccount::Cash => "1000",
+            LedgerAccount::CustomerDeposits => "2000",
+            LedgerAccount::ChequesClearing => "2100",
+            LedgerAccount::TransfersInTransit => "2200",
+            LedgerAccount::CardSettlements => "2300",
+            LedgerAccount::InterestExpense => "5000",
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            LedgerAccount::Cash => "Cash",
+            LedgerAccount::CustomerDeposits => "Customer deposits",
+            LedgerAccount::ChequesClearing => "Cheques clearing",
+            LedgerAccount::TransfersInTransit => "Transfers in transit",
+            LedgerAccount::CardSettlements => "Card settlements",
+            LedgerAccount::InterestExpense => "Interest expense",
+        }
+    }
+
+    pub fn account_type(&self) -> LedgerAccountType {
+        match self {
+            LedgerAccount::Cash => LedgerAccountType::Asset,
+            LedgerAccount::CustomerDeposits
+            | LedgerAccount::ChequesClearing
+            | LedgerAccount::TransfersInTransit
+            | LedgerAccount::CardSettlements => LedgerAccountType::Liability,
+            LedgerAccount::InterestExpense => LedgerAccountType::Expense,
+        }
+    }
+}
+
+impl Display for LedgerAccount {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> core::result::Result<(), std::fmt::Error> {
+        write!(f, "{}", self.code())
+    }
+}
+
+impl FromStr for LedgerAccount {
+    type Err = GeneralLedgerError;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        match code {
+            "1000" => Ok(LedgerAccount::Cash),
+            "2000" => Ok(LedgerAccount::CustomerDeposits),
+            "2100" => Ok(LedgerAccount::ChequesClearing),
+            "2200" => Ok(LedgerAccount::TransfersInTransit),
+            "2300" => Ok(LedgerAccount::CardSettlements),
+            "5000" => Ok(LedgerAccount::InterestExpense),
+            _ => Err(GeneralLedgerError::UnknownLedgerAccount(code.to_string())),
+        }
+    }
+}
+
+/// One side of a journal entry. Only one of `debit_minor_units` and `credit_minor_units` is set
+/// for a single posting, sums of postings may have both.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LedgerPosting {
+    pub ledger_account: LedgerAccount,
+    pub debit_minor_units: i64,
+    pub credit_minor_units: i64,
+    pub currency: String,
+}
+
+/// The journal entry a bank account event is booked as, moving `amount` from the `credit`
+/// ledger account to the `debit` one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LedgerEntry {
+    pub description: String,
+    pub debit: LedgerAccount,
+    pub credit: LedgerAccount,
+    pub amount: Money,
+}
+
+impl LedgerEntry {
+    fn new(description: &str, debit: LedgerAccount, credit: LedgerAccount, amount: &Money) -> Self {
+        Self {
+            description: description.to_string(),
+            debit,
+            credit,
+            amount: amount.clone(),
+        }
+    }
+
+    /// The entry the event is booked as, or `None` for the events that move no money.
+    pub fn from_event(event: &BankAccountEvent) -> Option<Self> {
+        let transaction = NewAccountTransaction::from_event(event)?;
+        let description = &transaction.description;
+        let entry = match event {
+            BankAccountEvent::CustomerDepositedMoney { amount, .. }
+            | BankAccountEvent::CustomerDepositedForeignCurrency { amount, .. } => Self::new(
+                description,
+                LedgerAccount::Cash,
+                LedgerAccount::CustomerDeposits,
+                amount,
+            ),
+
+            BankAccountEvent::CustomerWithdrewCash { amount, .. } => Self::new(
+                description,
+                LedgerAccount::CustomerDeposits,
+                LedgerAccount::Cash,
+                amount,
+            ),
+
+            BankAccountEvent::CustomerWroteCheck { amount, .. } => Self::new(
+                description,
+                LedgerAccount::CustomerDeposits,
+                LedgerAccount::ChequesClearing,
+                amount,
+            ),
+
+            BankAccountEvent::CheckCancelled { amount, .. } => Self::new(
+                description,
+                LedgerAccount::ChequesClearing,
+                LedgerAccount::CustomerDeposits,
+                amount,
+            ),
+
+            BankAccountEvent::TransferDebited { amount, .. } => Self::new(
+                description,
+                LedgerAccount::CustomerDeposits,
+                LedgerAccount::TransfersInTransit,
+                amount,
+            ),
+
+            BankAccountEvent::TransferCredited { amount, .. }
+            | BankAccountEvent::TransferRefunded { amount, .. } => Self::new(
+                description,
+                LedgerAccount::TransfersInTransit,
+                LedgerAccount::CustomerDeposits,
+                amount,
+            ),
+
+            BankAccountEvent::InterestPosted { amount, .. } => Self::new(
+                description,
+                LedgerAccount::InterestExpense,
+                LedgerAccount::CustomerDeposits,
+                amount,
+            ),
+
+            BankAccountEvent::HoldCaptured { amount, .. } => Self::new(
+                description,
+                LedgerAccount::CustomerDeposits,
+                LedgerAccount::CardSettlements,
+                amount,
+            ),
+
+            BankAccountEvent::AccountOpened { .. }
+            | BankAccountEvent::AccountFrozen { .. }
+            | BankAccountEvent::AccountUnfrozen { .. }
+            | BankAccountEvent::AccountClosed { .. }
+            | BankAccountEvent::OverdraftLimitSet { .. }
+            | BankAccountEvent::OverdraftEntered { .. }
+            | BankAccountEvent::AccessGranted { .. }
+            | BankAccountEvent::AccessRevoked { .. }
+            | BankAccountEvent::OwnerAssigned { .. }
+            | BankAccountEvent::InterestAccrued { .. }
+            | BankAccountEvent::HoldPlaced { .. }
+            | BankAccountEvent::HoldReleased { .. }
+            | BankAccountEvent::HoldExpired { .. } => return None,
+        };
+        Some(entry)
+    }
+
+    /// The debit and the credit posting of the entry, which always balance.
+    pub fn postings(&self) -> [LedgerPosting; 2] {
+        [
+            LedgerPosting {
+                ledger_account: self.debit,
+                debit_minor_units: self.amount.minor_units,
+                credit_minor_units: 0,
+                currency: self.amount.currency.clone(),
+            },
+            LedgerPosting {
+                ledger_account: self.credit,
+                debit_minor_units: 0,
+                credit_minor_units: self.amount.minor_units,
+                currency: self.amount.currency.clone(),
+            },
+        ]
+    }
+}
+
+/// The debits and credits of a ledger account in one currency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct TrialBalanceLine {
+    pub ledger_account: LedgerAccount,
+    pub code: String,
+    pub name: String,
+    pub account_type: LedgerAccountType,
+    pub debits: Money,
+    pub credits: Money,
+    /// Debits less credits, negative for the accounts with a credit balance
+    pub balance: Money,
+}
+
+/// The debits and credits of every ledger account in one currency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+pub struct TrialBalanceTotal {
+    pub debits: Money,
+    pub credits: Money,
+}
+
+/// The balance of every ledger account, per currency. The ledger balances when the debits and
+/// credits of each currency add up to the same total.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+pub struct TrialBalance {
+    pub lines: Vec<TrialBalanceLine>,
+    pub totals: Vec<TrialBalanceTotal>,
+    pub balanced: bool,
+}
+
+impl TrialBalance {
+    /// Sums the postings by ledger account and currency.
+    pub fn from_postings(postings: impl IntoIterator<Item = LedgerPosting>) -> Self {
+        let mut sums: BTreeMap<(LedgerAccount, String), (i64, i64)> = BTreeMap::new();
+        for posting in postings {
+            let sum = sums
+                .entry((posting.ledger_account, posting.currency))
+                .or_default();
+            sum.0 += posting.debit_minor_units;
+            sum.1 += posting.credit_minor_units;
+        }
+
+        let mut totals: BTreeMap<String, (i64, i64)> = BTreeMap::new();
+        let mut lines = vec![];
+        for ((ledger_account, currency), (debits, credits)) in sums {
+            let total = totals.entry(currency.clone()).or_default();
+            total.0 += debits;
+            total.1 += credits;
+            lines.push(TrialBalanceLine {
+                ledger_account,
+                code: ledger_account.code().to_string(),
+                name: ledger_account.name().to_string(),
+                account_type: ledger_account.account_type(),
+                debits: Money::new(debits, &currency),
+                credits: Money::new(credits, &currency),
+                balance: Money::new(debits - credits, &currency),
+            });
+        }
+        let totals: Vec<TrialBalanceTotal> = totals
+            .into_iter()
+            .map(|(currency, (debits, credits))| TrialBalanceTotal {
+                debits: Money::new(debits, &currency),
+                credits: Money::new(credits, &currency),
+            })
+            .collect();
+        Self {
+            balanced: totals.iter().all(|total| total.debits == total.credits),
+            lines,
+            totals,
+        }
+    }
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use async_trait::async_trait;
+        use cqrs_es::persist::PersistedEventStore;
+        use cqrs_es::EventStore;
+        use postgres_es::PostgresEventRepository;
+        use sqlx::{Connection, PgConnection, Pool, Postgres, Row};
+
+        use crate::infrastructure::projections::{
+            aggregate_ids, Projection, ProjectionError, ProjectionProgress, ProjectionRebuildReport,
+        };
+
+        /// Books every event that moves money as a debit and a credit posting in the
+        /// `ledger_postings` table, against the chart of accounts in `ledger_accounts`.
+        #[derive(Clone)]
+        pub struct GeneralLedgerQuery {
+            pool: Pool<Postgres>,
+        }
+
+        impl GeneralLedgerQuery {
+            pub fn new(pool: Pool<Postgres>) -> Self {
+                Self { pool }
+            }
+
+            /// Sums the postings of every ledger account.
+            pub async fn trial_balance(&self) -> Result<TrialBalance, GeneralLedgerError> {
+                let rows = sqlx::query(
+                    "SELECT ledger_account, currency, CAST(SUM(debit_minor_units) AS BIGINT) AS debits, CAST(SUM(credit_minor_units) AS BIGINT) AS credits FROM ledger_postings GROUP BY ledger_account, currency",
+                )
+                .fetch_all(&self.pool)
+                .await?;
+                let postings = rows
+                    .iter()
+                    .map(|row| -> Result<LedgerPosting, GeneralLedgerError> {
+                        let ledger_account: String = row.try_get("ledger_account")?;
+                        Ok(LedgerPosting {
+                            ledger_account: ledger_account.parse()?,
+                            debit_minor_units: row.try_get("debits")?,
+                            credit_minor_units: row.try_get("credits")?,
+                            currency: row.try_get("currency")?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(TrialBalance::from_postings(postings))
+            }
+
+            /// What the ledger owes the holder of the account, credits less debits of its customer
+            /// deposits, or `None` if nothing was posted for the account.
+            pub async fn customer_deposits(&self, account_id: &str) -> Result<Option<Money>, GeneralLedgerError> {
+                let row = sqlx::query(
+                    "SELECT currency, CAST(SUM(credit_minor_units - debit_minor_units) AS BIGINT) AS balance FROM ledger_postings WHERE account_id = $1 AND ledger_account = $2 GROUP BY currency",
+                )
+                .bind(account_id)
+                .bind(LedgerAccount::CustomerDeposits.to_string())
+                .fetch_optional(&self.pool)
+                .await?;
+                let Some(row) = row else {
+                    return Ok(None);
+                };
+                let currency: String = row.try_get("currency")?;
+                Ok(Some(Money::new(row.try_get("balance")?, &currency)))
+            }
+
+            // Postings are keyed on the event sequence, so events that are dispatched again, e.g.
+            // by a rebuild that overlaps with live traffic, are only booked once. Both postings
+            // of an entry are written in one transaction, so the ledger never holds half of one.
+            async fn record(&self, connection: &mut PgConnection, account_id: &str, events: &[EventEnvelope<BankAccount>]) -> Result<(), sqlx::Error> {
+                let committed_at = commit_times(&self.pool, account_id, events).await?;
+                for event in events {
+                    let Some(entry) = LedgerEntry::from_event(&event.payload) else {
+                        continue;
+                    };
+                    let mut transaction = connection.begin().await?;
+                    for posting in entry.postings() {
+                        sqlx::query(
+                            "INSERT INTO ledger_postings (account_id, sequence, ledger_account, debit_minor_units, credit_minor_units, currency, description, occurred_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8) ON CONFLICT (account_id, sequence, ledger_account) DO NOTHING",
+                        )
+                        .bind(account_id)
+                        .bind(event.sequence as i64)
+                        .bind(posting.ledger_account.to_string())
+                        .bind(posting.debit_minor_units)
+                        .bind(posting.credit_minor_units)
+                        .bind(posting.currency)
+                        .bind(&entry.description)
+                        .bind(occurred_at(event, &committed_at))
+                        .execute(&mut *transaction)
+                        .await?;
+                    }
+                    transaction.commit().await?;
+                }
+                Ok(())
+            }
+        }
+
+        #[async_trait]
+        impl Query<BankAccount> for GeneralLedgerQuery {
+            async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<BankAccount>]) {
+                let posted = match self.pool.acquire().await {
+                    Ok(mut connection) => self.record(&mut connection, aggregate_id, events).await,
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = posted {
+                    tracing::error!("failed to post the events of account {} to the ledger: {}", aggregate_id, err);
+                }
+            }
+        }
+
+        #[async_trait]
+        impl Projection for GeneralLedgerQuery {
+            fn name(&self) -> &str {
+                LEDGER_POSTINGS_TABLE
+            }
+
+            async fn rebuild(
+                &self,
+                aggregate_id: Option<&str>,
+                on_progress: &(dyn Fn(&ProjectionProgress) + Send + Sync),
+            ) -> Result<ProjectionRebuildReport, ProjectionError> {
+                let mut transaction = self.pool.begin().await?;
+                let aggregate_ids: Vec<String> = match aggregate_id {
+                    Some(aggregate_id) => {
+                        sqlx::query("DELETE FROM ledger_postings WHERE account_id = $1")
+                            .bind(aggregate_id)
+                            .execute(&mut *transaction)
+                            .await?;
+                        vec![aggregate_id.to_string()]
+                    }
+                    None => {
+                        sqlx::query("TRUNCATE TABLE ledger_postings")
+                            .execute(&mut *transaction)
+                            .await?;
+                        aggregate_ids::<BankAccount>(&self.pool).await?
+                    }
+                };
+
+                let event_store: PersistedEventStore<PostgresEventRepository, BankAccount> =
+                    PersistedEventStore::new_event_store(PostgresEventRepository::new(self.pool.clone()))
+                        .with_upcasters(bank_account_upcasters());
+                let total = aggregate_ids.len();
+                let mut events_replayed = 0;
+                for (index, aggregate_id) in aggregate_ids.into_iter().enumerate() {
+                    let replay_error = |reason: String| ProjectionError::Replay {
+                        aggregate_id: aggregate_id.clone(),
+                        reason,
+                    };
+                    let events = event_store
+                        .load_events(&aggregate_id)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    self.record(&mut transaction, &aggregate_id, &events)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    events_replayed += events.len();
+                    on_progress(&ProjectionProgress {
+                        aggregate_id,
+                        replayed: index + 1,
+                        total,
+                    });
+                }
+                transaction.commit().await?;
+                Ok(ProjectionRebuildReport {
+                    projection: LEDGER_POSTINGS_TABLE.to_string(),
+                    aggregates_replayed: total,
+                    events_replayed,
+                })
+            }
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use async_trait::async_trait;
+        use cqrs_es::persist::PersistedEventStore;
+        use cqrs_es::EventStore;
+        use mysql_es::MysqlEventRepository;
+        use sqlx::{Connection, MySql, MySqlConnection, Pool, Row};
+
+        use crate::infrastructure::projections::{
+            aggregate_ids, Projection, ProjectionError, ProjectionProgress, ProjectionRebuildReport,
+        };
+
+        /// Books every event that moves money as a debit and a credit posting in the
+        /// `ledger_postings` table, against the chart of accounts in `ledger_accounts`.
+        #[derive(Clone)]
+        pub struct GeneralLedgerQuery {
+            pool: Pool<MySql>,
+        }
+
+        impl GeneralLedgerQuery {
+            pub fn new(pool: Pool<MySql>) -> Self {
+                Self { pool }
+            }
+
+            /// Sums the postings of every ledger account.
+            pub async fn trial_balance(&self) -> Result<TrialBalance, GeneralLedgerError> {
+                let rows = sqlx::query(
+                    "SELECT ledger_account, currency, CAST(SUM(debit_minor_units) AS SIGNED) AS debits, CAST(SUM(credit_minor_units) AS SIGNED) AS credits FROM ledger_postings GROUP BY ledger_account, currency",
+                )
+                .fetch_all(&self.pool)
+                .await?;
+                let postings = rows
+                    .iter()
+                    .map(|row| -> Result<LedgerPosting, GeneralLedgerError> {
+                        let ledger_account: String = row.try_get("ledger_account")?;
+                        Ok(LedgerPosting {
+                            ledger_account: ledger_account.parse()?,
+                            debit_minor_units: row.try_get("debits")?,
+                            credit_minor_units: row.try_get("credits")?,
+                            currency: row.try_get("currency")?,
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(TrialBalance::from_postings(postings))
+            }
+
+            /// What the ledger owes the holder of the account, credits less debits of its customer
+            /// deposits, or `None` if nothing was posted for the account.
+            pub async fn customer_deposits(&self, account_id: &str) -> Result<Option<Money>, GeneralLedgerError> {
+                let row = sqlx::query(
+                    "SELECT currency, CAST(SUM(credit_minor_units - debit_minor_units) AS SIGNED) AS balance FROM ledger_postings WHERE account_id = ? AND ledger_account = ? GROUP BY currency",
+                )
+                .bind(account_id)
+                .bind(LedgerAccount::CustomerDeposits.to_string())
+                .fetch_optional(&self.pool)
+                .await?;
+                let Some(row) = row else {
+                    return Ok(None);
+                };
+                let currency: String = row.try_get("currency")?;
+                Ok(Some(Money::new(row.try_get("balance")?, &currency)))
+            }
+
+            // Postings are keyed on the event sequence, so events that are dispatched again, e.g.
+            // by a rebuild that overlaps with live traffic, are only booked once. Both postings
+            // of an entry are written in one transaction, so the ledger never holds half of one.
+            async fn record(&self, connection: &mut MySqlConnection, account_id: &str, events: &[EventEnvelope<BankAccount>]) -> Result<(), sqlx::Error> {
+                let committed_at = commit_times(&self.pool, account_id, events).await?;
+                for event in events {
+                    let Some(entry) = LedgerEntry::from_event(&event.payload) else {
+                        continue;
+                    };
+                    let mut transaction = connection.begin().await?;
+                    for posting in entry.postings() {
+                        sqlx::query(
+                            "INSERT IGNORE INTO ledger_postings (account_id, sequence, ledger_account, debit_minor_units, credit_minor_units, currency, description, occurred_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+                        )
+                        .bind(account_id)
+                        .bind(event.sequence as i64)
+                        .bind(posting.ledger_account.to_string())
+                        .bind(posting.debit_minor_units)
+                        .bind(posting.credit_minor_units)
+                        .bind(posting.currency)
+                        .bind(&entry.description)
+                        .bind(occurred_at(event, &committed_at))
+                        .execute(&mut *transaction)
+                        .await?;
+                    }
+                    transaction.commit().await?;
+                }
+                Ok(())
+            }
+        }
+
+        #[async_trait]
+        impl Query<BankAccount> for GeneralLedgerQuery {
+            async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<BankAccount>]) {
+                let posted = match self.pool.acquire().await {
+                    Ok(mut connection) => self.record(&mut connection, aggregate_id, events).await,
+                    Err(err) => Err(err),
+                };
+                if let Err(err) = posted {
+                    tracing::error!("failed to post the events of account {} to the ledger: {}", aggregate_id, err);
+                }
+            }
+        }
+
+        #[async_trait]
+        impl Projection for GeneralLedgerQuery {
+            fn name(&self) -> &str {
+                LEDGER_POSTINGS_TABLE
+            }
+
+            async fn rebuild(
+                &self,
+                aggregate_id: Option<&str>,
+                on_progress: &(dyn Fn(&ProjectionProgress) + Send + Sync),
+            ) -> Result<ProjectionRebuildReport, ProjectionError> {
+                let mut transaction = self.pool.begin().await?;
+                let aggregate_ids: Vec<String> = match aggregate_id {
+                    Some(aggregate_id) => {
+                        sqlx::query("DELETE FROM ledger_postings WHERE account_id = ?")
+                            .bind(aggregate_id)
+                            .execute(&mut *transaction)
+                            .await?;
+                        vec![aggregate_id.to_string()]
+                    }
+                    None => {
+                        sqlx::query("DELETE FROM ledger_postings")
+                            .execute(&mut *transaction)
+                            .await?;
+                        aggregate_ids::<BankAccount>(&self.pool).await?
+                    }
+                };
+
+                let event_store: PersistedEventStore<MysqlEventRepository, BankAccount> =
+                    PersistedEventStore::new_event_store(MysqlEventRepository::new(self.pool.clone()))
+                        .with_upcasters(bank_account_upcasters());
+                let total = aggregate_ids.len();
+                let mut events_replayed = 0;
+                for (index, aggregate_id) in aggregate_ids.into_iter().enumerate() {
+                    let replay_error = |reason: String| ProjectionError::Replay {
+                        aggregate_id: aggregate_id.clone(),
+                        reason,
+                    };
+                    let events = event_store
+                        .load_events(&aggregate_id)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    self.record(&mut transaction, &aggregate_id, &events)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    events_replayed += events.len();
+                    on_progress(&ProjectionProgress {
+                        aggregate_id,
+                        replayed: index + 1,
+                        total,
+                    });
+                }
+                transaction.commit().await?;
+                Ok(ProjectionRebuildReport {
+                    projection: LEDGER_POSTINGS_TABLE.to_string(),
+                    aggregates_replayed: total,
+                    events_replayed,
+                })
+            }
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+
+    fn usd(minor_units: i64) -> Money {
+        Money::new(minor_units, "USD")
+    }
+
+    // The event streams of two accounts covering every event that moves money, with a transfer
+    // between them that completes and one that is refunded.
+    fn event_streams() -> Vec<(&'static str, Vec<BankAccountEvent>)> {
+        let opened = |account_id: &str| BankAccountEvent::AccountOpened {
+            account_id: account_id.to_string(),
+            currency: "USD".to_string(),
+            owner_id: None,
+        };
+        vec![
+            (
+                "1234",
+                vec![
+                    opened("1234"),
+                    BankAccountEvent::CustomerDepositedMoney {
+                        amount: usd(100000),
+                        balance: usd(100000),
+                    },
+                    BankAccountEvent::CustomerWithdrewCash {
+                        amount: usd(20000),
+                        balance: usd(80000),
+                    },
+                    BankAccountEvent::CustomerWroteCheck {
+                        check_number: "1170".to_string(),
+                        amount: usd(5000),
+                        balance: usd(75000),
+                        written_at: None,
+                    },
+                    BankAccountEvent::CustomerWroteCheck {
+                        check_number: "1171".to_string(),
+                        amount: usd(3000),
+                        balance: usd(72000),
+                        written_at: None,
+                    },
+                    BankAccountEvent::CheckCancelled {
+                        check_number: "1171".to_string(),
+                        amount: usd(3000),
+                        balance: usd(75000),
+                    },
+                    BankAccountEvent::TransferDebited {
+                        transfer_id: "T-1".to_string(),
+                        destination_account_id: "5678".to_string(),
+                        amount: usd(10000),
+                        balance: usd(65000),
+                    },
+                    BankAccountEvent::TransferDebited {
+                        transfer_id: "T-2".to_string(),
+                        destination_account_id: "9999".to_string(),
+                        amount: usd(4000),
+                        balance: usd(61000),
+                    },
+                    BankAccountEvent::TransferRefunded {
+                        transfer_id: "T-2".to_string(),
+                        amount: usd(4000),
+                        balance: usd(65000),
+                    },
+                    BankAccountEvent::HoldPlaced {
+                        hold_id: "H-1".to_string(),
+                        amount: usd(2500),
+                        expires_at: "2023-11-21T12:00:00Z".parse().unwrap(),
+                        available_balance: usd(62500),
+                    },
+                    BankAccountEvent::HoldCaptured {
+                        hold_id: "H-1".to_string(),
+                        amount: usd(2000),
+                        released: usd(500),
+                        balance: usd(63000),
+                        available_balance: usd(63000),
+                    },
+                    BankAccountEvent::InterestPosted {
+                        period: "2023-11".to_string(),
+                        amount: usd(212),
+                        balance: usd(63212),
+                    },
+                ],
+            ),
+            (
+                "5678",
+                vec![
+                    opened("5678"),
+                    BankAccountEvent::CustomerDepositedForeignCurrency {
+                        original_amount: Money::new(1000, "EUR"),
+                        exchange_rate: ExchangeRate {
+                            from_currency: "EUR".to_string(),
+                            to_currency: "USD".to_string(),
+                            scaled_rate: 110000000,
+                        },
+                        amount: usd(1100),
+                        balance: usd(1100),
+                    },
+                    BankAccountEvent::TransferCredited {
+                        transfer_id: "T-1".to_string(),
+                        source_account_id: "1234".to_string(),
+                        amount: usd(10000),
+                        balance: usd(11100),
+                    },
+                    BankAccountEvent::CustomerWithdrewCash {
+                        amount: usd(12000),
+                        balance: usd(-900),
+                    },
+                    BankAccountEvent::OverdraftEntered { balance: usd(-900) },
+                ],
+            ),
+        ]
+    }
+
+    fn customer_deposits(postings: &[LedgerPosting]) -> Money {
+        let balance: i64 = postings
+            .iter()
+            .filter(|posting| posting.ledger_account == LedgerAccount::CustomerDeposits)
+            .map(|posting| posting.credit_minor_units - posting.debit_minor_units)
+            .sum();
+        usd(balance)
+    }
+
+    // Replays every event into the ledger the way the projection does, checking after each one
+    // that the customer deposits of the account match the balance the event left it at.
+    #[test]
+    fn ledger_balances_after_replaying_the_events() {
+        let mut ledger = vec![];
+        for (account_id, events) in event_streams() {
+            let mut account_postings = vec![];
+            for event in events {
+                if let Some(entry) = LedgerEntry::from_event(&event) {
+                    account_postings.extend(entry.postings());
+                }
+                if let Some(balance) = event.balance() {
+                    assert_eq!(
+                        &customer_deposits(&account_postings),
+                        balance,
+                        "customer deposits of {} after {:?}",
+                        account_id,
+                        event
+                    );
+                }
+            }
+            ledger.extend(account_postings);
+        }
+
+        let trial_balance = TrialBalance::from_postings(ledger);
+        assert!(trial_balance.balanced);
+        assert_eq!(
+            trial_balance
+                .lines
+                .iter()
+                .map(|line| (line.ledger_account, line.balance.minor_units))
+                .collect::<HashMap<_, _>>(),
+            HashMap::from([
+                (LedgerAccount::Cash, 100000 + 1100 - 20000 - 12000),
+                (LedgerAccount::CustomerDeposits, -(63212 - 900)),
+                (LedgerAccount::ChequesClearing, -5000),
+                // Both transfers have settled, one credited and one refunded.
+                (LedgerAccount::TransfersInTransit, 0),
+                (LedgerAccount::CardSettlements, -2000),
+                (LedgerAccount::InterestExpense, 212),
+            ])
+        );
+    }
+
+    #[test]
+    fn trial_balance_is_unbalanced_when_postings_are_missing() {
+        let entry = LedgerEntry::from_event(&BankAccountEvent::CustomerDepositedMoney {
+            amount: usd(1000),
+            balance: usd(1000),
+        })
+        .unwrap();
+        let [debit, _] = entry.postings();
+        let trial_balance = TrialBalance::from_postings(vec![debit]);
+        assert!(!trial_balance.balanced);
+        assert_eq!(
+            trial_balance.totals,
+            vec![TrialBalanceTotal {
+                debits: usd(1000),
+                credits: usd(0),
+            }]
+        );
+    }
+
+    #[test]
+    fn events_that_move_no_money_are_not_posted() {
+        let event = BankAccountEvent::HoldReleased {
+            hold_id: "H-1".to_string(),
+            amount: usd(2500),
+            available_balance: usd(10000),
+        };
+        assert_eq!(LedgerEntry::from_event(&event), None);
+    }
+
+    #[test]
+    fn round_trips_ledger_account_codes() {
+        for ledger_account in [
+            LedgerAccount::Cash,
+            LedgerAccount::CustomerDeposits,
+            LedgerAccount::ChequesClearing,
+            LedgerAccount::TransfersInTransit,
+            LedgerAccount::CardSettlements,
+            LedgerAccount::InterestExpense,
+        ] {
+            assert_eq!(
+                ledger_account.to_string().parse::<LedgerAccount>().unwrap(),
+                ledger_account
+            );
+        }
+    }
+}
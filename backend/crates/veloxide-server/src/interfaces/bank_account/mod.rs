@@ -1,21 +1,44 @@
 use super::*;
-use cqrs_es::persist::ViewRepository;
-use cqrs_es::Query;
+use cqrs_es::persist::{PersistedEventStore, ViewRepository};
 use cqrs_es::{persist::GenericQuery, EventEnvelope, View};
+use cqrs_es::{CqrsFramework, Query};
 
-use crate::application::{BankAccountServices, HappyPathBankAccountServices};
+use crate::application::{
+    BankAccountServices, StaticFxRateProvider, SystemClock, TransferProcessManager,
+    FX_RATES_ENV_VAR,
+};
+use crate::infrastructure::bank_account_services::{
+    AtmWithdrawals, BankAccountServicesConfiguration, BANK_ACCOUNT_SERVICES_ENV_VAR,
+};
 use crate::infrastructure::logging::SimpleLoggingQuery;
-use std::sync::Arc;
+use crate::infrastructure::outbox::{OutboxCqrs, OutboxEventRepository};
+use crate::infrastructure::projections::ViewProjection;
+use crate::infrastructure::snapshots::{SnapshotConfiguration, SNAPSHOT_SIZE_ENV_VAR};
+use crate::interfaces::webhooks::{WebhookQuery, WebhookStore};
+use std::sync::{Arc, Weak};
 
 use cfg_if::cfg_if;
 
+pub mod account_access;
+pub mod account_events;
+pub mod account_statements;
+pub mod account_transactions;
 pub mod bank_account_graphql;
 pub mod bank_account_views;
+pub mod general_ledger;
 
 // Re-exports
 
+pub use account_access::*;
+pub use account_events::*;
+pub use account_statements::*;
+pub use account_transactions::*;
 pub use bank_account_graphql::*;
 pub use bank_account_views::*;
+pub use general_ledger::*;
+
+/// The event store bank account commands are executed against.
+pub type BankAccountEventStore = PersistedEventStore<OutboxEventRepository, BankAccount>;
 
 cfg_if! {
     if #[cfg(feature = "postgres")] {
@@ -23,15 +46,17 @@ cfg_if! {
         use sqlx::{Pool, Postgres};
         pub fn get_bank_account_cqrs_framework(
             pool: Pool<Postgres>,
+            transfer_cqrs: Arc<PostgresCqrs<Transfer>>,
+            account_events: AccountEventBroadcaster,
         ) -> (
-            Arc<PostgresCqrs<BankAccount>>,
+            Arc<OutboxCqrs<BankAccount>>,
             Arc<PostgresViewRepository<BankAccountView, BankAccount>>,
         ) {
             // A very simple query that writes each event to stdout.
             let simple_query = SimpleLoggingQuery {};
 
             // A query that stores the current state of an individual account.
-            let account_view_repo = Arc::new(PostgresViewRepository::new("account_query", pool.clone()));
+            let account_view_repo = Arc::new(PostgresViewRepository::new(ACCOUNT_QUERY_TABLE, pool.clone()));
             let mut account_query = AccountQuery::new(account_view_repo.clone());
 
             // Without a query error handler there will be no indication if an
@@ -39,14 +64,86 @@ cfg_if! {
             // Consider logging an error or panicking in your own application.
             account_query.use_error_handler(Box::new(|e| println!("{}", e)));
 
-            // Create and return an event-sourced `CqrsFramework`.
-            let queries: Vec<Box<dyn Query<BankAccount>>> =
-                vec![Box::new(simple_query), Box::new(account_query)];
-            let services = BankAccountServices::new(Box::new(HappyPathBankAccountServices));
-            (
-                Arc::new(postgres_es::postgres_cqrs(pool, queries, services)),
-                account_view_repo,
-            )
+            // A query that records the transactions of each account, one row per transaction.
+            let account_transactions_query = AccountTransactionsQuery::new(pool.clone());
+
+            // A query that books each event that moves money in the double-entry general ledger.
+            let general_ledger_query = GeneralLedgerQuery::new(pool.clone());
+
+            // A query that records who may access each account and in which role.
+            let account_access_query = AccountAccessQuery::new(pool.clone());
+
+            // A query that queues each event for the webhooks subscribed to it. It runs after the
+            // access query, so that subscribers are matched against the access the event leaves.
+            let webhook_query = WebhookQuery::new(WebhookStore::new(pool.clone()));
+
+            let fx_rate_provider = StaticFxRateProvider::from_env()
+                .unwrap_or_else(|e| panic!("failed to read {}: {}", FX_RATES_ENV_VAR, e));
+            let services_configuration = BankAccountServicesConfiguration::from_env()
+                .unwrap_or_else(|e| panic!("failed to read {}: {}", BANK_ACCOUNT_SERVICES_ENV_VAR, e));
+            // A query that counts the committed ATM withdrawals, for the rules to limit them by.
+            let atm_withdrawals = AtmWithdrawals::default();
+            let services = BankAccountServices::new(
+                services_configuration.services(Box::new(fx_rate_provider), atm_withdrawals.clone()),
+                Arc::new(SystemClock),
+            );
+
+            let event_store = get_bank_account_event_store(pool);
+
+            // Create and return an event-sourced `CqrsFramework`. The transfer process manager
+            // issues commands back to this framework, so it is given a weak reference to it.
+            let cqrs = Arc::new_cyclic(|cqrs: &Weak<OutboxCqrs<BankAccount>>| {
+                let transfer_process_manager = TransferProcessManager::new(cqrs.clone(), transfer_cqrs);
+                let queries: Vec<Box<dyn Query<BankAccount>>> = vec![
+                    Box::new(simple_query),
+                    Box::new(account_events),
+                    Box::new(account_query),
+                    Box::new(account_transactions_query),
+                    Box::new(general_ledger_query),
+                    Box::new(account_access_query),
+                    Box::new(atm_withdrawals),
+                    Box::new(webhook_query),
+                    Box::new(transfer_process_manager),
+                ];
+                CqrsFramework::new(event_store, queries, services)
+            });
+            (cqrs, account_view_repo)
+        }
+
+        /// The bank account event store. Snapshotting bounds how many events are replayed for each
+        /// command, and upcasters allow events stored in an older shape to still be replayed.
+        /// Committed events are also written to the outbox, for the outbox relay to publish.
+        pub fn get_bank_account_event_store(pool: Pool<Postgres>) -> BankAccountEventStore {
+            let snapshot_configuration = SnapshotConfiguration::from_env()
+                .unwrap_or_else(|e| panic!("failed to read {}: {}", SNAPSHOT_SIZE_ENV_VAR, e));
+            snapshot_configuration
+                .event_store(OutboxEventRepository::new(pool))
+                .with_upcasters(bank_account_upcasters())
+        }
+
+        /// The account views, e.g. for the tasks that run instead of the server.
+        pub fn get_account_view_repository(pool: Pool<Postgres>) -> PostgresViewRepository<BankAccountView, BankAccount> {
+            PostgresViewRepository::new(ACCOUNT_QUERY_TABLE, pool)
+        }
+
+        /// The account views, so that they can be rebuilt from the event store.
+        pub fn get_account_projection(pool: Pool<Postgres>) -> ViewProjection<BankAccount, BankAccountView> {
+            ViewProjection::new(ACCOUNT_QUERY_TABLE, pool, bank_account_upcasters())
+        }
+
+        /// The transaction history of every account, so that it can be rebuilt from the event store.
+        pub fn get_account_transactions_projection(pool: Pool<Postgres>) -> AccountTransactionsQuery {
+            AccountTransactionsQuery::new(pool)
+        }
+
+        /// The postings of the general ledger, so that they can be rebuilt from the event store.
+        pub fn get_general_ledger_projection(pool: Pool<Postgres>) -> GeneralLedgerQuery {
+            GeneralLedgerQuery::new(pool)
+        }
+
+        /// Who may access each account, so that it can be rebuilt from the event store.
+        pub fn get_account_access_projection(pool: Pool<Postgres>) -> AccountAccessQuery {
+            AccountAccessQuery::new(pool)
         }
     } else if #[cfg(feature = "mysql")] {
         use sqlx::{Pool, MySql};
@@ -54,15 +151,17 @@ cfg_if! {
 
         pub fn get_bank_account_cqrs_framework(
             pool: Pool<MySql>,
+            transfer_cqrs: Arc<MysqlCqrs<Transfer>>,
+            account_events: AccountEventBroadcaster,
         ) -> (
-            Arc<MysqlCqrs<BankAccount>>,
+            Arc<OutboxCqrs<BankAccount>>,
             Arc<MysqlViewRepository<BankAccountView, BankAccount>>,
         ) {
             // A very simple query that writes each event to stdout.
             let simple_query = SimpleLoggingQuery {};
 
             // A query that stores the current state of an individual account.
-            let account_view_repo = Arc::new(MysqlViewRepository::new("account_query", pool.clone()));
+            let account_view_repo = Arc::new(MysqlViewRepository::new(ACCOUNT_QUERY_TABLE, pool.clone()));
             let mut account_query = AccountQuery::new(account_view_repo.clone());
 
             // Without a query error handler there will be no indication if an
@@ -70,14 +169,86 @@ cfg_if! {
             // Consider logging an error or panicking in your own application.
             account_query.use_error_handler(Box::new(|e| println!("{}", e)));
 
-            // Create and return an event-sourced `CqrsFramework`.
-            let queries: Vec<Box<dyn Query<BankAccount>>> =
-                vec![Box::new(simple_query), Box::new(account_query)];
-            let services = BankAccountServices::new(Box::new(HappyPathBankAccountServices));
-            (
-                Arc::new(mysql_es::mysql_cqrs(pool, queries, services)),
-                account_view_repo,
-            )
+            // A query that records the transactions of each account, one row per transaction.
+            let account_transactions_query = AccountTransactionsQuery::new(pool.clone());
+
+            // A query that books each event that moves money in the double-entry general ledger.
+            let general_ledger_query = GeneralLedgerQuery::new(pool.clone());
+
+            // A query that records who may access each account and in which role.
+            let account_access_query = AccountAccessQuery::new(pool.clone());
+
+            // A query that queues each event for the webhooks subscribed to it. It runs after the
+            // access query, so that subscribers are matched against the access the event leaves.
+            let webhook_query = WebhookQuery::new(WebhookStore::new(pool.clone()));
+
+            let fx_rate_provider = StaticFxRateProvider::from_env()
+                .unwrap_or_else(|e| panic!("failed to read {}: {}", FX_RATES_ENV_VAR, e));
+            let services_configuration = BankAccountServicesConfiguration::from_env()
+                .unwrap_or_else(|e| panic!("failed to read {}: {}", BANK_ACCOUNT_SERVICES_ENV_VAR, e));
+            // A query that counts the committed ATM withdrawals, for the rules to limit them by.
+            let atm_withdrawals = AtmWithdrawals::default();
+            let services = BankAccountServices::new(
+                services_configuration.services(Box::new(fx_rate_provider), atm_withdrawals.clone()),
+                Arc::new(SystemClock),
+            );
+
+            let event_store = get_bank_account_event_store(pool);
+
+            // Create and return an event-sourced `CqrsFramework`. The transfer process manager
+            // issues commands back to this framework, so it is given a weak reference to it.
+            let cqrs = Arc::new_cyclic(|cqrs: &Weak<OutboxCqrs<BankAccount>>| {
+                let transfer_process_manager = TransferProcessManager::new(cqrs.clone(), transfer_cqrs);
+                let queries: Vec<Box<dyn Query<BankAccount>>> = vec![
+                    Box::new(simple_query),
+                    Box::new(account_events),
+                    Box::new(account_query),
+                    Box::new(account_transactions_query),
+                    Box::new(general_ledger_query),
+                    Box::new(account_access_query),
+                    Box::new(atm_withdrawals),
+                    Box::new(webhook_query),
+                    Box::new(transfer_process_manager),
+                ];
+                CqrsFramework::new(event_store, queries, services)
+            });
+            (cqrs, account_view_repo)
+        }
+
+        /// The bank account event store. Snapshotting bounds how many events are replayed for each
+        /// command, and upcasters allow events stored in an older shape to still be replayed.
+        /// Committed events are also written to the outbox, for the outbox relay to publish.
+        pub fn get_bank_account_event_store(pool: Pool<MySql>) -> BankAccountEventStore {
+            let snapshot_configuration = SnapshotConfiguration::from_env()
+                .unwrap_or_else(|e| panic!("failed to read {}: {}", SNAPSHOT_SIZE_ENV_VAR, e));
+            snapshot_configuration
+                .event_store(OutboxEventRepository::new(pool))
+                .with_upcasters(bank_account_upcasters())
+        }
+
+        /// The account views, e.g. for the tasks that run instead of the server.
+        pub fn get_account_view_repository(pool: Pool<MySql>) -> MysqlViewRepository<BankAccountView, BankAccount> {
+            MysqlViewRepository::new(ACCOUNT_QUERY_TABLE, pool)
+        }
+
+        /// The account views, so that they can be rebuilt from the event store.
+        pub fn get_account_projection(pool: Pool<MySql>) -> ViewProjection<BankAccount, BankAccountView> {
+            ViewProjection::new(ACCOUNT_QUERY_TABLE, pool, bank_account_upcasters())
+        }
+
+        /// The transaction history of every account, so that it can be rebuilt from the event store.
+        pub fn get_account_transactions_projection(pool: Pool<MySql>) -> AccountTransactionsQuery {
+            AccountTransactionsQuery::new(pool)
+        }
+
+        /// The postings of the general ledger, so that they can be rebuilt from the event store.
+        pub fn get_general_ledger_projection(pool: Pool<MySql>) -> GeneralLedgerQuery {
+            GeneralLedgerQuery::new(pool)
+        }
+
+        /// Who may access each account, so that it can be rebuilt from the event store.
+        pub fn get_account_access_projection(pool: Pool<MySql>) -> AccountAccessQuery {
+            AccountAccessQuery::new(pool)
         }
     } else {
         compile_error!("Must specify either mysql or postgres feature");
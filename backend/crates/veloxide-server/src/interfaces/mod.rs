@@ -9,6 +9,12 @@ use utoipa::{ToResponse, ToSchema};
 
 pub mod bank_account;
 pub mod hello;
+pub mod scheduled_payment;
+pub mod transfer;
+pub mod webhooks;
 
 // Re-exports
 pub use bank_account::*;
+pub use scheduled_payment::*;
+pub use transfer::*;
+pub use webhooks::*;
@@ -0,0 +1,56 @@
+use super::*;
+use cqrs_es::persist::PersistedEventStore;
+use cqrs_es::{CqrsFramework, EventEnvelope, Query, View};
+
+use std::sync::Arc;
+
+use cfg_if::cfg_if;
+
+pub mod scheduled_payment_graphql;
+pub mod scheduled_payment_views;
+
+// Re-exports
+
+pub use scheduled_payment_graphql::*;
+pub use scheduled_payment_views::*;
+
+cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use postgres_es::{PostgresCqrs, PostgresEventRepository};
+        use sqlx::{Pool, Postgres};
+        pub fn get_scheduled_payment_cqrs_framework(
+            pool: Pool<Postgres>,
+        ) -> Arc<PostgresCqrs<ScheduledPayment>> {
+            // A query that keeps the current state of every payment, and when it is next due.
+            let queries: Vec<Box<dyn Query<ScheduledPayment>>> =
+                vec![Box::new(ScheduledPaymentQuery::new(pool.clone()))];
+            let event_store = PersistedEventStore::new_event_store(PostgresEventRepository::new(pool));
+            Arc::new(CqrsFramework::new(event_store, queries, ()))
+        }
+
+        /// The scheduled payments, so that they can be rebuilt from the event store.
+        pub fn get_scheduled_payment_projection(pool: Pool<Postgres>) -> ScheduledPaymentQuery {
+            ScheduledPaymentQuery::new(pool)
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use sqlx::{Pool, MySql};
+        use mysql_es::{MysqlCqrs, MysqlEventRepository};
+
+        pub fn get_scheduled_payment_cqrs_framework(
+            pool: Pool<MySql>,
+        ) -> Arc<MysqlCqrs<ScheduledPayment>> {
+            // A query that keeps the current state of every payment, and when it is next due.
+            let queries: Vec<Box<dyn Query<ScheduledPayment>>> =
+                vec![Box::new(ScheduledPaymentQuery::new(pool.clone()))];
+            let event_store = PersistedEventStore::new_event_store(MysqlEventRepository::new(pool));
+            Arc::new(CqrsFramework::new(event_store, queries, ()))
+        }
+
+        /// The scheduled payments, so that they can be rebuilt from the event store.
+        pub fn get_scheduled_payment_projection(pool: Pool<MySql>) -> ScheduledPaymentQuery {
+            ScheduledPaymentQuery::new(pool)
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
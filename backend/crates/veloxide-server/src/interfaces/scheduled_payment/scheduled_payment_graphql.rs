@@ -0,0 +1,93 @@
+use super::*;
+
+use async_graphql::{Context, Object};
+
+use crate::application::{Actor, ScheduledPaymentApplicationService};
+
+#[derive(Default)]
+pub struct ScheduledPaymentGraphQlQuery {}
+
+#[derive(Default)]
+pub struct ScheduledPaymentGraphQlMutation {}
+
+#[Object]
+impl ScheduledPaymentGraphQlQuery {
+    #[instrument(skip(self, ctx))]
+    /// Get a scheduled payment by its ID
+    async fn scheduled_payment<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: String,
+    ) -> async_graphql::Result<ScheduledPaymentView> {
+        let scheduled_payment_service =
+            ctx.data::<Arc<dyn ScheduledPaymentApplicationService>>()?;
+        scheduled_payment_service
+            .get_scheduled_payment(ctx.data::<Actor>()?, id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))
+    }
+
+    #[instrument(skip(self, ctx))]
+    /// List the payments scheduled on a bank account
+    async fn scheduled_payments<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        account_id: String,
+    ) -> async_graphql::Result<Vec<ScheduledPaymentView>> {
+        let scheduled_payment_service =
+            ctx.data::<Arc<dyn ScheduledPaymentApplicationService>>()?;
+        scheduled_payment_service
+            .list_scheduled_payments(ctx.data::<Actor>()?, account_id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))
+    }
+}
+
+#[Object]
+impl ScheduledPaymentGraphQlMutation {
+    #[instrument(skip(self, ctx))]
+    /// Schedule a deposit or withdrawal to run once or on a recurring schedule
+    async fn create_scheduled_payment<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        command: CreateScheduledPaymentCommandData,
+    ) -> async_graphql::Result<ScheduledPaymentView> {
+        let scheduled_payment_service =
+            ctx.data::<Arc<dyn ScheduledPaymentApplicationService>>()?;
+        scheduled_payment_service
+            .create_scheduled_payment(ctx.data::<Actor>()?, command)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))
+    }
+
+    #[instrument(skip(self, ctx))]
+    /// Change the amount and schedule of a scheduled payment
+    async fn update_scheduled_payment<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: String,
+        command: UpdateScheduledPaymentCommandData,
+    ) -> async_graphql::Result<ScheduledPaymentView> {
+        let scheduled_payment_service =
+            ctx.data::<Arc<dyn ScheduledPaymentApplicationService>>()?;
+        scheduled_payment_service
+            .update_scheduled_payment(ctx.data::<Actor>()?, id, command)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))
+    }
+
+    #[instrument(skip(self, ctx))]
+    /// Stop a scheduled payment from running again
+    async fn cancel_scheduled_payment<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: String,
+    ) -> async_graphql::Result<ScheduledPaymentView> {
+        let scheduled_payment_service =
+            ctx.data::<Arc<dyn ScheduledPaymentApplicationService>>()?;
+        scheduled_payment_service
+            .cancel_scheduled_payment(ctx.data::<Actor>()?, id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))
+    }
+}
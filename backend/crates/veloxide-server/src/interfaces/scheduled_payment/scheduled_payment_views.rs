@@ -0,0 +1,580 @@
+#[cfg(feature = "graphql")]
+use async_graphql::SimpleObject;
+
+#[cfg(feature = "frontend")]
+use ts_rs::TS;
+
+use super::*;
+use chrono::{DateTime, NaiveDateTime, Utc};
+
+/// The table the `ScheduledPaymentView`s are stored in, along with when each is next due.
+pub const SCHEDULED_PAYMENTS_TABLE: &str = "scheduled_payments";
+
+#[derive(thiserror::Error, Debug)]
+pub enum ScheduledPaymentQueryError {
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+// The view of a scheduled payment, showing its schedule and how its last run went.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+#[cfg_attr(feature = "graphql", derive(SimpleObject))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct ScheduledPaymentView {
+    pub payment_id: String,
+    pub account_id: String,
+    /// Id of the user who created the payment, who it runs on behalf of
+    pub owner_id: String,
+    pub kind: ScheduledPaymentKind,
+    pub amount: Money,
+    pub frequency: PaymentFrequency,
+    /// RFC 3339 timestamp of the first run
+    pub starts_at: String,
+    /// RFC 3339 timestamp after which the payment no longer runs
+    pub ends_at: Option<String>,
+    pub status: ScheduledPaymentStatus,
+    /// RFC 3339 timestamp of the next run, absent once the payment is cancelled or completed
+    pub next_run_at: Option<String>,
+    /// RFC 3339 timestamp of the next attempt at the next run, later than `next_run_at` while
+    /// a failed attempt waits to be retried
+    pub next_attempt_at: Option<String>,
+    /// Failed attempts at the next run
+    pub attempts: u32,
+    /// RFC 3339 timestamp of the last successful run
+    pub last_executed_at: Option<String>,
+    /// Why the last failed attempt failed
+    pub last_failure: Option<String>,
+}
+
+impl ScheduledPaymentView {
+    /// When the run that is due was scheduled for, which identifies it across its attempts.
+    pub fn run_at(&self) -> Option<DateTime<Utc>> {
+        self.next_run_at
+            .as_deref()
+            .and_then(parse_schedule_timestamp)
+    }
+
+    fn next_attempt_column(&self) -> Option<NaiveDateTime> {
+        self.next_attempt_at
+            .as_deref()
+            .and_then(parse_schedule_timestamp)
+            .map(|next_attempt_at| next_attempt_at.naive_utc())
+    }
+
+    fn schedule_next_run(&mut self, next_run_at: Option<DateTime<Utc>>) {
+        self.next_run_at = next_run_at.map(format_schedule_timestamp);
+        self.next_attempt_at = self.next_run_at.clone();
+        self.attempts = 0;
+        if next_run_at.is_none() && self.status == ScheduledPaymentStatus::Active {
+            self.status = ScheduledPaymentStatus::Completed;
+        }
+    }
+}
+
+impl View<ScheduledPayment> for ScheduledPaymentView {
+    fn update(&mut self, event: &EventEnvelope<ScheduledPayment>) {
+        match &event.payload {
+            ScheduledPaymentEvent::ScheduledPaymentCreated {
+                account_id,
+                owner_id,
+                kind,
+                amount,
+                frequency,
+                starts_at,
+                ends_at,
+            } => {
+                self.payment_id = event.aggregate_id.clone();
+                self.account_id = account_id.clone();
+                self.owner_id = owner_id.clone();
+                self.kind = *kind;
+                self.amount = amount.clone();
+                self.frequency = *frequency;
+                self.starts_at = format_schedule_timestamp(*starts_at);
+                self.ends_at = ends_at.map(format_schedule_timestamp);
+                self.status = ScheduledPaymentStatus::Active;
+                self.schedule_next_run(Some(*starts_at));
+            }
+            ScheduledPaymentEvent::ScheduledPaymentUpdated {
+                amount,
+                frequency,
+                starts_at,
+                ends_at,
+            } => {
+                self.amount = amount.clone();
+                self.frequency = *frequency;
+                self.starts_at = format_schedule_timestamp(*starts_at);
+                self.ends_at = ends_at.map(format_schedule_timestamp);
+                self.schedule_next_run(Some(*starts_at));
+            }
+            ScheduledPaymentEvent::ScheduledPaymentCancelled => {
+                self.status = ScheduledPaymentStatus::Cancelled;
+                self.schedule_next_run(None);
+            }
+            ScheduledPaymentEvent::PaymentExecuted {
+                executed_at,
+                next_run_at,
+                ..
+            } => {
+                self.last_executed_at = Some(format_schedule_timestamp(*executed_at));
+                self.schedule_next_run(*next_run_at);
+            }
+            ScheduledPaymentEvent::PaymentAttemptFailed {
+                reason, retry_at, ..
+            } => {
+                self.attempts += 1;
+                self.last_failure = Some(reason.clone());
+                self.next_attempt_at = Some(format_schedule_timestamp(*retry_at));
+            }
+            ScheduledPaymentEvent::PaymentFailed {
+                reason,
+                next_run_at,
+                ..
+            } => {
+                self.last_failure = Some(reason.clone());
+                self.schedule_next_run(*next_run_at);
+            }
+        }
+    }
+}
+
+// Events up to the version already projected are skipped, so that dispatching them again, e.g.
+// during a rebuild, is harmless. Returns `None` when there is nothing new to store.
+fn project(
+    mut view: ScheduledPaymentView,
+    version: usize,
+    events: &[EventEnvelope<ScheduledPayment>],
+) -> Option<(ScheduledPaymentView, usize)> {
+    let mut projected = version;
+    for event in events.iter().filter(|event| event.sequence > version) {
+        view.update(event);
+        projected = event.sequence;
+    }
+    (projected > version).then_some((view, projected))
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use async_trait::async_trait;
+        use cqrs_es::EventStore;
+        use sqlx::{PgConnection, Row};
+
+        use crate::infrastructure::projections::{
+            aggregate_ids, Projection, ProjectionError, ProjectionProgress, ProjectionRebuildReport,
+        };
+
+        /// Projects every scheduled payment into the `scheduled_payments` table, one row per
+        /// payment holding its view, so that payments can be listed by account and the ones due
+        /// found without replaying their events.
+        #[derive(Clone)]
+        pub struct ScheduledPaymentQuery {
+            pool: Pool<Postgres>,
+        }
+
+        impl ScheduledPaymentQuery {
+            pub fn new(pool: Pool<Postgres>) -> Self {
+                Self { pool }
+            }
+
+            pub async fn get(&self, payment_id: &str) -> Result<Option<ScheduledPaymentView>, ScheduledPaymentQueryError> {
+                let mut connection = self.pool.acquire().await?;
+                Ok(self.load(&mut connection, payment_id).await?.map(|(view, _)| view))
+            }
+
+            /// The payments scheduled on the account, oldest first.
+            pub async fn list(&self, account_id: &str) -> Result<Vec<ScheduledPaymentView>, ScheduledPaymentQueryError> {
+                let payloads: Vec<String> = sqlx::query_scalar(
+                    "SELECT payload FROM scheduled_payments WHERE account_id = $1 ORDER BY created_at, payment_id",
+                )
+                .bind(account_id)
+                .fetch_all(&self.pool)
+                .await?;
+                payloads.iter().map(|payload| Ok(serde_json::from_str(payload)?)).collect()
+            }
+
+            /// Up to `limit` active payments with an attempt due at `now`, the longest due first.
+            pub async fn due(&self, now: DateTime<Utc>, limit: usize) -> Result<Vec<ScheduledPaymentView>, ScheduledPaymentQueryError> {
+                let payloads: Vec<String> = sqlx::query_scalar(
+                    "SELECT payload FROM scheduled_payments WHERE status = $1 AND next_attempt_at <= $2 ORDER BY next_attempt_at LIMIT $3",
+                )
+                .bind(ScheduledPaymentStatus::Active.to_string())
+                .bind(now.naive_utc())
+                .bind(limit as i64)
+                .fetch_all(&self.pool)
+                .await?;
+                payloads.iter().map(|payload| Ok(serde_json::from_str(payload)?)).collect()
+            }
+
+            async fn load(&self, connection: &mut PgConnection, payment_id: &str) -> Result<Option<(ScheduledPaymentView, usize)>, ScheduledPaymentQueryError> {
+                let row = sqlx::query("SELECT payload, version FROM scheduled_payments WHERE payment_id = $1")
+                    .bind(payment_id)
+                    .fetch_optional(&mut *connection)
+                    .await?;
+                let Some(row) = row else {
+                    return Ok(None);
+                };
+                let payload: String = row.try_get("payload")?;
+                let version: i64 = row.try_get("version")?;
+                Ok(Some((serde_json::from_str(&payload)?, version as usize)))
+            }
+
+            async fn record(&self, connection: &mut PgConnection, payment_id: &str, events: &[EventEnvelope<ScheduledPayment>]) -> Result<(), ScheduledPaymentQueryError> {
+                let (view, version) = self.load(&mut *connection, payment_id).await?.unwrap_or_default();
+                let Some((view, version)) = project(view, version, events) else {
+                    return Ok(());
+                };
+                sqlx::query(
+                    "INSERT INTO scheduled_payments (payment_id, account_id, status, next_attempt_at, version, payload) VALUES ($1, $2, $3, $4, $5, $6) \
+                     ON CONFLICT (payment_id) DO UPDATE SET status = EXCLUDED.status, next_attempt_at = EXCLUDED.next_attempt_at, version = EXCLUDED.version, payload = EXCLUDED.payload",
+                )
+                .bind(payment_id)
+                .bind(&view.account_id)
+                .bind(view.status.to_string())
+                .bind(view.next_attempt_column())
+                .bind(version as i64)
+                .bind(serde_json::to_string(&view)?)
+                .execute(&mut *connection)
+                .await?;
+                Ok(())
+            }
+        }
+
+        #[async_trait]
+        impl Query<ScheduledPayment> for ScheduledPaymentQuery {
+            async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<ScheduledPayment>]) {
+                let recorded = match self.pool.acquire().await {
+                    Ok(mut connection) => self.record(&mut connection, aggregate_id, events).await,
+                    Err(err) => Err(err.into()),
+                };
+                if let Err(err) = recorded {
+                    tracing::error!("failed to record scheduled payment {}: {}", aggregate_id, err);
+                }
+            }
+        }
+
+        #[async_trait]
+        impl Projection for ScheduledPaymentQuery {
+            fn name(&self) -> &str {
+                SCHEDULED_PAYMENTS_TABLE
+            }
+
+            async fn rebuild(
+                &self,
+                aggregate_id: Option<&str>,
+                on_progress: &(dyn Fn(&ProjectionProgress) + Send + Sync),
+            ) -> Result<ProjectionRebuildReport, ProjectionError> {
+                let mut transaction = self.pool.begin().await?;
+                let aggregate_ids: Vec<String> = match aggregate_id {
+                    Some(aggregate_id) => {
+                        sqlx::query("DELETE FROM scheduled_payments WHERE payment_id = $1")
+                            .bind(aggregate_id)
+                            .execute(&mut *transaction)
+                            .await?;
+                        vec![aggregate_id.to_string()]
+                    }
+                    None => {
+                        sqlx::query("TRUNCATE TABLE scheduled_payments")
+                            .execute(&mut *transaction)
+                            .await?;
+                        aggregate_ids::<ScheduledPayment>(&self.pool).await?
+                    }
+                };
+
+                let event_store: PersistedEventStore<PostgresEventRepository, ScheduledPayment> =
+                    PersistedEventStore::new_event_store(PostgresEventRepository::new(self.pool.clone()));
+                let total = aggregate_ids.len();
+                let mut events_replayed = 0;
+                for (index, aggregate_id) in aggregate_ids.into_iter().enumerate() {
+                    let replay_error = |reason: String| ProjectionError::Replay {
+                        aggregate_id: aggregate_id.clone(),
+                        reason,
+                    };
+                    let events = event_store
+                        .load_events(&aggregate_id)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    self.record(&mut transaction, &aggregate_id, &events)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    events_replayed += events.len();
+                    on_progress(&ProjectionProgress {
+                        aggregate_id,
+                        replayed: index + 1,
+                        total,
+                    });
+                }
+                transaction.commit().await?;
+                Ok(ProjectionRebuildReport {
+                    projection: SCHEDULED_PAYMENTS_TABLE.to_string(),
+                    aggregates_replayed: total,
+                    events_replayed,
+                })
+            }
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use async_trait::async_trait;
+        use cqrs_es::EventStore;
+        use sqlx::{MySqlConnection, Row};
+
+        use crate::infrastructure::projections::{
+            aggregate_ids, Projection, ProjectionError, ProjectionProgress, ProjectionRebuildReport,
+        };
+
+        /// Projects every scheduled payment into the `scheduled_payments` table, one row per
+        /// payment holding its view, so that payments can be listed by account and the ones due
+        /// found without replaying their events.
+        #[derive(Clone)]
+        pub struct ScheduledPaymentQuery {
+            pool: Pool<MySql>,
+        }
+
+        impl ScheduledPaymentQuery {
+            pub fn new(pool: Pool<MySql>) -> Self {
+                Self { pool }
+            }
+
+            pub async fn get(&self, payment_id: &str) -> Result<Option<ScheduledPaymentView>, ScheduledPaymentQueryError> {
+                let mut connection = self.pool.acquire().await?;
+                Ok(self.load(&mut connection, payment_id).await?.map(|(view, _)| view))
+            }
+
+            /// The payments scheduled on the account, oldest first.
+            pub async fn list(&self, account_id: &str) -> Result<Vec<ScheduledPaymentView>, ScheduledPaymentQueryError> {
+                let payloads: Vec<String> = sqlx::query_scalar(
+                    "SELECT payload FROM scheduled_payments WHERE account_id = ? ORDER BY created_at, payment_id",
+                )
+                .bind(account_id)
+                .fetch_all(&self.pool)
+                .await?;
+                payloads.iter().map(|payload| Ok(serde_json::from_str(payload)?)).collect()
+            }
+
+            /// Up to `limit` active payments with an attempt due at `now`, the longest due first.
+            pub async fn due(&self, now: DateTime<Utc>, limit: usize) -> Result<Vec<ScheduledPaymentView>, ScheduledPaymentQueryError> {
+                let payloads: Vec<String> = sqlx::query_scalar(
+                    "SELECT payload FROM scheduled_payments WHERE status = ? AND next_attempt_at <= ? ORDER BY next_attempt_at LIMIT ?",
+                )
+                .bind(ScheduledPaymentStatus::Active.to_string())
+                .bind(now.naive_utc())
+                .bind(limit as i64)
+                .fetch_all(&self.pool)
+                .await?;
+                payloads.iter().map(|payload| Ok(serde_json::from_str(payload)?)).collect()
+            }
+
+            async fn load(&self, connection: &mut MySqlConnection, payment_id: &str) -> Result<Option<(ScheduledPaymentView, usize)>, ScheduledPaymentQueryError> {
+                let row = sqlx::query("SELECT payload, version FROM scheduled_payments WHERE payment_id = ?")
+                    .bind(payment_id)
+                    .fetch_optional(&mut *connection)
+                    .await?;
+                let Some(row) = row else {
+                    return Ok(None);
+                };
+                let payload: String = row.try_get("payload")?;
+                let version: i64 = row.try_get("version")?;
+                Ok(Some((serde_json::from_str(&payload)?, version as usize)))
+            }
+
+            async fn record(&self, connection: &mut MySqlConnection, payment_id: &str, events: &[EventEnvelope<ScheduledPayment>]) -> Result<(), ScheduledPaymentQueryError> {
+                let (view, version) = self.load(&mut *connection, payment_id).await?.unwrap_or_default();
+                let Some((view, version)) = project(view, version, events) else {
+                    return Ok(());
+                };
+                sqlx::query(
+                    "INSERT INTO scheduled_payments (payment_id, account_id, status, next_attempt_at, version, payload) VALUES (?, ?, ?, ?, ?, ?) \
+                     ON DUPLICATE KEY UPDATE status = VALUES(status), next_attempt_at = VALUES(next_attempt_at), version = VALUES(version), payload = VALUES(payload)",
+                )
+                .bind(payment_id)
+                .bind(&view.account_id)
+                .bind(view.status.to_string())
+                .bind(view.next_attempt_column())
+                .bind(version as i64)
+                .bind(serde_json::to_string(&view)?)
+                .execute(&mut *connection)
+                .await?;
+                Ok(())
+            }
+        }
+
+        #[async_trait]
+        impl Query<ScheduledPayment> for ScheduledPaymentQuery {
+            async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<ScheduledPayment>]) {
+                let recorded = match self.pool.acquire().await {
+                    Ok(mut connection) => self.record(&mut connection, aggregate_id, events).await,
+                    Err(err) => Err(err.into()),
+                };
+                if let Err(err) = recorded {
+                    tracing::error!("failed to record scheduled payment {}: {}", aggregate_id, err);
+                }
+            }
+        }
+
+        #[async_trait]
+        impl Projection for ScheduledPaymentQuery {
+            fn name(&self) -> &str {
+                SCHEDULED_PAYMENTS_TABLE
+            }
+
+            async fn rebuild(
+                &self,
+                aggregate_id: Option<&str>,
+                on_progress: &(dyn Fn(&ProjectionProgress) + Send + Sync),
+            ) -> Result<ProjectionRebuildReport, ProjectionError> {
+                let mut transaction = self.pool.begin().await?;
+                let aggregate_ids: Vec<String> = match aggregate_id {
+                    Some(aggregate_id) => {
+                        sqlx::query("DELETE FROM scheduled_payments WHERE payment_id = ?")
+                            .bind(aggregate_id)
+                            .execute(&mut *transaction)
+                            .await?;
+                        vec![aggregate_id.to_string()]
+                    }
+                    None => {
+                        sqlx::query("DELETE FROM scheduled_payments")
+                            .execute(&mut *transaction)
+                            .await?;
+                        aggregate_ids::<ScheduledPayment>(&self.pool).await?
+                    }
+                };
+
+                let event_store: PersistedEventStore<MysqlEventRepository, ScheduledPayment> =
+                    PersistedEventStore::new_event_store(MysqlEventRepository::new(self.pool.clone()));
+                let total = aggregate_ids.len();
+                let mut events_replayed = 0;
+                for (index, aggregate_id) in aggregate_ids.into_iter().enumerate() {
+                    let replay_error = |reason: String| ProjectionError::Replay {
+                        aggregate_id: aggregate_id.clone(),
+                        reason,
+                    };
+                    let events = event_store
+                        .load_events(&aggregate_id)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    self.record(&mut transaction, &aggregate_id, &events)
+                        .await
+                        .map_err(|err| replay_error(err.to_string()))?;
+                    events_replayed += events.len();
+                    on_progress(&ProjectionProgress {
+                        aggregate_id,
+                        replayed: index + 1,
+                        total,
+                    });
+                }
+                transaction.commit().await?;
+                Ok(ProjectionRebuildReport {
+                    projection: SCHEDULED_PAYMENTS_TABLE.to_string(),
+                    aggregates_replayed: total,
+                    events_replayed,
+                })
+            }
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+
+    fn envelope(
+        sequence: usize,
+        payload: ScheduledPaymentEvent,
+    ) -> EventEnvelope<ScheduledPayment> {
+        EventEnvelope {
+            aggregate_id: "7f1c".to_string(),
+            sequence,
+            payload,
+            metadata: HashMap::new(),
+        }
+    }
+
+    fn at(timestamp: &str) -> DateTime<Utc> {
+        parse_schedule_timestamp(timestamp).unwrap()
+    }
+
+    fn created() -> EventEnvelope<ScheduledPayment> {
+        envelope(
+            1,
+            ScheduledPaymentEvent::ScheduledPaymentCreated {
+                account_id: "1234".to_string(),
+                owner_id: "1111".to_string(),
+                kind: ScheduledPaymentKind::Deposit,
+                amount: Money::new(5000, "USD"),
+                frequency: PaymentFrequency::Monthly,
+                starts_at: at("2023-12-01T09:00:00Z"),
+                ends_at: None,
+            },
+        )
+    }
+
+    #[test]
+    fn failed_attempts_delay_the_next_attempt_but_not_the_run() {
+        let (view, version) = project(
+            ScheduledPaymentView::default(),
+            0,
+            &[
+                created(),
+                envelope(
+                    2,
+                    ScheduledPaymentEvent::PaymentAttemptFailed {
+                        run_at: at("2023-12-01T09:00:00Z"),
+                        failed_at: at("2023-12-01T09:00:01Z"),
+                        reason: "database unavailable".to_string(),
+                        retry_at: at("2023-12-01T09:01:01Z"),
+                    },
+                ),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(version, 2);
+        assert_eq!(view.payment_id, "7f1c");
+        assert_eq!(view.next_run_at.as_deref(), Some("2023-12-01T09:00:00Z"));
+        assert_eq!(
+            view.next_attempt_at.as_deref(),
+            Some("2023-12-01T09:01:01Z")
+        );
+        assert_eq!(view.attempts, 1);
+        assert_eq!(view.run_at(), Some(at("2023-12-01T09:00:00Z")));
+    }
+
+    #[test]
+    fn the_last_run_completes_the_payment() {
+        let (view, _) = project(
+            ScheduledPaymentView::default(),
+            0,
+            &[
+                created(),
+                envelope(
+                    2,
+                    ScheduledPaymentEvent::PaymentExecuted {
+                        run_at: at("2023-12-01T09:00:00Z"),
+                        executed_at: at("2023-12-01T09:00:01Z"),
+                        next_run_at: None,
+                    },
+                ),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(view.status, ScheduledPaymentStatus::Completed);
+        assert_eq!(view.next_attempt_at, None);
+        assert_eq!(
+            view.last_executed_at.as_deref(),
+            Some("2023-12-01T09:00:01Z")
+        );
+    }
+
+    #[test]
+    fn events_already_projected_are_skipped() {
+        let (view, version) = project(ScheduledPaymentView::default(), 0, &[created()]).unwrap();
+        assert_eq!(project(view, version, &[created()]), None);
+    }
+}
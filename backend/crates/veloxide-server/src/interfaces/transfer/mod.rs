@@ -0,0 +1,76 @@
+use super::*;
+use cqrs_es::persist::{GenericQuery, PersistedEventStore};
+use cqrs_es::{CqrsFramework, EventEnvelope, Query, View};
+
+use std::sync::Arc;
+
+use crate::infrastructure::projections::ViewProjection;
+
+use cfg_if::cfg_if;
+
+pub mod transfer_graphql;
+pub mod transfer_views;
+
+// Re-exports
+
+pub use transfer_graphql::*;
+pub use transfer_views::*;
+
+cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use postgres_es::{PostgresCqrs, PostgresEventRepository, PostgresViewRepository};
+        use sqlx::{Pool, Postgres};
+        pub fn get_transfer_cqrs_framework(
+            pool: Pool<Postgres>,
+        ) -> (
+            Arc<PostgresCqrs<Transfer>>,
+            Arc<PostgresViewRepository<TransferView, Transfer>>,
+        ) {
+            // A query that stores the current state of an individual transfer.
+            let transfer_view_repo = Arc::new(PostgresViewRepository::new(TRANSFER_QUERY_TABLE, pool.clone()));
+            let mut transfer_query = TransferQuery::new(transfer_view_repo.clone());
+            transfer_query.use_error_handler(Box::new(|e| println!("{}", e)));
+
+            let queries: Vec<Box<dyn Query<Transfer>>> = vec![Box::new(transfer_query)];
+            let event_store = PersistedEventStore::new_event_store(PostgresEventRepository::new(pool));
+            (
+                Arc::new(CqrsFramework::new(event_store, queries, ())),
+                transfer_view_repo,
+            )
+        }
+
+        /// The transfer views, so that they can be rebuilt from the event store.
+        pub fn get_transfer_projection(pool: Pool<Postgres>) -> ViewProjection<Transfer, TransferView> {
+            ViewProjection::new(TRANSFER_QUERY_TABLE, pool, Vec::new())
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use sqlx::{Pool, MySql};
+        use mysql_es::{MysqlCqrs, MysqlEventRepository, MysqlViewRepository};
+
+        pub fn get_transfer_cqrs_framework(
+            pool: Pool<MySql>,
+        ) -> (
+            Arc<MysqlCqrs<Transfer>>,
+            Arc<MysqlViewRepository<TransferView, Transfer>>,
+        ) {
+            // A query that stores the current state of an individual transfer.
+            let transfer_view_repo = Arc::new(MysqlViewRepository::new(TRANSFER_QUERY_TABLE, pool.clone()));
+            let mut transfer_query = TransferQuery::new(transfer_view_repo.clone());
+            transfer_query.use_error_handler(Box::new(|e| println!("{}", e)));
+
+            let queries: Vec<Box<dyn Query<Transfer>>> = vec![Box::new(transfer_query)];
+            let event_store = PersistedEventStore::new_event_store(MysqlEventRepository::new(pool));
+            (
+                Arc::new(CqrsFramework::new(event_store, queries, ())),
+                transfer_view_repo,
+            )
+        }
+
+        /// The transfer views, so that they can be rebuilt from the event store.
+        pub fn get_transfer_projection(pool: Pool<MySql>) -> ViewProjection<Transfer, TransferView> {
+            ViewProjection::new(TRANSFER_QUERY_TABLE, pool, Vec::new())
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
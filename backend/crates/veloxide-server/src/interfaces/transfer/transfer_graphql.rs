@@ -0,0 +1,47 @@
+use super::*;
+
+use async_graphql::{Context, Object};
+
+use crate::application::{Actor, TransferApplicationService};
+
+#[derive(Default)]
+pub struct TransferGraphQlQuery {}
+
+#[derive(Default)]
+pub struct TransferGraphQlMutation {}
+
+#[Object]
+impl TransferGraphQlQuery {
+    #[instrument(skip(self, ctx))]
+    /// Get a transfer by its ID
+    async fn transfer_query<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        id: String,
+    ) -> async_graphql::Result<TransferView> {
+        let transfer_service = ctx.data::<Arc<dyn TransferApplicationService>>()?;
+        transfer_service
+            .get_transfer(ctx.data::<Actor>()?, id)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))
+    }
+}
+
+#[Object]
+impl TransferGraphQlMutation {
+    #[instrument(skip(self, ctx))]
+    /// Transfer money between two bank accounts
+    async fn transfer_money<'ctx>(
+        &self,
+        ctx: &Context<'ctx>,
+        command: StartTransferCommandData,
+        #[graphql(desc = "Retrying with the same key returns the transfer started the first time")]
+        idempotency_key: Option<String>,
+    ) -> async_graphql::Result<TransferView> {
+        let transfer_service = ctx.data::<Arc<dyn TransferApplicationService>>()?;
+        transfer_service
+            .transfer_money(ctx.data::<Actor>()?, command, idempotency_key)
+            .await
+            .map_err(|err| async_graphql::Error::new(err.to_string()))
+    }
+}
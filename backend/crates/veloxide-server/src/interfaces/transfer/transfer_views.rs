@@ -0,0 +1,78 @@
+#[cfg(feature = "graphql")]
+use async_graphql::SimpleObject;
+
+#[cfg(feature = "frontend")]
+use ts_rs::TS;
+
+use super::*;
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        pub type TransferQuery = GenericQuery<
+            PostgresViewRepository<TransferView, Transfer>,
+            TransferView,
+            Transfer,
+        >;
+    } else if #[cfg(feature = "mysql")] {
+        pub type TransferQuery = GenericQuery<
+            MysqlViewRepository<TransferView, Transfer>,
+            TransferView,
+            Transfer,
+        >;
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+/// The table the `TransferView`s are stored in.
+pub const TRANSFER_QUERY_TABLE: &str = "transfer_query";
+
+// The view of a transfer, showing how far through the saga it has progressed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+#[cfg_attr(feature = "graphql", derive(SimpleObject))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct TransferView {
+    pub transfer_id: String,
+    pub source_account_id: String,
+    pub destination_account_id: String,
+    pub amount: Money,
+    pub status: TransferStatus,
+    pub failure_reason: Option<String>,
+}
+
+impl View<Transfer> for TransferView {
+    fn update(&mut self, event: &EventEnvelope<Transfer>) {
+        match &event.payload {
+            TransferEvent::TransferStarted {
+                source_account_id,
+                destination_account_id,
+                amount,
+            } => {
+                self.transfer_id = event.aggregate_id.clone();
+                self.source_account_id = source_account_id.clone();
+                self.destination_account_id = destination_account_id.clone();
+                self.amount = amount.clone();
+                self.status = TransferStatus::Started;
+            }
+            TransferEvent::SourceAccountDebited => {
+                self.status = TransferStatus::SourceDebited;
+            }
+            TransferEvent::SourceAccountDebitFailed { reason } => {
+                self.status = TransferStatus::Failed;
+                self.failure_reason = Some(reason.clone());
+            }
+            TransferEvent::DestinationAccountCredited => {
+                self.status = TransferStatus::Completed;
+            }
+            TransferEvent::DestinationAccountCreditFailed { reason } => {
+                self.status = TransferStatus::Compensating;
+                self.failure_reason = Some(reason.clone());
+            }
+            TransferEvent::TransferCompensated => {
+                self.status = TransferStatus::Compensated;
+            }
+        }
+    }
+}
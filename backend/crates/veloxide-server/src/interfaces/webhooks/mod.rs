@@ -0,0 +1,10 @@
+use super::*;
+use cqrs_es::{DomainEvent, EventEnvelope, Query};
+
+pub mod webhook_query;
+pub mod webhook_subscriptions;
+
+// Re-exports
+
+pub use webhook_query::*;
+pub use webhook_subscriptions::*;
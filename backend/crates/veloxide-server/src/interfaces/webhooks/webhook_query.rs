@@ -0,0 +1,101 @@
+use super::*;
+use async_trait::async_trait;
+use serde_json::json;
+
+/// Queues the events committed on bank accounts for delivery to the webhooks subscribed to
+/// them. The queued deliveries are made by the `WebhookSender`, so a slow or failing webhook
+/// never holds up a command.
+#[derive(Clone)]
+pub struct WebhookQuery {
+    store: WebhookStore,
+}
+
+impl WebhookQuery {
+    pub fn new(store: WebhookStore) -> Self {
+        Self { store }
+    }
+
+    async fn enqueue(
+        &self,
+        account_id: &str,
+        events: &[EventEnvelope<BankAccount>],
+    ) -> Result<(), WebhookError> {
+        let subscribers = self.store.subscribers(account_id).await?;
+        for event in events {
+            let event_type = event.payload.event_type();
+            for subscription in subscribers
+                .iter()
+                .filter(|subscription| subscription.accepts(&event_type))
+            {
+                self.store
+                    .enqueue(
+                        &subscription.subscription_id,
+                        account_id,
+                        event.sequence,
+                        &event_type,
+                        &webhook_payload(&subscription.subscription_id, event),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The JSON body a webhook is called back with.
+pub fn webhook_payload(subscription_id: &str, event: &EventEnvelope<BankAccount>) -> String {
+    json!({
+        "subscription_id": subscription_id,
+        "account_id": event.aggregate_id,
+        "sequence": event.sequence,
+        "event_type": event.payload.event_type(),
+        "event_version": event.payload.event_version(),
+        "event": event.payload,
+    })
+    .to_string()
+}
+
+#[async_trait]
+impl Query<BankAccount> for WebhookQuery {
+    async fn dispatch(&self, aggregate_id: &str, events: &[EventEnvelope<BankAccount>]) {
+        if let Err(err) = self.enqueue(aggregate_id, events).await {
+            tracing::error!(
+                "failed to queue webhook deliveries for account {}: {}",
+                aggregate_id,
+                err
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::HashMap;
+
+    #[test]
+    fn payloads_carry_the_event_and_where_it_belongs() {
+        let event = EventEnvelope::<BankAccount> {
+            aggregate_id: "1234".to_string(),
+            sequence: 2,
+            payload: BankAccountEvent::AccountFrozen {
+                reason: "suspected fraud".to_string(),
+            },
+            metadata: HashMap::new(),
+        };
+        let payload: serde_json::Value =
+            serde_json::from_str(&webhook_payload("0b6a3c52", &event)).unwrap();
+        assert_eq!(
+            payload,
+            json!({
+                "subscription_id": "0b6a3c52",
+                "account_id": "1234",
+                "sequence": 2,
+                "event_type": "AccountFrozen",
+                "event_version": "1.0",
+                "event": { "AccountFrozen": { "reason": "suspected fraud" } },
+            })
+        );
+    }
+}
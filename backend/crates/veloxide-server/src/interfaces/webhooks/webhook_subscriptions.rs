@@ -0,0 +1,770 @@
+#[cfg(feature = "frontend")]
+use ts_rs::TS;
+
+use super::*;
+use chrono::{NaiveDateTime, Utc};
+use std::fmt::Display;
+use std::str::FromStr;
+
+#[derive(thiserror::Error, Debug)]
+pub enum WebhookError {
+    #[error("invalid webhook delivery status: {0}")]
+    InvalidStatus(String),
+
+    #[error(transparent)]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// A URL called back with the events committed on a bank account, or on every account its
+/// owner owns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct WebhookSubscription {
+    pub subscription_id: String,
+    /// Id of the user who registered the webhook
+    pub owner_id: String,
+    /// Only call back with the events of this account, or of every account the owner owns when
+    /// absent
+    pub account_id: Option<String>,
+    pub url: String,
+    /// Only call back with events of these types, or with every event when empty
+    pub event_types: Vec<String>,
+    /// Key the payloads are signed with, see the `X-Veloxide-Signature` header
+    pub secret: String,
+    /// RFC 3339 timestamp
+    pub created_at: String,
+}
+
+impl WebhookSubscription {
+    pub fn accepts(&self, event_type: &str) -> bool {
+        self.event_types.is_empty()
+            || self
+                .event_types
+                .iter()
+                .any(|accepted| accepted == event_type)
+    }
+}
+
+/// What to call back and with which events, to register or change a webhook.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct WebhookSubscriptionRequest {
+    pub url: String,
+    /// Only call back with the events of this account, or of every account the user owns when
+    /// absent
+    #[serde(default)]
+    pub account_id: Option<String>,
+    /// Event types such as `CustomerDepositedMoney`, every event when empty
+    #[serde(default)]
+    pub event_types: Vec<String>,
+}
+
+/// Where a delivery stands. Failed deliveries are retried with exponential backoff until they
+/// run out of attempts, after which they are dead-lettered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToSchema))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    DeadLettered,
+}
+
+impl Display for WebhookDeliveryStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> core::result::Result<(), std::fmt::Error> {
+        let name = match self {
+            WebhookDeliveryStatus::Pending => "Pending",
+            WebhookDeliveryStatus::Delivered => "Delivered",
+            WebhookDeliveryStatus::DeadLettered => "DeadLettered",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for WebhookDeliveryStatus {
+    type Err = WebhookError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "Pending" => Ok(WebhookDeliveryStatus::Pending),
+            "Delivered" => Ok(WebhookDeliveryStatus::Delivered),
+            "DeadLettered" => Ok(WebhookDeliveryStatus::DeadLettered),
+            _ => Err(WebhookError::InvalidStatus(name.to_string())),
+        }
+    }
+}
+
+/// An event delivered, or still to be delivered, to a webhook.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(ToResponse, ToSchema))]
+#[cfg_attr(feature = "frontend", derive(TS))]
+#[ts(export, export_to = "frontend/src/bindings/")]
+pub struct WebhookDelivery {
+    pub delivery_id: String,
+    pub subscription_id: String,
+    pub account_id: String,
+    pub sequence: usize,
+    pub event_type: String,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: u32,
+    /// RFC 3339 timestamp of the next attempt while pending
+    pub next_attempt_at: String,
+    /// HTTP status the webhook responded to the last attempt with
+    pub last_response_status: Option<u16>,
+    pub last_error: Option<String>,
+    /// RFC 3339 timestamp
+    pub created_at: String,
+}
+
+/// A pending delivery whose next attempt is due, with what it takes to make it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DueWebhookDelivery {
+    pub delivery_id: String,
+    pub subscription_id: String,
+    pub event_type: String,
+    pub url: String,
+    pub secret: String,
+    pub payload: String,
+    pub attempts: u32,
+}
+
+// Event types are stored comma separated, none meaning every event.
+fn join_event_types(event_types: &[String]) -> String {
+    event_types.join(",")
+}
+
+fn split_event_types(event_types: &str) -> Vec<String> {
+    event_types
+        .split(',')
+        .filter(|event_type| !event_type.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn rfc3339(timestamp: NaiveDateTime) -> String {
+    timestamp.and_utc().to_rfc3339()
+}
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "postgres")] {
+        use sqlx::postgres::PgRow;
+        use sqlx::{Pool, Postgres, Row};
+
+        fn subscription_from_row(row: &PgRow) -> Result<WebhookSubscription, WebhookError> {
+            let event_types: String = row.try_get("event_types")?;
+            let created_at: NaiveDateTime = row.try_get("created_at")?;
+            Ok(WebhookSubscription {
+                subscription_id: row.try_get("subscription_id")?,
+                owner_id: row.try_get("owner_id")?,
+                account_id: row.try_get("account_id")?,
+                url: row.try_get("url")?,
+                event_types: split_event_types(&event_types),
+                secret: row.try_get("secret")?,
+                created_at: rfc3339(created_at),
+            })
+        }
+
+        fn delivery_from_row(row: &PgRow) -> Result<WebhookDelivery, WebhookError> {
+            let sequence: i64 = row.try_get("sequence")?;
+            let status: String = row.try_get("status")?;
+            let attempts: i32 = row.try_get("attempts")?;
+            let next_attempt_at: NaiveDateTime = row.try_get("next_attempt_at")?;
+            let last_response_status: Option<i32> = row.try_get("last_response_status")?;
+            let created_at: NaiveDateTime = row.try_get("created_at")?;
+            Ok(WebhookDelivery {
+                delivery_id: row.try_get("delivery_id")?,
+                subscription_id: row.try_get("subscription_id")?,
+                account_id: row.try_get("account_id")?,
+                sequence: sequence as usize,
+                event_type: row.try_get("event_type")?,
+                status: status.parse()?,
+                attempts: attempts as u32,
+                next_attempt_at: rfc3339(next_attempt_at),
+                last_response_status: last_response_status.map(|status| status as u16),
+                last_error: row.try_get("last_error")?,
+                created_at: rfc3339(created_at),
+            })
+        }
+
+        fn due_delivery_from_row(row: &PgRow) -> Result<DueWebhookDelivery, WebhookError> {
+            let attempts: i32 = row.try_get("attempts")?;
+            Ok(DueWebhookDelivery {
+                delivery_id: row.try_get("delivery_id")?,
+                subscription_id: row.try_get("subscription_id")?,
+                event_type: row.try_get("event_type")?,
+                url: row.try_get("url")?,
+                secret: row.try_get("secret")?,
+                payload: row.try_get("payload")?,
+                attempts: attempts as u32,
+            })
+        }
+
+        /// Stores the registered webhooks and the deliveries made to them.
+        #[derive(Clone)]
+        pub struct WebhookStore {
+            pool: Pool<Postgres>,
+        }
+
+        impl WebhookStore {
+            pub fn new(pool: Pool<Postgres>) -> Self {
+                Self { pool }
+            }
+
+            /// Registers a webhook for the user, signing its payloads with `secret`.
+            pub async fn create(
+                &self,
+                owner_id: &str,
+                request: WebhookSubscriptionRequest,
+                secret: String,
+            ) -> Result<WebhookSubscription, WebhookError> {
+                let created_at = Utc::now().naive_utc();
+                let subscription = WebhookSubscription {
+                    subscription_id: uuid::Uuid::new_v4().to_string(),
+                    owner_id: owner_id.to_string(),
+                    account_id: request.account_id,
+                    url: request.url,
+                    event_types: request.event_types,
+                    secret,
+                    created_at: rfc3339(created_at),
+                };
+                sqlx::query(
+                    "INSERT INTO webhook_subscriptions (subscription_id, owner_id, account_id, url, event_types, secret, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                )
+                .bind(&subscription.subscription_id)
+                .bind(&subscription.owner_id)
+                .bind(&subscription.account_id)
+                .bind(&subscription.url)
+                .bind(join_event_types(&subscription.event_types))
+                .bind(&subscription.secret)
+                .bind(created_at)
+                .execute(&self.pool)
+                .await?;
+                Ok(subscription)
+            }
+
+            /// Changes the account, URL and event types of the webhook.
+            pub async fn update(&self, subscription: &WebhookSubscription) -> Result<(), WebhookError> {
+                sqlx::query("UPDATE webhook_subscriptions SET account_id = $1, url = $2, event_types = $3 WHERE subscription_id = $4")
+                    .bind(&subscription.account_id)
+                    .bind(&subscription.url)
+                    .bind(join_event_types(&subscription.event_types))
+                    .bind(&subscription.subscription_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            pub async fn get(&self, subscription_id: &str) -> Result<Option<WebhookSubscription>, WebhookError> {
+                let row = sqlx::query("SELECT subscription_id, owner_id, account_id, url, event_types, secret, created_at FROM webhook_subscriptions WHERE subscription_id = $1")
+                    .bind(subscription_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                row.as_ref().map(subscription_from_row).transpose()
+            }
+
+            /// The webhooks registered by the user, oldest first.
+            pub async fn list(&self, owner_id: &str) -> Result<Vec<WebhookSubscription>, WebhookError> {
+                let rows = sqlx::query("SELECT subscription_id, owner_id, account_id, url, event_types, secret, created_at FROM webhook_subscriptions WHERE owner_id = $1 ORDER BY created_at, subscription_id")
+                    .bind(owner_id)
+                    .fetch_all(&self.pool)
+                    .await?;
+                rows.iter().map(subscription_from_row).collect()
+            }
+
+            /// Removes the webhook along with its delivery log. Its dead letters are kept.
+            pub async fn delete(&self, subscription_id: &str) -> Result<(), WebhookError> {
+                let mut transaction = self.pool.begin().await?;
+                sqlx::query("DELETE FROM webhook_deliveries WHERE subscription_id = $1")
+                    .bind(subscription_id)
+                    .execute(&mut *transaction)
+                    .await?;
+                sqlx::query("DELETE FROM webhook_subscriptions WHERE subscription_id = $1")
+                    .bind(subscription_id)
+                    .execute(&mut *transaction)
+                    .await?;
+                transaction.commit().await?;
+                Ok(())
+            }
+
+            /// The webhooks to call back with the events of the account: the ones registered for
+            /// the account by users who can still access it, and the ones registered for every
+            /// account by its owner. Accounts opened before accounts had owners have no access
+            /// rows, and everyone may access those.
+            pub async fn subscribers(&self, account_id: &str) -> Result<Vec<WebhookSubscription>, WebhookError> {
+                let rows = sqlx::query(
+                    "SELECT s.subscription_id, s.owner_id, s.account_id, s.url, s.event_types, s.secret, s.created_at FROM webhook_subscriptions s WHERE (s.account_id = $1 AND (EXISTS (SELECT 1 FROM account_access a WHERE a.account_id = $2 AND a.user_id = s.owner_id) OR NOT EXISTS (SELECT 1 FROM account_access a WHERE a.account_id = $3))) OR (s.account_id IS NULL AND EXISTS (SELECT 1 FROM account_access a WHERE a.account_id = $4 AND a.user_id = s.owner_id AND a.role = 'Owner'))",
+                )
+                .bind(account_id)
+                .bind(account_id)
+                .bind(account_id)
+                .bind(account_id)
+                .fetch_all(&self.pool)
+                .await?;
+                rows.iter().map(subscription_from_row).collect()
+            }
+
+            /// Queues an event for delivery to the webhook, unless it was queued before.
+            pub async fn enqueue(
+                &self,
+                subscription_id: &str,
+                account_id: &str,
+                sequence: usize,
+                event_type: &str,
+                payload: &str,
+            ) -> Result<(), WebhookError> {
+                let now = Utc::now().naive_utc();
+                sqlx::query(
+                    "INSERT INTO webhook_deliveries (delivery_id, subscription_id, account_id, sequence, event_type, payload, status, attempts, next_attempt_at, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7, 0, $8, $9) ON CONFLICT (subscription_id, account_id, sequence) DO NOTHING",
+                )
+                .bind(uuid::Uuid::new_v4().to_string())
+                .bind(subscription_id)
+                .bind(account_id)
+                .bind(sequence as i64)
+                .bind(event_type)
+                .bind(payload)
+                .bind(WebhookDeliveryStatus::Pending.to_string())
+                .bind(now)
+                .bind(now)
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            /// The delivery log of the webhook, newest first.
+            pub async fn deliveries(&self, subscription_id: &str, limit: usize) -> Result<Vec<WebhookDelivery>, WebhookError> {
+                let rows = sqlx::query(
+                    "SELECT delivery_id, subscription_id, account_id, sequence, event_type, status, attempts, next_attempt_at, last_response_status, last_error, created_at FROM webhook_deliveries WHERE subscription_id = $1 ORDER BY created_at DESC, sequence DESC LIMIT $2",
+                )
+                .bind(subscription_id)
+                .bind(limit as i64)
+                .fetch_all(&self.pool)
+                .await?;
+                rows.iter().map(delivery_from_row).collect()
+            }
+
+            /// Pending deliveries whose next attempt is due, the longest overdue first.
+            pub async fn due(&self, limit: usize) -> Result<Vec<DueWebhookDelivery>, WebhookError> {
+                let rows = sqlx::query(
+                    "SELECT d.delivery_id, d.subscription_id, d.event_type, d.payload, d.attempts, s.url, s.secret FROM webhook_deliveries d JOIN webhook_subscriptions s ON s.subscription_id = d.subscription_id WHERE d.status = $1 AND d.next_attempt_at <= $2 ORDER BY d.next_attempt_at LIMIT $3",
+                )
+                .bind(WebhookDeliveryStatus::Pending.to_string())
+                .bind(Utc::now().naive_utc())
+                .bind(limit as i64)
+                .fetch_all(&self.pool)
+                .await?;
+                rows.iter().map(due_delivery_from_row).collect()
+            }
+
+            pub async fn record_delivered(
+                &self,
+                delivery: &DueWebhookDelivery,
+                attempts: u32,
+                response_status: u16,
+            ) -> Result<(), WebhookError> {
+                sqlx::query("UPDATE webhook_deliveries SET status = $1, attempts = $2, last_response_status = $3, last_error = NULL WHERE delivery_id = $4")
+                    .bind(WebhookDeliveryStatus::Delivered.to_string())
+                    .bind(attempts as i32)
+                    .bind(response_status as i32)
+                    .bind(&delivery.delivery_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            /// Records a failed attempt, to be retried at `next_attempt_at`.
+            pub async fn record_retry(
+                &self,
+                delivery: &DueWebhookDelivery,
+                attempts: u32,
+                response_status: Option<u16>,
+                error: &str,
+                next_attempt_at: NaiveDateTime,
+            ) -> Result<(), WebhookError> {
+                sqlx::query("UPDATE webhook_deliveries SET attempts = $1, last_response_status = $2, last_error = $3, next_attempt_at = $4 WHERE delivery_id = $5")
+                    .bind(attempts as i32)
+                    .bind(response_status.map(i32::from))
+                    .bind(error)
+                    .bind(next_attempt_at)
+                    .bind(&delivery.delivery_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            /// Records the last failed attempt and moves the delivery to the dead letters.
+            pub async fn record_dead_letter(
+                &self,
+                delivery: &DueWebhookDelivery,
+                attempts: u32,
+                response_status: Option<u16>,
+                error: &str,
+            ) -> Result<(), WebhookError> {
+                let mut transaction = self.pool.begin().await?;
+                sqlx::query("UPDATE webhook_deliveries SET status = $1, attempts = $2, last_response_status = $3, last_error = $4 WHERE delivery_id = $5")
+                    .bind(WebhookDeliveryStatus::DeadLettered.to_string())
+                    .bind(attempts as i32)
+                    .bind(response_status.map(i32::from))
+                    .bind(error)
+                    .bind(&delivery.delivery_id)
+                    .execute(&mut *transaction)
+                    .await?;
+                sqlx::query(
+                    "INSERT INTO webhook_dead_letters (delivery_id, subscription_id, url, payload, attempts, last_error, dead_lettered_at) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+                )
+                .bind(&delivery.delivery_id)
+                .bind(&delivery.subscription_id)
+                .bind(&delivery.url)
+                .bind(&delivery.payload)
+                .bind(attempts as i32)
+                .bind(error)
+                .bind(Utc::now().naive_utc())
+                .execute(&mut *transaction)
+                .await?;
+                transaction.commit().await?;
+                Ok(())
+            }
+        }
+    } else if #[cfg(feature = "mysql")] {
+        use sqlx::mysql::MySqlRow;
+        use sqlx::{MySql, Pool, Row};
+
+        fn subscription_from_row(row: &MySqlRow) -> Result<WebhookSubscription, WebhookError> {
+            let event_types: String = row.try_get("event_types")?;
+            let created_at: NaiveDateTime = row.try_get("created_at")?;
+            Ok(WebhookSubscription {
+                subscription_id: row.try_get("subscription_id")?,
+                owner_id: row.try_get("owner_id")?,
+                account_id: row.try_get("account_id")?,
+                url: row.try_get("url")?,
+                event_types: split_event_types(&event_types),
+                secret: row.try_get("secret")?,
+                created_at: rfc3339(created_at),
+            })
+        }
+
+        fn delivery_from_row(row: &MySqlRow) -> Result<WebhookDelivery, WebhookError> {
+            let sequence: i64 = row.try_get("sequence")?;
+            let status: String = row.try_get("status")?;
+            let attempts: i32 = row.try_get("attempts")?;
+            let next_attempt_at: NaiveDateTime = row.try_get("next_attempt_at")?;
+            let last_response_status: Option<i32> = row.try_get("last_response_status")?;
+            let created_at: NaiveDateTime = row.try_get("created_at")?;
+            Ok(WebhookDelivery {
+                delivery_id: row.try_get("delivery_id")?,
+                subscription_id: row.try_get("subscription_id")?,
+                account_id: row.try_get("account_id")?,
+                sequence: sequence as usize,
+                event_type: row.try_get("event_type")?,
+                status: status.parse()?,
+                attempts: attempts as u32,
+                next_attempt_at: rfc3339(next_attempt_at),
+                last_response_status: last_response_status.map(|status| status as u16),
+                last_error: row.try_get("last_error")?,
+                created_at: rfc3339(created_at),
+            })
+        }
+
+        fn due_delivery_from_row(row: &MySqlRow) -> Result<DueWebhookDelivery, WebhookError> {
+            let attempts: i32 = row.try_get("attempts")?;
+            Ok(DueWebhookDelivery {
+                delivery_id: row.try_get("delivery_id")?,
+                subscription_id: row.try_get("subscription_id")?,
+                event_type: row.try_get("event_type")?,
+                url: row.try_get("url")?,
+                secret: row.try_get("secret")?,
+                payload: row.try_get("payload")?,
+                attempts: attempts as u32,
+            })
+        }
+
+        /// Stores the registered webhooks and the deliveries made to them.
+        #[derive(Clone)]
+        pub struct WebhookStore {
+            pool: Pool<MySql>,
+        }
+
+        impl WebhookStore {
+            pub fn new(pool: Pool<MySql>) -> Self {
+                Self { pool }
+            }
+
+            /// Registers a webhook for the user, signing its payloads with `secret`.
+            pub async fn create(
+                &self,
+                owner_id: &str,
+                request: WebhookSubscriptionRequest,
+                secret: String,
+            ) -> Result<WebhookSubscription, WebhookError> {
+                let created_at = Utc::now().naive_utc();
+                let subscription = WebhookSubscription {
+                    subscription_id: uuid::Uuid::new_v4().to_string(),
+                    owner_id: owner_id.to_string(),
+                    account_id: request.account_id,
+                    url: request.url,
+                    event_types: request.event_types,
+                    secret,
+                    created_at: rfc3339(created_at),
+                };
+                sqlx::query(
+                    "INSERT INTO webhook_subscriptions (subscription_id, owner_id, account_id, url, event_types, secret, created_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                )
+                .bind(&subscription.subscription_id)
+                .bind(&subscription.owner_id)
+                .bind(&subscription.account_id)
+                .bind(&subscription.url)
+                .bind(join_event_types(&subscription.event_types))
+                .bind(&subscription.secret)
+                .bind(created_at)
+                .execute(&self.pool)
+                .await?;
+                Ok(subscription)
+            }
+
+            /// Changes the account, URL and event types of the webhook.
+            pub async fn update(&self, subscription: &WebhookSubscription) -> Result<(), WebhookError> {
+                sqlx::query("UPDATE webhook_subscriptions SET account_id = ?, url = ?, event_types = ? WHERE subscription_id = ?")
+                    .bind(&subscription.account_id)
+                    .bind(&subscription.url)
+                    .bind(join_event_types(&subscription.event_types))
+                    .bind(&subscription.subscription_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            pub async fn get(&self, subscription_id: &str) -> Result<Option<WebhookSubscription>, WebhookError> {
+                let row = sqlx::query("SELECT subscription_id, owner_id, account_id, url, event_types, secret, created_at FROM webhook_subscriptions WHERE subscription_id = ?")
+                    .bind(subscription_id)
+                    .fetch_optional(&self.pool)
+                    .await?;
+                row.as_ref().map(subscription_from_row).transpose()
+            }
+
+            /// The webhooks registered by the user, oldest first.
+            pub async fn list(&self, owner_id: &str) -> Result<Vec<WebhookSubscription>, WebhookError> {
+                let rows = sqlx::query("SELECT subscription_id, owner_id, account_id, url, event_types, secret, created_at FROM webhook_subscriptions WHERE owner_id = ? ORDER BY created_at, subscription_id")
+                    .bind(owner_id)
+                    .fetch_all(&self.pool)
+                    .await?;
+                rows.iter().map(subscription_from_row).collect()
+            }
+
+            /// Removes the webhook along with its delivery log. Its dead letters are kept.
+            pub async fn delete(&self, subscription_id: &str) -> Result<(), WebhookError> {
+                let mut transaction = self.pool.begin().await?;
+                sqlx::query("DELETE FROM webhook_deliveries WHERE subscription_id = ?")
+                    .bind(subscription_id)
+                    .execute(&mut *transaction)
+                    .await?;
+                sqlx::query("DELETE FROM webhook_subscriptions WHERE subscription_id = ?")
+                    .bind(subscription_id)
+                    .execute(&mut *transaction)
+                    .await?;
+                transaction.commit().await?;
+                Ok(())
+            }
+
+            /// The webhooks to call back with the events of the account: the ones registered for
+            /// the account by users who can still access it, and the ones registered for every
+            /// account by its owner. Accounts opened before accounts had owners have no access
+            /// rows, and everyone may access those.
+            pub async fn subscribers(&self, account_id: &str) -> Result<Vec<WebhookSubscription>, WebhookError> {
+                let rows = sqlx::query(
+                    "SELECT s.subscription_id, s.owner_id, s.account_id, s.url, s.event_types, s.secret, s.created_at FROM webhook_subscriptions s WHERE (s.account_id = ? AND (EXISTS (SELECT 1 FROM account_access a WHERE a.account_id = ? AND a.user_id = s.owner_id) OR NOT EXISTS (SELECT 1 FROM account_access a WHERE a.account_id = ?))) OR (s.account_id IS NULL AND EXISTS (SELECT 1 FROM account_access a WHERE a.account_id = ? AND a.user_id = s.owner_id AND a.role = 'Owner'))",
+                )
+                .bind(account_id)
+                .bind(account_id)
+                .bind(account_id)
+                .bind(account_id)
+                .fetch_all(&self.pool)
+                .await?;
+                rows.iter().map(subscription_from_row).collect()
+            }
+
+            /// Queues an event for delivery to the webhook, unless it was queued before.
+            pub async fn enqueue(
+                &self,
+                subscription_id: &str,
+                account_id: &str,
+                sequence: usize,
+                event_type: &str,
+                payload: &str,
+            ) -> Result<(), WebhookError> {
+                let now = Utc::now().naive_utc();
+                sqlx::query(
+                    "INSERT IGNORE INTO webhook_deliveries (delivery_id, subscription_id, account_id, sequence, event_type, payload, status, attempts, next_attempt_at, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, 0, ?, ?)",
+                )
+                .bind(uuid::Uuid::new_v4().to_string())
+                .bind(subscription_id)
+                .bind(account_id)
+                .bind(sequence as i64)
+                .bind(event_type)
+                .bind(payload)
+                .bind(WebhookDeliveryStatus::Pending.to_string())
+                .bind(now)
+                .bind(now)
+                .execute(&self.pool)
+                .await?;
+                Ok(())
+            }
+
+            /// The delivery log of the webhook, newest first.
+            pub async fn deliveries(&self, subscription_id: &str, limit: usize) -> Result<Vec<WebhookDelivery>, WebhookError> {
+                let rows = sqlx::query(
+                    "SELECT delivery_id, subscription_id, account_id, sequence, event_type, status, attempts, next_attempt_at, last_response_status, last_error, created_at FROM webhook_deliveries WHERE subscription_id = ? ORDER BY created_at DESC, sequence DESC LIMIT ?",
+                )
+                .bind(subscription_id)
+                .bind(limit as i64)
+                .fetch_all(&self.pool)
+                .await?;
+                rows.iter().map(delivery_from_row).collect()
+            }
+
+            /// Pending deliveries whose next attempt is due, the longest overdue first.
+            pub async fn due(&self, limit: usize) -> Result<Vec<DueWebhookDelivery>, WebhookError> {
+                let rows = sqlx::query(
+                    "SELECT d.delivery_id, d.subscription_id, d.event_type, d.payload, d.attempts, s.url, s.secret FROM webhook_deliveries d JOIN webhook_subscriptions s ON s.subscription_id = d.subscription_id WHERE d.status = ? AND d.next_attempt_at <= ? ORDER BY d.next_attempt_at LIMIT ?",
+                )
+                .bind(WebhookDeliveryStatus::Pending.to_string())
+                .bind(Utc::now().naive_utc())
+                .bind(limit as i64)
+                .fetch_all(&self.pool)
+                .await?;
+                rows.iter().map(due_delivery_from_row).collect()
+            }
+
+            pub async fn record_delivered(
+                &self,
+                delivery: &DueWebhookDelivery,
+                attempts: u32,
+                response_status: u16,
+            ) -> Result<(), WebhookError> {
+                sqlx::query("UPDATE webhook_deliveries SET status = ?, attempts = ?, last_response_status = ?, last_error = NULL WHERE delivery_id = ?")
+                    .bind(WebhookDeliveryStatus::Delivered.to_string())
+                    .bind(attempts as i32)
+                    .bind(response_status as i32)
+                    .bind(&delivery.delivery_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            /// Records a failed attempt, to be retried at `next_attempt_at`.
+            pub async fn record_retry(
+                &self,
+                delivery: &DueWebhookDelivery,
+                attempts: u32,
+                response_status: Option<u16>,
+                error: &str,
+                next_attempt_at: NaiveDateTime,
+            ) -> Result<(), WebhookError> {
+                sqlx::query("UPDATE webhook_deliveries SET attempts = ?, last_response_status = ?, last_error = ?, next_attempt_at = ? WHERE delivery_id = ?")
+                    .bind(attempts as i32)
+                    .bind(response_status.map(i32::from))
+                    .bind(error)
+                    .bind(next_attempt_at)
+                    .bind(&delivery.delivery_id)
+                    .execute(&self.pool)
+                    .await?;
+                Ok(())
+            }
+
+            /// Records the last failed attempt and moves the delivery to the dead letters.
+            pub async fn record_dead_letter(
+                &self,
+                delivery: &DueWebhookDelivery,
+                attempts: u32,
+                response_status: Option<u16>,
+                error: &str,
+            ) -> Result<(), WebhookError> {
+                let mut transaction = self.pool.begin().await?;
+                sqlx::query("UPDATE webhook_deliveries SET status = ?, attempts = ?, last_response_status = ?, last_error = ? WHERE delivery_id = ?")
+                    .bind(WebhookDeliveryStatus::DeadLettered.to_string())
+                    .bind(attempts as i32)
+                    .bind(response_status.map(i32::from))
+                    .bind(error)
+                    .bind(&delivery.delivery_id)
+                    .execute(&mut *transaction)
+                    .await?;
+                sqlx::query(
+                    "INSERT INTO webhook_dead_letters (delivery_id, subscription_id, url, payload, attempts, last_error, dead_lettered_at) VALUES (?, ?, ?, ?, ?, ?, ?)",
+                )
+                .bind(&delivery.delivery_id)
+                .bind(&delivery.subscription_id)
+                .bind(&delivery.url)
+                .bind(&delivery.payload)
+                .bind(attempts as i32)
+                .bind(error)
+                .bind(Utc::now().naive_utc())
+                .execute(&mut *transaction)
+                .await?;
+                transaction.commit().await?;
+                Ok(())
+            }
+        }
+    } else {
+        compile_error!("Must specify either mysql or postgres feature");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn subscription(event_types: &[&str]) -> WebhookSubscription {
+        WebhookSubscription {
+            subscription_id: "0b6a3c52".to_string(),
+            owner_id: "1234".to_string(),
+            account_id: None,
+            url: "https://partner.example.com/hooks".to_string(),
+            event_types: event_types
+                .iter()
+                .map(|event_type| event_type.to_string())
+                .collect(),
+            secret: "whsec_3f0c2b9e".to_string(),
+            created_at: "2023-11-10T09:00:00+00:00".to_string(),
+        }
+    }
+
+    #[test]
+    fn accepts_every_event_without_event_types() {
+        assert!(subscription(&[]).accepts("AccountOpened"));
+    }
+
+    #[test]
+    fn accepts_only_the_subscribed_event_types() {
+        let subscription = subscription(&["CustomerDepositedMoney", "CustomerWithdrewCash"]);
+        assert!(subscription.accepts("CustomerWithdrewCash"));
+        assert!(!subscription.accepts("AccountOpened"));
+    }
+
+    #[test]
+    fn event_types_round_trip_through_their_column() {
+        let event_types = vec!["AccountFrozen".to_string(), "AccountClosed".to_string()];
+        assert_eq!(
+            split_event_types(&join_event_types(&event_types)),
+            event_types
+        );
+        assert!(split_event_types(&join_event_types(&[])).is_empty());
+    }
+
+    #[test]
+    fn delivery_statuses_round_trip_through_their_names() {
+        for status in [
+            WebhookDeliveryStatus::Pending,
+            WebhookDeliveryStatus::Delivered,
+            WebhookDeliveryStatus::DeadLettered,
+        ] {
+            assert_eq!(
+                status.to_string().parse::<WebhookDeliveryStatus>().unwrap(),
+                status
+            );
+        }
+    }
+}
@@ -6,14 +6,47 @@
 use std::sync::Arc;
 
 use crate::application::auth_service::AuthServiceImpl;
-use crate::application::bank_account_application_service::BankAccountServiceImpl;
+use crate::application::bank_account_application_service::{
+    BankAccountApplicationService, BankAccountServiceImpl,
+};
+use crate::application::clock::SystemClock;
+use crate::application::scheduled_payment_application_service::{
+    ScheduledPaymentApplicationService, ScheduledPaymentServiceImpl,
+};
+use crate::application::scheduled_payment_scheduler::ScheduledPaymentScheduler;
+use crate::application::transfer_application_service::{
+    TransferApplicationService, TransferServiceImpl,
+};
+use crate::application::transfer_process_manager::TransferProcessManager;
+use crate::application::webhook_application_service::{
+    WebhookApplicationService, WebhookServiceImpl,
+};
 use crate::infrastructure::grpc::bank_account_grpc_service::GRpcBankAccountService;
+use crate::infrastructure::holds::HoldExpiryJob;
+use crate::infrastructure::idempotency::{
+    IdempotencyConfiguration, IdempotencyKeyStore, IDEMPOTENCY_KEY_LEASE_ENV_VAR,
+    IDEMPOTENCY_KEY_TTL_ENV_VAR,
+};
+use crate::infrastructure::interest::{InterestConfiguration, InterestJob};
+use crate::infrastructure::outbox::{
+    poll_interval_from_env, EventPublisherConfiguration, OutboxRelay,
+};
+use crate::infrastructure::projections::ProjectionRebuilder;
+use crate::infrastructure::reconciliation::{Reconciler, ReconciliationConfiguration};
+use crate::infrastructure::scheduled_payments::{
+    ScheduledPaymentConfiguration, ScheduledPaymentWorker,
+};
+use crate::infrastructure::snapshots::{SnapshotConfiguration, SnapshotRebuilder};
+use crate::infrastructure::transfers::PendingTransferJob;
+use crate::infrastructure::webhooks::{WebhookConfiguration, WebhookSender};
 use crate::infrastructure::{
     grpc::bank_account_service_server::BankAccountServiceServer,
     web_server::configuration::WebServerConfiguration,
 };
 use crate::interfaces::hello::hello_world::greeter_server::GreeterServer;
 use crate::interfaces::hello::MyGreeter;
+use crate::interfaces::scheduled_payment::ScheduledPaymentQuery;
+use crate::interfaces::webhooks::WebhookStore;
 use auth_grpc_service::authentication_server::AuthenticationServer;
 use auth_grpc_service::UserView;
 use axum::{
@@ -27,7 +60,6 @@ use infrastructure::middleware::auth;
 use infrastructure::web_server::graphql::new_graphql_router;
 use infrastructure::{logging, observability};
 use infrastructure::{repositories, web_server, web_server::openapi::ApiDoc};
-use tower::ServiceBuilder;
 use tower_cookies::CookieManagerLayer;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
@@ -60,25 +92,215 @@ async fn main() -> crate::prelude::Result<()> {
 
     let pool = infrastructure::get_db_connection().await?;
 
+    let projection_rebuilder = Arc::new(ProjectionRebuilder::new(vec![
+        Arc::new(interfaces::get_account_projection(pool.clone())),
+        Arc::new(interfaces::get_account_transactions_projection(
+            pool.clone(),
+        )),
+        Arc::new(interfaces::get_general_ledger_projection(pool.clone())),
+        Arc::new(interfaces::get_account_access_projection(pool.clone())),
+        Arc::new(interfaces::get_transfer_projection(pool.clone())),
+        Arc::new(interfaces::get_scheduled_payment_projection(pool.clone())),
+    ]));
+
+    // Maintenance tasks run instead of the server, e.g. `veloxide-server rebuild-snapshots`.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return run_task(&args, pool, &projection_rebuilder).await;
+    }
+
+    // Publishes the events committed on bank accounts to the configured broker.
+    if let Some(publisher_configuration) = EventPublisherConfiguration::from_env()? {
+        let relay = OutboxRelay::new(
+            pool.clone(),
+            publisher_configuration.publisher().await?,
+            poll_interval_from_env()?,
+        );
+        tokio::spawn(relay.run());
+    }
+
     let cors_layer = infrastructure::web_server::new_cors_layer();
 
-    // Bank account init
+    // Bank account and transfer init
+    let (transfer_cqrs, transfer_view_repository) =
+        interfaces::get_transfer_cqrs_framework(pool.clone());
+    let idempotency_key_store = IdempotencyKeyStore::new(
+        pool.clone(),
+        IdempotencyConfiguration::from_env().unwrap_or_else(|e| {
+            panic!(
+                "failed to read {} or {}: {}",
+                IDEMPOTENCY_KEY_TTL_ENV_VAR, IDEMPOTENCY_KEY_LEASE_ENV_VAR, e
+            )
+        }),
+    );
+    let account_events = interfaces::AccountEventBroadcaster::new(pool.clone());
     let (bank_account_cqrs, bank_account_view_repository) =
-        interfaces::get_bank_account_cqrs_framework(pool.clone());
+        interfaces::get_bank_account_cqrs_framework(
+            pool.clone(),
+            transfer_cqrs.clone(),
+            account_events.clone(),
+        );
+    let bank_account_service: Arc<dyn BankAccountApplicationService> =
+        Arc::new(BankAccountServiceImpl::new(
+            bank_account_cqrs.clone(),
+            interfaces::get_bank_account_event_store(pool.clone()),
+            bank_account_view_repository.clone(),
+            interfaces::AccountTransactionsQuery::new(pool.clone()),
+            interfaces::AccountAccessQuery::new(pool.clone()),
+            interfaces::AccountStatementGenerator::new(pool.clone()),
+            idempotency_key_store.clone(),
+        ));
+    let transfer_service: Arc<dyn TransferApplicationService> = Arc::new(TransferServiceImpl::new(
+        bank_account_service.clone(),
+        bank_account_cqrs.clone(),
+        transfer_cqrs.clone(),
+        transfer_view_repository.clone(),
+        idempotency_key_store,
+    ));
+    let webhook_store = WebhookStore::new(pool.clone());
+    let webhook_service: Arc<dyn WebhookApplicationService> = Arc::new(WebhookServiceImpl::new(
+        bank_account_service.clone(),
+        webhook_store.clone(),
+    ));
+
+    // Calls the registered webhooks back with the events queued for them, retrying failures.
+    let webhook_configuration = WebhookConfiguration::from_env()
+        .unwrap_or_else(|e| panic!("failed to read the webhook configuration: {}", e));
+    tokio::spawn(WebhookSender::new(webhook_store, webhook_configuration).run());
+
+    let scheduled_payment_cqrs = interfaces::get_scheduled_payment_cqrs_framework(pool.clone());
+    let scheduled_payment_service: Arc<dyn ScheduledPaymentApplicationService> =
+        Arc::new(ScheduledPaymentServiceImpl::new(
+            bank_account_service.clone(),
+            scheduled_payment_cqrs.clone(),
+            ScheduledPaymentQuery::new(pool.clone()),
+        ));
+
+    // Makes the scheduled payments as they fall due, retrying the runs that fail transiently.
+    let scheduled_payment_configuration = ScheduledPaymentConfiguration::from_env()
+        .unwrap_or_else(|e| panic!("failed to read the scheduled payment configuration: {}", e));
+    tokio::spawn(
+        ScheduledPaymentWorker::new(
+            ScheduledPaymentQuery::new(pool.clone()),
+            ScheduledPaymentScheduler::new(
+                bank_account_service.clone(),
+                scheduled_payment_cqrs,
+                Arc::new(SystemClock),
+                scheduled_payment_configuration,
+            ),
+        )
+        .run(),
+    );
+
+    // Takes the transfers that stopped part way on to being completed or compensated.
+    tokio::spawn(
+        PendingTransferJob::new(
+            pool.clone(),
+            TransferProcessManager::new(Arc::downgrade(&bank_account_cqrs), transfer_cqrs),
+            transfer_view_repository,
+        )
+        .run(),
+    );
+
+    // Releases the holds that expired without being captured.
+    tokio::spawn(
+        HoldExpiryJob::new(
+            pool.clone(),
+            bank_account_cqrs.clone(),
+            bank_account_view_repository.clone(),
+            Arc::new(SystemClock),
+        )
+        .run(),
+    );
+
+    // Accrues interest on the accounts daily and posts it monthly, when rates are configured.
+    let interest_configuration = InterestConfiguration::from_env()
+        .unwrap_or_else(|e| panic!("failed to read the interest configuration: {}", e));
+    if interest_configuration.is_enabled() {
+        tokio::spawn(
+            InterestJob::new(
+                pool.clone(),
+                bank_account_cqrs.clone(),
+                bank_account_view_repository.clone(),
+                Arc::new(SystemClock),
+                interest_configuration,
+            )
+            .run(),
+        );
+    }
+
+    // Compares the balances of the accounts with their views and the ledger, when an interval
+    // is configured.
+    let reconciliation_configuration = ReconciliationConfiguration::from_env()
+        .unwrap_or_else(|e| panic!("failed to read the reconciliation configuration: {}", e));
+    if reconciliation_configuration.is_enabled() {
+        tokio::spawn(
+            Reconciler::new(
+                pool.clone(),
+                bank_account_view_repository,
+                projection_rebuilder.clone(),
+            )
+            .run(reconciliation_configuration),
+        );
+    }
+
     let graphql_enabled: bool = std::env::var("GRAHQL_ENABLED")
         .unwrap_or("false".to_string())
         .parse()
         .expect("expected to be able to parse graphql_enabled as a bool");
-    let bank_account_routes = Router::new().route(
-        "/:id",
-        get(web_server::bank_account_handlers::query_handler)
-            .post(web_server::bank_account_handlers::command_handler)
-            .layer(
-                ServiceBuilder::new()
-                    .layer(Extension(bank_account_cqrs.clone()))
-                    .layer(Extension(bank_account_view_repository.clone())),
-            ),
-    );
+    let bank_account_routes = Router::new()
+        .route(
+            "/:id",
+            get(web_server::bank_account_handlers::query_handler)
+                .post(web_server::bank_account_handlers::command_handler),
+        )
+        .route(
+            "/:id/transactions",
+            get(web_server::bank_account_handlers::transactions_handler),
+        )
+        .route(
+            "/:id/access",
+            get(web_server::bank_account_handlers::access_handler),
+        )
+        .route(
+            "/:id/statements/:period",
+            get(web_server::bank_account_handlers::statement_handler),
+        )
+        .layer(Extension(bank_account_service.clone()));
+    let transfer_routes = Router::new()
+        .route("/", post(web_server::transfer_handlers::command_handler))
+        .route("/:id", get(web_server::transfer_handlers::query_handler))
+        .layer(Extension(transfer_service.clone()));
+    let webhook_routes = Router::new()
+        .route(
+            "/",
+            get(web_server::webhook_handlers::list_handler)
+                .post(web_server::webhook_handlers::register_handler),
+        )
+        .route(
+            "/:id",
+            get(web_server::webhook_handlers::query_handler)
+                .put(web_server::webhook_handlers::update_handler)
+                .delete(web_server::webhook_handlers::delete_handler),
+        )
+        .route(
+            "/:id/deliveries",
+            get(web_server::webhook_handlers::deliveries_handler),
+        )
+        .layer(Extension(webhook_service));
+    let scheduled_payment_routes = Router::new()
+        .route(
+            "/",
+            get(web_server::scheduled_payment_handlers::list_handler)
+                .post(web_server::scheduled_payment_handlers::create_handler),
+        )
+        .route(
+            "/:id",
+            get(web_server::scheduled_payment_handlers::query_handler)
+                .put(web_server::scheduled_payment_handlers::update_handler)
+                .delete(web_server::scheduled_payment_handlers::cancel_handler),
+        )
+        .layer(Extension(scheduled_payment_service.clone()));
 
     // Auth init
     let auth_config = infrastructure::middleware::auth::AuthConfiguration::from_env();
@@ -94,7 +316,28 @@ async fn main() -> crate::prelude::Result<()> {
             "/auth/google/callback",
             get(web_server::oauth::google_oauth_callback_handler),
         );
-    let api_routes = Router::new().nest("/bank-accounts", bank_account_routes);
+    let admin_routes = Router::new()
+        .route(
+            "/projections/:name/rebuild",
+            post(web_server::admin_handlers::rebuild_projection_handler),
+        )
+        .route(
+            "/ledger/trial-balance",
+            get(web_server::admin_handlers::trial_balance_handler),
+        )
+        .route(
+            "/bank-accounts/:id/owner",
+            post(web_server::admin_handlers::assign_owner_handler),
+        )
+        .layer(Extension(projection_rebuilder))
+        .layer(Extension(interfaces::GeneralLedgerQuery::new(pool.clone())))
+        .layer(Extension(bank_account_cqrs));
+    let api_routes = Router::new()
+        .nest("/bank-accounts", bank_account_routes)
+        .nest("/transfers", transfer_routes)
+        .nest("/webhooks", webhook_routes)
+        .nest("/scheduled-payments", scheduled_payment_routes)
+        .nest("/admin", admin_routes);
 
     let (prometheus_layer, metric_handle) = PrometheusMetricLayer::pair();
 
@@ -108,8 +351,10 @@ async fn main() -> crate::prelude::Result<()> {
         axum_router = axum_router.nest(
             "/grahql",
             new_graphql_router(
-                bank_account_cqrs.clone(),
-                bank_account_view_repository.clone(),
+                bank_account_service.clone(),
+                transfer_service.clone(),
+                scheduled_payment_service,
+                account_events.clone(),
             ),
         );
     }
@@ -130,14 +375,12 @@ async fn main() -> crate::prelude::Result<()> {
         .route("/health", get(|| async move { "HEALTHY" }));
 
     let web_server_config = WebServerConfiguration::from_env();
-    let auth_application_service = AuthServiceImpl::new(Arc::new(user_repository));
+    let auth_application_service = AuthServiceImpl::new(Arc::new(user_repository.clone()));
     let auth_grpc_service =
         auth_grpc_service::GRpcAuthService::new(Box::new(auth_application_service));
-    let bank_account_application_service =
-        BankAccountServiceImpl::new(bank_account_view_repository.clone());
-    let bank_account_service =
-        GRpcBankAccountService::new(Box::new(bank_account_application_service));
-    let bank_account_service_server = BankAccountServiceServer::new(bank_account_service);
+    let grpc_bank_account_service =
+        GRpcBankAccountService::new(bank_account_service, transfer_service, account_events);
+    let bank_account_service_server = BankAccountServiceServer::new(grpc_bank_account_service);
     let grpc_web_bank_account_service = tonic_web::enable(bank_account_service_server);
     let tonic_greeter_service = tonic_web::enable(GreeterServer::new(MyGreeter::default()));
     let auth_server = tonic_web::enable(AuthenticationServer::new(auth_grpc_service));
@@ -145,9 +388,89 @@ async fn main() -> crate::prelude::Result<()> {
         .nest_tonic(tonic_greeter_service)
         .nest_tonic(grpc_web_bank_account_service)
         .nest_tonic(auth_server)
-        .layer(cors_layer);
+        // Resolves the signed in user for the bank account service, same as the REST routes
+        .layer(axum::middleware::from_fn(auth::mw_authenticate))
+        .layer(Extension(user_repository))
+        .layer(cors_layer)
+        .layer(CookieManagerLayer::new());
     let multiplexed_service = RestGrpcService::new(axum_router, grpc_router);
     Ok(Server::bind(&web_server_config.get_address())
         .serve(multiplexed_service.into_make_service())
         .await?)
 }
+
+const TASK_USAGE: &str = "expected one of: rebuild-snapshots, \
+    rebuild-projection <name> [aggregate id], purge-idempotency-keys, reconcile [--repair]";
+
+async fn run_task(
+    args: &[String],
+    pool: infrastructure::DbPool,
+    projection_rebuilder: &ProjectionRebuilder,
+) -> crate::prelude::Result<()> {
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        ["rebuild-snapshots"] => {
+            let rebuilder = SnapshotRebuilder::new(pool, SnapshotConfiguration::from_env()?);
+            let rebuilt = rebuilder
+                .rebuild::<domain::BankAccount>(domain::bank_account_upcasters())
+                .await?;
+            println!("rebuilt {} bank account snapshots", rebuilt);
+        }
+        ["rebuild-projection", name, aggregate_id @ ..] if aggregate_id.len() <= 1 => {
+            let report = projection_rebuilder
+                .rebuild(name, aggregate_id.first().copied(), &|progress| {
+                    println!(
+                        "replayed {}/{} ({})",
+                        progress.replayed, progress.total, progress.aggregate_id
+                    )
+                })
+                .await?;
+            println!(
+                "rebuilt {} from {} events of {} aggregates",
+                report.projection, report.events_replayed, report.aggregates_replayed
+            );
+        }
+        ["reconcile", options @ ..] if options.iter().all(|option| *option == "--repair") => {
+            let reconciler = Reconciler::new(
+                pool.clone(),
+                Arc::new(interfaces::get_account_view_repository(pool)),
+                Arc::new(projection_rebuilder.clone()),
+            );
+            let report = reconciler.reconcile(!options.is_empty()).await?;
+            // One JSON line per mismatch, for the output to be processed by other tools.
+            for mismatch in &report.mismatches {
+                println!("{}", serde_json::to_string(mismatch)?);
+            }
+            println!(
+                "reconciled {} accounts, {} mismatched and {} failed",
+                report.accounts_checked,
+                report.mismatches.len(),
+                report.accounts_failed
+            );
+            if options.is_empty() && !report.mismatches.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        ["purge-idempotency-keys"] => {
+            let store = IdempotencyKeyStore::new(pool, IdempotencyConfiguration::from_env()?);
+            let purged = store.purge_expired().await?;
+            println!("purged {} expired idempotency keys", purged);
+        }
+        ["rebuild-projection", ..] => {
+            eprintln!(
+                "usage: rebuild-projection <name> [aggregate id], where name is one of: {}",
+                projection_rebuilder.names().join(", ")
+            );
+            std::process::exit(2);
+        }
+        _ => {
+            eprintln!("unknown task {}, {}", args.join(" "), TASK_USAGE);
+            std::process::exit(2);
+        }
+    }
+    Ok(())
+}
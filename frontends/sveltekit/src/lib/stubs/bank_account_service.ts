@@ -12,6 +12,21 @@ import type { PartialMessage } from "@protobuf-ts/runtime";
 import { reflectionMergePartial } from "@protobuf-ts/runtime";
 import { MESSAGE_TYPE } from "@protobuf-ts/runtime";
 import { MessageType } from "@protobuf-ts/runtime";
+/**
+ * Exact monetary amount
+ *
+ * @generated from protobuf message bank_account_service.Money
+ */
+export interface Money {
+    /**
+     * @generated from protobuf field: int64 minor_units = 1;
+     */
+    minorUnits: bigint; // Amount in the currency's minor unit, e.g. 1050 is 10.50 USD
+    /**
+     * @generated from protobuf field: string currency = 2;
+     */
+    currency: string; // ISO-4217 currency code
+}
 /**
  * Bank account transaction
  *
@@ -23,9 +38,9 @@ export interface AccountTransaction {
      */
     description: string;
     /**
-     * @generated from protobuf field: double amount = 2;
+     * @generated from protobuf field: bank_account_service.Money amount = 3;
      */
-    amount: number;
+    amount?: Money;
 }
 /**
  * Bank account view
@@ -37,18 +52,14 @@ export interface BankAccountView {
      * @generated from protobuf field: string account_id = 1;
      */
     accountId: string;
-    /**
-     * @generated from protobuf field: double balance = 2;
-     */
-    balance: number;
     /**
      * @generated from protobuf field: repeated string written_checks = 3;
      */
     writtenChecks: string[];
     /**
-     * @generated from protobuf field: repeated bank_account_service.AccountTransaction account_transactions = 4;
+     * @generated from protobuf field: bank_account_service.Money balance = 5;
      */
-    accountTransactions: AccountTransaction[];
+    balance?: Money;
 }
 /**
  * Request to get bank account details
@@ -73,15 +84,69 @@ export interface GetBankAccountResponse {
     accountView?: BankAccountView;
 }
 // @generated message type with reflection information, may provide speed optimized methods
+class Money$Type extends MessageType<Money> {
+    constructor() {
+        super("bank_account_service.Money", [
+            { no: 1, name: "minor_units", kind: "scalar", T: 3 /*ScalarType.INT64*/, L: 0 /*LongType.BIGINT*/ },
+            { no: 2, name: "currency", kind: "scalar", T: 9 /*ScalarType.STRING*/ }
+        ]);
+    }
+    create(value?: PartialMessage<Money>): Money {
+        const message = { minorUnits: 0n, currency: "" };
+        globalThis.Object.defineProperty(message, MESSAGE_TYPE, { enumerable: false, value: this });
+        if (value !== undefined)
+            reflectionMergePartial<Money>(this, message, value);
+        return message;
+    }
+    internalBinaryRead(reader: IBinaryReader, length: number, options: BinaryReadOptions, target?: Money): Money {
+        let message = target ?? this.create(), end = reader.pos + length;
+        while (reader.pos < end) {
+            let [fieldNo, wireType] = reader.tag();
+            switch (fieldNo) {
+                case /* int64 minor_units */ 1:
+                    message.minorUnits = reader.int64().toBigInt();
+                    break;
+                case /* string currency */ 2:
+                    message.currency = reader.string();
+                    break;
+                default:
+                    let u = options.readUnknownField;
+                    if (u === "throw")
+                        throw new globalThis.Error(`Unknown field ${fieldNo} (wire type ${wireType}) for ${this.typeName}`);
+                    let d = reader.skip(wireType);
+                    if (u !== false)
+                        (u === true ? UnknownFieldHandler.onRead : u)(this.typeName, message, fieldNo, wireType, d);
+            }
+        }
+        return message;
+    }
+    internalBinaryWrite(message: Money, writer: IBinaryWriter, options: BinaryWriteOptions): IBinaryWriter {
+        /* int64 minor_units = 1; */
+        if (message.minorUnits !== 0n)
+            writer.tag(1, WireType.Varint).int64(message.minorUnits);
+        /* string currency = 2; */
+        if (message.currency !== "")
+            writer.tag(2, WireType.LengthDelimited).string(message.currency);
+        let u = options.writeUnknownFields;
+        if (u !== false)
+            (u == true ? UnknownFieldHandler.onWrite : u)(this.typeName, message, writer);
+        return writer;
+    }
+}
+/**
+ * @generated MessageType for protobuf message bank_account_service.Money
+ */
+export const Money = new Money$Type();
+// @generated message type with reflection information, may provide speed optimized methods
 class AccountTransaction$Type extends MessageType<AccountTransaction> {
     constructor() {
         super("bank_account_service.AccountTransaction", [
             { no: 1, name: "description", kind: "scalar", T: 9 /*ScalarType.STRING*/ },
-            { no: 2, name: "amount", kind: "scalar", T: 1 /*ScalarType.DOUBLE*/ }
+            { no: 3, name: "amount", kind: "message", T: () => Money }
         ]);
     }
     create(value?: PartialMessage<AccountTransaction>): AccountTransaction {
-        const message = { description: "", amount: 0 };
+        const message = { description: "" };
         globalThis.Object.defineProperty(message, MESSAGE_TYPE, { enumerable: false, value: this });
         if (value !== undefined)
             reflectionMergePartial<AccountTransaction>(this, message, value);
@@ -95,8 +160,8 @@ class AccountTransaction$Type extends MessageType<AccountTransaction> {
                 case /* string description */ 1:
                     message.description = reader.string();
                     break;
-                case /* double amount */ 2:
-                    message.amount = reader.double();
+                case /* bank_account_service.Money amount */ 3:
+                    message.amount = Money.internalBinaryRead(reader, reader.uint32(), options, message.amount);
                     break;
                 default:
                     let u = options.readUnknownField;
@@ -113,9 +178,9 @@ class AccountTransaction$Type extends MessageType<AccountTransaction> {
         /* string description = 1; */
         if (message.description !== "")
             writer.tag(1, WireType.LengthDelimited).string(message.description);
-        /* double amount = 2; */
-        if (message.amount !== 0)
-            writer.tag(2, WireType.Bit64).double(message.amount);
+        /* bank_account_service.Money amount = 3; */
+        if (message.amount)
+            Money.internalBinaryWrite(message.amount, writer.tag(3, WireType.LengthDelimited).fork(), options).join();
         let u = options.writeUnknownFields;
         if (u !== false)
             (u == true ? UnknownFieldHandler.onWrite : u)(this.typeName, message, writer);
@@ -131,13 +196,12 @@ class BankAccountView$Type extends MessageType<BankAccountView> {
     constructor() {
         super("bank_account_service.BankAccountView", [
             { no: 1, name: "account_id", kind: "scalar", T: 9 /*ScalarType.STRING*/ },
-            { no: 2, name: "balance", kind: "scalar", T: 1 /*ScalarType.DOUBLE*/ },
             { no: 3, name: "written_checks", kind: "scalar", repeat: 2 /*RepeatType.UNPACKED*/, T: 9 /*ScalarType.STRING*/ },
-            { no: 4, name: "account_transactions", kind: "message", repeat: 1 /*RepeatType.PACKED*/, T: () => AccountTransaction }
+            { no: 5, name: "balance", kind: "message", T: () => Money }
         ]);
     }
     create(value?: PartialMessage<BankAccountView>): BankAccountView {
-        const message = { accountId: "", balance: 0, writtenChecks: [], accountTransactions: [] };
+        const message = { accountId: "", writtenChecks: [] };
         globalThis.Object.defineProperty(message, MESSAGE_TYPE, { enumerable: false, value: this });
         if (value !== undefined)
             reflectionMergePartial<BankAccountView>(this, message, value);
@@ -151,14 +215,11 @@ class BankAccountView$Type extends MessageType<BankAccountView> {
                 case /* string account_id */ 1:
                     message.accountId = reader.string();
                     break;
-                case /* double balance */ 2:
-                    message.balance = reader.double();
-                    break;
                 case /* repeated string written_checks */ 3:
                     message.writtenChecks.push(reader.string());
                     break;
-                case /* repeated bank_account_service.AccountTransaction account_transactions */ 4:
-                    message.accountTransactions.push(AccountTransaction.internalBinaryRead(reader, reader.uint32(), options));
+                case /* bank_account_service.Money balance */ 5:
+                    message.balance = Money.internalBinaryRead(reader, reader.uint32(), options, message.balance);
                     break;
                 default:
                     let u = options.readUnknownField;
@@ -175,15 +236,12 @@ class BankAccountView$Type extends MessageType<BankAccountView> {
         /* string account_id = 1; */
         if (message.accountId !== "")
             writer.tag(1, WireType.LengthDelimited).string(message.accountId);
-        /* double balance = 2; */
-        if (message.balance !== 0)
-            writer.tag(2, WireType.Bit64).double(message.balance);
         /* repeated string written_checks = 3; */
         for (let i = 0; i < message.writtenChecks.length; i++)
             writer.tag(3, WireType.LengthDelimited).string(message.writtenChecks[i]);
-        /* repeated bank_account_service.AccountTransaction account_transactions = 4; */
-        for (let i = 0; i < message.accountTransactions.length; i++)
-            AccountTransaction.internalBinaryWrite(message.accountTransactions[i], writer.tag(4, WireType.LengthDelimited).fork(), options).join();
+        /* bank_account_service.Money balance = 5; */
+        if (message.balance)
+            Money.internalBinaryWrite(message.balance, writer.tag(5, WireType.LengthDelimited).fork(), options).join();
         let u = options.writeUnknownFields;
         if (u !== false)
             (u == true ? UnknownFieldHandler.onWrite : u)(this.typeName, message, writer);
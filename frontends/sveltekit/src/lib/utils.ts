@@ -1,2 +1,15 @@
 import { z } from 'zod';
+import type { Money } from '$lib/stubs/bank_account_service';
+
 export const URLschema = z.string().url();
+
+// Formats a Money value in major units, e.g. { minorUnits: 1050n, currency: 'USD' } => '10.50 USD'
+export function formatMoney(money: Money): string {
+	const exponent = ['JPY', 'KRW', 'VND', 'CLP', 'ISK'].includes(money.currency) ? 0 : 2;
+	const negative = money.minorUnits < 0n;
+	const abs = negative ? -money.minorUnits : money.minorUnits;
+	const factor = 10n ** BigInt(exponent);
+	const major = (abs / factor).toString();
+	const minor = exponent === 0 ? '' : '.' + (abs % factor).toString().padStart(exponent, '0');
+	return `${negative ? '-' : ''}${major}${minor} ${money.currency}`;
+}
@@ -37,11 +37,32 @@ is_graphql_route {
     input.path == ["graphql"]
 }
 
+# GraphQL subscriptions are served over a WebSocket, opened with a GET request
+is_graphql_subscription_route {
+    is_get_method
+    input.path == ["grahql", "ws"]
+}
+
+# Users allowed to run operational tasks such as rebuilding projections, read from the
+# `veloxide.admins` data document so that they can be changed without changing the policy
+admins := {email | email := data.veloxide.admins[_]}
+
+is_admin(user) {
+    is_valid_user(user)
+    admins[user.email]
+}
+
 is_valid_user(user) {
     # This is just demonstrating running policy against data extracted by axum
     contains(user.email, "@")
 }
 
+# Bank accounts belong to users, the application checks the user owns the account
+is_account_user(user) {
+    is_valid_user(user)
+    user.id != null
+}
+
 is_callback_path {
     is_get_method
     input.path == ["auth", "google", "callback"]
@@ -91,6 +112,26 @@ is_bank_account_path {
     input.path[1] == "bank-accounts"
 }
 
+is_transfer_path {
+    is_api_path
+    input.path[1] == "transfers"
+}
+
+is_webhook_path {
+    is_api_path
+    input.path[1] == "webhooks"
+}
+
+is_scheduled_payment_path {
+    is_api_path
+    input.path[1] == "scheduled-payments"
+}
+
+is_admin_path {
+    is_api_path
+    input.path[1] == "admin"
+}
+
 # Main rule
 allow {
     is_login_route
@@ -98,6 +139,27 @@ allow {
 
 allow {
     is_bank_account_path
+    is_account_user(input.user)
+}
+
+allow {
+    is_transfer_path
+    is_account_user(input.user)
+}
+
+allow {
+    is_webhook_path
+    is_account_user(input.user)
+}
+
+allow {
+    is_scheduled_payment_path
+    is_account_user(input.user)
+}
+
+allow {
+    is_admin_path
+    is_admin(input.user)
 }
 
 allow {
@@ -148,3 +210,8 @@ allow {
     is_graphql_playground
 }
 
+allow {
+    is_graphql_subscription_route
+    is_account_user(input.user)
+}
+
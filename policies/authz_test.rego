@@ -69,7 +69,55 @@ test_allow_login_route {
 }
 
 test_allow_bank_account_route {
-    allow with input as {"method": "GET", "path": ["api", "bank-accounts", "123"]}
+    allow with input as {"method": "GET", "path": ["api", "bank-accounts", "123"], "user": {"id": "5f2b4e1c", "email": "ltest@example.com"}}
+}
+
+test_deny_bank_account_route_without_user {
+    not allow with input as {"method": "GET", "path": ["api", "bank-accounts", "123"]}
+}
+
+test_allow_graphql_subscription_route {
+    allow with input as {"method": "GET", "path": ["grahql", "ws"], "user": {"id": "5f2b4e1c", "email": "ltest@example.com"}}
+}
+
+test_deny_graphql_subscription_route_without_user {
+    not allow with input as {"method": "GET", "path": ["grahql", "ws"]}
+}
+
+test_allow_transfer_route {
+    allow with input as {"method": "POST", "path": ["api", "transfers"], "user": {"id": "5f2b4e1c", "email": "ltest@example.com"}}
+}
+
+test_deny_transfer_route_without_user {
+    not allow with input as {"method": "POST", "path": ["api", "transfers"]}
+}
+
+test_allow_webhook_route {
+    allow with input as {"method": "DELETE", "path": ["api", "webhooks", "8d1c5e7a"], "user": {"id": "5f2b4e1c", "email": "ltest@example.com"}}
+}
+
+test_deny_webhook_route_without_user {
+    not allow with input as {"method": "GET", "path": ["api", "webhooks"]}
+}
+
+test_allow_scheduled_payment_route {
+    allow with input as {"method": "POST", "path": ["api", "scheduled-payments"], "user": {"id": "5f2b4e1c", "email": "ltest@example.com"}}
+}
+
+test_deny_scheduled_payment_route_without_user {
+    not allow with input as {"method": "GET", "path": ["api", "scheduled-payments", "3a9f0c2d"]}
+}
+
+test_allow_admin_route_for_admin {
+    allow with input as {"method": "POST", "path": ["api", "admin", "projections", "account_query", "rebuild"], "user": {"email": "admin@veloxide.dev"}} with data.veloxide.admins as ["admin@veloxide.dev"]
+}
+
+test_allow_trial_balance_for_admin {
+    allow with input as {"method": "GET", "path": ["api", "admin", "ledger", "trial-balance"], "user": {"email": "admin@veloxide.dev"}} with data.veloxide.admins as ["admin@veloxide.dev"]
+}
+
+test_allow_assigning_owners_for_admin {
+    allow with input as {"method": "POST", "path": ["api", "admin", "bank-accounts", "1234", "owner"], "user": {"email": "admin@veloxide.dev"}} with data.veloxide.admins as ["admin@veloxide.dev"]
 }
 
 test_allow_logout_route {
@@ -140,6 +188,26 @@ test_deny_protected_route_without_valid_user {
     not allow with input as {"method": "GET", "path": ["protected"], "user": {"email": "testexample.com"}}
 }
 
+test_deny_admin_route_for_other_users {
+    not allow with input as {"method": "POST", "path": ["api", "admin", "projections", "account_query", "rebuild"], "user": {"email": "ltest@example.com"}} with data.veloxide.admins as ["admin@veloxide.dev"]
+}
+
+test_deny_admin_route_when_no_admins_are_configured {
+    not allow with input as {"method": "POST", "path": ["api", "admin", "projections", "account_query", "rebuild"], "user": {"email": "admin@veloxide.dev"}} with data.veloxide.admins as []
+}
+
+test_deny_trial_balance_for_other_users {
+    not allow with input as {"method": "GET", "path": ["api", "admin", "ledger", "trial-balance"], "user": {"email": "ltest@example.com"}}
+}
+
+test_deny_assigning_owners_for_other_users {
+    not allow with input as {"method": "POST", "path": ["api", "admin", "bank-accounts", "1234", "owner"], "user": {"id": "5678", "email": "ltest@example.com"}} with data.veloxide.admins as ["admin@veloxide.dev"]
+}
+
+test_deny_admin_route_without_user {
+    not allow with input as {"method": "POST", "path": ["api", "admin", "projections", "account_query", "rebuild"]}
+}
+
 test_deny_unknown_route {
     not allow with input as {"method": "GET", "path": ["unknown"]}
 }